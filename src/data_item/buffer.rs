@@ -1,17 +1,22 @@
-use std::collections::{HashMap, LinkedList};
+use std::collections::{HashMap, LinkedList, VecDeque};
+use std::collections::hash_map::Entry;
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
-use std::mem::size_of;
 use std::path::Path;
+use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 
 use uuid::Uuid;
 
+use crate::data_item::wal::Wal;
 use crate::page::page_item::{Page, PAGE_SIZE};
 use crate::util::error::Error;
 use crate::util::data_gen::get_empty_data;
 use byteorder::{WriteBytesExt, ReadBytesExt};
 
+/// 在多个线程间共享的缓冲区，每次读写页之前加锁
+pub type SharedBuffer = Arc<Mutex<Box<dyn Buffer>>>;
+
 /// 缓冲区自己管理的配置页的索引
 pub const META_PAGE: usize = 0;
 
@@ -25,26 +30,113 @@ pub const FIRST_UUID_OFFSET: usize = 0;
 /// 初始化文件的页大小
 pub const INIT_FILE_PAGE_NUM: usize = 4;
 
+/// 文件头需要容纳页数与初始页表，页大小不能小于该值
+pub const MIN_PAGE_SIZE: usize = (INIT_FILE_PAGE_NUM + 1) * 32;
+
+/// LFUBuffer 每隔多少次访问将所有页面的访问次数减半
+pub const LFU_DECAY_INTERVAL: usize = 64;
+
+/// 缓冲区至少要能放下的页数，少于该值时构造返回 InvalidBufferSize
+/// 只有一页时每次读写都会换出上一页，B+树分裂时同时修改多个节点，最好留出几页
+pub const MIN_BUFFER_SIZE: usize = 1;
+
 /// 文件页数所在页
 pub const FILE_PAGE_NUM_PAGE_NUM: usize = 0;
 /// 文件页数所在页的偏移
 pub const FILE_PAGE_NUM_OFFSET: usize = 0;
 
-/// 文件页表描述方式： 每次一个usize表示剩余空间
+/// 文件页表描述方式：
+/// 文件第 0 页划分成 PAGE_TABLE_ENTRY_SIZE 大小的槽，第 j 个槽对应文件的第 j 页
+/// 第 0 个槽的前 4 字节是文件页数，包括文件头的 INIT_FILE_PAGE_NUM 页
+/// 数据页 i 是文件的第 INIT_FILE_PAGE_NUM + i 页，它的剩余空间以大端 u32 存在对应槽的前 4 字节
+/// 文件头的页没有页表项，对应的槽不使用
 /// 文件页表所在页
 pub const FILE_PAGE_TABLE_PAGE_NUM: usize = 0;
-/// 文件页表偏移
-pub const FILE_PAGE_TABLE_OFFSET: usize = size_of::<usize>();
+/// 页表项的大小
+pub const PAGE_TABLE_ENTRY_SIZE: usize = 32;
+/// 文件页表偏移，即第 0 个数据页的页表项位置
+pub const FILE_PAGE_TABLE_OFFSET: usize = PAGE_TABLE_ENTRY_SIZE * INIT_FILE_PAGE_NUM;
+
+/// Pager 保存空闲页链表的保留页，位于文件头的非数据页中
+pub const FREE_LIST_PAGE: usize = 1;
+
+/// Pager 保存值的个数的保留页
+pub const VALUE_COUNT_PAGE: usize = 2;
+
+/// 事务的撤销日志，记录事务开始后每个页第一次被修改前的内容
+#[derive(Default)]
+struct UndoLog {
+    /// (文件名, 页号) -> 修改前的页面
+    pages: HashMap<(String, usize), Vec<u8>>,
+    /// (文件名, 保留页下标) -> 修改前的保留页
+    reserved: HashMap<(String, usize), Vec<u8>>,
+}
+
+impl UndoLog {
+    /// 页面在事务中第一次被写入时记下它原来的内容
+    /// 调用时撤销日志已经从缓冲区中取出，读页不会再被记录
+    fn record_page(&mut self, buffer: &mut dyn Buffer, file_name: &str, page_num: usize) -> Result<(), Error> {
+        if let Entry::Vacant(entry) = self.pages.entry((String::from(file_name), page_num)) {
+            let page = buffer.get_page(file_name, page_num)?;
+            entry.insert(page.get_bytes().to_vec());
+        }
+        Ok(())
+    }
+
+    fn record_reserved(&mut self, buffer: &mut dyn Buffer, file_name: &str, index: usize) -> Result<(), Error> {
+        if let Entry::Vacant(entry) = self.reserved.entry((String::from(file_name), index)) {
+            entry.insert(buffer.read_reserved_page(file_name, index)?);
+        }
+        Ok(())
+    }
+
+    /// 将记录的内容写回，缓冲区此时已经没有进行中的事务
+    fn restore(self, buffer: &mut dyn Buffer) -> Result<(), Error> {
+        for ((file_name, page_num), data) in self.pages {
+            buffer.write_page(Page::from_vec(data, &file_name, page_num))?;
+        }
+        for ((file_name, index), data) in self.reserved {
+            buffer.write_reserved_page(&file_name, index, &data)?;
+        }
+        Ok(())
+    }
+}
 
+/// insert_bytes 写入的字节在文件中的位置
+/// page_num 为从0开始计数的数据页下标，offset 为页内偏移
 pub struct Position {
     file_name: String,
     page_num: usize,
     offset: usize,
 }
 
+impl Position {
+    /// 从各部分重建一个位置，用于重新读取之前保存的字节
+    pub fn new(file_name: String, page_num: usize, offset: usize) -> Position {
+        Position {
+            file_name,
+            page_num,
+            offset,
+        }
+    }
+
+    pub fn get_file_name(&self) -> &str {
+        self.file_name.as_str()
+    }
+
+    pub fn get_page_num(&self) -> usize {
+        self.page_num
+    }
+
+    pub fn get_offset(&self) -> usize {
+        self.offset
+    }
+}
+
 /// 缓冲区的trait，实现了通过缓冲区获取页、写入页、强制刷新页
-/// todo 检查page_num 拒绝所有0, page_num从1开始计数，0为幽灵页
-pub trait Buffer {
+/// page_num从1开始计数，0为幽灵页，获取、写入、刷新0号页都返回 InvalidPageNum
+/// 缓冲区可以放进 SharedBuffer 在线程间共享，因此要求 Send
+pub trait Buffer: Send {
     fn add_file(&mut self, path: &Path) -> Result<(), Error>;
 
     fn fill_up_to(&mut self, file_name: &str, num_of_page: usize) -> Result<(), Error>;
@@ -53,8 +145,16 @@ pub trait Buffer {
 
     fn write_page(&mut self, page: Page) -> Result<(), Error>;
 
+    /// 预先把文件的若干页载入缓冲区，之后读取这些页时可以直接命中
+    /// 已经在缓冲区中的页不重复读取，载入时与 get_page 一样可能淘汰其他页面
+    fn prefetch(&mut self, file_name: &str, page_nums: &[usize]) -> Result<(), Error>;
+
     fn flush(&mut self, file_name: &str, page_num: &usize) -> Result<(), Error>;
 
+    fn pin(&mut self, file_name: &str, page_num: usize) -> Result<(), Error>;
+
+    fn unpin(&mut self, file_name: &str, page_num: usize) -> Result<(), Error>;
+
     fn get_first_uuid(&mut self) -> Result<Uuid, Error>;
 
     fn update_first_uuid(&mut self, uuid: Uuid) -> Result<(), Error>;
@@ -65,18 +165,222 @@ pub trait Buffer {
 
     fn get_buffer_size(&self) -> usize;
 
+    /// 获取缓冲区使用的页大小
+    fn get_page_size(&self) -> usize;
+
+    fn get_meta_file_name(&self) -> String;
+
     fn flush_file(&mut self, file_name: &str) -> Result<(), Error>;
 
+    /// 写回所有脏页，按文件分组、同一文件按页号递增的顺序写入，见 write_order
     fn flush_all(&mut self) -> Result<(), Error>;
+
+    fn remove_file(&mut self, file_name: &str) -> Result<(), Error>;
+
+    /// 获取缓冲区的运行统计
+    fn stats(&self) -> BufferStats;
+
+    /// 清零缓冲区的运行统计
+    fn reset_stats(&mut self);
+
+    /// 读取文件头中的一个保留页，index 需要在 1 到 NON_DATA_PAGE - 1 之间
+    /// 保留页不经过缓冲，直接读写磁盘
+    fn read_reserved_page(&mut self, file_name: &str, index: usize) -> Result<Vec<u8>, Error>;
+
+    /// 覆盖写入文件头中的一个保留页
+    fn write_reserved_page(&mut self, file_name: &str, index: usize, data: &[u8]) -> Result<(), Error>;
+
+    /// 启用预写日志，之后每次 write_page 都先把页面追加到日志
+    fn open_wal(&mut self, path: &Path) -> Result<(), Error>;
+
+    /// 将日志中的记录写回数据文件，用于崩溃后重新打开
+    /// 需要在加入日志涉及的文件之后、读取页面之前调用
+    fn recover(&mut self) -> Result<(), Error>;
+
+    /// 将日志应用到数据文件并刷新缓冲区，然后清空日志
+    fn checkpoint(&mut self) -> Result<(), Error>;
+
+    /// 析构时不再写回缓存的页面，用于需要丢弃修改的测试
+    fn no_sync_on_drop(&mut self);
+
+    /// 先写回文件的脏页，再检查磁盘上的每个数据页，返回损坏的页号
+    /// 页面写回时在页表项中记下校验和，校验和不符或页表项超出范围的页视为损坏
+    fn verify_file(&mut self, file_name: &str) -> Result<Vec<usize>, Error>;
+
+    /// 开始一个事务，之后每个页和保留页第一次被修改前的内容都记入撤销日志
+    /// insert_bytes 直接写文件，不在撤销范围内
+    fn begin_transaction(&mut self) -> Result<(), Error>;
+
+    /// 提交事务，丢弃撤销日志并写回所有脏页
+    fn commit_transaction(&mut self) -> Result<(), Error>;
+
+    /// 回滚事务，将事务中修改过的页和保留页恢复成事务开始时的内容
+    fn rollback_transaction(&mut self) -> Result<(), Error>;
+}
+
+
+/// 数据页 i 的页表项在文件中的偏移，布局见 FILE_PAGE_TABLE_OFFSET
+fn page_table_entry(data_page: usize) -> u64 {
+    (FILE_PAGE_TABLE_OFFSET + data_page * PAGE_TABLE_ENTRY_SIZE) as u64
+}
+
+/// 页表项中页面校验和的偏移，跟在剩余空间之后
+/// 校验和为 0 表示没有记录，用于兼容旧文件
+const PAGE_CHECKSUM_OFFSET: u64 = 4;
+
+/// CRC-32(IEEE 多项式)的查找表，编译时生成
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            k += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// 数据页的 CRC-32 校验和
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, b| (crc >> 8) ^ CRC32_TABLE[((crc ^ *b as u32) & 0xff) as usize])
+}
+
+/// 依次写回的 (页号, 页面数据)
+#[cfg(test)]
+pub(crate) type WriteLog = Vec<(usize, Vec<u8>)>;
+
+#[cfg(test)]
+thread_local! {
+    /// 测试用的写回记录，为 Some 时 write_data_page 记下每次写回
+    pub(crate) static WRITE_LOG: std::cell::RefCell<Option<WriteLog>> = const { std::cell::RefCell::new(None) };
+}
+
+/// 将缓冲区的第 page_num 页(从 1 开始)写回文件，并在它的页表项中记下校验和
+pub(crate) fn write_data_page(file: &mut File, page_num: usize, data: &[u8], page_size: usize) -> Result<(), Error> {
+    #[cfg(test)]
+    WRITE_LOG.with(|log| {
+        if let Some(log) = log.borrow_mut().as_mut() {
+            log.push((page_num, data.to_vec()));
+        }
+    });
+    file.seek(SeekFrom::Start(((page_num - 1 + NON_DATA_PAGE) * page_size) as u64))?;
+    file.write_all(data)?;
+    file.seek(SeekFrom::Start(page_table_entry(page_num - 1) + PAGE_CHECKSUM_OFFSET))?;
+    file.write_u32::<byteorder::BigEndian>(crc32(data))?;
+    Ok(())
+}
+
+/// 读出数据页 data_page(从 0 开始)，重新计算并记下它的校验和
+fn update_page_checksum(file: &mut File, data_page: usize, page_size: usize) -> Result<(), Error> {
+    let mut data = vec![0u8; page_size];
+    file.seek(SeekFrom::Start(((INIT_FILE_PAGE_NUM + data_page) * page_size) as u64))?;
+    file.read_exact(&mut data)?;
+    file.seek(SeekFrom::Start(page_table_entry(data_page) + PAGE_CHECKSUM_OFFSET))?;
+    file.write_u32::<byteorder::BigEndian>(crc32(data.as_slice()))?;
+    Ok(())
 }
 
+/// 检查文件中的每个数据页，返回校验和不符或剩余空间超出页大小的页号(从 1 开始)
+/// 全零的页是还没有使用或已经清空的页，视为有效
+fn verify_pages(file: &mut File, page_size: usize) -> Result<Vec<usize>, Error> {
+    file.seek(SeekFrom::Start(0))?;
+    let page_num = file.read_u32::<byteorder::BigEndian>()? as usize;
+    let mut res = Vec::<usize>::new();
+    for i in 0..page_num.saturating_sub(INIT_FILE_PAGE_NUM) {
+        file.seek(SeekFrom::Start(page_table_entry(i)))?;
+        let remain = file.read_u32::<byteorder::BigEndian>()? as usize;
+        let sum = file.read_u32::<byteorder::BigEndian>()?;
+        let mut data = vec![0u8; page_size];
+        file.seek(SeekFrom::Start(((INIT_FILE_PAGE_NUM + i) * page_size) as u64))?;
+        file.read_exact(&mut data)?;
+        let page = Page::from_vec(data, "", 0);
+        if page.is_empty() {
+            continue;
+        }
+        if remain > page_size || (sum != 0 && sum != crc32(page.get_bytes())) {
+            res.push(i + 1);
+        }
+    }
+    Ok(res)
+}
+
+/// 写回多个页面时的顺序，返回页面在 pages 中的下标
+/// 按文件名分组，同一文件内按页号递增，减少磁盘寻道，写回的顺序也与页面在缓冲区中的位置无关
+pub(crate) fn write_order<'a>(pages: impl Iterator<Item = &'a Page>) -> Vec<usize> {
+    let mut order: Vec<(&str, usize, usize)> = pages
+        .enumerate()
+        .map(|(index, page)| (page.file_name.as_str(), page.page_num, index))
+        .collect();
+    order.sort_unstable();
+    order.into_iter().map(|(_, _, index)| index).collect()
+}
+
+/// 找到保留页对应的文件并定位到页首
+/// 第 0 页由缓冲区自己使用，不能作为保留页
+fn seek_reserved<'a>(files: &'a mut HashMap<String, File>, file_name: &str, index: usize, page_size: usize) -> Result<&'a mut File, Error> {
+    if index == META_PAGE || index >= NON_DATA_PAGE {
+        return Err(Error::InvalidPageNum);
+    }
+    let file = match files.get_mut(file_name) {
+        Some(file) => file,
+        None => return Err(Error::FileNotFound)
+    };
+    file.seek(SeekFrom::Start((index * page_size) as u64))?;
+    Ok(file)
+}
+
+fn read_reserved(files: &mut HashMap<String, File>, file_name: &str, index: usize, page_size: usize) -> Result<Vec<u8>, Error> {
+    let file = seek_reserved(files, file_name, index, page_size)?;
+    let mut data = vec![0; page_size];
+    file.read_exact(&mut data)?;
+    Ok(data)
+}
+
+fn write_reserved(files: &mut HashMap<String, File>, file_name: &str, index: usize, data: &[u8], page_size: usize) -> Result<(), Error> {
+    if data.len() > page_size {
+        return Err(Error::UnexpectedError);
+    }
+    let file = seek_reserved(files, file_name, index, page_size)?;
+    file.write_all(data)?;
+    Ok(())
+}
+
+/// 缓冲区的运行统计，用于比较不同淘汰算法的效果
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BufferStats {
+    /// get_page 在缓冲区中找到页面的次数
+    pub hits: usize,
+    /// get_page 需要从磁盘载入页面的次数
+    pub misses: usize,
+    /// 淘汰页面的次数
+    pub evictions: usize,
+    /// 写回磁盘的页数
+    pub writes: usize,
+}
 
 /// LRU算法实现的Buffer
 pub struct LRUBuffer {
     pub(crate) list: LinkedList<LRUBufferItem>,
     len: usize,
+    /// 命中、缺页、淘汰与写回次数的统计
+    stats: BufferStats,
     buff_size: usize,
+    /// 页大小，缓冲区管理的所有文件使用相同的页大小
+    page_size: usize,
     file: HashMap<String, File>,
+    /// 预写日志，没有启用时为 None
+    wal: Option<Wal>,
+    /// 进行中的事务的撤销日志，没有事务时为 None
+    undo: Option<UndoLog>,
+    /// 析构时是否把缓存的页面写回文件
+    sync_on_drop: bool,
     meta_file_name: String
 }
 
@@ -84,11 +388,28 @@ pub struct LRUBuffer {
 pub struct LRUBufferItem {
     pub(crate) page: Page,
     time: SystemTime,
+    /// 页面载入后是否被修改过，只有脏页需要写回磁盘
+    dirty: bool,
+    /// 页面被固定的次数，被固定的页面不会被淘汰
+    pin_cnt: usize,
 }
 
 impl LRUBuffer {
     /// LRUBuffer的构造方法
     pub fn new(buff_size: usize, meta_file_name: String) -> Result<LRUBuffer, Error> {
+        Self::with_page_size(buff_size, meta_file_name, PAGE_SIZE)
+    }
+
+    /// 使用指定页大小构造，用于测试小页面的边界情况或适配不同的块设备
+    /// 页大小小于 MIN_PAGE_SIZE 时返回 InvalidPageSize，缓冲页数小于 MIN_BUFFER_SIZE 时返回 InvalidBufferSize
+    /// B+树节点的布局依赖默认的 PAGE_SIZE，索引文件只能使用默认页大小
+    pub fn with_page_size(buff_size: usize, meta_file_name: String, page_size: usize) -> Result<LRUBuffer, Error> {
+        if page_size < MIN_PAGE_SIZE {
+            return Err(Error::InvalidPageSize);
+        }
+        if buff_size < MIN_BUFFER_SIZE {
+            return Err(Error::InvalidBufferSize);
+        }
         let path = Path::new(meta_file_name.as_str());
         let mut hashmap = HashMap::<String, File>::new();
         let fd = OpenOptions::new()
@@ -114,8 +435,13 @@ impl LRUBuffer {
         let mut res = LRUBuffer {
             list: LinkedList::<LRUBufferItem>::new(),
             len: 0,
+            stats: BufferStats::default(),
             buff_size,
+            page_size,
             file: hashmap,
+            wal: None,
+            undo: None,
+            sync_on_drop: true,
             meta_file_name: meta_file_name.clone()
         };
         res.fill_up_to(meta_file_name.as_str(), METADATA_FILE_PAGE_NUM)?;
@@ -139,19 +465,71 @@ impl LRUBuffer {
             }
             None => false
         };
-        for i in self.list.iter_mut() {
-            if (!has_file_name || i.page.file_name == file_name) && (!has_page_num || i.page.page_num == page_num) {
-                if updated {
-                    i.time = SystemTime::now();
-                }
-                let file = self.file.get_mut(i.page.file_name.as_str()).unwrap();
-                file.seek(SeekFrom::Start(((i.page.page_num - 1) * PAGE_SIZE + NON_DATA_PAGE * PAGE_SIZE) as u64))?;
-                file.write_all(&i.page.get_data())?;
+        let mut items: Vec<&mut LRUBufferItem> = self.list
+            .iter_mut()
+            .filter(|i| (!has_file_name || i.page.file_name == file_name) && (!has_page_num || i.page.page_num == page_num))
+            .collect();
+        for index in write_order(items.iter().map(|i| &i.page)) {
+            let i = &mut items[index];
+            if updated {
+                i.time = SystemTime::now();
+            }
+            // 干净的页面与磁盘一致，无需写回
+            if !i.dirty {
+                continue;
             }
+            let file = self.file.get_mut(i.page.file_name.as_str()).unwrap();
+            write_data_page(file, i.page.page_num, i.page.get_bytes(), self.page_size)?;
+            i.dirty = false;
+            self.stats.writes += 1;
         }
         Ok(())
     }
 
+    /// 选出没有被固定的最旧页作为牺牲者，写回磁盘后返回其在链表中的下标
+    /// 所有页都被固定时返回 NoEvictablePage
+    fn evict(&mut self) -> Result<usize, Error> {
+        let mut victim: Option<(usize, SystemTime)> = None;
+        for (index, i) in self.list.iter().enumerate() {
+            if i.pin_cnt > 0 {
+                continue;
+            }
+            match victim {
+                Some((_, min_time)) if min_time <= i.time => (),
+                _ => victim = Some((index, i.time)),
+            }
+        }
+
+        let index = match victim {
+            Some((index, _)) => index,
+            None => return Err(Error::NoEvictablePage)
+        };
+        let (file_name, page_num) = match self.list.iter().nth(index) {
+            Some(item) => (item.page.file_name.clone(), item.page.page_num),
+            None => return Err(Error::UnexpectedError)
+        };
+        // 刷新时不更新访问时间
+        self.flush_internal(Some(file_name.as_str()), Some(&page_num), false)?;
+        self.stats.evictions += 1;
+        Ok(index)
+    }
+
+    /// 调整页面的固定次数，页面不在缓冲区时返回不在缓冲区异常
+    fn update_pin(&mut self, file_name: &str, page_num: usize, pin: bool) -> Result<(), Error> {
+        for i in self.list.iter_mut() {
+            if i.page.file_name == file_name && i.page.page_num == page_num {
+                if pin {
+                    i.pin_cnt += 1;
+                } else if i.pin_cnt == 0 {
+                    return Err(Error::UnexpectedError);
+                } else {
+                    i.pin_cnt -= 1;
+                }
+                return Ok(());
+            }
+        }
+        Err(Error::NotInBufferError)
+    }
 }
 
 impl Buffer for LRUBuffer {
@@ -163,20 +541,18 @@ impl Buffer for LRUBuffer {
             .write(true)
             .open(path)?;
 
-        // 初始化文件大小
-        fd.seek(SeekFrom::Start(0))?;
-        fd.write_all(get_empty_data(INIT_FILE_PAGE_NUM * PAGE_SIZE).as_slice())?;
-
-        // 填充文件头配置信息
-        // 文件页数
-        fd.seek(SeekFrom::Start(0))?;
-        fd.write_u32::<byteorder::BigEndian>(INIT_FILE_PAGE_NUM as u32)?;
-
-        // 文件页表
-        fd.write_u32::<byteorder::BigEndian>(PAGE_SIZE as u32 - (32 * NON_DATA_PAGE + 32) as u32)?;
-        fd.write_u32::<byteorder::BigEndian>(PAGE_SIZE as u32)?;
-        fd.write_u32::<byteorder::BigEndian>(PAGE_SIZE as u32)?;
-        fd.write_u32::<byteorder::BigEndian>(PAGE_SIZE as u32)?;
+        // 已有文件头的文件直接加载，不重新初始化
+        if fd.metadata()?.len() < (INIT_FILE_PAGE_NUM * self.page_size) as u64 {
+            // 初始化文件大小
+            fd.seek(SeekFrom::Start(0))?;
+            fd.write_all(get_empty_data(INIT_FILE_PAGE_NUM * self.page_size).as_slice())?;
+
+            // 填充文件头配置信息
+            // 文件页数
+            fd.seek(SeekFrom::Start(0))?;
+            fd.write_u32::<byteorder::BigEndian>(INIT_FILE_PAGE_NUM as u32)?;
+            // 新文件没有数据页，页表为空
+        }
 
         // 获取文件名
         let raw_file_name = path.to_str();
@@ -197,31 +573,29 @@ impl Buffer for LRUBuffer {
         match raw_file {
             Some(file) => {
                 file.seek(SeekFrom::Start(0))?;
-                let page_num = match file.read_u32::<byteorder::BigEndian>() {
-                    Ok(pn) => pn,
-                    _ => return Err(Error::UnexpectedError)
-                };
-                if PAGE_SIZE < (INIT_FILE_PAGE_NUM + num_of_page + 1) * 32 {
+                let page_num = file.read_u32::<byteorder::BigEndian>()?;
+                if self.page_size < (INIT_FILE_PAGE_NUM + num_of_page + 1) * PAGE_TABLE_ENTRY_SIZE {
                     return Err(Error::PageNumOutOfSize);
                 }
+                // 文件已经足够大，无需填充
+                if INIT_FILE_PAGE_NUM + num_of_page <= page_num as usize {
+                    return Ok(());
+                }
 
                 // 填充文件
-                file.seek(SeekFrom::Start((page_num as usize * PAGE_SIZE) as u64))?;
-                let siz = (num_of_page - page_num as usize + INIT_FILE_PAGE_NUM) * PAGE_SIZE;
+                file.seek(SeekFrom::Start((page_num as usize * self.page_size) as u64))?;
+                let siz = (num_of_page + INIT_FILE_PAGE_NUM - page_num as usize) * self.page_size;
                 file.write_all(get_empty_data(siz).as_slice())?;
 
                 // 更新文件头
                 file.seek(SeekFrom::Start(0))?;
                 file.write_u32::<byteorder::BigEndian>((INIT_FILE_PAGE_NUM + num_of_page) as u32)?;
 
-                // 第一页占用空间
-                file.write_u32::<byteorder::BigEndian>((PAGE_SIZE - (INIT_FILE_PAGE_NUM + num_of_page + 1) * 32) as u32)?;
-
-
-                file.seek(SeekFrom::Start((1 + page_num as u64) * 32))?;
-                // 其余页占用空间
-                for _i in 1..=num_of_page - page_num as usize + INIT_FILE_PAGE_NUM {
-                    file.write_u32::<byteorder::BigEndian>(PAGE_SIZE as u32)?;
+                // 新数据页的页表项，剩余空间为整页
+                let first = (page_num as usize).saturating_sub(INIT_FILE_PAGE_NUM);
+                for i in first..num_of_page {
+                    file.seek(SeekFrom::Start(page_table_entry(i)))?;
+                    file.write_u32::<byteorder::BigEndian>(self.page_size as u32)?;
                 }
 
                 Ok(())
@@ -235,66 +609,50 @@ impl Buffer for LRUBuffer {
     /// 否则，加载一个磁盘页面到缓冲区
     /// 如果缓冲区已满，淘汰时间最早的页面
     fn get_page(&mut self, file_name: &str, page_num: usize) -> Result<Page, Error> {
+        // 页号从1开始计数，0为幽灵页
+        if page_num == 0 {
+            return Err(Error::InvalidPageNum);
+        }
         // 查询缓冲
         for i in self.list.iter_mut() {
             if i.page.file_name == file_name && i.page.page_num == page_num {
                 i.time = SystemTime::now();
-                return Ok(Page::new(i.page.get_data(), file_name, page_num));
+                self.stats.hits += 1;
+                return Ok(Page::from_vec(i.page.get_bytes().to_vec(), file_name, page_num));
             }
         }
 
+        self.stats.misses += 1;
         // 获取对应页数据
-        let mut page: [u8; PAGE_SIZE] = [0x00; PAGE_SIZE];
-        let file = self.file.get_mut(file_name).unwrap();
-        file.seek(SeekFrom::Start(((page_num - 1) * PAGE_SIZE + NON_DATA_PAGE * PAGE_SIZE) as u64))?;
+        let mut page = vec![0x00; self.page_size];
+        let file = match self.file.get_mut(file_name) {
+            Some(file) => file,
+            None => return Err(Error::FileNotFound)
+        };
+        file.seek(SeekFrom::Start(((page_num - 1) * self.page_size + NON_DATA_PAGE * self.page_size) as u64))?;
         file.read_exact(&mut page)?;
 
         // 更新缓冲
         // 如果缓冲没满
         if self.len < self.buff_size {
             self.list.push_back(LRUBufferItem {
-                page: Page::new(page, file_name, page_num),
+                page: Page::from_vec(page.clone(), file_name, page_num),
                 time: SystemTime::now(),
+                dirty: false,
+                pin_cnt: 0,
             });
             self.len += 1;
-            Ok(Page::new(page, file_name, page_num))
+            Ok(Page::from_vec(page.clone(), file_name, page_num))
         } else {
-            let mut min_time = SystemTime::now();
-            let mut buffer_item: Option<&mut LRUBufferItem> = None;
-            let mut min_time_page_num: Option<usize> = None;
-            let mut min_time_file_name: Option<String> = None;
-
-            // 寻找最旧页
-            for i in self.list.iter() {
-                if min_time > i.time {
-                    min_time = i.time;
-                    min_time_page_num = Some(i.page.page_num);
-                    min_time_file_name = Some(i.page.file_name.clone());
-                }
-            }
-
-            // 刷新最旧页
-            match (min_time_page_num, min_time_file_name) {
-                (Some(p_num), Some(f_name)) => {
-                    self.flush_internal(Some(f_name.as_str()), Some(&p_num), false)?
-                }
-                (_, _) => return Err(Error::UnexpectedError)
-            }
-
-            // 获取缓冲引用
-            for i in self.list.iter_mut() {
-                if min_time == i.time {
-                    buffer_item = Some(i);
-                    break;
-                }
-            }
-
-            // 更新缓冲
-            match buffer_item {
+            // 淘汰最旧页
+            let index = self.evict()?;
+            match self.list.iter_mut().nth(index) {
                 Some(item) => {
-                    item.page = Page::new(page, file_name, page_num);
+                    item.page = Page::from_vec(page.clone(), file_name, page_num);
                     item.time = SystemTime::now();
-                    Ok(Page::new(page, file_name, page_num))
+                    item.dirty = false;
+                    item.pin_cnt = 0;
+                    Ok(Page::from_vec(page.clone(), file_name, page_num))
                 }
                 None => Err(Error::UnexpectedError)
             }
@@ -303,11 +661,26 @@ impl Buffer for LRUBuffer {
 
     /// 向缓冲区写入一个页面
     fn write_page(&mut self, page: Page) -> Result<(), Error> {
+        // 页号从1开始计数，0为幽灵页
+        if page.page_num == 0 {
+            return Err(Error::InvalidPageNum);
+        }
+        // 事务中先记下页面原来的内容
+        if let Some(mut undo) = self.undo.take() {
+            let res = undo.record_page(self, &page.file_name, page.page_num);
+            self.undo = Some(undo);
+            res?;
+        }
+        // 先写日志
+        if let Some(wal) = self.wal.as_mut() {
+            wal.append(&page)?;
+        }
         // 查询缓冲
         for i in &mut self.list {
             if i.page.file_name == page.file_name && page.page_num == i.page.page_num {
                 i.page = page;
                 i.time = SystemTime::now();
+                i.dirty = true;
                 return Ok(());
             }
         }
@@ -318,44 +691,20 @@ impl Buffer for LRUBuffer {
             self.list.push_back(LRUBufferItem {
                 page,
                 time: SystemTime::now(),
+                dirty: true,
+                pin_cnt: 0,
             });
             self.len += 1;
             Ok(())
         } else {
-            let mut min_time = SystemTime::now();
-            let mut buffer_item: Option<&mut LRUBufferItem> = None;
-            let mut min_time_page_num: Option<usize> = None;
-            let mut min_time_file_name: Option<String> = None;
-
-            // 寻找最旧缓冲
-            for i in self.list.iter() {
-                if min_time > i.time {
-                    min_time = i.time;
-                    min_time_page_num = Some(i.page.page_num);
-                    min_time_file_name = Some(i.page.file_name.clone());
-                }
-            }
-
-            // 刷新最旧缓冲
-            match (min_time_page_num, min_time_file_name) {
-                (Some(p_num), Some(f_name)) => {
-                    self.flush(f_name.as_str(), &p_num)?
-                }
-                (_, _) => return Err(Error::UnexpectedError)
-            };
-
-            // 获取缓冲引用
-            for i in self.list.iter_mut() {
-                if min_time == i.time {
-                    buffer_item = Some(i);
-                }
-            }
-
-            // 更新缓冲
-            match buffer_item {
+            // 淘汰最旧缓冲
+            let index = self.evict()?;
+            match self.list.iter_mut().nth(index) {
                 Some(item) => {
                     item.page = page;
                     item.time = SystemTime::now();
+                    item.dirty = true;
+                    item.pin_cnt = 0;
                     Ok(())
                 }
                 None => Err(Error::UnexpectedError)
@@ -366,9 +715,24 @@ impl Buffer for LRUBuffer {
     /// 强制刷新一个缓冲区的页面至磁盘
     /// 若页面不在缓冲区，则返回不在缓冲区异常
     fn flush(&mut self, file_name: &str, page_num: &usize) -> Result<(), Error> {
+        // 页号从1开始计数，0为幽灵页
+        if *page_num == 0 {
+            return Err(Error::InvalidPageNum);
+        }
         self.flush_internal(Some(file_name), Some(page_num), true)
     }
 
+    /// 固定一个页面，页面不在缓冲区时先载入
+    fn pin(&mut self, file_name: &str, page_num: usize) -> Result<(), Error> {
+        self.get_page(file_name, page_num)?;
+        self.update_pin(file_name, page_num, true)
+    }
+
+    /// 取消一次固定
+    fn unpin(&mut self, file_name: &str, page_num: usize) -> Result<(), Error> {
+        self.update_pin(file_name, page_num, false)
+    }
+
     // 获取第一个uuid
     fn get_first_uuid(&mut self) -> Result<Uuid, Error> {
         // 获取uuid所在的页
@@ -395,6 +759,10 @@ impl Buffer for LRUBuffer {
 
     fn insert_bytes(&mut self, file_name: &str, bytes: &[u8]) -> Result<Position, Error> {
         let len = bytes.len();
+        // 剩余空间需要严格大于数据长度，超过单页的数据扩充文件也放不下
+        if len >= self.page_size {
+            return Err(Error::ValueTooLarge);
+        }
         let raw_file = self.file.get_mut(file_name);
 
         let file = match raw_file {
@@ -404,28 +772,29 @@ impl Buffer for LRUBuffer {
 
         file.seek(SeekFrom::Start(0))?;
         let page_num = file.read_u32::<byteorder::BigEndian>()?;
-        let offset = 32 * INIT_FILE_PAGE_NUM;
-        for i in 0..page_num as u64 {
-            file.seek(SeekFrom::Start(offset as u64 + i * 32))?;
+        let data_pages = (page_num as usize).saturating_sub(INIT_FILE_PAGE_NUM);
+        for i in 0..data_pages {
+            file.seek(SeekFrom::Start(page_table_entry(i)))?;
             let res = file.read_u32::<byteorder::BigEndian>()?;
             if res > len as u32 {
                 // 找到插入位置并插入
-                file.seek(SeekFrom::Start((INIT_FILE_PAGE_NUM * PAGE_SIZE + i as usize * PAGE_SIZE + PAGE_SIZE - res as usize) as u64))?;
+                file.seek(SeekFrom::Start(((INIT_FILE_PAGE_NUM + i) * self.page_size + self.page_size - res as usize) as u64))?;
                 file.write_all(bytes)?;
 
                 // 更新文件头
-                file.seek(SeekFrom::Start(offset as u64 + i * 32))?;
+                file.seek(SeekFrom::Start(page_table_entry(i)))?;
                 file.write_u32::<byteorder::BigEndian>(res - len as u32)?;
+                update_page_checksum(file, i, self.page_size)?;
                 return Ok(Position {
                     file_name: String::from(file_name),
-                    page_num: i as usize,
-                    offset: PAGE_SIZE - res as usize,
+                    page_num: i,
+                    offset: self.page_size - res as usize,
                 });
             }
         }
         // 如果文件不够大
         // 填充文件
-        self.fill_up_to(file_name, 2 * page_num as usize)?;
+        self.fill_up_to(file_name, (2 * data_pages).max(1))?;
         // 重新插入
         self.insert_bytes(file_name, bytes)
     }
@@ -438,16 +807,17 @@ impl Buffer for LRUBuffer {
         };
         file.seek(SeekFrom::Start(0))?;
         let page_num = file.read_u32::<byteorder::BigEndian>()?;
-        if pos.page_num + INIT_FILE_PAGE_NUM > page_num as usize {
+        if pos.page_num + INIT_FILE_PAGE_NUM >= page_num as usize {
             return Err(Error::PageNumOutOfSize);
         }
-        file.seek(SeekFrom::Start(((1 + INIT_FILE_PAGE_NUM + pos.page_num) * 32) as u64))?;
+        // 与 insert_bytes 使用相同的页表项位置
+        file.seek(SeekFrom::Start(page_table_entry(pos.page_num)))?;
         let res = file.read_u32::<byteorder::BigEndian>()?;
-        if res as usize + pos.offset > PAGE_SIZE {
+        if res as usize + pos.offset > self.page_size {
             return Err(Error::UnexpectedError);
         }
-        let page = &mut [0; PAGE_SIZE];
-        file.seek(SeekFrom::Start((INIT_FILE_PAGE_NUM * PAGE_SIZE + pos.page_num * PAGE_SIZE) as u64))?;
+        let page = &mut vec![0; self.page_size];
+        file.seek(SeekFrom::Start((INIT_FILE_PAGE_NUM * self.page_size + pos.page_num * self.page_size) as u64))?;
         file.read_exact(page)?;
 
         Ok(page[pos.offset..pos.offset + size].to_vec())
@@ -457,6 +827,14 @@ impl Buffer for LRUBuffer {
         self.buff_size
     }
 
+    fn get_page_size(&self) -> usize {
+        self.page_size
+    }
+
+    fn get_meta_file_name(&self) -> String {
+        self.meta_file_name.clone()
+    }
+
     fn flush_file(&mut self, file_name: &str) -> Result<(), Error> {
         self.flush_internal(Some(file_name), None, true)
     }
@@ -464,15 +842,134 @@ impl Buffer for LRUBuffer {
     fn flush_all(&mut self) -> Result<(), Error> {
         self.flush_internal(None, None, true)
     }
+
+    /// 刷新并移出文件在缓冲区中的所有页面，然后关闭文件
+    fn remove_file(&mut self, file_name: &str) -> Result<(), Error> {
+        if !self.file.contains_key(file_name) {
+            return Err(Error::FileNotFound);
+        }
+        self.flush_internal(Some(file_name), None, false)?;
+        self.list = std::mem::take(&mut self.list)
+            .into_iter()
+            .filter(|i| i.page.file_name != file_name)
+            .collect();
+        self.len = self.list.len();
+        self.file.remove(file_name);
+        Ok(())
+    }
+
+    fn stats(&self) -> BufferStats {
+        self.stats
+    }
+
+    fn reset_stats(&mut self) {
+        self.stats = BufferStats::default();
+    }
+
+    fn read_reserved_page(&mut self, file_name: &str, index: usize) -> Result<Vec<u8>, Error> {
+        read_reserved(&mut self.file, file_name, index, self.page_size)
+    }
+
+    fn write_reserved_page(&mut self, file_name: &str, index: usize, data: &[u8]) -> Result<(), Error> {
+        if let Some(mut undo) = self.undo.take() {
+            let res = undo.record_reserved(self, file_name, index);
+            self.undo = Some(undo);
+            res?;
+        }
+        write_reserved(&mut self.file, file_name, index, data, self.page_size)
+    }
+
+    fn open_wal(&mut self, path: &Path) -> Result<(), Error> {
+        self.wal = Some(Wal::open(path)?);
+        Ok(())
+    }
+
+    fn recover(&mut self) -> Result<(), Error> {
+        match self.wal.as_mut() {
+            Some(wal) => wal.replay(&mut self.file, self.page_size),
+            None => Ok(())
+        }
+    }
+
+    fn checkpoint(&mut self) -> Result<(), Error> {
+        self.recover()?;
+        self.flush_all()?;
+        match self.wal.as_mut() {
+            Some(wal) => wal.truncate(),
+            None => Ok(())
+        }
+    }
+
+    fn no_sync_on_drop(&mut self) {
+        self.sync_on_drop = false;
+    }
+
+    fn verify_file(&mut self, file_name: &str) -> Result<Vec<usize>, Error> {
+        self.flush_file(file_name)?;
+        match self.file.get_mut(file_name) {
+            Some(file) => verify_pages(file, self.page_size),
+            None => Err(Error::FileNotFound)
+        }
+    }
+
+    fn prefetch(&mut self, file_name: &str, page_nums: &[usize]) -> Result<(), Error> {
+        for page_num in page_nums {
+            if !self.list.iter().any(|i| i.page.file_name == file_name && i.page.page_num == *page_num) {
+                self.get_page(file_name, *page_num)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn begin_transaction(&mut self) -> Result<(), Error> {
+        if self.undo.is_some() {
+            return Err(Error::TransactionState);
+        }
+        self.undo = Some(UndoLog::default());
+        Ok(())
+    }
+
+    fn commit_transaction(&mut self) -> Result<(), Error> {
+        if self.undo.take().is_none() {
+            return Err(Error::TransactionState);
+        }
+        self.flush_all()
+    }
+
+    fn rollback_transaction(&mut self) -> Result<(), Error> {
+        match self.undo.take() {
+            Some(undo) => undo.restore(self),
+            None => Err(Error::TransactionState)
+        }
+    }
+}
+
+impl Drop for LRUBuffer {
+    /// 析构时写回所有缓存的页面，析构无法返回错误，写回失败时直接忽略
+    fn drop(&mut self) {
+        if self.sync_on_drop {
+            let _ = self.flush_all();
+        }
+    }
 }
 
 /// 采用时钟算法实现的Buffer
 pub struct ClockBuffer {
     pub(crate) list: Vec<ClockBufferItem>,
     len: usize,
+    /// 命中、缺页、淘汰与写回次数的统计
+    stats: BufferStats,
     file: HashMap<String, File>,
     cur: usize,
     buff_size: usize,
+    /// 页大小，缓冲区管理的所有文件使用相同的页大小
+    page_size: usize,
+    /// 预写日志，没有启用时为 None
+    wal: Option<Wal>,
+    /// 进行中的事务的撤销日志，没有事务时为 None
+    undo: Option<UndoLog>,
+    /// 析构时是否把缓存的页面写回文件
+    sync_on_drop: bool,
     meta_file_name: String
 }
 
@@ -480,11 +977,28 @@ pub struct ClockBuffer {
 pub struct ClockBufferItem {
     pub(crate) page: Page,
     access: u8,
+    /// 页面载入后是否被修改过，只有脏页需要写回磁盘
+    dirty: bool,
+    /// 页面被固定的次数，被固定的页面不会被淘汰
+    pin_cnt: usize,
 }
 
 impl ClockBuffer {
     #[allow(dead_code)]
     pub(crate) fn new(buff_size: usize, meta_file_name: String) -> Result<ClockBuffer, Error> {
+        Self::with_page_size(buff_size, meta_file_name, PAGE_SIZE)
+    }
+
+    /// 使用指定页大小构造，用于测试小页面的边界情况或适配不同的块设备
+    /// 页大小小于 MIN_PAGE_SIZE 时返回 InvalidPageSize，缓冲页数小于 MIN_BUFFER_SIZE 时返回 InvalidBufferSize
+    /// B+树节点的布局依赖默认的 PAGE_SIZE，索引文件只能使用默认页大小
+    pub fn with_page_size(buff_size: usize, meta_file_name: String, page_size: usize) -> Result<ClockBuffer, Error> {
+        if page_size < MIN_PAGE_SIZE {
+            return Err(Error::InvalidPageSize);
+        }
+        if buff_size < MIN_BUFFER_SIZE {
+            return Err(Error::InvalidBufferSize);
+        }
         let path = Path::new(meta_file_name.as_str());
         let mut hashmap = HashMap::<String, File>::new();
         let fd = OpenOptions::new()
@@ -510,14 +1024,71 @@ impl ClockBuffer {
         let mut res = ClockBuffer {
             list: Vec::<ClockBufferItem>::new(),
             len: 0,
+            stats: BufferStats::default(),
             buff_size,
+            page_size,
             file: hashmap,
             cur: 0,
+            wal: None,
+            undo: None,
+            sync_on_drop: true,
             meta_file_name: meta_file_name.clone()
         };
         res.fill_up_to(meta_file_name.as_str(), METADATA_FILE_PAGE_NUM)?;
         Ok(res)
     }
+
+    /// 转动CLOCK指针选出牺牲者，并将其写回磁盘
+    /// 淘汰第一个遇到的access为0且没有被固定的页面，并将沿途access为1的页面置0
+    /// 所有页都被固定时返回 NoEvictablePage
+    fn evict(&mut self) -> Result<(), Error> {
+        let mut new_cur: Option<usize> = None;
+
+        // 最多转两圈，第一圈可能只是把标志置0
+        for i in 0..2 * self.buff_size {
+            let item = &mut self.list[(self.cur + i) % self.buff_size];
+            if item.pin_cnt > 0 {
+                continue;
+            }
+            // 将沿途为1的标志置0
+            if item.access == 1 {
+                item.access -= 1;
+            } else {
+                // 不为1的标志淘汰
+                new_cur = Some((self.cur + i) % self.buff_size);
+                break;
+            }
+        }
+        // 更新CLOCK指针
+        self.cur = match new_cur {
+            Some(ind) => ind,
+            None => return Err(Error::NoEvictablePage)
+        };
+        // 刷新被淘汰页
+        let prev_page = &self.list[self.cur].page;
+        let f_name = prev_page.file_name.clone();
+        let p_num = prev_page.page_num;
+        self.flush(f_name.as_str(), &p_num)?;
+        self.stats.evictions += 1;
+        Ok(())
+    }
+
+    /// 调整页面的固定次数，页面不在缓冲区时返回不在缓冲区异常
+    fn update_pin(&mut self, file_name: &str, page_num: usize, pin: bool) -> Result<(), Error> {
+        for i in self.list.iter_mut() {
+            if i.page.file_name == file_name && i.page.page_num == page_num {
+                if pin {
+                    i.pin_cnt += 1;
+                } else if i.pin_cnt == 0 {
+                    return Err(Error::UnexpectedError);
+                } else {
+                    i.pin_cnt -= 1;
+                }
+                return Ok(());
+            }
+        }
+        Err(Error::NotInBufferError)
+    }
 }
 
 impl Buffer for ClockBuffer {
@@ -529,20 +1100,18 @@ impl Buffer for ClockBuffer {
             .write(true)
             .open(path)?;
 
-        // 初始化文件大小
-        fd.seek(SeekFrom::Start(0))?;
-        fd.write_all(get_empty_data(INIT_FILE_PAGE_NUM * PAGE_SIZE).as_slice())?;
-
-        // 填充文件头配置信息
-        // 文件页数
-        fd.seek(SeekFrom::Start(0))?;
-        fd.write_u32::<byteorder::BigEndian>(INIT_FILE_PAGE_NUM as u32)?;
-
-        // 文件页表
-        fd.write_u32::<byteorder::BigEndian>(PAGE_SIZE as u32 - (32 * NON_DATA_PAGE + 32) as u32)?;
-        fd.write_u32::<byteorder::BigEndian>(PAGE_SIZE as u32)?;
-        fd.write_u32::<byteorder::BigEndian>(PAGE_SIZE as u32)?;
-        fd.write_u32::<byteorder::BigEndian>(PAGE_SIZE as u32)?;
+        // 已有文件头的文件直接加载，不重新初始化
+        if fd.metadata()?.len() < (INIT_FILE_PAGE_NUM * self.page_size) as u64 {
+            // 初始化文件大小
+            fd.seek(SeekFrom::Start(0))?;
+            fd.write_all(get_empty_data(INIT_FILE_PAGE_NUM * self.page_size).as_slice())?;
+
+            // 填充文件头配置信息
+            // 文件页数
+            fd.seek(SeekFrom::Start(0))?;
+            fd.write_u32::<byteorder::BigEndian>(INIT_FILE_PAGE_NUM as u32)?;
+            // 新文件没有数据页，页表为空
+        }
 
         // 获取文件名
         let raw_file_name = path.to_str();
@@ -564,26 +1133,27 @@ impl Buffer for ClockBuffer {
             Some(file) => {
                 file.seek(SeekFrom::Start(0))?;
                 let page_num = file.read_u32::<byteorder::BigEndian>()?;
-                if PAGE_SIZE < (INIT_FILE_PAGE_NUM + num_of_page + 1) * 32 {
+                if self.page_size < (INIT_FILE_PAGE_NUM + num_of_page + 1) * PAGE_TABLE_ENTRY_SIZE {
                     return Err(Error::PageNumOutOfSize);
                 }
+                // 文件已经足够大，无需填充
+                if INIT_FILE_PAGE_NUM + num_of_page <= page_num as usize {
+                    return Ok(());
+                }
 
                 // 填充文件
-                file.seek(SeekFrom::Start((page_num as usize * PAGE_SIZE) as u64))?;
-                file.write_all(get_empty_data((num_of_page - page_num as usize + INIT_FILE_PAGE_NUM) * PAGE_SIZE).as_slice())?;
+                file.seek(SeekFrom::Start((page_num as usize * self.page_size) as u64))?;
+                file.write_all(get_empty_data((num_of_page + INIT_FILE_PAGE_NUM - page_num as usize) * self.page_size).as_slice())?;
 
                 // 更新文件头
                 file.seek(SeekFrom::Start(0))?;
                 file.write_u32::<byteorder::BigEndian>((INIT_FILE_PAGE_NUM + num_of_page) as u32)?;
 
-                // 第一页占用空间
-                file.write_u32::<byteorder::BigEndian>((PAGE_SIZE - (INIT_FILE_PAGE_NUM + num_of_page + 1) * 32) as u32)?;
-
-
-                file.seek(SeekFrom::Start((1 + page_num as u64) * 32))?;
-                // 其余页占用空间
-                for _i in 1..=num_of_page - page_num as usize + INIT_FILE_PAGE_NUM {
-                    file.write_u32::<byteorder::BigEndian>(PAGE_SIZE as u32)?;
+                // 新数据页的页表项，剩余空间为整页
+                let first = (page_num as usize).saturating_sub(INIT_FILE_PAGE_NUM);
+                for i in first..num_of_page {
+                    file.seek(SeekFrom::Start(page_table_entry(i)))?;
+                    file.write_u32::<byteorder::BigEndian>(self.page_size as u32)?;
                 }
 
                 Ok(())
@@ -598,71 +1168,75 @@ impl Buffer for ClockBuffer {
     /// 若缓冲区已满，则淘汰第一个遇到的access为0的页面，并将沿途access为1的页面置0，
     /// 新加载的页面的access置1
     fn get_page(&mut self, file_name: &str, page_num: usize) -> Result<Page, Error> {
+        // 页号从1开始计数，0为幽灵页
+        if page_num == 0 {
+            return Err(Error::InvalidPageNum);
+        }
 
         // 查询缓冲区
         for i in self.list.iter_mut() {
             if i.page.file_name == file_name && i.page.page_num == page_num {
                 i.access = 1;
-                return Ok(Page::new(i.page.get_data(), file_name, page_num));
+                self.stats.hits += 1;
+                return Ok(Page::from_vec(i.page.get_bytes().to_vec(), file_name, page_num));
             }
         }
 
+        self.stats.misses += 1;
         // 获取磁盘页数据
-        let mut page: [u8; PAGE_SIZE] = [0x00; PAGE_SIZE];
-        let file = self.file.get_mut(file_name).unwrap();
-        file.seek(SeekFrom::Start(((page_num - 1) * PAGE_SIZE + NON_DATA_PAGE * PAGE_SIZE) as u64))?;
+        let mut page = vec![0x00; self.page_size];
+        let file = match self.file.get_mut(file_name) {
+            Some(file) => file,
+            None => return Err(Error::FileNotFound)
+        };
+        file.seek(SeekFrom::Start(((page_num - 1) * self.page_size + NON_DATA_PAGE * self.page_size) as u64))?;
         file.read_exact(&mut page)?;
 
         // 更新缓冲
         if self.len < self.buff_size {
             self.len += 1;
             self.list.push(ClockBufferItem {
-                page: Page::new(page, file_name, page_num),
+                page: Page::from_vec(page.clone(), file_name, page_num),
                 access: 1,
+                dirty: false,
+                pin_cnt: 0,
             });
         } else {
-            let mut new_cur: Option<usize> = None;
-
-            // 循环遍历缓冲区
-            for i in 0..self.buff_size {
-                let item = &mut self.list[(self.cur + i) % self.buff_size];
-                // 将沿途为1的标志置0
-                if item.access == 1 {
-                    item.access -= 1;
-                } else {
-                    // 不为1的标志淘汰
-                    new_cur = Some((self.cur + i) % self.buff_size);
-                    break;
-                }
-            }
-            // 更新CLOCK指针
-            self.cur = match new_cur {
-                Some(ind) => {
-                    ind
-                }
-                None => self.cur
-            };
-            // 刷新被淘汰页
-            let prev_page = &self.list[self.cur].page;
-            let f_name = prev_page.file_name.clone();
-            let p_num = prev_page.page_num;
-            self.flush(f_name.as_str(), &p_num)?;
+            // 淘汰并刷新一个页面
+            self.evict()?;
             // 更新缓冲
             self.list[self.cur] = ClockBufferItem {
-                page: Page::new(page, file_name, page_num),
+                page: Page::from_vec(page.clone(), file_name, page_num),
                 access: 1,
+                dirty: false,
+                pin_cnt: 0,
             };
         }
 
-        Ok(Page::new(page, file_name, page_num))
+        Ok(Page::from_vec(page.clone(), file_name, page_num))
     }
 
     /// 向缓冲区写入一个页面, 需要确保page.page_num正确
     fn write_page(&mut self, page: Page) -> Result<(), Error> {
+        // 页号从1开始计数，0为幽灵页
+        if page.page_num == 0 {
+            return Err(Error::InvalidPageNum);
+        }
+        // 事务中先记下页面原来的内容
+        if let Some(mut undo) = self.undo.take() {
+            let res = undo.record_page(self, &page.file_name, page.page_num);
+            self.undo = Some(undo);
+            res?;
+        }
+        // 先写日志
+        if let Some(wal) = self.wal.as_mut() {
+            wal.append(&page)?;
+        }
         // 查询缓冲
         for i in &mut self.list {
-            if i.page.page_num == page.page_num {
+            if i.page.file_name == page.file_name && i.page.page_num == page.page_num {
                 i.page = page;
+                i.dirty = true;
                 return Ok(());
             }
         }
@@ -673,39 +1247,19 @@ impl Buffer for ClockBuffer {
             self.list.push(ClockBufferItem {
                 page,
                 access: 1,
+                dirty: true,
+                pin_cnt: 0,
             });
             Ok(())
         } else {
-            let mut new_cur: Option<usize> = None;
-
-            // 循环遍历缓冲区
-            for i in 0..self.buff_size {
-                let item = &mut self.list[(self.cur + i) % self.buff_size];
-                // 将沿途标志置0
-                if item.access == 1 {
-                    item.access -= 1;
-                } else {
-                    // 如果有0标志则淘汰
-                    new_cur = Some((self.cur + i) % self.buff_size);
-                    break;
-                }
-            }
-            // 更新CLOCK指针
-            self.cur = match new_cur {
-                Some(ind) => {
-                    ind
-                }
-                None => self.cur
-            };
-            // 刷新旧页
-            let prev_page = &self.list[self.cur].page;
-            let f_name = prev_page.file_name.clone();
-            let p_num = prev_page.page_num;
-            self.flush(f_name.as_str(), &p_num)?;
+            // 淘汰并刷新旧页
+            self.evict()?;
             // 更新缓冲
             self.list[self.cur] = ClockBufferItem {
                 page,
                 access: 1,
+                dirty: true,
+                pin_cnt: 0,
             };
             Ok(())
         }
@@ -714,17 +1268,37 @@ impl Buffer for ClockBuffer {
     /// 强制刷新一个缓冲区的页面至磁盘
     /// 若页面不在缓冲区，则返回不在缓冲区异常
     fn flush(&mut self, file_name: &str, page_num: &usize) -> Result<(), Error> {
-        for i in self.list.iter() {
+        // 页号从1开始计数，0为幽灵页
+        if *page_num == 0 {
+            return Err(Error::InvalidPageNum);
+        }
+        for i in self.list.iter_mut() {
             if i.page.file_name == file_name && i.page.page_num == *page_num {
+                // 干净的页面与磁盘一致，无需写回
+                if !i.dirty {
+                    return Ok(());
+                }
                 let file = self.file.get_mut(file_name).unwrap();
-                file.seek(SeekFrom::Start(((page_num - 1) * PAGE_SIZE + NON_DATA_PAGE * PAGE_SIZE) as u64))?;
-                file.write_all(&i.page.get_data())?;
+                write_data_page(file, *page_num, i.page.get_bytes(), self.page_size)?;
+                i.dirty = false;
+                self.stats.writes += 1;
                 return Ok(());
             }
         }
         Err(Error::NotInBufferError)
     }
 
+    /// 固定一个页面，页面不在缓冲区时先载入
+    fn pin(&mut self, file_name: &str, page_num: usize) -> Result<(), Error> {
+        self.get_page(file_name, page_num)?;
+        self.update_pin(file_name, page_num, true)
+    }
+
+    /// 取消一次固定
+    fn unpin(&mut self, file_name: &str, page_num: usize) -> Result<(), Error> {
+        self.update_pin(file_name, page_num, false)
+    }
+
     fn get_first_uuid(&mut self) -> Result<Uuid, Error> {
         let page = self.get_page(self.meta_file_name.clone().as_str(), METADATA_FILE_PAGE_NUM)?;
         let bytes = page.get_ptr_from_offset(FIRST_UUID_OFFSET, 16);
@@ -744,6 +1318,10 @@ impl Buffer for ClockBuffer {
 
     fn insert_bytes(&mut self, file_name: &str, bytes: &[u8]) -> Result<Position, Error> {
         let len = bytes.len();
+        // 剩余空间需要严格大于数据长度，超过单页的数据扩充文件也放不下
+        if len >= self.page_size {
+            return Err(Error::ValueTooLarge);
+        }
         let raw_file = self.file.get_mut(file_name);
 
         let file = match raw_file {
@@ -753,28 +1331,29 @@ impl Buffer for ClockBuffer {
 
         file.seek(SeekFrom::Start(0))?;
         let page_num = file.read_u32::<byteorder::BigEndian>()?;
-        let offset = 32 * INIT_FILE_PAGE_NUM;
-        for i in 0..page_num as u64 {
-            file.seek(SeekFrom::Start(offset as u64 + i * 32))?;
+        let data_pages = (page_num as usize).saturating_sub(INIT_FILE_PAGE_NUM);
+        for i in 0..data_pages {
+            file.seek(SeekFrom::Start(page_table_entry(i)))?;
             let res = file.read_u32::<byteorder::BigEndian>()?;
             if res > len as u32 {
                 // 找到插入位置并插入
-                file.seek(SeekFrom::Start((INIT_FILE_PAGE_NUM * PAGE_SIZE + i as usize * PAGE_SIZE + PAGE_SIZE - res as usize) as u64))?;
+                file.seek(SeekFrom::Start(((INIT_FILE_PAGE_NUM + i) * self.page_size + self.page_size - res as usize) as u64))?;
                 file.write_all(bytes)?;
 
                 // 更新文件头
-                file.seek(SeekFrom::Start(offset as u64 + i * 32))?;
+                file.seek(SeekFrom::Start(page_table_entry(i)))?;
                 file.write_u32::<byteorder::BigEndian>(res - len as u32)?;
+                update_page_checksum(file, i, self.page_size)?;
                 return Ok(Position {
                     file_name: String::from(file_name),
-                    page_num: i as usize,
-                    offset: PAGE_SIZE - res as usize,
+                    page_num: i,
+                    offset: self.page_size - res as usize,
                 });
             }
         }
         // 如果文件不够大
         // 填充文件
-        self.fill_up_to(file_name, 2 * page_num as usize)?;
+        self.fill_up_to(file_name, (2 * data_pages).max(1))?;
         // 重新插入
         self.insert_bytes(file_name, bytes)
     }
@@ -787,16 +1366,17 @@ impl Buffer for ClockBuffer {
         };
         file.seek(SeekFrom::Start(0))?;
         let page_num = file.read_u32::<byteorder::BigEndian>()?;
-        if pos.page_num + INIT_FILE_PAGE_NUM > page_num as usize {
+        if pos.page_num + INIT_FILE_PAGE_NUM >= page_num as usize {
             return Err(Error::PageNumOutOfSize);
         }
-        file.seek(SeekFrom::Start(((1 + INIT_FILE_PAGE_NUM + pos.page_num) * 32) as u64))?;
+        // 与 insert_bytes 使用相同的页表项位置
+        file.seek(SeekFrom::Start(page_table_entry(pos.page_num)))?;
         let res = file.read_u32::<byteorder::BigEndian>()?;
-        if res as usize + pos.offset > PAGE_SIZE {
+        if res as usize + pos.offset > self.page_size {
             return Err(Error::UnexpectedError);
         }
-        let page = &mut [0; PAGE_SIZE];
-        file.seek(SeekFrom::Start((INIT_FILE_PAGE_NUM * PAGE_SIZE + pos.page_num * PAGE_SIZE) as u64))?;
+        let page = &mut vec![0; self.page_size];
+        file.seek(SeekFrom::Start((INIT_FILE_PAGE_NUM * self.page_size + pos.page_num * self.page_size) as u64))?;
         file.read_exact(page)?;
 
         Ok(page[pos.offset..pos.offset + size].to_vec())
@@ -806,24 +1386,730 @@ impl Buffer for ClockBuffer {
         self.buff_size
     }
 
+    fn get_page_size(&self) -> usize {
+        self.page_size
+    }
+
+    fn get_meta_file_name(&self) -> String {
+        self.meta_file_name.clone()
+    }
+
 
     fn flush_file(&mut self, file_name: &str) -> Result<(), Error> {
-        for i in self.list.iter() {
-            if i.page.file_name == file_name {
+        for index in write_order(self.list.iter().map(|i| &i.page)) {
+            let i = &mut self.list[index];
+            if i.page.file_name == file_name && i.dirty {
                 let file = self.file.get_mut(file_name).unwrap();
-                file.seek(SeekFrom::Start(((i.page.page_num - 1) * PAGE_SIZE + NON_DATA_PAGE * PAGE_SIZE) as u64))?;
-                file.write_all(&i.page.get_data())?;
+                write_data_page(file, i.page.page_num, i.page.get_bytes(), self.page_size)?;
+                i.dirty = false;
+                self.stats.writes += 1;
             }
         }
         Ok(())
     }
 
     fn flush_all(&mut self) -> Result<(), Error> {
-        for i in self.list.iter() {
+        for index in write_order(self.list.iter().map(|i| &i.page)) {
+            let i = &mut self.list[index];
+            if !i.dirty {
+                continue;
+            }
             let file = self.file.get_mut(i.page.file_name.as_str()).unwrap();
-            file.seek(SeekFrom::Start(((i.page.page_num - 1) * PAGE_SIZE + NON_DATA_PAGE * PAGE_SIZE) as u64))?;
-            file.write_all(&i.page.get_data())?;
+            write_data_page(file, i.page.page_num, i.page.get_bytes(), self.page_size)?;
+            i.dirty = false;
+            self.stats.writes += 1;
         }
         Ok(())
     }
+
+    /// 刷新并移出文件在缓冲区中的所有页面，然后关闭文件
+    fn remove_file(&mut self, file_name: &str) -> Result<(), Error> {
+        if !self.file.contains_key(file_name) {
+            return Err(Error::FileNotFound);
+        }
+        self.flush_file(file_name)?;
+        self.list.retain(|i| i.page.file_name != file_name);
+        self.len = self.list.len();
+        if self.cur >= self.len {
+            self.cur = 0;
+        }
+        self.file.remove(file_name);
+        Ok(())
+    }
+
+    fn stats(&self) -> BufferStats {
+        self.stats
+    }
+
+    fn reset_stats(&mut self) {
+        self.stats = BufferStats::default();
+    }
+
+    fn read_reserved_page(&mut self, file_name: &str, index: usize) -> Result<Vec<u8>, Error> {
+        read_reserved(&mut self.file, file_name, index, self.page_size)
+    }
+
+    fn write_reserved_page(&mut self, file_name: &str, index: usize, data: &[u8]) -> Result<(), Error> {
+        if let Some(mut undo) = self.undo.take() {
+            let res = undo.record_reserved(self, file_name, index);
+            self.undo = Some(undo);
+            res?;
+        }
+        write_reserved(&mut self.file, file_name, index, data, self.page_size)
+    }
+
+    fn open_wal(&mut self, path: &Path) -> Result<(), Error> {
+        self.wal = Some(Wal::open(path)?);
+        Ok(())
+    }
+
+    fn recover(&mut self) -> Result<(), Error> {
+        match self.wal.as_mut() {
+            Some(wal) => wal.replay(&mut self.file, self.page_size),
+            None => Ok(())
+        }
+    }
+
+    fn checkpoint(&mut self) -> Result<(), Error> {
+        self.recover()?;
+        self.flush_all()?;
+        match self.wal.as_mut() {
+            Some(wal) => wal.truncate(),
+            None => Ok(())
+        }
+    }
+
+    fn no_sync_on_drop(&mut self) {
+        self.sync_on_drop = false;
+    }
+
+    fn verify_file(&mut self, file_name: &str) -> Result<Vec<usize>, Error> {
+        self.flush_file(file_name)?;
+        match self.file.get_mut(file_name) {
+            Some(file) => verify_pages(file, self.page_size),
+            None => Err(Error::FileNotFound)
+        }
+    }
+
+    fn prefetch(&mut self, file_name: &str, page_nums: &[usize]) -> Result<(), Error> {
+        for page_num in page_nums {
+            if !self.list.iter().any(|i| i.page.file_name == file_name && i.page.page_num == *page_num) {
+                self.get_page(file_name, *page_num)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn begin_transaction(&mut self) -> Result<(), Error> {
+        if self.undo.is_some() {
+            return Err(Error::TransactionState);
+        }
+        self.undo = Some(UndoLog::default());
+        Ok(())
+    }
+
+    fn commit_transaction(&mut self) -> Result<(), Error> {
+        if self.undo.take().is_none() {
+            return Err(Error::TransactionState);
+        }
+        self.flush_all()
+    }
+
+    fn rollback_transaction(&mut self) -> Result<(), Error> {
+        match self.undo.take() {
+            Some(undo) => undo.restore(self),
+            None => Err(Error::TransactionState)
+        }
+    }
+}
+
+impl Drop for ClockBuffer {
+    /// 析构时写回所有缓存的页面，析构无法返回错误，写回失败时直接忽略
+    fn drop(&mut self) {
+        if self.sync_on_drop {
+            let _ = self.flush_all();
+        }
+    }
+}
+
+/// PolicyBuffer 的淘汰策略
+/// 文件、预写日志、保留页和事务都由 PolicyBuffer 处理，策略只决定记录哪些访问、淘汰哪个页面
+pub trait EvictionPolicy: Default + Send {
+    /// 第 index 项被载入或访问后调用
+    fn access(&mut self, list: &mut VecDeque<PolicyBufferItem>, index: usize);
+
+    /// 缓冲区已满时选出要淘汰的项，被固定的页面不能淘汰，全部被固定时返回 None
+    fn victim(&self, list: &VecDeque<PolicyBufferItem>) -> Option<usize>;
+}
+
+/// 先进先出：淘汰最早载入的页面，不考虑最近是否访问过
+#[derive(Default)]
+pub struct FIFOPolicy;
+
+impl EvictionPolicy for FIFOPolicy {
+    fn access(&mut self, _list: &mut VecDeque<PolicyBufferItem>, _index: usize) {}
+
+    /// 页面按载入顺序排在队列中，淘汰最靠前的没有被固定的页面
+    fn victim(&self, list: &VecDeque<PolicyBufferItem>) -> Option<usize> {
+        list.iter().position(|i| i.pin_cnt == 0)
+    }
+}
+
+/// 最不经常使用：淘汰访问次数最少的页面，次数相同时淘汰最早载入的
+/// 访问次数定期减半，过去访问很多、最近不再访问的页面最终也会被淘汰
+#[derive(Default)]
+pub struct LFUPolicy {
+    /// 上次衰减之后的访问次数，达到 LFU_DECAY_INTERVAL 时所有页面的访问次数减半
+    accesses: usize,
+}
+
+impl EvictionPolicy for LFUPolicy {
+    /// 增加页面的访问次数，每 LFU_DECAY_INTERVAL 次访问将所有页面的访问次数减半
+    fn access(&mut self, list: &mut VecDeque<PolicyBufferItem>, index: usize) {
+        list[index].count += 1;
+        self.accesses += 1;
+        if self.accesses >= LFU_DECAY_INTERVAL {
+            self.accesses = 0;
+            for i in list.iter_mut() {
+                i.count /= 2;
+            }
+        }
+    }
+
+    /// min_by_key 在次数相同时返回第一个，即最早载入的页面
+    fn victim(&self, list: &VecDeque<PolicyBufferItem>) -> Option<usize> {
+        list.iter().enumerate().filter(|(_, i)| i.pin_cnt == 0).min_by_key(|(_, i)| i.count).map(|(index, _)| index)
+    }
+}
+
+/// 先进先出算法实现的Buffer
+pub type FIFOBuffer = PolicyBuffer<FIFOPolicy>;
+
+/// 最不经常使用算法实现的Buffer
+pub type LFUBuffer = PolicyBuffer<LFUPolicy>;
+
+/// 由淘汰策略 P 决定换出哪个页面的Buffer
+pub struct PolicyBuffer<P: EvictionPolicy> {
+    pub(crate) list: VecDeque<PolicyBufferItem>,
+    /// 淘汰策略
+    policy: P,
+    buff_size: usize,
+    /// 页大小，缓冲区管理的所有文件使用相同的页大小
+    page_size: usize,
+    /// 命中、缺页、淘汰与写回次数的统计
+    stats: BufferStats,
+    file: HashMap<String, File>,
+    /// 预写日志，没有启用时为 None
+    wal: Option<Wal>,
+    /// 进行中的事务的撤销日志，没有事务时为 None
+    undo: Option<UndoLog>,
+    /// 析构时是否把缓存的页面写回文件
+    sync_on_drop: bool,
+    meta_file_name: String
+}
+
+/// PolicyBuffer中的每一项
+pub struct PolicyBufferItem {
+    pub(crate) page: Page,
+    /// 页面被 get_page 和 write_page 访问的次数，只有 LFUPolicy 使用
+    count: usize,
+    /// 页面载入后是否被修改过，只有脏页需要写回磁盘
+    dirty: bool,
+    /// 页面被固定的次数，被固定的页面不会被淘汰
+    pin_cnt: usize,
+}
+
+impl<P: EvictionPolicy> PolicyBuffer<P> {
+    /// PolicyBuffer的构造方法
+    pub fn new(buff_size: usize, meta_file_name: String) -> Result<PolicyBuffer<P>, Error> {
+        Self::with_page_size(buff_size, meta_file_name, PAGE_SIZE)
+    }
+
+    /// 使用指定页大小构造，用于测试小页面的边界情况或适配不同的块设备
+    /// 页大小小于 MIN_PAGE_SIZE 时返回 InvalidPageSize，缓冲页数小于 MIN_BUFFER_SIZE 时返回 InvalidBufferSize
+    /// B+树节点的布局依赖默认的 PAGE_SIZE，索引文件只能使用默认页大小
+    pub fn with_page_size(buff_size: usize, meta_file_name: String, page_size: usize) -> Result<PolicyBuffer<P>, Error> {
+        if page_size < MIN_PAGE_SIZE {
+            return Err(Error::InvalidPageSize);
+        }
+        if buff_size < MIN_BUFFER_SIZE {
+            return Err(Error::InvalidBufferSize);
+        }
+        let path = Path::new(meta_file_name.as_str());
+        let mut hashmap = HashMap::<String, File>::new();
+        let fd = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path);
+        match fd {
+            Ok(file) => {
+                hashmap.insert(meta_file_name.clone(), file);
+            }
+            Err(_) => {
+                let mut new_metadata = OpenOptions::new()
+                    .create(true)
+                    .truncate(false)
+                    .read(true)
+                    .write(true)
+                    .open(path)?;
+                new_metadata.seek(SeekFrom::Start(0))?;
+                new_metadata.write_u32::<byteorder::BigEndian>(0)?;
+                new_metadata.flush()?;
+                hashmap.insert(meta_file_name.clone(), new_metadata);
+            }
+        }
+        let mut res = PolicyBuffer {
+            list: VecDeque::<PolicyBufferItem>::new(),
+            policy: P::default(),
+            buff_size,
+            page_size,
+            stats: BufferStats::default(),
+            file: hashmap,
+            wal: None,
+            undo: None,
+            sync_on_drop: true,
+            meta_file_name: meta_file_name.clone()
+        };
+        res.fill_up_to(meta_file_name.as_str(), METADATA_FILE_PAGE_NUM)?;
+        Ok(res)
+    }
+
+    /// 将页面写回磁盘
+    fn flush_page(&mut self, page: &Page) -> Result<(), Error> {
+        let file = match self.file.get_mut(page.file_name.as_str()) {
+            Some(file) => file,
+            None => return Err(Error::FileNotFound)
+        };
+        write_data_page(file, page.page_num, page.get_bytes(), self.page_size)?;
+        self.stats.writes += 1;
+        Ok(())
+    }
+
+    /// 将缓冲区中第 index 项写回磁盘，干净的页面直接跳过
+    fn flush_item(&mut self, index: usize) -> Result<(), Error> {
+        let page = match self.list.get(index) {
+            Some(item) if item.dirty => Page::from_vec(item.page.get_bytes().to_vec(), item.page.file_name.as_str(), item.page.page_num),
+            _ => return Ok(())
+        };
+        self.flush_page(&page)?;
+        self.list[index].dirty = false;
+        Ok(())
+    }
+
+    /// 将页面放入缓冲区队尾，并记为一次访问
+    /// 如果缓冲区已满，先刷新并淘汰策略选出的页面
+    fn push_page(&mut self, page: Page, dirty: bool) -> Result<(), Error> {
+        if self.list.len() >= self.buff_size {
+            let index = match self.policy.victim(&self.list) {
+                Some(index) => index,
+                None => return Err(Error::NoEvictablePage)
+            };
+            self.flush_item(index)?;
+            self.list.remove(index);
+            self.stats.evictions += 1;
+        }
+        self.list.push_back(PolicyBufferItem { page, count: 0, dirty, pin_cnt: 0 });
+        let index = self.list.len() - 1;
+        self.policy.access(&mut self.list, index);
+        Ok(())
+    }
+
+    /// 调整页面的固定次数，页面不在缓冲区时返回不在缓冲区异常
+    fn update_pin(&mut self, file_name: &str, page_num: usize, pin: bool) -> Result<(), Error> {
+        for i in self.list.iter_mut() {
+            if i.page.file_name == file_name && i.page.page_num == page_num {
+                if pin {
+                    i.pin_cnt += 1;
+                } else if i.pin_cnt == 0 {
+                    return Err(Error::UnexpectedError);
+                } else {
+                    i.pin_cnt -= 1;
+                }
+                return Ok(());
+            }
+        }
+        Err(Error::NotInBufferError)
+    }
+}
+
+impl<P: EvictionPolicy> Buffer for PolicyBuffer<P> {
+    fn add_file(&mut self, path: &Path) -> Result<(), Error> {
+        // 创建文件
+        let mut fd = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(path)?;
+
+        // 已有文件头的文件直接加载，不重新初始化
+        if fd.metadata()?.len() < (INIT_FILE_PAGE_NUM * self.page_size) as u64 {
+            // 初始化文件大小
+            fd.seek(SeekFrom::Start(0))?;
+            fd.write_all(get_empty_data(INIT_FILE_PAGE_NUM * self.page_size).as_slice())?;
+
+            // 填充文件头配置信息
+            // 文件页数
+            fd.seek(SeekFrom::Start(0))?;
+            fd.write_u32::<byteorder::BigEndian>(INIT_FILE_PAGE_NUM as u32)?;
+            // 新文件没有数据页，页表为空
+        }
+
+        // 获取文件名
+        let raw_file_name = path.to_str();
+        let file_name = match raw_file_name {
+            Some(file_name) => file_name,
+            None => return Err(Error::FileNotFound)
+        };
+
+        // 文件保存在哈希表中
+        self.file.insert(String::from(file_name), fd);
+        Ok(())
+    }
+
+    /// 向文件填充占位符至指定页数
+    fn fill_up_to(&mut self, file_name: &str, num_of_page: usize) -> Result<(), Error> {
+        // 查询文件fd
+        let raw_file = self.file.get_mut(file_name);
+        match raw_file {
+            Some(file) => {
+                file.seek(SeekFrom::Start(0))?;
+                let page_num = file.read_u32::<byteorder::BigEndian>()?;
+                if self.page_size < (INIT_FILE_PAGE_NUM + num_of_page + 1) * PAGE_TABLE_ENTRY_SIZE {
+                    return Err(Error::PageNumOutOfSize);
+                }
+                // 文件已经足够大，无需填充
+                if INIT_FILE_PAGE_NUM + num_of_page <= page_num as usize {
+                    return Ok(());
+                }
+
+                // 填充文件
+                file.seek(SeekFrom::Start((page_num as usize * self.page_size) as u64))?;
+                let siz = (num_of_page + INIT_FILE_PAGE_NUM - page_num as usize) * self.page_size;
+                file.write_all(get_empty_data(siz).as_slice())?;
+
+                // 更新文件头
+                file.seek(SeekFrom::Start(0))?;
+                file.write_u32::<byteorder::BigEndian>((INIT_FILE_PAGE_NUM + num_of_page) as u32)?;
+
+                // 新数据页的页表项，剩余空间为整页
+                let first = (page_num as usize).saturating_sub(INIT_FILE_PAGE_NUM);
+                for i in first..num_of_page {
+                    file.seek(SeekFrom::Start(page_table_entry(i)))?;
+                    file.write_u32::<byteorder::BigEndian>(self.page_size as u32)?;
+                }
+
+                Ok(())
+            }
+            None => Err(Error::FileNotFound)
+        }
+    }
+
+    /// 获取一个页
+    /// 如果缓冲区有，直接从缓冲区拿，并告知淘汰策略这次访问
+    /// 否则，加载一个磁盘页面到队尾
+    fn get_page(&mut self, file_name: &str, page_num: usize) -> Result<Page, Error> {
+        // 页号从1开始计数，0为幽灵页
+        if page_num == 0 {
+            return Err(Error::InvalidPageNum);
+        }
+        // 查询缓冲
+        if let Some(index) = self.list.iter().position(|i| i.page.file_name == file_name && i.page.page_num == page_num) {
+            self.stats.hits += 1;
+            self.policy.access(&mut self.list, index);
+            return Ok(Page::from_vec(self.list[index].page.get_bytes().to_vec(), file_name, page_num));
+        }
+
+        self.stats.misses += 1;
+        // 获取对应页数据
+        let mut page = vec![0x00; self.page_size];
+        let file = match self.file.get_mut(file_name) {
+            Some(file) => file,
+            None => return Err(Error::FileNotFound)
+        };
+        file.seek(SeekFrom::Start(((page_num - 1) * self.page_size + NON_DATA_PAGE * self.page_size) as u64))?;
+        file.read_exact(&mut page)?;
+
+        // 更新缓冲
+        self.push_page(Page::from_vec(page.clone(), file_name, page_num), false)?;
+        Ok(Page::from_vec(page.clone(), file_name, page_num))
+    }
+
+    /// 向缓冲区写入一个页面
+    fn write_page(&mut self, page: Page) -> Result<(), Error> {
+        // 页号从1开始计数，0为幽灵页
+        if page.page_num == 0 {
+            return Err(Error::InvalidPageNum);
+        }
+        // 事务中先记下页面原来的内容
+        if let Some(mut undo) = self.undo.take() {
+            let res = undo.record_page(self, &page.file_name, page.page_num);
+            self.undo = Some(undo);
+            res?;
+        }
+        // 先写日志
+        if let Some(wal) = self.wal.as_mut() {
+            wal.append(&page)?;
+        }
+        // 查询缓冲
+        if let Some(index) = self.list.iter().position(|i| i.page.file_name == page.file_name && i.page.page_num == page.page_num) {
+            self.list[index].page = page;
+            self.list[index].dirty = true;
+            self.policy.access(&mut self.list, index);
+            return Ok(());
+        }
+
+        // 缓冲没命中，放入队尾
+        self.push_page(page, true)
+    }
+
+    /// 强制刷新一个缓冲区的页面至磁盘
+    /// 若页面不在缓冲区，则返回不在缓冲区异常
+    fn flush(&mut self, file_name: &str, page_num: &usize) -> Result<(), Error> {
+        // 页号从1开始计数，0为幽灵页
+        if *page_num == 0 {
+            return Err(Error::InvalidPageNum);
+        }
+        let index = self.list
+            .iter()
+            .position(|i| i.page.file_name == file_name && i.page.page_num == *page_num);
+        match index {
+            Some(index) => self.flush_item(index),
+            None => Err(Error::NotInBufferError)
+        }
+    }
+
+    /// 固定一个页面，页面不在缓冲区时先载入
+    fn pin(&mut self, file_name: &str, page_num: usize) -> Result<(), Error> {
+        self.get_page(file_name, page_num)?;
+        self.update_pin(file_name, page_num, true)
+    }
+
+    /// 取消一次固定
+    fn unpin(&mut self, file_name: &str, page_num: usize) -> Result<(), Error> {
+        self.update_pin(file_name, page_num, false)
+    }
+
+    // 获取第一个uuid
+    fn get_first_uuid(&mut self) -> Result<Uuid, Error> {
+        // 获取uuid所在的页
+        let page = self.get_page(self.meta_file_name.clone().as_str(), METADATA_FILE_PAGE_NUM)?;
+        // 获取对应字节数组
+        let bytes = page.get_ptr_from_offset(FIRST_UUID_OFFSET, 16);
+        let uuid = Uuid::from_slice(bytes);
+        match uuid {
+            Ok(uuid) => Ok(uuid),
+            _ => Err(Error::UnexpectedError)
+        }
+    }
+
+    // 更新第一个uuid
+    fn update_first_uuid(&mut self, uuid: Uuid) -> Result<(), Error> {
+        // 获取uuid所在页
+        let mut page = self.get_page(self.meta_file_name.clone().as_str(), METADATA_FILE_PAGE_NUM)?;
+        // 写入对应的字节数组
+        page.write_bytes_at_offset(uuid.as_bytes(), FIRST_UUID_OFFSET, 16)?;
+        // 将页写回的缓冲池
+        self.write_page(page)?;
+        Ok(())
+    }
+
+    fn insert_bytes(&mut self, file_name: &str, bytes: &[u8]) -> Result<Position, Error> {
+        let len = bytes.len();
+        // 剩余空间需要严格大于数据长度，超过单页的数据扩充文件也放不下
+        if len >= self.page_size {
+            return Err(Error::ValueTooLarge);
+        }
+        let raw_file = self.file.get_mut(file_name);
+
+        let file = match raw_file {
+            Some(file) => file,
+            None => return Err(Error::FileNotFound)
+        };
+
+        file.seek(SeekFrom::Start(0))?;
+        let page_num = file.read_u32::<byteorder::BigEndian>()?;
+        let data_pages = (page_num as usize).saturating_sub(INIT_FILE_PAGE_NUM);
+        for i in 0..data_pages {
+            file.seek(SeekFrom::Start(page_table_entry(i)))?;
+            let res = file.read_u32::<byteorder::BigEndian>()?;
+            if res > len as u32 {
+                // 找到插入位置并插入
+                file.seek(SeekFrom::Start(((INIT_FILE_PAGE_NUM + i) * self.page_size + self.page_size - res as usize) as u64))?;
+                file.write_all(bytes)?;
+
+                // 更新文件头
+                file.seek(SeekFrom::Start(page_table_entry(i)))?;
+                file.write_u32::<byteorder::BigEndian>(res - len as u32)?;
+                update_page_checksum(file, i, self.page_size)?;
+                return Ok(Position {
+                    file_name: String::from(file_name),
+                    page_num: i,
+                    offset: self.page_size - res as usize,
+                });
+            }
+        }
+        // 如果文件不够大
+        // 填充文件
+        self.fill_up_to(file_name, (2 * data_pages).max(1))?;
+        // 重新插入
+        self.insert_bytes(file_name, bytes)
+    }
+
+    fn read_bytes(&mut self, pos: Position, size: usize) -> Result<Vec<u8>, Error> {
+        let raw_file = self.file.get_mut(&pos.file_name);
+        let file = match raw_file {
+            Some(file) => file,
+            None => return Err(Error::FileNotFound)
+        };
+        file.seek(SeekFrom::Start(0))?;
+        let page_num = file.read_u32::<byteorder::BigEndian>()?;
+        if pos.page_num + INIT_FILE_PAGE_NUM >= page_num as usize {
+            return Err(Error::PageNumOutOfSize);
+        }
+        // 与 insert_bytes 使用相同的页表项位置
+        file.seek(SeekFrom::Start(page_table_entry(pos.page_num)))?;
+        let res = file.read_u32::<byteorder::BigEndian>()?;
+        if res as usize + pos.offset > self.page_size {
+            return Err(Error::UnexpectedError);
+        }
+        let page = &mut vec![0; self.page_size];
+        file.seek(SeekFrom::Start((INIT_FILE_PAGE_NUM * self.page_size + pos.page_num * self.page_size) as u64))?;
+        file.read_exact(page)?;
+
+        Ok(page[pos.offset..pos.offset + size].to_vec())
+    }
+
+    fn get_buffer_size(&self) -> usize {
+        self.buff_size
+    }
+
+    fn get_page_size(&self) -> usize {
+        self.page_size
+    }
+
+    fn get_meta_file_name(&self) -> String {
+        self.meta_file_name.clone()
+    }
+
+    fn flush_file(&mut self, file_name: &str) -> Result<(), Error> {
+        for index in write_order(self.list.iter().map(|i| &i.page)) {
+            if self.list[index].page.file_name == file_name {
+                self.flush_item(index)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn flush_all(&mut self) -> Result<(), Error> {
+        for index in write_order(self.list.iter().map(|i| &i.page)) {
+            self.flush_item(index)?;
+        }
+        Ok(())
+    }
+
+    /// 刷新并移出文件在缓冲区中的所有页面，然后关闭文件
+    fn remove_file(&mut self, file_name: &str) -> Result<(), Error> {
+        if !self.file.contains_key(file_name) {
+            return Err(Error::FileNotFound);
+        }
+        self.flush_file(file_name)?;
+        self.list.retain(|i| i.page.file_name != file_name);
+        self.file.remove(file_name);
+        Ok(())
+    }
+
+    fn stats(&self) -> BufferStats {
+        self.stats
+    }
+
+    fn reset_stats(&mut self) {
+        self.stats = BufferStats::default();
+    }
+
+    fn read_reserved_page(&mut self, file_name: &str, index: usize) -> Result<Vec<u8>, Error> {
+        read_reserved(&mut self.file, file_name, index, self.page_size)
+    }
+
+    fn write_reserved_page(&mut self, file_name: &str, index: usize, data: &[u8]) -> Result<(), Error> {
+        if let Some(mut undo) = self.undo.take() {
+            let res = undo.record_reserved(self, file_name, index);
+            self.undo = Some(undo);
+            res?;
+        }
+        write_reserved(&mut self.file, file_name, index, data, self.page_size)
+    }
+
+    fn open_wal(&mut self, path: &Path) -> Result<(), Error> {
+        self.wal = Some(Wal::open(path)?);
+        Ok(())
+    }
+
+    fn recover(&mut self) -> Result<(), Error> {
+        match self.wal.as_mut() {
+            Some(wal) => wal.replay(&mut self.file, self.page_size),
+            None => Ok(())
+        }
+    }
+
+    fn checkpoint(&mut self) -> Result<(), Error> {
+        self.recover()?;
+        self.flush_all()?;
+        match self.wal.as_mut() {
+            Some(wal) => wal.truncate(),
+            None => Ok(())
+        }
+    }
+
+    fn no_sync_on_drop(&mut self) {
+        self.sync_on_drop = false;
+    }
+
+    fn verify_file(&mut self, file_name: &str) -> Result<Vec<usize>, Error> {
+        self.flush_file(file_name)?;
+        match self.file.get_mut(file_name) {
+            Some(file) => verify_pages(file, self.page_size),
+            None => Err(Error::FileNotFound)
+        }
+    }
+
+    fn prefetch(&mut self, file_name: &str, page_nums: &[usize]) -> Result<(), Error> {
+        for page_num in page_nums {
+            if !self.list.iter().any(|i| i.page.file_name == file_name && i.page.page_num == *page_num) {
+                self.get_page(file_name, *page_num)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn begin_transaction(&mut self) -> Result<(), Error> {
+        if self.undo.is_some() {
+            return Err(Error::TransactionState);
+        }
+        self.undo = Some(UndoLog::default());
+        Ok(())
+    }
+
+    fn commit_transaction(&mut self) -> Result<(), Error> {
+        if self.undo.take().is_none() {
+            return Err(Error::TransactionState);
+        }
+        self.flush_all()
+    }
+
+    fn rollback_transaction(&mut self) -> Result<(), Error> {
+        match self.undo.take() {
+            Some(undo) => undo.restore(self),
+            None => Err(Error::TransactionState)
+        }
+    }
+}
+
+impl<P: EvictionPolicy> Drop for PolicyBuffer<P> {
+    /// 析构时写回所有缓存的页面，析构无法返回错误，写回失败时直接忽略
+    fn drop(&mut self) {
+        if self.sync_on_drop {
+            let _ = self.flush_all();
+        }
+    }
 }
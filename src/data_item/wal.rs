@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use crate::data_item::buffer::write_data_page;
+use crate::page::page_item::Page;
+use crate::util::error::Error;
+
+/// 预写日志中的一条记录，保存一个页面写入后的完整内容
+/// 磁盘格式: 文件名长度(u32) 文件名 页号(u32) 数据长度(u32) 数据 校验和(u32)
+pub struct WalRecord {
+    pub file_name: String,
+    pub page_num: usize,
+    pub data: Vec<u8>,
+}
+
+/// 预写日志
+/// 页面写入缓冲区前先追加到日志并落盘，崩溃后可以从日志中恢复数据文件
+pub struct Wal {
+    file: File,
+}
+
+impl Wal {
+    /// 打开日志文件，文件不存在时创建
+    pub fn open(path: &Path) -> Result<Wal, Error> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(path)?;
+        Ok(Wal { file })
+    }
+
+    /// 追加一个页面的完整内容并落盘
+    pub fn append(&mut self, page: &Page) -> Result<(), Error> {
+        let data = page.get_bytes();
+        let mut record = Vec::<u8>::with_capacity(page.file_name.len() + data.len() + 16);
+        record.write_u32::<byteorder::BigEndian>(page.file_name.len() as u32)?;
+        record.write_all(page.file_name.as_bytes())?;
+        record.write_u32::<byteorder::BigEndian>(page.page_num as u32)?;
+        record.write_u32::<byteorder::BigEndian>(data.len() as u32)?;
+        record.write_all(data)?;
+        record.write_u32::<byteorder::BigEndian>(checksum(data))?;
+
+        self.file.seek(SeekFrom::End(0))?;
+        self.file.write_all(record.as_slice())?;
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    /// 按写入顺序读出日志中的所有完整记录
+    /// 崩溃时只写了一半的末尾记录会被忽略
+    pub fn records(&mut self) -> Result<Vec<WalRecord>, Error> {
+        let mut bytes = Vec::<u8>::new();
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.read_to_end(&mut bytes)?;
+
+        let mut res = Vec::<WalRecord>::new();
+        let mut reader = bytes.as_slice();
+        while let Some(record) = read_record(&mut reader) {
+            res.push(record);
+        }
+        Ok(res)
+    }
+
+    /// 将日志中的记录依次写回数据文件，后写的记录覆盖先写的
+    /// 日志中出现的文件需要已经加入 files
+    pub fn replay(&mut self, files: &mut HashMap<String, File>, page_size: usize) -> Result<(), Error> {
+        for record in self.records()? {
+            let file = match files.get_mut(record.file_name.as_str()) {
+                Some(file) => file,
+                None => return Err(Error::FileNotFound)
+            };
+            write_data_page(file, record.page_num, record.data.as_slice(), page_size)?;
+        }
+        for file in files.values_mut() {
+            file.sync_all()?;
+        }
+        Ok(())
+    }
+
+    /// 清空日志
+    pub fn truncate(&mut self) -> Result<(), Error> {
+        self.file.set_len(0)?;
+        self.file.sync_all()?;
+        Ok(())
+    }
+}
+
+/// 从 reader 中读出一条记录，数据不完整或校验失败时返回 None
+fn read_record(reader: &mut &[u8]) -> Option<WalRecord> {
+    let name_len = reader.read_u32::<byteorder::BigEndian>().ok()? as usize;
+    if name_len > reader.len() {
+        return None;
+    }
+    let mut name = vec![0u8; name_len];
+    reader.read_exact(&mut name).ok()?;
+    let page_num = reader.read_u32::<byteorder::BigEndian>().ok()? as usize;
+    let data_len = reader.read_u32::<byteorder::BigEndian>().ok()? as usize;
+    if data_len > reader.len() {
+        return None;
+    }
+    let mut data = vec![0u8; data_len];
+    reader.read_exact(&mut data).ok()?;
+    let sum = reader.read_u32::<byteorder::BigEndian>().ok()?;
+    if sum != checksum(data.as_slice()) || page_num == 0 {
+        return None;
+    }
+    Some(WalRecord {
+        file_name: String::from_utf8(name).ok()?,
+        page_num,
+        data,
+    })
+}
+
+/// 简单的校验和，用于识别写了一半的记录
+fn checksum(data: &[u8]) -> u32 {
+    data.iter().fold(0u32, |sum, b| sum.rotate_left(5) ^ *b as u32)
+}
@@ -1,24 +1,39 @@
+use std::cmp::Ordering;
+use std::collections::{HashSet, VecDeque};
 use std::convert::TryFrom;
+use std::ops::Bound;
 use std::sync::{Arc, RwLock};
 
-use crate::index::key_value_pair::KeyValuePair;
-use crate::index::node::{Node, NodeSpec, NodeType, LEAF_NODE_NEXT_NODE_PTR_OFFSET, LEAF_NODE_PREVIOUS_NODE_PTR_OFFSET};
-use crate::page::page_item::{Page, PAGE_SIZE};
+use crate::index::key_value_pair::{compare_keys, trim_key, KeyValuePair};
+use crate::index::node::{Node, NodeSpec, NodeType, LEAF_NODE_NEXT_NODE_PTR_OFFSET, LEAF_NODE_PREVIOUS_NODE_PTR_OFFSET, KEY_SIZE, leaf_max_pairs, internal_max_keys};
+use crate::page::page_item::{Page, PAGE_SIZE, PTR_SIZE};
 use crate::page::pager::Pager;
 use crate::util::error::Error;
-use crate::data_item::buffer::Buffer;
+use crate::data_item::buffer::{Buffer, SharedBuffer};
 
 /// B+树 配置
 pub const MAX_BRANCHING_FACTOR: usize = 200;
 pub const MIN_BRANCHING_FACTOR: usize = 100;
 pub const NODE_KEYS_LIMIT: usize = MAX_BRANCHING_FACTOR - 1;
 
+/// B+树元数据页，固定为索引文件的第一个页
+/// 依次记录根节点页号、第一个叶子节点页号、已分配页数、键宽
+pub const TREE_META_PAGE_NUM: usize = 1;
+const TREE_META_ROOT_OFFSET: usize = 0;
+const TREE_META_FIRST_OFFSET: usize = TREE_META_ROOT_OFFSET + PTR_SIZE;
+const TREE_META_PAGE_CNT_OFFSET: usize = TREE_META_FIRST_OFFSET + PTR_SIZE;
+const TREE_META_KEY_SIZE_OFFSET: usize = TREE_META_PAGE_CNT_OFFSET + PTR_SIZE;
+
+/// 分裂后每个节点至少要有两个儿子
+const MIN_CHILDREN: usize = 2;
+
 /// B+树的定义
 pub struct BTree {
     file_name: String,
     root: Arc<RwLock<Node>>,
     pub(crate) pager: Box<Pager>,
     first_offset: usize,
+    key_size: usize,
 }
 
 impl Clone for BTree {
@@ -28,12 +43,26 @@ impl Clone for BTree {
             root: Arc::clone(&self.root),
             pager: self.pager.clone(),
             first_offset: self.first_offset,
+            key_size: self.key_size,
         }
     }
 }
 
 impl BTree {
-    pub(crate) fn new(mut pager: Box<Pager>, file_name: String, buffer: &mut Box<dyn Buffer>) -> Result<BTree, Error> {
+    pub(crate) fn new(pager: Box<Pager>, file_name: String, buffer: &mut Box<dyn Buffer>) -> Result<BTree, Error> {
+        BTree::new_with_key_size(pager, file_name, KEY_SIZE, buffer)
+    }
+
+    /// 以指定的键宽创建一棵树
+    pub(crate) fn new_with_key_size(mut pager: Box<Pager>, file_name: String, key_size: usize, buffer: &mut Box<dyn Buffer>) -> Result<BTree, Error> {
+        BTree::check_key_size(key_size)?;
+
+        // 元数据页必须是文件的第一个页，这样重新打开时才能找到
+        let meta_page = pager.get_new_page(buffer)?;
+        if meta_page.page_num != TREE_META_PAGE_NUM {
+            return Err(Error::UnexpectedError);
+        }
+
         let page = pager.get_new_page(buffer)?;
         let page_num = page.page_num;
         let root =
@@ -45,18 +74,233 @@ impl BTree {
                         page_num,
                         true,
                         page,
+                        key_size,
                     )?
                 )
             );
 
-        Ok(BTree {
+        let mut tree = BTree {
             file_name,
             pager,
             root,
             first_offset: page_num,
+            key_size,
+        };
+        // 根节点的页头只写在内存中，需要写回，之后才能从缓冲区中读出
+        tree.write_root(buffer)?;
+        tree.write_meta(buffer)?;
+        Ok(tree)
+    }
+
+    /// 由按键严格递增的键值对自底向上构建一棵树
+    /// 叶子依次装满并前后相连，再逐层为每组节点建立父节点，每个页只写一次
+    /// 键没有严格递增时返回错误，键重复时返回 KeyAlreadyExists
+    pub fn bulk_load(sorted_pairs: Vec<KeyValuePair>, pager: Box<Pager>, file_name: String, buffer: &mut Box<dyn Buffer>) -> Result<BTree, Error> {
+        BTree::bulk_load_with_key_size(sorted_pairs, pager, file_name, KEY_SIZE, buffer)
+    }
+
+    /// 以指定的键宽批量构建一棵树
+    pub fn bulk_load_with_key_size(sorted_pairs: Vec<KeyValuePair>, mut pager: Box<Pager>, file_name: String, key_size: usize, buffer: &mut Box<dyn Buffer>) -> Result<BTree, Error> {
+        BTree::check_key_size(key_size)?;
+        for kv in sorted_pairs.iter() {
+            BTree::check_key_len(&kv.key, key_size)?;
+        }
+        for pair in sorted_pairs.windows(2) {
+            match compare_keys(&pair[0].key, &pair[1].key) {
+                Ordering::Equal => return Err(Error::KeyAlreadyExists),
+                Ordering::Greater => return Err(Error::KeysNotSorted),
+                Ordering::Less => ()
+            }
+        }
+        if sorted_pairs.is_empty() {
+            return BTree::new_with_key_size(pager, file_name, key_size, buffer);
+        }
+
+        let meta_page = pager.get_new_page(buffer)?;
+        if meta_page.page_num != TREE_META_PAGE_NUM {
+            return Err(Error::UnexpectedError);
+        }
+
+        // 先算出每一层的分组并分配好所有的页，写节点时才能填上相邻叶子和父节点的页号
+        let chunks: Vec<&[KeyValuePair]> = sorted_pairs.chunks(leaf_max_pairs(key_size)).collect();
+        let capacity = internal_max_keys(key_size).min(MAX_BRANCHING_FACTOR);
+        // groups[i] 是第 i + 1 层每个节点在第 i 层中的儿子下标范围，第 0 层为叶子
+        let mut groups = Vec::<Vec<(usize, usize)>>::new();
+        let mut len = chunks.len();
+        while len > 1 {
+            let bounds = BTree::group_bounds(len, capacity);
+            len = bounds.len();
+            groups.push(bounds);
+        }
+        let mut pages = Vec::<Vec<Page>>::new();
+        for size in std::iter::once(chunks.len()).chain(groups.iter().map(|bounds| bounds.len())) {
+            let mut level_pages = Vec::<Page>::new();
+            for _ in 0..size {
+                level_pages.push(pager.get_new_page(buffer)?);
+            }
+            pages.push(level_pages);
+        }
+        let offsets: Vec<Vec<usize>> = pages.iter()
+            .map(|level_pages| level_pages.iter().map(|page| page.page_num).collect())
+            .collect();
+        // 第 depth 层每个节点的父节点页号，最高一层为根，没有父节点
+        let parent_offsets = |depth: usize| -> Vec<usize> {
+            match groups.get(depth) {
+                Some(bounds) => {
+                    let mut res = Vec::<usize>::new();
+                    for (g, (_, end)) in bounds.iter().enumerate() {
+                        res.resize(*end, offsets[depth + 1][g]);
+                    }
+                    res
+                }
+                None => vec![0],
+            }
+        };
+
+        // 每一层记录各节点中最大的键
+        let mut level = Vec::<String>::new();
+        let is_root = groups.is_empty();
+        let leaf_offsets = &offsets[0];
+        let leaf_parents = parent_offsets(0);
+        for (i, (chunk, page)) in chunks.iter().zip(pages.remove(0)).enumerate() {
+            let mut leaf = Node::new(NodeType::Leaf, leaf_parents[i], page.page_num, is_root, page, key_size)?;
+            for kv in chunk.iter() {
+                leaf.add_key_value_pair(kv.clone())?;
+            }
+            if i > 0 {
+                leaf.add_previous_node(leaf_offsets[i - 1])?;
+            }
+            if i + 1 < leaf_offsets.len() {
+                leaf.add_next_node(leaf_offsets[i + 1])?;
+            }
+            pager.write_page(leaf.page.copy_into(&leaf.page.file_name, leaf.page.page_num), buffer)?;
+            level.push(chunk[chunk.len() - 1].key.clone());
+        }
+
+        for (depth, (bounds, level_pages)) in groups.iter().zip(pages).enumerate() {
+            let is_root = depth + 1 == groups.len();
+            let parents = parent_offsets(depth + 1);
+            let mut maxima = Vec::<String>::new();
+            for (g, ((start, end), page)) in bounds.iter().zip(level_pages).enumerate() {
+                let mut node = Node::new(NodeType::Internal, parents[g], page.page_num, is_root, page, key_size)?;
+                node.set_keys_and_children(&level[*start..*end], &offsets[depth][*start..*end])?;
+                pager.write_page(node.page.copy_into(&node.page.file_name, node.page.page_num), buffer)?;
+                maxima.push(level[*end - 1].clone());
+            }
+            level = maxima;
+        }
+
+        let root_offset = offsets[offsets.len() - 1][0];
+        let root = BTree::load_node(&pager, root_offset, root_offset, buffer)?;
+        let tree = BTree {
+            file_name,
+            pager,
+            root: Arc::new(RwLock::new(root)),
+            first_offset: offsets[0][0],
+            key_size,
+        };
+        tree.write_meta(buffer)?;
+        Ok(tree)
+    }
+
+    /// 用 dump 导出的键值对批量构建一棵新树，pager 应指向一个新的索引文件
+    /// 键宽取默认键宽和最长的键中较大的一个，导出的树的键宽不需要与原来的树相同
+    pub fn load_from(pairs: Vec<KeyValuePair>, pager: Box<Pager>, file_name: String, buffer: &mut Box<dyn Buffer>) -> Result<BTree, Error> {
+        let key_size = pairs.iter().map(|kv| trim_key(&kv.key).len()).fold(KEY_SIZE, usize::max);
+        BTree::bulk_load_with_key_size(pairs, pager, file_name, key_size, buffer)
+    }
+
+    /// 从元数据页中读取根节点页号，重新打开一棵已有的树
+    pub fn open(mut pager: Box<Pager>, file_name: String, buffer: &mut Box<dyn Buffer>) -> Result<BTree, Error> {
+        let meta_page = pager.get_page(&TREE_META_PAGE_NUM, buffer)?;
+        let root_page_num = meta_page.get_value_from_offset(TREE_META_ROOT_OFFSET)?;
+        let first_offset = meta_page.get_value_from_offset(TREE_META_FIRST_OFFSET)?;
+        let page_cnt = meta_page.get_value_from_offset(TREE_META_PAGE_CNT_OFFSET)?;
+        let key_size = meta_page.get_value_from_offset(TREE_META_KEY_SIZE_OFFSET)?;
+
+        // 元数据页为空，说明文件中没有树
+        if root_page_num == 0 {
+            return Err(Error::IndexWithoutBTree);
+        }
+        BTree::check_key_size(key_size)?;
+        pager.restore_cnt(page_cnt, buffer)?;
+
+        let root_node = BTree::load_node(&pager, root_page_num, root_page_num, buffer)?;
+
+        Ok(BTree {
+            file_name,
+            pager,
+            root: Arc::new(RwLock::new(root_node)),
+            first_offset,
+            key_size,
         })
     }
 
+    /// 检查键能否放进键宽为 key_size 的节点
+    /// 超长的键写入节点时会被截断，不同的键可能变成同一个键
+    pub(crate) fn check_key_len(key: &str, key_size: usize) -> Result<(), Error> {
+        if trim_key(key).len() > key_size {
+            return Err(Error::KeyTooLong);
+        }
+        Ok(())
+    }
+
+    /// 将 len 个节点平均分成若干组，每组不超过 capacity 个，返回每组的下标范围
+    /// 平均分组避免最后一组只有一个节点
+    fn group_bounds(len: usize, capacity: usize) -> Vec<(usize, usize)> {
+        let groups = (0..len).step_by(capacity).len();
+        let mut bounds = Vec::<(usize, usize)>::new();
+        let mut start = 0;
+        for g in 0..groups {
+            let end = start + (len - start) / (groups - g);
+            bounds.push((start, end));
+            start = end;
+        }
+        bounds
+    }
+
+    /// 检查键宽是否能让节点满足最小分支数
+    /// 键过宽时，一页放不下两个儿子，树无法分裂
+    pub fn check_key_size(key_size: usize) -> Result<(), Error> {
+        if key_size == 0
+            || internal_max_keys(key_size) < MIN_CHILDREN
+            || leaf_max_pairs(key_size) < MIN_CHILDREN {
+            return Err(Error::KeySizeOutOfPage);
+        }
+        Ok(())
+    }
+
+    /// 读取 page_num 页上的节点
+    /// try_from 得到的是幽灵页，换回真实的页才能写回缓冲区
+    fn load_node(pager: &Pager, page_num: usize, offset: usize, buffer: &mut Box<dyn Buffer>) -> Result<Node, Error> {
+        let page = pager.get_page(&page_num, buffer)?;
+        let mut node = Node::try_from(NodeSpec {
+            page_data: page.get_data(),
+            offset,
+        })?;
+        node.page = page;
+        Ok(node)
+    }
+
+    /// 获取树的键宽
+    pub fn get_key_size(&self) -> usize {
+        self.key_size
+    }
+
+    /// 将根节点页号、第一个叶子页号、已分配页数写入元数据页
+    fn write_meta(&self, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let root_page_num = match self.root.read() {
+            Ok(root) => root.offset,
+            Err(_) => return Err(Error::UnexpectedError)
+        };
+        let mut meta_page = self.pager.get_page(&TREE_META_PAGE_NUM, buffer)?;
+        meta_page.write_value_at_offset(TREE_META_ROOT_OFFSET, root_page_num)?;
+        meta_page.write_value_at_offset(TREE_META_FIRST_OFFSET, self.first_offset)?;
+        meta_page.write_value_at_offset(TREE_META_PAGE_CNT_OFFSET, self.pager.cnt)?;
+        meta_page.write_value_at_offset(TREE_META_KEY_SIZE_OFFSET, self.key_size)?;
+        self.pager.write_page(meta_page, buffer)
+    }
+
     /// 在树上查询一个键
     pub fn search(&self, key: String, buffer: &mut Box<dyn Buffer>) -> Result<KeyValuePair, Error> {
         let (_, kv) = self.search_node(Arc::clone(&self.root), &key, buffer)?;
@@ -66,186 +310,346 @@ impl BTree {
         }
     }
 
-    /// 在树上查询一个两个键之间的所有节点
-    pub fn search_range(&self, raw_left_key: Option<String>, raw_right_key: Option<String>, buffer: &mut Box<dyn Buffer>) -> Result<Vec<KeyValuePair>, Error> {
-        match raw_left_key {
-            Some(left_key) => {
-                let (node, raw_kv) = self.search_node(Arc::clone(&self.root), &left_key, buffer)?;
-                let mut res = Vec::<KeyValuePair>::new();
-                match raw_kv {
-                    Some(kv) => kv,
-                    None => return Err(Error::KeyNotFound),
-                };
-                let read_node = match node.read() {
-                    Ok(rn) => rn,
-                    _ => return Err(Error::UnexpectedError)
-                };
-                let mut next_node_offset = read_node.offset;
-                let mut right_key = "".to_string();
-                let has_right_key = match raw_right_key {
-                    Some(right_key_data) => {
-                        right_key = right_key_data;
-                        true
-                    }
-                    None => false
-                };
-                while next_node_offset != 0 {
-                    let page_num = next_node_offset;
-                    let new_node =
-                        Arc::new(
-                            RwLock::new(
-                                Node::try_from(
-                                    NodeSpec {
-                                        page_data: self.pager.get_page(&page_num, buffer).unwrap().get_data(),
-                                        offset: next_node_offset,
-                                    }
-                                )?
-                            )
-                        );
-                    let read_node = match new_node.read() {
-                        Ok(rn ) => rn,
-                        _ => return Err(Error::UnexpectedError)
+    /// 与 search 相同，但使用在线程间共享的缓冲区
+    /// 只在读入每个节点时锁住缓冲区，多个线程的查询可以交替进行
+    /// 写操作仍然需要 &mut self，与查询互斥
+    pub fn search_shared(&self, key: String, buffer: &SharedBuffer) -> Result<KeyValuePair, Error> {
+        let mut page_num = self.root_offset()?;
+        loop {
+            let node = match buffer.lock() {
+                Ok(mut buffer) => BTree::load_node(&self.pager, page_num, page_num, &mut buffer)?,
+                Err(_) => return Err(Error::UnexpectedError)
+            };
+            match node.node_type {
+                NodeType::Leaf => {
+                    return match node.get_key_value_pairs()?.into_iter().find(|kv| compare_keys(&kv.key, &key) == Ordering::Equal) {
+                        Some(kv) => Ok(kv),
+                        None => Err(Error::KeyNotFound)
                     };
-                    next_node_offset = read_node.page.get_value_from_offset(LEAF_NODE_NEXT_NODE_PTR_OFFSET)?;
-                    let mut ok = false;
-                    if has_right_key {
-                        for i in read_node.get_keys()? {
-                            if i.trim() == right_key.trim() {
-                                ok = true;
-                                break;
-                            }
-                        }
-                    }
-                    if ok {
-                        let mut kv_pairs = read_node.get_key_value_pairs()?;
-                        kv_pairs.sort();
-
-                        for i in kv_pairs {
-                            if i.key.trim() <= right_key.trim() {
-                                res.push(i);
-                            } else {
-                                break;
-                            }
-                        }
-                        break;
-                    } else {
-                        for i in read_node.get_key_value_pairs()? {
-                            res.push(i);
-                        }
-                    }
                 }
-                Ok(res)
+                NodeType::Internal => {
+                    // 第一个不小于待查询键的键对应的儿子
+                    let index = match node.get_keys()?.iter().position(|iter_key| compare_keys(&key, iter_key) != Ordering::Greater) {
+                        Some(index) => index,
+                        None => return Err(Error::KeyNotFound)
+                    };
+                    page_num = match node.get_children()?.get(index) {
+                        Some(child_offset) => *child_offset,
+                        None => return Err(Error::UnexpectedError)
+                    };
+                }
+                NodeType::Unknown => return Err(Error::UnexpectedError)
             }
-            None => {
-                match raw_right_key {
-                    Some(right_key) => {
-                        let (node, raw_kv) = self.search_node(Arc::clone(&self.root), &right_key, buffer)?;
-                        match raw_kv {
-                            Some(kv) => kv,
-                            None => return Err(Error::KeyNotFound),
-                        };
-                        let read_node = match node.read() {
-                            Ok(rn) => rn,
-                            _ => return Err(Error::UnexpectedError)
-                        };
-                        let mut res = Vec::<KeyValuePair>::new();
-                        let mut next_node_offset = read_node.offset;
-                        while next_node_offset != 0 {
-                            let page_num = next_node_offset;
-                            let new_node =
-                                Arc::new(
-                                    RwLock::new(
-                                        Node::try_from(
-                                            NodeSpec {
-                                                page_data: self.pager.get_page(&page_num, buffer).unwrap().get_data(),
-                                                offset: next_node_offset,
-                                            }
-                                        )?
-                                    )
-                                );
-                            let read_node = match new_node.read() {
-                                Ok(rn) => rn,
-                                _ => return Err(Error::UnexpectedError)
-                            };
-                            next_node_offset = read_node.page.get_value_from_offset(LEAF_NODE_PREVIOUS_NODE_PTR_OFFSET)?;
-                            for i in read_node.get_key_value_pairs()? {
-                                res.push(i);
-                            }
-                        }
-                        Ok(res)
+        }
+    }
+
+    /// 在树上查询键在 left 和 right 之间（包括两端）的所有键值对，None 表示该侧无界
+    /// 四种情况都从第一个叶子开始向后读，超过右边界时停止，边界上的键不必存在
+    pub fn search_range(&self, left: Option<String>, right: Option<String>, buffer: &mut Box<dyn Buffer>) -> Result<Vec<KeyValuePair>, Error> {
+        self.search_range_ex(to_bound(left), to_bound(right), buffer)
+    }
+
+    /// 在树上查询键在 left 和 right 之间的所有键值对，每一端可以包括或不包括边界，也可以无界
+    pub fn search_range_ex(&self, left: Bound<String>, right: Bound<String>, buffer: &mut Box<dyn Buffer>) -> Result<Vec<KeyValuePair>, Error> {
+        let mut cursor = self.cursor_ex(left, right);
+        let mut res = Vec::<KeyValuePair>::new();
+        while let Some(kv) = cursor.next(self, buffer) {
+            res.push(kv?);
+        }
+        Ok(res)
+    }
+
+    /// 统计键在 left 和 right 之间（包括两端）的键值对数，None 表示该侧无界
+    /// 与 search_range 使用同样的边界判断，但不把键值对收集起来
+    pub fn count(&self, left: Option<String>, right: Option<String>, buffer: &mut Box<dyn Buffer>) -> Result<usize, Error> {
+        let mut cursor = self.cursor(left, right);
+        let mut cnt = 0;
+        while let Some(kv) = cursor.next(self, buffer) {
+            kv?;
+            cnt += 1;
+        }
+        Ok(cnt)
+    }
+
+    /// 树中最小的键值对，沿最左边的儿子走到第一个叶子
+    /// 空树返回 KeyNotFound
+    pub fn min_key(&self, buffer: &mut Box<dyn Buffer>) -> Result<KeyValuePair, Error> {
+        let leaf = self.edge_leaf(true, buffer)?;
+        match leaf.get_key_value_pairs()?.into_iter().min() {
+            Some(kv) => Ok(kv),
+            None => Err(Error::KeyNotFound)
+        }
+    }
+
+    /// 树中最大的键值对，沿最右边的儿子走到最后一个叶子
+    /// 空树返回 KeyNotFound
+    pub fn max_key(&self, buffer: &mut Box<dyn Buffer>) -> Result<KeyValuePair, Error> {
+        let leaf = self.edge_leaf(false, buffer)?;
+        match leaf.get_key_value_pairs()?.into_iter().max() {
+            Some(kv) => Ok(kv),
+            None => Err(Error::KeyNotFound)
+        }
+    }
+
+    /// 从根出发一直走最左边（leftmost 为 true）或最右边的儿子，返回到达的非空叶子
+    fn edge_leaf(&self, leftmost: bool, buffer: &mut Box<dyn Buffer>) -> Result<Node, Error> {
+        let root_offset = self.root_offset()?;
+        let mut node = BTree::load_node(&self.pager, root_offset, root_offset, buffer)?;
+        while node.node_type == NodeType::Internal {
+            let children = node.get_children()?;
+            let child = if leftmost { children.first() } else { children.last() };
+            let offset = match child {
+                Some(offset) => *offset,
+                None => return Err(Error::UnexpectedError)
+            };
+            node = BTree::load_node(&self.pager, offset, offset, buffer)?;
+        }
+        // 删除不会合并叶子，边上的叶子可能已经空了，继续向内找
+        let ptr_offset = if leftmost { LEAF_NODE_NEXT_NODE_PTR_OFFSET } else { LEAF_NODE_PREVIOUS_NODE_PTR_OFFSET };
+        while node.get_keys_len()? == 0 {
+            let offset = node.page.get_value_from_offset(ptr_offset)?;
+            if offset == 0 {
+                break;
+            }
+            node = BTree::load_node(&self.pager, offset, offset, buffer)?;
+        }
+        Ok(node)
+    }
+
+    /// 从第一个叶子开始按键的顺序逐个读取键在 left 和 right 之间（包括两端）的键值对
+    /// None 表示该侧无界，每次只读入一个叶子，调用者可以随时停止
+    pub fn iter_range<'a>(&'a self, left: Option<String>, right: Option<String>, buffer: &'a mut Box<dyn Buffer>) -> RangeIter<'a> {
+        RangeIter {
+            btree: self,
+            buffer,
+            cursor: self.cursor(left, right),
+        }
+    }
+
+    /// 沿叶子链按键的顺序导出树中所有的键值对，可以交给 load_from 重建一棵相同的树
+    pub fn dump(&self, buffer: &mut Box<dyn Buffer>) -> Result<Vec<KeyValuePair>, Error> {
+        self.iter_range(None, None, buffer).collect()
+    }
+
+    /// 从最右边的叶子开始沿前一个叶子的指针向前读，按键从大到小返回所有键值对
+    pub fn iter_rev<'a>(&'a self, buffer: &'a mut Box<dyn Buffer>) -> RevIter<'a> {
+        RevIter {
+            btree: self,
+            buffer,
+            next_offset: None,
+            pending: Vec::<KeyValuePair>::new(),
+            done: false,
+        }
+    }
+
+    /// 在 left 和 right 之间逐个读取键值对的游标，读取时再传入缓冲区
+    pub(crate) fn cursor(&self, left: Option<String>, right: Option<String>) -> LeafCursor {
+        self.cursor_ex(to_bound(left), to_bound(right))
+    }
+
+    /// 与 cursor 相同，但每一端可以选择是否包括边界
+    pub(crate) fn cursor_ex(&self, left: Bound<String>, right: Bound<String>) -> LeafCursor {
+        LeafCursor {
+            next_offset: self.first_offset,
+            pending: VecDeque::<KeyValuePair>::new(),
+            left: trim_bound(left),
+            right: trim_bound(right),
+            done: false,
+        }
+    }
+
+    /// 插入一个键值对
+    /// 从根向下找到叶子并记下经过的中间节点，叶子满时分裂，再自底向上把分裂出的节点加到父节点中
+    pub fn insert(&mut self, kv: KeyValuePair, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        BTree::check_key_len(&kv.key, self.key_size)?;
+        let mut path = Vec::<usize>::new();
+        let mut page_num = self.root_offset()?;
+        let mut node = BTree::load_node(&self.pager, page_num, page_num, buffer)?;
+        while node.node_type == NodeType::Internal {
+            let keys = node.get_keys()?;
+            let children = node.get_children()?;
+            let index = match keys.iter().position(|key| compare_keys(&kv.key, key) != Ordering::Greater) {
+                Some(index) => index,
+                None => {
+                    // 比所有的键都大，扩大最后一个键，并插入到最后一个儿子中
+                    let last_key = match keys.last() {
+                        Some(last_key) => last_key,
+                        None => return Err(Error::UnexpectedError)
+                    };
+                    node.update_internal_key(last_key, &kv.key)?;
+                    self.write_node(&node, buffer)?;
+                    keys.len() - 1
+                }
+            };
+            path.push(page_num);
+            page_num = match children.get(index) {
+                Some(child_offset) => *child_offset,
+                None => return Err(Error::UnexpectedError)
+            };
+            node = BTree::load_node(&self.pager, page_num, page_num, buffer)?;
+        }
+
+        if node.get_keys()?.iter().any(|key| compare_keys(key, &kv.key) == Ordering::Equal) {
+            return Err(Error::KeyAlreadyExists)
+        }
+        if node.get_keys_len()? < leaf_max_pairs(self.key_size) {
+            node.add_key_value_pair(kv)?;
+            self.write_node(&node, buffer)?;
+        } else {
+            // 叶子已满，先分裂再插入到对应的一半中
+            let (mut left, left_max, mut right) = node.split_leaf(&mut self.pager, buffer)?;
+            if compare_keys(&kv.key, &left_max) != Ordering::Greater {
+                left.add_key_value_pair(kv)?;
+            } else {
+                right.add_key_value_pair(kv)?;
+            }
+            self.write_node(&left, buffer)?;
+            self.write_node(&right, buffer)?;
+            if node.offset == self.first_offset {
+                self.first_offset = left.offset;
+            }
+            self.insert_into_parent(path, node.offset, &left, left_max, &right, buffer)?;
+        }
+
+        self.reload_root(buffer)?;
+        self.write_meta(buffer)
+    }
+
+    /// 节点 old_offset 分裂成了 left 和 right，将它们加到父节点中
+    /// 父节点满了就继续分裂，一直到根节点，根节点分裂时根所在的页改为只有两个儿子的中间节点
+    fn insert_into_parent(&mut self, mut path: Vec<usize>, old_offset: usize, left: &Node, left_max: String, right: &Node, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let mut old_offset = old_offset;
+        let mut left_offset = left.offset;
+        let mut left_max = left_max;
+        let mut right_offset = right.offset;
+        let mut right_max = BTree::max_key_of(right)?;
+        loop {
+            match path.pop() {
+                None => {
+                    // 根节点的页号不变，重新写成中间节点
+                    let page = self.pager.get_page(&old_offset, buffer)?;
+                    let mut root = Node::new(
+                        NodeType::Internal,
+                        0,
+                        old_offset,
+                        true,
+                        Page::new([0u8; PAGE_SIZE], &page.file_name, page.page_num),
+                        self.key_size,
+                    )?;
+                    root.set_keys_and_children(&[left_max, right_max], &[left_offset, right_offset])?;
+                    return self.write_node(&root, buffer);
+                }
+                Some(parent_offset) => {
+                    let mut parent = BTree::load_node(&self.pager, parent_offset, parent_offset, buffer)?;
+                    parent.add_key_and_left_child(left_max, left_offset)?;
+                    parent.update_internal_value(&old_offset, &right_offset)?;
+                    // 旧节点已经被两个新节点替代，根节点的页继续使用
+                    if old_offset != self.root_offset()? {
+                        self.pager.free_page(old_offset, buffer)?;
                     }
-                    None => {
-                        let mut res = Vec::<KeyValuePair>::new();
-                        if self.first_offset == 0 {
-                            return Ok(res);
-                        }
-                        let mut next_node_offset = self.first_offset;
-                        while next_node_offset != 0 {
-                            let page_num = next_node_offset;
-                            let new_node =
-                                Arc::new(
-                                    RwLock::new(
-                                        Node::try_from(
-                                            NodeSpec {
-                                                page_data: self.pager.get_page(&page_num, buffer).unwrap().get_data(),
-                                                offset: next_node_offset,
-                                            }
-                                        )?
-                                    )
-                                );
-                            let read_node = match new_node.read() {
-                                Ok(rn ) => rn,
-                                _ => return Err(Error::UnexpectedError)
-                            };
-                            next_node_offset = read_node.page.get_value_from_offset(LEAF_NODE_NEXT_NODE_PTR_OFFSET)?;
-                            for i in read_node.get_key_value_pairs()? {
-                                res.push(i);
-                            }
-                        }
-                        Ok(res)
+                    if parent.get_keys_len()? < internal_max_keys(self.key_size) {
+                        return self.write_node(&parent, buffer);
                     }
+
+                    let (new_left, new_left_max, new_right) = parent.split_internal(&mut self.pager, buffer)?;
+                    self.write_node(&new_left, buffer)?;
+                    self.write_node(&new_right, buffer)?;
+                    old_offset = parent_offset;
+                    left_offset = new_left.offset;
+                    left_max = new_left_max;
+                    right_offset = new_right.offset;
+                    right_max = BTree::max_key_of(&new_right)?;
                 }
             }
         }
     }
 
+    /// 节点中最大的键
+    fn max_key_of(node: &Node) -> Result<String, Error> {
+        match node.get_keys()?.into_iter().max_by(|a, b| compare_keys(a, b)) {
+            Some(key) => Ok(key),
+            None => Err(Error::UnexpectedError)
+        }
+    }
 
-    /// 插入一个键值对，可能沿途分裂节点
-    pub fn insert(&mut self, kv: KeyValuePair, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
-        let (node, kv_pair_exists) = self.search_node_inserted(Arc::clone(&self.root), &kv.key, buffer)?;
-        if kv_pair_exists.is_some() {
-            return Err(Error::KeyAlreadyExists)
-        };
-        // 在这里加键可能会沿途分裂节点
-        let mut guarded_node = match node.write() {
-            Err(_) => return Err(Error::UnexpectedError),
-            Ok(node) => node,
-        };
-        let keys_len = guarded_node.get_keys_len()?;
-        if keys_len < NODE_KEYS_LIMIT {
-            // 向叶子节点插入键值对.
-            guarded_node.add_key_value_pair(kv)?;
-            // 将对应页写入磁盘.
-            return self
-                .pager.as_mut()
-                .write_page(Page::new(guarded_node.page.get_data(), &guarded_node.page.file_name, guarded_node.page.page_num), buffer);
+    /// 根节点的页号
+    fn root_offset(&self) -> Result<usize, Error> {
+        match self.root.read() {
+            Ok(root) => Ok(root.offset),
+            Err(_) => Err(Error::UnexpectedError)
         }
-        self.split_node(Arc::clone(&node), buffer)
     }
 
+    /// 根节点的页可能被修改过，重新读入
+    fn reload_root(&mut self, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let root_offset = self.root_offset()?;
+        self.root = Arc::new(RwLock::new(BTree::load_node(&self.pager, root_offset, root_offset, buffer)?));
+        Ok(())
+    }
+
+    /// 页面被恢复成之前的内容后，从元数据页重新读入根节点和第一个叶子
+    /// 克隆出的树与原来的树共享根节点，恢复后必须重新读入
+    pub(crate) fn reload(&mut self, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let meta_page = self.pager.get_page(&TREE_META_PAGE_NUM, buffer)?;
+        let root_page_num = meta_page.get_value_from_offset(TREE_META_ROOT_OFFSET)?;
+        self.first_offset = meta_page.get_value_from_offset(TREE_META_FIRST_OFFSET)?;
+        self.root = Arc::new(RwLock::new(BTree::load_node(&self.pager, root_page_num, root_page_num, buffer)?));
+        Ok(())
+    }
 
-    /// 将key所对应的值更新为value
-    pub fn update(&mut self, kv: KeyValuePair, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+    /// 将根节点所在的页写回
+    fn write_root(&mut self, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let page = match self.root.read() {
+            Ok(root) => root.page.copy_into(&root.page.file_name, root.page.page_num),
+            Err(_) => return Err(Error::UnexpectedError)
+        };
+        self.pager.write_page(page, buffer)
+    }
+
+    /// 将节点所在的页写回
+    fn write_node(&mut self, node: &Node, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        self.pager.write_page(node.page.copy_into(&node.page.file_name, node.page.page_num), buffer)
+    }
+
+    /// 将key所对应的值更新为value，返回更新前的键值对
+    pub fn update(&mut self, kv: KeyValuePair, buffer: &mut Box<dyn Buffer>) -> Result<KeyValuePair, Error> {
         let (node, kv_pair_exists) = self.search_node(Arc::clone(&self.root), &kv.key, buffer)?;
-        match kv_pair_exists {
+        let old_kv = match kv_pair_exists {
             None => return Err(Error::KeyNotFound),
-            Some(_) => ()
-        }
+            Some(old_kv) => old_kv
+        };
         let mut guarded_node = match node.write() {
             Err(_) => return Err(Error::UnexpectedError),
             Ok(node) => node
         };
-        guarded_node.update_value(kv)
+        guarded_node.update_value(kv)?;
+        self
+            .pager
+            .write_page(guarded_node.page.copy_into(&guarded_node.page.file_name, guarded_node.page.page_num), buffer)?;
+        Ok(old_kv)
+    }
+
+    /// 只有键当前的值等于 expected 时才将它更新为 value，否则返回 CasMismatch 且不做修改
+    /// 调用者可以在失败后重新读取当前值再重试
+    pub fn update_if(&mut self, key: String, expected: usize, value: usize, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let current = self.search(key.clone(), buffer)?;
+        if current.value != expected {
+            return Err(Error::CasMismatch)
+        }
+        self.update(KeyValuePair::new(key, value), buffer)?;
+        Ok(())
+    }
+
+    /// 键已存在时将它的值更新为 kv 中的值，否则插入 kv
+    pub fn upsert(&mut self, kv: KeyValuePair, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        match self.search(kv.key.clone(), buffer) {
+            Ok(_) => {
+                self.update(kv, buffer)?;
+                Ok(())
+            }
+            Err(Error::KeyNotFound) => self.insert(kv, buffer),
+            Err(err) => Err(err)
+        }
     }
 
     /// 查找并删除满足key的叶子节点
@@ -259,7 +663,111 @@ impl BTree {
             Err(_) => return Err(Error::UnexpectedError),
             Ok(node) => node
         };
-        guarded_node.delete()
+        guarded_node.remove_key_value_pair(&key)?;
+        self
+            .pager
+            .write_page(guarded_node.page.copy_into(&guarded_node.page.file_name, guarded_node.page.page_num), buffer)
+    }
+
+    /// 从根开始检查整棵树的结构，用于排查分裂等操作引入的错误，遇到第一处问题就返回 CorruptNode
+    /// 检查的内容：
+    /// 节点的键数不超过键宽允许的上限，中间节点的键与儿子一一对应且严格递增
+    /// 子树中的键都落在父节点中相邻两个键划定的范围内，不超过子树对应的键
+    /// 叶子内没有重复的键（叶子内的键值对按插入顺序存放，分裂时才排序，因此不要求有序）
+    /// 每个节点页中记录的父节点正确，所有叶子在同一层
+    /// 叶子的前后指针按键的顺序连成一条链，链头是记录的第一个叶子
+    pub fn validate(&self, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let mut state = Validation {
+            visited: HashSet::<usize>::new(),
+            leaves: Vec::<(usize, usize, usize)>::new(),
+            leaf_depth: None,
+        };
+        let root_offset = self.root_offset()?;
+        self.validate_node(root_offset, 0, (None, None), 0, &mut state, buffer)?;
+
+        match state.leaves.first() {
+            Some((offset, _, _)) if *offset == self.first_offset => (),
+            _ => return Err(Error::CorruptNode)
+        }
+        for (i, (offset, previous, next)) in state.leaves.iter().enumerate() {
+            let expected_previous = if i == 0 { 0 } else { state.leaves[i - 1].0 };
+            let expected_next = match state.leaves.get(i + 1) {
+                Some((next_offset, _, _)) => *next_offset,
+                None => 0
+            };
+            if *previous != expected_previous || *next != expected_next || *offset == 0 {
+                return Err(Error::CorruptNode);
+            }
+        }
+        Ok(())
+    }
+
+    /// 检查以 offset 为根的子树，子树中的键需要大于 low 且不大于 high，None 表示该侧无界
+    /// 叶子按从左到右的顺序记入 state
+    fn validate_node(&self, offset: usize, parent_offset: usize, (low, high): (Option<&str>, Option<&str>), depth: usize, state: &mut Validation, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        // 同一页出现两次说明指针成环或被多个父节点共用
+        if !state.visited.insert(offset) {
+            return Err(Error::CorruptNode);
+        }
+        let node = match BTree::load_node(&self.pager, offset, offset, buffer) {
+            Err(Error::UnexpectedError) => return Err(Error::CorruptNode),
+            res => res?
+        };
+        // 根节点的父节点记录为 0
+        if node.parent_offset != parent_offset || node.key_size != self.key_size {
+            return Err(Error::CorruptNode);
+        }
+        let keys = node.get_keys()?;
+        let in_range = |key: &String| {
+            let above_low = match low {
+                Some(low) => compare_keys(key, low) == Ordering::Greater,
+                None => true
+            };
+            let below_high = match high {
+                Some(high) => compare_keys(key, high) != Ordering::Greater,
+                None => true
+            };
+            above_low && below_high
+        };
+        if !keys.iter().all(in_range) {
+            return Err(Error::CorruptNode);
+        }
+
+        match node.node_type {
+            NodeType::Leaf => {
+                if keys.len() > leaf_max_pairs(self.key_size) {
+                    return Err(Error::CorruptNode);
+                }
+                let mut sorted = keys.clone();
+                sorted.sort_by(|a, b| compare_keys(a, b));
+                if sorted.windows(2).any(|pair| compare_keys(&pair[0], &pair[1]) == Ordering::Equal) {
+                    return Err(Error::CorruptNode);
+                }
+                match state.leaf_depth {
+                    Some(leaf_depth) if leaf_depth != depth => return Err(Error::CorruptNode),
+                    _ => state.leaf_depth = Some(depth)
+                }
+                let previous = node.page.get_value_from_offset(LEAF_NODE_PREVIOUS_NODE_PTR_OFFSET)?;
+                let next = node.page.get_value_from_offset(LEAF_NODE_NEXT_NODE_PTR_OFFSET)?;
+                state.leaves.push((offset, previous, next));
+                Ok(())
+            }
+            _ => {
+                let children = node.get_children()?;
+                if keys.is_empty() || keys.len() != children.len() || keys.len() > internal_max_keys(self.key_size) {
+                    return Err(Error::CorruptNode);
+                }
+                if keys.windows(2).any(|pair| compare_keys(&pair[0], &pair[1]) != Ordering::Less) {
+                    return Err(Error::CorruptNode);
+                }
+                let mut child_low = low;
+                for (key, child) in keys.iter().zip(children) {
+                    self.validate_node(child, offset, (child_low, Some(key.as_str())), depth + 1, state, buffer)?;
+                    child_low = Some(key.as_str());
+                }
+                Ok(())
+            }
+        }
     }
 
     /// search_node 以当前节点为根的子树递归查询一个键
@@ -290,7 +798,7 @@ impl BTree {
             NodeType::Leaf => {
                 let keys = guarded_node.get_keys()?;
                 for (i, key) in keys.iter().enumerate() {
-                    if *key == *search_key {
+                    if compare_keys(key, search_key) == Ordering::Equal {
                         let kv_pairs = guarded_node.get_key_value_pairs()?;
                         return match kv_pairs.get(i) {
                             None => Ok((Arc::clone(&node), None)),
@@ -310,7 +818,7 @@ impl BTree {
                 let keys = guarded_node.get_keys()?;
                 let mut index: Option<usize> = None;
                 for (i, key) in keys.iter().enumerate() {
-                    if *search_key <= *key.as_str() {
+                    if compare_keys(search_key, key) != Ordering::Greater {
                         index = Some(i);
                         break;
                     }
@@ -323,11 +831,8 @@ impl BTree {
                             None => return Err(Error::UnexpectedError),
                             Some(child_offset) => child_offset,
                         };
-                        let page_num = child_offset / PAGE_SIZE;
-                        let child_node = Node::try_from(NodeSpec {
-                            offset: *child_offset,
-                            page_data: self.pager.get_page(&page_num, buffer)?.get_data(),
-                        })?;
+                        // 节点的偏移就是所在的页号
+                        let child_node = BTree::load_node(&self.pager, *child_offset, *child_offset, buffer)?;
                         self.search_node(Arc::new(RwLock::new(child_node)), search_key, buffer)
                     }
                     None => Err(Error::KeyNotFound)
@@ -338,158 +843,153 @@ impl BTree {
             }
         }
     }
+}
 
-    /// search_node 以当前节点为根的子树递归查询一个键
-    /// 使用 pager 来获取页来遍历子树
-    /// 如果遍历了所有的叶子节点，还没有找到对应的键
-    /// 返回叶子节点和空来表示没找到
-    /// 否则，继续递归或者返回合适的错误
-    /// inserted字段控制在找不到合适节点时是否插入新节点并返回
-    fn search_node_inserted(
-        &mut self,
-        node: Arc<RwLock<Node>>,
-        search_key: &str,
-        buffer: &mut Box<dyn Buffer>,
-    ) -> Result<(Arc<RwLock<Node>>, Option<KeyValuePair>), Error> {
+/// validate 遍历时收集的状态
+struct Validation {
+    /// 已经访问过的页
+    visited: HashSet<usize>,
+    /// 从左到右的叶子，依次为页号、前一个叶子的页号、后一个叶子的页号
+    leaves: Vec<(usize, usize, usize)>,
+    /// 第一个叶子所在的深度
+    leaf_depth: Option<usize>,
+}
 
-        // 获取待查询子树的读权限
-        let guarded_node = match node.read() {
-            Err(_) => return Err(Error::UnexpectedError),
-            Ok(node) => node,
-        };
+/// None 表示无界，否则包括边界
+pub(crate) fn to_bound<T>(key: Option<T>) -> Bound<T> {
+    match key {
+        Some(key) => Bound::Included(key),
+        None => Bound::Unbounded
+    }
+}
 
-        // 分派节点类型
-        match guarded_node.node_type {
+/// 去掉边界键两端填充的 \0，与节点中读出的键一致
+fn trim_bound(bound: Bound<String>) -> Bound<String> {
+    match bound {
+        Bound::Included(key) => Bound::Included(trim_key(&key).to_string()),
+        Bound::Excluded(key) => Bound::Excluded(trim_key(&key).to_string()),
+        Bound::Unbounded => Bound::Unbounded
+    }
+}
 
-            // 对于叶子节点
-            // 获取叶子的所有的键
-            // 然后匹配这些键
-            NodeType::Leaf => {
-                let keys = guarded_node.get_keys()?;
-                for (i, key) in keys.iter().enumerate() {
-                    if *key == *search_key {
-                        let kv_pairs = guarded_node.get_key_value_pairs()?;
-                        return match kv_pairs.get(i) {
-                            None => Ok((Arc::clone(&node), None)),
-                            Some(kv) => Ok((Arc::clone(&node), Some(kv.clone()))),
-                        };
-                    }
-                }
-                Ok((Arc::clone(&node), None))
-            }
+/// 按键的顺序逐个叶子读取键值对的游标
+/// 叶子内的键值对按插入顺序存放，读入一个叶子后先排序
+pub(crate) struct LeafCursor {
+    next_offset: usize,
+    pending: VecDeque<KeyValuePair>,
+    left: Bound<String>,
+    right: Bound<String>,
+    done: bool,
+}
 
-            // 对于中间节点
-            // 获取节点所有的键
-            // 找到第一个比待查询键大的键
-            // 若找到，获取键左边的儿子，并递归查询
-            // 若找不到，且需要插入，则扩大最后一个键，并递归插入
-            NodeType::Internal => {
-                let keys = guarded_node.get_keys()?;
-                let mut index: Option<usize> = None;
-                for (i, key) in keys.iter().enumerate() {
-                    if *search_key <= *key.as_str() {
-                        index = Some(i);
-                        break;
-                    }
+impl LeafCursor {
+    /// 读取下一个键值对，超过右边界或读完所有叶子时返回 None
+    pub(crate) fn next(&mut self, btree: &BTree, buffer: &mut Box<dyn Buffer>) -> Option<Result<KeyValuePair, Error>> {
+        loop {
+            if let Some(kv) = self.pending.pop_front() {
+                let beyond = match &self.right {
+                    Bound::Included(right) => compare_keys(&kv.key, right) == Ordering::Greater,
+                    Bound::Excluded(right) => compare_keys(&kv.key, right) != Ordering::Less,
+                    Bound::Unbounded => false
                 };
-
-                match index {
-                    Some(i) => {
-                        let children_ptrs = guarded_node.get_children()?;
-                        let child_offset = match children_ptrs.get(i) {
-                            None => return Err(Error::UnexpectedError),
-                            Some(child_offset) => child_offset,
-                        };
-                        let page_num = child_offset / PAGE_SIZE;
-                        let child_node = Node::try_from(NodeSpec {
-                            offset: *child_offset,
-                            page_data: self.pager.as_mut().get_page(&page_num, buffer)?.get_data(),
-                        })?;
-                        self.search_node_inserted(Arc::new(RwLock::new(child_node)), search_key, buffer)
-                    }
-                    None => {
-                        // 获取最后一个键用于插入
-                        let last_key = keys.last();
-
-                        match last_key {
-                            Some(last_key) => {
-                                //获取写权限
-                                let mut write_node = match node.write() {
-                                    Err(_) => return Err(Error::UnexpectedError),
-                                    Ok(node) => node
-                                };
-
-                                // 更新最后一个键
-                                write_node.update_internal_key(last_key, search_key)?;
-
-                                // 获取最后一个儿子
-                                let children_ptrs = write_node.get_children()?;
-                                let child_offset = match children_ptrs.last() {
-                                    None => return Err(Error::UnexpectedError),
-                                    Some(child_offset) => child_offset,
-                                };
-                                let pager = self.pager.as_mut();
-                                let page_num = child_offset / PAGE_SIZE;
-                                let child_node = Node::try_from(NodeSpec {
-                                    offset: *child_offset,
-                                    page_data: pager.get_page(&page_num, buffer)?.get_data(),
-                                })?;
-
-                                // 查询最后一个儿子， 实际上这里会导致递归插入
-                                self.search_node_inserted(Arc::new(RwLock::new(child_node)), search_key, buffer)
-                            }
-                            None => Err(Error::UnexpectedError)
-                        }
-                    }
+                if beyond {
+                    self.done = true;
+                    self.pending.clear();
+                    return None;
                 }
+                return Some(Ok(kv));
             }
-            NodeType::Unknown => {
-                Err(Error::UnexpectedError)
+            if self.done || self.next_offset == 0 {
+                return None;
+            }
+            match self.load_leaf(btree, buffer) {
+                Ok(()) => (),
+                Err(err) => {
+                    // 出错后不再继续读
+                    self.done = true;
+                    return Some(Err(err));
+                }
             }
         }
     }
 
-    /// 沿当前节点向上检查所有的节点是否超过最大节点数
-    /// 若超过，则分裂
-    fn split_node(&mut self, node: Arc<RwLock<Node>>, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+    /// 读入 next_offset 处的叶子，留下左边界之后的键值对
+    fn load_leaf(&mut self, btree: &BTree, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let node = BTree::load_node(&btree.pager, self.next_offset, self.next_offset, buffer)?;
+        self.next_offset = node.page.get_value_from_offset(LEAF_NODE_NEXT_NODE_PTR_OFFSET)?;
+        let mut kv_pairs = node.get_key_value_pairs()?;
+        kv_pairs.sort();
+        for kv in kv_pairs {
+            let before = match &self.left {
+                Bound::Included(left) => compare_keys(&kv.key, left) == Ordering::Less,
+                Bound::Excluded(left) => compare_keys(&kv.key, left) != Ordering::Greater,
+                Bound::Unbounded => false
+            };
+            if before {
+                continue;
+            }
+            self.pending.push_back(kv);
+        }
+        Ok(())
+    }
+}
 
-        // 获取写权限
-        let mut guarded_node = match node.write() {
-            Err(_) => return Err(Error::UnexpectedError),
-            Ok(node) => node,
+/// BTree::iter_range 返回的迭代器
+pub struct RangeIter<'a> {
+    btree: &'a BTree,
+    buffer: &'a mut Box<dyn Buffer>,
+    cursor: LeafCursor,
+}
+
+impl<'a> Iterator for RangeIter<'a> {
+    type Item = Result<KeyValuePair, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.cursor.next(self.btree, self.buffer)
+    }
+}
+
+/// BTree::iter_rev 返回的迭代器，每次读入一个叶子
+pub struct RevIter<'a> {
+    btree: &'a BTree,
+    buffer: &'a mut Box<dyn Buffer>,
+    /// 下一个要读的叶子，None 表示还没有找到最右边的叶子
+    next_offset: Option<usize>,
+    /// 当前叶子中还没有返回的键值对，按键从小到大排列，从末尾取出
+    pending: Vec<KeyValuePair>,
+    done: bool,
+}
+
+impl<'a> RevIter<'a> {
+    /// 读入下一个叶子
+    fn load_leaf(&mut self) -> Result<(), Error> {
+        let node = match self.next_offset {
+            None => self.btree.edge_leaf(false, self.buffer)?,
+            Some(offset) => BTree::load_node(&self.btree.pager, offset, offset, self.buffer)?
         };
+        self.next_offset = Some(node.page.get_value_from_offset(LEAF_NODE_PREVIOUS_NODE_PTR_OFFSET)?);
+        self.pending = node.get_key_value_pairs()?;
+        self.pending.sort();
+        Ok(())
+    }
+}
 
-        if guarded_node.is_root {
-            // 如果是根节点，直接分裂
-            let (is_split, offset) = guarded_node.split(&mut self.pager, buffer)?;
-            if guarded_node.offset == self.first_offset && is_split {
-                self.first_offset = offset;
-            }
-            Ok(())
-        } else {
-            // 如果当前节点分裂，则父节点也可能需要分裂
-            let (is_split, offset) = guarded_node.split(&mut self.pager, buffer)?;
-            if is_split {
-                if guarded_node.offset == self.first_offset {
-                    self.first_offset = offset;
-                }
-                let page_num = guarded_node.parent_offset / PAGE_SIZE;
-                let parent_node =
-                    Arc::new(
-                        RwLock::new(
-                            Node::try_from(
-                                NodeSpec {
-                                    page_data: self.pager.get_page(&page_num, buffer).unwrap().get_data(),
-                                    offset: guarded_node.parent_offset,
-                                }
-                            )?
-                        )
-                    );
-                // 递归分裂父节点
-                self.split_node(parent_node, buffer)?;
+impl<'a> Iterator for RevIter<'a> {
+    type Item = Result<KeyValuePair, Error>;
 
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(kv) = self.pending.pop() {
+                return Some(Ok(kv));
+            }
+            if self.done || self.next_offset == Some(0) {
+                return None;
+            }
+            if let Err(err) = self.load_leaf() {
+                // 出错后不再继续读
+                self.done = true;
+                return Some(Err(err));
             }
-            Ok(())
         }
     }
 }
@@ -1,6 +1,5 @@
 use std::cmp::Ordering;
 
-#[derive(Eq, PartialEq)]
 pub struct KeyValuePair {
     pub key: String,
     pub value: usize,
@@ -21,6 +20,15 @@ impl Clone for KeyValuePair {
     }
 }
 
+/// 相等与排序一致：去掉填充后的键相同且值相同
+impl PartialEq for KeyValuePair {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for KeyValuePair {}
+
 impl PartialOrd for KeyValuePair {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
@@ -29,6 +37,18 @@ impl PartialOrd for KeyValuePair {
 
 impl Ord for KeyValuePair {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.key.cmp(&other.key)
+        // 先比较键，键相同时再比较值
+        compare_keys(&self.key, &other.key).then(self.value.cmp(&other.value))
     }
 }
+
+/// 去掉键两端的 \0，节点中的键以 \0 补足键宽
+pub(crate) fn trim_key(key: &str) -> &str {
+    key.trim_matches(char::from(0))
+}
+
+/// 比较两个键，比较前统一去掉填充的 \0
+/// 插入、分裂和查找都用它比较键，填充与否不影响顺序
+pub(crate) fn compare_keys(a: &str, b: &str) -> Ordering {
+    trim_key(a).cmp(trim_key(b))
+}
@@ -1,9 +1,9 @@
+use std::cmp::Ordering;
 use std::convert::TryFrom;
 use std::str;
-use std::sync::{Arc, RwLock};
 
 use crate::index::btree::MAX_BRANCHING_FACTOR;
-use crate::index::key_value_pair::KeyValuePair;
+use crate::index::key_value_pair::{compare_keys, trim_key, KeyValuePair};
 use crate::page::page_item::{Page, PAGE_SIZE, PTR_SIZE};
 use crate::page::pager::Pager;
 use crate::util::error::Error;
@@ -56,9 +56,36 @@ const INTERNAL_NODE_KEY_OFFSET: usize = INTERNAL_NODE_CHILDREN_OFFSET + MAX_SPAC
 pub(crate) const MAX_SPACE_FOR_KEYS: usize = PAGE_SIZE - INTERNAL_NODE_HEADER_SIZE - MAX_SPACE_FOR_CHILDREN;
 
 /// 键和值的大小
+/// KEY_SIZE 是索引默认的键宽，每个索引可以在创建时指定自己的键宽
 pub(crate) const KEY_SIZE: usize = 10;
 pub(crate) const VALUE_SIZE: usize = PTR_SIZE;
 
+/// 节点所用的键宽记录在页尾，为 0 时表示默认键宽 KEY_SIZE
+const NODE_KEY_SIZE_OFFSET: usize = PAGE_SIZE - PTR_SIZE;
+
+/// 给定键宽时，叶子节点最多能存放的键值对数
+pub(crate) fn leaf_max_pairs(key_size: usize) -> usize {
+    let capacity = (NODE_KEY_SIZE_OFFSET - LEAF_NODE_HEADER_SIZE) / (key_size + VALUE_SIZE);
+    if capacity < LEAF_NODE_MAX_KEY_VALUE_PAIRS {
+        capacity
+    } else {
+        LEAF_NODE_MAX_KEY_VALUE_PAIRS
+    }
+}
+
+/// 给定键宽时，中间节点最多能存放的键数
+pub(crate) fn internal_max_keys(key_size: usize) -> usize {
+    if key_size == 0 {
+        return 0;
+    }
+    let capacity = (NODE_KEY_SIZE_OFFSET - INTERNAL_NODE_KEY_OFFSET) / key_size;
+    if capacity < MAX_BRANCHING_FACTOR {
+        capacity
+    } else {
+        MAX_BRANCHING_FACTOR
+    }
+}
+
 #[derive(PartialEq)]
 pub enum NodeType {
     Internal = 1,
@@ -108,6 +135,7 @@ pub struct Node {
     pub parent_offset: usize,
     pub is_root: bool,
     pub offset: usize,
+    pub key_size: usize,
     pub page: Page,
 }
 
@@ -118,6 +146,7 @@ impl Node {
         offset: usize,
         is_root: bool,
         mut page: Page,
+        key_size: usize,
     ) -> Result<Node, Error> {
         match node_type {
             NodeType::Internal => {
@@ -137,11 +166,14 @@ impl Node {
             }
             _ => return Err(Error::UnexpectedError)
         }
+        page.write_value_at_offset(PARENT_POINTER_OFFSET, parent_offset)?;
+        page.write_value_at_offset(NODE_KEY_SIZE_OFFSET, key_size)?;
         Ok(Node {
             node_type,
             parent_offset,
             offset,
             is_root,
+            key_size,
             page,
         })
     }
@@ -158,19 +190,19 @@ impl Node {
                 offset = LEAF_NODE_HEADER_SIZE;
 
                 for _i in 0..num_keys_val_pairs {
-                    let key_raw = self.page.get_ptr_from_offset(offset, KEY_SIZE);
+                    let key_raw = self.page.get_ptr_from_offset(offset, self.key_size);
                     let key = match str::from_utf8(key_raw) {
                         Ok(key) => key,
                         Err(_) => return Err(Error::UTF8Error),
                     };
-                    offset += KEY_SIZE;
+                    offset += self.key_size;
 
                     let value = self.page.get_value_from_offset(offset)?;
                     offset += VALUE_SIZE;
 
                     // 去除首位0字符
                     res.push(KeyValuePair::new(
-                        key.trim_matches(char::from(0)).to_string(),
+                        trim_key(key).to_string(),
                         value,
                     ))
                 }
@@ -208,14 +240,14 @@ impl Node {
                 let mut offset = INTERNAL_NODE_KEY_OFFSET;
                 let num_keys = self.page.get_value_from_offset(INTERNAL_NODE_NUM_KEY_OFFSET)?;
                 for _i in 1..=num_keys {
-                    let key_raw = self.page.get_ptr_from_offset(offset, KEY_SIZE);
+                    let key_raw = self.page.get_ptr_from_offset(offset, self.key_size);
                     let key = match str::from_utf8(key_raw) {
                         Ok(key) => key,
                         Err(_) => return Err(Error::UTF8Error),
                     };
-                    offset += KEY_SIZE;
+                    offset += self.key_size;
                     // 去掉首尾 \0 字符
-                    result.push(key.trim_matches(char::from(0)).to_string());
+                    result.push(trim_key(key).to_string());
                 }
                 Ok(result)
             }
@@ -225,14 +257,14 @@ impl Node {
                 let num_keys_val_pairs = self.page.get_value_from_offset(offset)?;
                 offset = LEAF_NODE_HEADER_SIZE;
                 for _i in 1..=num_keys_val_pairs {
-                    let key_raw = self.page.get_ptr_from_offset(offset, KEY_SIZE);
+                    let key_raw = self.page.get_ptr_from_offset(offset, self.key_size);
                     let key = match str::from_utf8(key_raw) {
                         Ok(key) => key,
                         Err(_) => return Err(Error::UTF8Error),
                     };
                     // 跳过value
-                    offset += KEY_SIZE + VALUE_SIZE;
-                    res.push(key.trim_matches(char::from(0)).to_string());
+                    offset += self.key_size + VALUE_SIZE;
+                    res.push(trim_key(key).to_string());
                 }
                 Ok(res)
             }
@@ -246,82 +278,65 @@ impl Node {
         match self.node_type {
             NodeType::Leaf => {
                 let num_keys_val_pairs = self.page.get_value_from_offset(LEAF_NODE_NUM_PAIRS_OFFSET)?;
-                if num_keys_val_pairs >= LEAF_NODE_MAX_KEY_VALUE_PAIRS {
+                if num_keys_val_pairs >= leaf_max_pairs(self.key_size) {
                     return Err(Error::UnexpectedError);
                 }
-                let offset = LEAF_NODE_HEADER_SIZE + (KEY_SIZE + VALUE_SIZE) * num_keys_val_pairs;
+                let offset = LEAF_NODE_HEADER_SIZE + (self.key_size + VALUE_SIZE) * num_keys_val_pairs;
                 // 更新键值对数
                 self.page.write_value_at_offset(LEAF_NODE_NUM_PAIRS_OFFSET, num_keys_val_pairs + 1)?;
 
                 // 写入键值对
-                let key_raw = kv.key.as_bytes();
-                self.page.write_bytes_at_offset(key_raw, offset, KEY_SIZE)?;
+                let key_raw = trim_key(&kv.key).as_bytes();
+                self.page.write_bytes_at_offset(key_raw, offset, self.key_size)?;
                 let value_raw = kv.value.to_be_bytes();
-                self.page.write_bytes_at_offset(&value_raw, offset + KEY_SIZE, VALUE_SIZE)?;
+                self.page.write_bytes_at_offset(&value_raw, offset + self.key_size, VALUE_SIZE)?;
                 Ok(())
             }
             _ => Err(Error::UnexpectedError),
         }
     }
 
-    /// 增加一个键, 和该键的右子节点
+    /// 增加一个键, 和该键对应的左子节点
+    /// 中间节点的键与儿子一一对应，键是对应子树中最大的键
     /// 只应当在中间节点上使用.
     pub fn add_key_and_left_child(&mut self, key: String, left_child_offset: usize) -> Result<(), Error> {
         match self.node_type {
             NodeType::Internal => {
-                // 更新孩子数 (等于键数+1)
-                let num_children = self.page.get_value_from_offset(INTERNAL_NODE_NUM_CHILDREN_OFFSET)?;
-                self.page.write_value_at_offset(INTERNAL_NODE_NUM_CHILDREN_OFFSET, num_children + 1)?;
-
-                // 寻找新键的位置.
-                let num_keys = self.page.get_value_from_offset(INTERNAL_NODE_NUM_KEY_OFFSET)?;
-
-                let mut offset = INTERNAL_NODE_KEY_OFFSET;
-                let end_key_data = offset + num_keys * KEY_SIZE;
+                let key = trim_key(&key).to_string();
+                let mut keys = self.get_keys()?;
+                let mut children = self.get_children()?;
+
+                // 寻找新键的位置，保持键有序
+                let index = match keys.iter().position(|iter_key| compare_keys(iter_key, &key) == Ordering::Greater) {
+                    Some(index) => index,
+                    None => keys.len(),
+                };
+                keys.insert(index, key);
+                children.insert(index, left_child_offset);
+                self.set_keys_and_children(&keys, &children)
+            }
+            _ => Err(Error::UnexpectedError),
+        }
+    }
 
-                for i in 0..num_keys {
-                    let key_raw = self.page.get_ptr_from_offset(offset, KEY_SIZE);
-                    let iter_key = match str::from_utf8(key_raw) {
-                        Ok(key) => key,
-                        Err(_) => return Err(Error::UTF8Error),
-                    };
-                    if *iter_key > *key.as_str() {
-                        // 找到位置.
-                        self.page.insert_bytes_at_offset(
-                            key.as_bytes(),
-                            offset,
-                            end_key_data,
-                            KEY_SIZE,
-                        )?;
-                        offset = INTERNAL_NODE_CHILDREN_OFFSET;
-                        let end_child_data = offset + num_children * PTR_SIZE;
-                        offset += i * PTR_SIZE;
-                        self.page.insert_bytes_at_offset(
-                            &left_child_offset.to_be_bytes(),
-                            offset,
-                            end_child_data,
-                            PTR_SIZE,
-                        )?;
-                        return Ok(());
-                    }
-                    offset += KEY_SIZE;
+    /// 用给定的键和儿子覆盖中间节点原有的键和儿子
+    pub(crate) fn set_keys_and_children(&mut self, keys: &[String], children: &[usize]) -> Result<(), Error> {
+        match self.node_type {
+            NodeType::Internal => {
+                if keys.len() > internal_max_keys(self.key_size) || children.len() > MAX_BRANCHING_FACTOR + 1 {
+                    return Err(Error::UnexpectedError);
+                }
+                self.page.write_value_at_offset(INTERNAL_NODE_NUM_CHILDREN_OFFSET, children.len())?;
+                self.page.write_value_at_offset(INTERNAL_NODE_NUM_KEY_OFFSET, keys.len())?;
+                for (i, child) in children.iter().enumerate() {
+                    self.page.write_value_at_offset(INTERNAL_NODE_CHILDREN_OFFSET + i * PTR_SIZE, *child)?;
+                }
+                for (i, key) in keys.iter().enumerate() {
+                    // 不足键宽的部分补 \0
+                    let mut key_raw = key.as_bytes().to_vec();
+                    key_raw.resize(self.key_size, 0);
+                    self.page.write_bytes_at_offset(&key_raw, INTERNAL_NODE_KEY_OFFSET + i * self.key_size, self.key_size)?;
                 }
-                // 找到位置.
-                self.page.insert_bytes_at_offset(
-                    key.as_bytes(),
-                    offset,
-                    end_key_data,
-                    KEY_SIZE,
-                )?;
-                offset = INTERNAL_NODE_CHILDREN_OFFSET;
-                let end_child_data = offset + num_children * PTR_SIZE;
-                offset += num_children * PTR_SIZE - KEY_SIZE;
-                self.page.insert_bytes_at_offset(
-                    &left_child_offset.to_be_bytes(),
-                    offset,
-                    end_child_data,
-                    PTR_SIZE,
-                )?;
                 Ok(())
             }
             _ => Err(Error::UnexpectedError),
@@ -337,13 +352,30 @@ impl Node {
         }
     }
 
+    /// 修改父节点的页号，同时写入页中
+    pub(crate) fn set_parent_offset(&mut self, parent_offset: usize) -> Result<(), Error> {
+        self.page.write_value_at_offset(PARENT_POINTER_OFFSET, parent_offset)?;
+        self.parent_offset = parent_offset;
+        Ok(())
+    }
+
+    /// 分裂出的新节点的父节点
+    /// 根节点分裂后所在的页继续作为根，成为新节点的父节点
+    fn split_parent_offset(&self) -> usize {
+        if self.is_root {
+            self.offset
+        } else {
+            self.parent_offset
+        }
+    }
+
     /// get_keys 返回当前节点中包含键的键值对.
     pub fn find_key_value_pair(&self, key: String) -> Result<KeyValuePair, Error> {
         match self.node_type {
             NodeType::Leaf => {
                 let kv_pairs = self.get_key_value_pairs()?;
                 for kv_pair in kv_pairs {
-                    if kv_pair.key == key {
+                    if compare_keys(&kv_pair.key, &key) == Ordering::Equal {
                         return Ok(kv_pair);
                     }
                 }
@@ -357,28 +389,20 @@ impl Node {
     pub fn update_internal_key(&mut self, old_key: &str, new_key: &str) -> Result<(), Error> {
         match self.node_type {
             NodeType::Internal => {
-                let num_children = self.page.get_value_from_offset(INTERNAL_NODE_NUM_CHILDREN_OFFSET)?;
-                let mut offset = INTERNAL_NODE_HEADER_SIZE + num_children * PTR_SIZE;
-                let num_keys = self.page.get_value_from_offset(INTERNAL_NODE_NUM_KEY_OFFSET)?;
-                for _i in 1..=num_keys {
-                    let key_raw = self.page.get_ptr_from_offset(offset, KEY_SIZE);
-                    let key = match str::from_utf8(key_raw) {
-                        Ok(key) => key,
-                        Err(_) => return Err(Error::UTF8Error),
-                    };
-                    if *key == *old_key {
-                        return self.page.write_bytes_at_offset(new_key.trim_matches(char::from(0)).as_bytes(), offset, KEY_SIZE);
-                    }
-                    offset += KEY_SIZE;
+                let mut keys = self.get_keys()?;
+                let children = self.get_children()?;
+                match keys.iter().position(|key| compare_keys(key, old_key) == Ordering::Equal) {
+                    Some(index) => keys[index] = trim_key(new_key).to_string(),
+                    None => return Err(Error::KeyNotFound)
                 }
-                Err(Error::KeyNotFound)
+                self.set_keys_and_children(&keys, &children)
             }
             _ => Err(Error::UnexpectedError)
         }
     }
 
     /// 将内部节点的指定offset更新成新的offset
-    fn update_internal_value(&mut self, old_node_offset: &usize, new_node_offset: &usize) -> Result<(), Error> {
+    pub(crate) fn update_internal_value(&mut self, old_node_offset: &usize, new_node_offset: &usize) -> Result<(), Error> {
         match self.node_type {
             NodeType::Internal => {
                 for (i, offset) in self.get_children()?.iter().enumerate() {
@@ -393,6 +417,30 @@ impl Node {
         }
     }
 
+    /// 删除一个键和它对应的儿子，其后的键和儿子依次前移，返回被删除的儿子
+    /// 是 add_key_and_left_child 的逆操作，只应当在中间节点上使用.
+    pub fn remove_key_and_child(&mut self, key: &str) -> Result<usize, Error> {
+        match self.node_type {
+            NodeType::Internal => {
+                let mut keys = self.get_keys()?;
+                let mut children = self.get_children()?;
+                let index = match keys.iter().position(|k| compare_keys(k, key) == Ordering::Equal) {
+                    Some(index) if index < children.len() => index,
+                    _ => return Err(Error::KeyNotFound),
+                };
+                keys.remove(index);
+                let child_offset = children.remove(index);
+                self.set_keys_and_children(&keys, &children)?;
+
+                // 清空原来的最后一个键和儿子
+                self.page.write_bytes_at_offset(&vec![0u8; self.key_size], INTERNAL_NODE_KEY_OFFSET + keys.len() * self.key_size, self.key_size)?;
+                self.page.write_value_at_offset(INTERNAL_NODE_CHILDREN_OFFSET + children.len() * PTR_SIZE, 0)?;
+                Ok(child_offset)
+            }
+            _ => Err(Error::UnexpectedError)
+        }
+    }
+
     /// update_value 更新当前节点中包含键的键值对.
     pub fn update_value(&mut self, kv: KeyValuePair) -> Result<(), Error> {
         match self.node_type {
@@ -403,13 +451,13 @@ impl Node {
                 offset = LEAF_NODE_HEADER_SIZE;
 
                 for _i in 0..num_keys_val_pairs {
-                    let key_raw = self.page.get_ptr_from_offset(offset, KEY_SIZE);
+                    let key_raw = self.page.get_ptr_from_offset(offset, self.key_size);
                     let key = match str::from_utf8(key_raw) {
                         Ok(key) => key,
                         Err(_) => return Err(Error::UTF8Error),
                     };
-                    offset += KEY_SIZE;
-                    if key.trim_matches(char::from(0)) == kv.key.trim_matches(char::from(0)) {
+                    offset += self.key_size;
+                    if compare_keys(key, &kv.key) == Ordering::Equal {
                         let value_raw = kv.value.to_be_bytes();
                         self.page.write_bytes_at_offset(&value_raw, offset, VALUE_SIZE)?;
                         return Ok(());
@@ -422,80 +470,32 @@ impl Node {
         }
     }
 
-    /// 向key和children数量一样的节点加一个child
-    fn add_child(&mut self, child_offset: usize) -> Result<(), Error> {
-        match self.node_type {
-            NodeType::Internal => {
-                let child_num = self.page.get_value_from_offset(INTERNAL_NODE_NUM_CHILDREN_OFFSET)?;
-                let key_num = self.page.get_value_from_offset(INTERNAL_NODE_NUM_KEY_OFFSET)?;
-                if key_num < child_num {
-                    return Err(Error::UnexpectedError);
-                }
-                self.page.write_bytes_at_offset(&(child_num + 1).to_be_bytes(), INTERNAL_NODE_CHILDREN_OFFSET, INTERNAL_NODE_NUM_CHILDREN_SIZE)?;
-                let offset = INTERNAL_NODE_CHILDREN_OFFSET + PTR_SIZE * child_num;
-                self.page.write_bytes_at_offset(&child_offset.to_be_bytes(), offset, PTR_SIZE)?;
-                Ok(())
-            }
-            _ => Err(Error::UnexpectedError)
+    /// 分裂内部节点，前一半的键和儿子给新左儿子，后一半给新右儿子
+    /// 返回新左儿子、新左儿子中最大的键和新右儿子，新右儿子中最大的键仍是当前节点最大的键
+    pub(crate) fn split_internal(&mut self, pager: &mut Pager, buffer: &mut Box<dyn Buffer>) -> Result<(Node, String, Node), Error> {
+        let keys = self.get_keys()?;
+        let children = self.get_children()?;
+        if keys.len() < 2 || keys.len() != children.len() {
+            return Err(Error::UnexpectedError);
         }
-    }
+        let mid = keys.len() / 2;
 
-    /// 分裂内部节点
-    /// !!!不做任何检查!!!
-    fn split_internal(&mut self, pager: &mut Pager, buffer: &mut Box<dyn Buffer>) -> Result<(Node, String, Node), Error> {
-        let mut offset = INTERNAL_NODE_KEY_OFFSET;
-        let num_key = self.page.get_value_from_offset(INTERNAL_NODE_NUM_KEY_OFFSET)?;
-        let children = self.get_children()?;
-        let split_node_num_key = num_key / 2;
         let left_page = pager.get_new_page(buffer)?;
         let right_page = pager.get_new_page(buffer)?;
-        let mut left_node = Node::new(NodeType::Internal, self.parent_offset, left_page.page_num, false, left_page)?;
-        let mut right_node = Node::new(NodeType::Internal, self.parent_offset, right_page.page_num, false, right_page)?;
-
-        // 前一半的键给新左儿子
-        for i in 1..split_node_num_key {
-            let key_raw = self.page.get_ptr_from_offset(offset, KEY_SIZE);
-            let child_offset = children.get(i - 1).unwrap();
-            let key = match str::from_utf8(key_raw) {
-                Ok(key) => key,
-                Err(_) => return Err(Error::UTF8Error),
-            };
-            left_node.add_key_and_left_child(key.trim_matches(char::from(0)).to_string(), *child_offset)?;
-            offset += KEY_SIZE;
-        }
-
-        // 跳过中间键（中间键需要上弹）
-        offset += KEY_SIZE;
-
-        // 中间键的左儿子给新左儿子
-        let median_offset = children.get(split_node_num_key).unwrap();
-        left_node.add_child(*median_offset)?;
-
-        // 后一半的键给新右儿子
-        for i in split_node_num_key + 1..num_key {
-            let key_raw = self.page.get_ptr_from_offset(offset, KEY_SIZE);
-            let child_offset = children.get(i).unwrap();
-            let key = match str::from_utf8(key_raw) {
-                Ok(key) => key,
-                Err(_) => return Err(Error::UTF8Error),
-            };
-            right_node.add_key_and_left_child(String::from(key), *child_offset)?;
-            offset += KEY_SIZE;
+        let parent_offset = self.split_parent_offset();
+        let mut left_node = Node::new(NodeType::Internal, parent_offset, left_page.page_num, false, left_page, self.key_size)?;
+        let mut right_node = Node::new(NodeType::Internal, parent_offset, right_page.page_num, false, right_page, self.key_size)?;
+        left_node.set_keys_and_children(&keys[..mid], &children[..mid])?;
+        right_node.set_keys_and_children(&keys[mid..], &children[mid..])?;
+
+        // 儿子换到了新节点下，改写它们页中的父节点
+        for (i, child_offset) in children.iter().enumerate() {
+            let mut child = Node::load(pager, *child_offset, buffer)?;
+            child.set_parent_offset(if i < mid { left_node.offset } else { right_node.offset })?;
+            pager.write_page(child.page, buffer)?;
         }
 
-        // 最后一个儿子给右儿子
-        let child_offset = children.get(num_key).unwrap();
-        right_node.add_child(*child_offset)?;
-
-        // 将中间键作为上弹的键
-        offset = INTERNAL_NODE_KEY_OFFSET;
-        let median_key_raw = self.page.get_ptr_from_offset(offset, KEY_SIZE);
-        let median_key = match str::from_utf8(median_key_raw) {
-            Ok(key) => key,
-            Err(_) => return Err(Error::UTF8Error),
-        };
-
-        Ok((left_node, median_key.trim_matches(char::from(0)).to_string(), right_node))
+        Ok((left_node, keys[mid - 1].clone(), right_node))
     }
 
     pub fn add_next_node(&mut self, offset: usize) -> Result<(), Error> {
@@ -504,14 +504,7 @@ impl Node {
 
     pub fn get_next_node(&self, pager: &mut Pager, buffer: &mut Box<dyn Buffer>) -> Result<Node, Error> {
         let offset = self.page.get_value_from_offset(LEAF_NODE_NEXT_NODE_PTR_OFFSET)?;
-        let page_num = offset / PAGE_SIZE;
-        let next_node = Node::try_from(
-            NodeSpec {
-                page_data: pager.get_page(&page_num, buffer).unwrap().get_data(),
-                offset,
-            }
-        )?;
-        Ok(next_node)
+        Node::load(pager, offset, buffer)
     }
 
     pub fn add_previous_node(&mut self, offset: usize) -> Result<(), Error> {
@@ -520,26 +513,32 @@ impl Node {
 
     pub fn get_previous_node(&self, pager: &mut Pager, buffer: &mut Box<dyn Buffer>) -> Result<Node, Error> {
         let offset = self.page.get_value_from_offset(LEAF_NODE_PREVIOUS_NODE_PTR_OFFSET)?;
-        let page_num = offset / PAGE_SIZE;
-        let previous_node = Node::try_from(
-            NodeSpec {
-                page_data: pager.get_page(&page_num, buffer).unwrap().get_data(),
-                offset,
-            }
-        )?;
-        Ok(previous_node)
+        Node::load(pager, offset, buffer)
+    }
+
+    /// 读取 page_num 页上的节点，节点的偏移就是页号
+    /// try_from 得到的是幽灵页，换回真实的页才能写回缓冲区
+    pub(crate) fn load(pager: &Pager, page_num: usize, buffer: &mut Box<dyn Buffer>) -> Result<Node, Error> {
+        let page = pager.get_page(&page_num, buffer)?;
+        let mut node = Node::try_from(NodeSpec {
+            page_data: page.get_data(),
+            offset: page_num,
+        })?;
+        node.page = page;
+        Ok(node)
     }
 
 
-    /// 分裂叶子节点
+    /// 分裂叶子节点，返回新左叶子、新左叶子中最大的键和新右叶子
     /// !!!不做任何检查!!!
-    fn split_leaf(&mut self, pager: &mut Pager, buffer: &mut Box<dyn Buffer>) -> Result<(Node, String, Node), Error> {
+    pub(crate) fn split_leaf(&mut self, pager: &mut Pager, buffer: &mut Box<dyn Buffer>) -> Result<(Node, String, Node), Error> {
         // 初始化新的左右叶子节点
         let mut kv_pairs = self.get_key_value_pairs()?;
         let left_leaf_page = pager.get_new_page(buffer)?;
         let right_leaf_page = pager.get_new_page(buffer)?;
-        let mut left_leaf = Node::new(NodeType::Leaf, self.parent_offset, left_leaf_page.page_num, false, left_leaf_page)?;
-        let mut right_leaf = Node::new(NodeType::Leaf, self.parent_offset, right_leaf_page.page_num, false, right_leaf_page)?;
+        let parent_offset = self.split_parent_offset();
+        let mut left_leaf = Node::new(NodeType::Leaf, parent_offset, left_leaf_page.page_num, false, left_leaf_page, self.key_size)?;
+        let mut right_leaf = Node::new(NodeType::Leaf, parent_offset, right_leaf_page.page_num, false, right_leaf_page, self.key_size)?;
         left_leaf.add_next_node(right_leaf.offset)?;
         let previous_node_offset = self.page.get_value_from_offset(LEAF_NODE_PREVIOUS_NODE_PTR_OFFSET)?;
         left_leaf.add_previous_node(previous_node_offset)?;
@@ -548,14 +547,17 @@ impl Node {
         let next_node_offset = self.page.get_value_from_offset(LEAF_NODE_NEXT_NODE_PTR_OFFSET)?;
         right_leaf.add_next_node(next_node_offset)?;
 
+        // 相邻叶子的指针改为指向新叶子并写回
         if previous_node_offset != 0 {
             let mut previous_node = left_leaf.get_previous_node(pager, buffer)?;
             previous_node.add_next_node(left_leaf.offset)?;
+            pager.write_page(previous_node.page.copy_into(&previous_node.page.file_name, previous_node.page.page_num), buffer)?;
         }
 
         if next_node_offset != 0 {
             let mut next_node = right_leaf.get_next_node(pager, buffer)?;
             next_node.add_previous_node(right_leaf.offset)?;
+            pager.write_page(next_node.page.copy_into(&next_node.page.file_name, next_node.page.page_num), buffer)?;
         }
 
         kv_pairs.sort();
@@ -568,115 +570,37 @@ impl Node {
             }
         }
 
-        Ok((left_leaf, kv_pairs.get(mid).unwrap().key.clone(), right_leaf))
+        // 父节点中左叶子对应的键是左叶子中最大的键
+        let left_max = match kv_pairs.get(mid.wrapping_sub(1)) {
+            Some(kv) => kv.key.clone(),
+            None => return Err(Error::UnexpectedError)
+        };
+        Ok((left_leaf, left_max, right_leaf))
     }
 
 
-    /// 将当前节点分裂成两个节点，并返回中介节点的键和两个节点
-    pub(crate) fn split(&mut self, pager: &mut Pager, buffer: &mut Box<dyn Buffer>) -> Result<(bool, usize), Error> {
-        if self.is_root {
-
-            // 根节点不满足分裂要求
-            if self.get_keys_len()? <= MAX_BRANCHING_FACTOR {
-                return Ok((false, 0));
-            }
-
-            let (left_node, median_key, right_node) = self.split_internal(pager, buffer)?;
-
-            // 新的根节点只有两个儿子，分别是新左儿子、新右儿子
-            self.page.write_value_at_offset(INTERNAL_NODE_NUM_CHILDREN_OFFSET, 2)?;
-
-            // 将新左儿子、新右儿子写入到根节点的儿子偏移处
-            let offset = INTERNAL_NODE_CHILDREN_OFFSET;
-            self.page.write_bytes_at_offset(&left_node.offset.to_be_bytes(), offset, PTR_SIZE)?;
-            self.page.write_bytes_at_offset(&right_node.offset.to_be_bytes(), offset, PTR_SIZE)?;
-
-            // 将新的键写入根节点
-            self.page.write_bytes_at_offset(median_key.as_bytes(), offset, KEY_SIZE)?;
-
-            // 有分裂，返回true
-            return Ok((true, left_node.offset));
-        }
-
-        // 不是根节点的情况
+    /// 从叶子节点中删除一个键值对，其后的键值对依次前移
+    /// 非叶子节点抛出异常
+    pub fn remove_key_value_pair(&mut self, key: &str) -> Result<(), Error> {
         match self.node_type {
-            NodeType::Internal => {
-
-                // 是中间节点且不满足分裂条件
-                if self.get_keys_len()? < MAX_BRANCHING_FACTOR {
-                    return Ok((false, 0));
-                }
-
-                // 分裂当前节点
-                let (left_node, median_key, right_node) = self.split_internal(pager, buffer)?;
-
-                // 获取父节点
-                let parent_offset = self.parent_offset;
-                let page_num = parent_offset / PAGE_SIZE;
-                let lock =
-                    Arc::new(
-                        RwLock::new(
-                            Node::try_from(
-                                NodeSpec {
-                                    page_data: pager.get_page(&page_num, buffer).unwrap().get_data(),
-                                    offset: parent_offset,
-                                }
-                            )?
-                        )
-                    );
-                let mut parent_node = match lock.write() {
-                    Err(_) => return Err(Error::UnexpectedError),
-                    Ok(node) => node,
-                };
-                // 将新左儿子加到父亲
-                parent_node.add_key_and_left_child(median_key, left_node.offset)?;
-                parent_node.update_internal_value(&self.offset, &right_node.offset)?;
-                // todo 释放当前节点
-                Ok((true, left_node.offset))
-            }
             NodeType::Leaf => {
+                let num_keys_val_pairs = self.page.get_value_from_offset(LEAF_NODE_NUM_PAIRS_OFFSET)?;
+                let pair_size = self.key_size + VALUE_SIZE;
+                let index = match self.get_keys()?.iter().position(|k| compare_keys(k, key) == Ordering::Equal) {
+                    Some(index) => index,
+                    None => return Err(Error::KeyNotFound),
+                };
 
-                // 是叶子节点，且不满足分裂条件
-                if self.get_key_value_pairs()?.len() < LEAF_NODE_MAX_KEY_VALUE_PAIRS {
-                    return Ok((false, 0));
-                }
+                // 后面的键值对整体前移一格
+                let offset = LEAF_NODE_HEADER_SIZE + pair_size * index;
+                let end = LEAF_NODE_HEADER_SIZE + pair_size * num_keys_val_pairs;
+                let tail = self.page.get_ptr_from_offset(offset + pair_size, end - offset - pair_size).to_vec();
+                self.page.write_bytes_at_offset(&tail, offset, tail.len())?;
 
-                // 分裂当前节点
-                let (left_leaf, median_key, right_leaf) = self.split_leaf(pager, buffer)?;
-
-                // 获取父节点
-                let parent_offset = self.parent_offset;
-                let page_num = parent_offset / PAGE_SIZE;
-                let lock_parent_node =
-                    Arc::new(
-                        RwLock::new(
-                            Node::try_from(
-                                NodeSpec {
-                                    page_data: pager.get_page(&page_num, buffer).unwrap().get_data(),
-                                    offset: parent_offset,
-                                }
-                            )?
-                        )
-                    );
-                let mut parent_node = match lock_parent_node.write() {
-                    Err(_) => return Err(Error::UnexpectedError),
-                    Ok(node) => node,
-                };
-                parent_node.add_key_and_left_child(median_key, left_leaf.offset)?;
-                parent_node.update_internal_value(&self.offset, &right_leaf.offset)?;
-                // todo 释放当前节点
-                Ok((true, left_leaf.offset))
+                // 清空原来的最后一个键值对
+                self.page.write_bytes_at_offset(&vec![0u8; pair_size], end - pair_size, pair_size)?;
+                self.page.write_value_at_offset(LEAF_NODE_NUM_PAIRS_OFFSET, num_keys_val_pairs - 1)
             }
-            NodeType::Unknown => Err(Error::UnexpectedError),
-        }
-    }
-
-    /// 将叶子节点的有效位置零
-    /// 非叶子节点抛出异常
-    /// todo 节点删除
-    pub fn delete(&mut self) -> Result<(), Error> {
-        match self.node_type {
-            NodeType::Leaf => Err(Error::UnexpectedError),
             _ => Err(Error::UnexpectedError)
         }
     }
@@ -685,10 +609,18 @@ impl Node {
 impl TryFrom<Node> for [u8; PAGE_SIZE] {
     type Error = Error;
 
+    /// 节点页中的数据就是节点的内容，只需要把结构体中的头部字段写回
     fn try_from(node: Node) -> Result<Self, Self::Error> {
-        let mut result: [u8; PAGE_SIZE] = [0x00; PAGE_SIZE];
+        let mut result = node.page.get_data();
 
         result[IS_ROOT_OFFSET] = node.is_root.to_byte();
+        result[NODE_TYPE_OFFSET] = match node.node_type {
+            NodeType::Internal => 0x01,
+            NodeType::Leaf => 0x02,
+            NodeType::Unknown => return Err(Error::UnexpectedError),
+        };
+        result[PARENT_POINTER_OFFSET..PARENT_POINTER_OFFSET + PARENT_POINTER_SIZE]
+            .clone_from_slice(&node.parent_offset.to_be_bytes());
 
         Ok(result)
     }
@@ -711,6 +643,30 @@ impl TryFrom<NodeSpec> for Node {
             return Err(Error::UnexpectedError);
         }
         let parent_pointer_offset = page.get_value_from_offset(PARENT_POINTER_OFFSET)?;
+        let key_size = match page.get_value_from_offset(NODE_KEY_SIZE_OFFSET)? {
+            0 => KEY_SIZE,
+            key_size => key_size,
+        };
+
+        // 计数超出结构允许的最大值时，之后按计数读取会越过页尾
+        if key_size > NODE_KEY_SIZE_OFFSET {
+            return Err(Error::CorruptNode);
+        }
+        match node_type {
+            NodeType::Internal => {
+                let num_of_children = page.get_value_from_offset(INTERNAL_NODE_NUM_CHILDREN_OFFSET)?;
+                let num_of_key = page.get_value_from_offset(INTERNAL_NODE_NUM_KEY_OFFSET)?;
+                if num_of_children > MAX_BRANCHING_FACTOR + 1 || num_of_key > internal_max_keys(key_size) {
+                    return Err(Error::CorruptNode);
+                }
+            }
+            _ => {
+                let num_of_pairs = page.get_value_from_offset(LEAF_NODE_NUM_PAIRS_OFFSET)?;
+                if num_of_pairs > leaf_max_pairs(key_size) {
+                    return Err(Error::CorruptNode);
+                }
+            }
+        }
 
         Node::new(
             node_type,
@@ -718,6 +674,7 @@ impl TryFrom<NodeSpec> for Node {
             spec.offset,
             is_root,
             page,
+            key_size,
         )
     }
 }
@@ -1,5 +1,8 @@
 use sqlparser::dialect::GenericDialect;
 use sqlparser::parser::Parser;
+use crate::data_item::buffer::LRUBuffer;
+use crate::table::table_manager::TableManager;
+use crate::table::executor::execute;
 
 pub mod index;
 pub mod util;
@@ -17,10 +20,14 @@ fn main() {
 
     let dialect = GenericDialect {};
 
-    let _ast = &Parser::parse_sql(&dialect, sql).unwrap()[0];
-
-
-
-    // println!("{:#?}", ast);
+    let ast = Parser::parse_sql(&dialect, sql).unwrap();
 
+    let buffer = Box::new(LRUBuffer::new(4, "metadata.db".to_string()).unwrap());
+    let mut table_manager = TableManager::new(buffer);
+    for statement in &ast {
+        match execute(statement, &mut table_manager) {
+            Ok(rows) => println!("{} rows", rows.len()),
+            Err(e) => println!("{}", e),
+        }
+    }
 }
\ No newline at end of file
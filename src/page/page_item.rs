@@ -1,6 +1,8 @@
 use std::convert::TryFrom;
 use std::mem::size_of;
 
+use byteorder::{BigEndian, ByteOrder};
+
 use crate::util::error::Error;
 
 /// 一个页的大小
@@ -14,10 +16,11 @@ pub struct Value(usize);
 
 /// 对单页内存数据的包装
 /// 提供一些方便的接口
+/// 页的大小由数据长度决定，默认为 PAGE_SIZE
 pub struct Page {
     pub(crate) file_name: String,
     pub(crate) page_num: usize,
-    data: Box<[u8; PAGE_SIZE]>,
+    data: Vec<u8>,
 }
 
 impl Page {
@@ -25,23 +28,48 @@ impl Page {
         Page {
             file_name: String::new(),
             page_num: 0, // 0为孤立页面，不放在缓冲池、磁盘内
-            data: Box::new(data),
+            data: data.to_vec(),
         }
     }
 
     pub fn new(data: [u8; PAGE_SIZE], file_name: &str, page_num: usize) -> Page {
+        Page::from_vec(data.to_vec(), file_name, page_num)
+    }
+
+    /// 使用任意长度的数据构造一个页，用于非默认页大小的缓冲区
+    pub fn from_vec(data: Vec<u8>, file_name: &str, page_num: usize) -> Page {
         Page {
             file_name: String::from(file_name),
             page_num, // 0为孤立页面，不放在缓冲池、磁盘内
-            data: Box::new(data),
+            data,
         }
     }
 
+    /// 复制页的数据，得到属于 file_name 文件第 page_num 页的新页，原来的页不变
+    pub fn copy_into(&self, file_name: &str, page_num: usize) -> Page {
+        Page::from_vec(self.data.clone(), file_name, page_num)
+    }
+
+    /// 页的大小
+    pub fn size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// 将页的数据全部清零，文件名和页号不变
+    pub fn clear(&mut self) {
+        self.data.iter_mut().for_each(|b| *b = 0);
+    }
+
+    /// 页的每个字节都是 0 时返回 true
+    pub fn is_empty(&self) -> bool {
+        self.data.iter().all(|b| *b == 0)
+    }
+
 
     /// 向指定偏移写入一个值
     /// 覆盖指定偏移上的值
     pub fn write_value_at_offset(&mut self, offset: usize, value: usize) -> Result<(), Error> {
-        if offset > PAGE_SIZE - PTR_SIZE {
+        if offset + PTR_SIZE > self.data.len() {
             return Err(Error::UnexpectedError);
         }
         // 转换成字节数组后写入
@@ -58,6 +86,38 @@ impl Page {
         Ok(res)
     }
 
+    /// 从指定偏移读取一个 4 字节的大端 u32，与文件头、页表中的格式一致
+    pub fn read_u32_at(&self, offset: usize) -> Result<u32, Error> {
+        Ok(BigEndian::read_u32(self.checked_range(offset, 4)?))
+    }
+
+    /// 从指定偏移读取一个 8 字节的大端 u64
+    pub fn read_u64_at(&self, offset: usize) -> Result<u64, Error> {
+        Ok(BigEndian::read_u64(self.checked_range(offset, 8)?))
+    }
+
+    /// 以 4 字节大端格式向指定偏移写入一个 u32
+    pub fn write_u32_at(&mut self, offset: usize, value: u32) -> Result<(), Error> {
+        self.checked_range(offset, 4)?;
+        BigEndian::write_u32(&mut self.data[offset..offset + 4], value);
+        Ok(())
+    }
+
+    /// 以 8 字节大端格式向指定偏移写入一个 u64
+    pub fn write_u64_at(&mut self, offset: usize, value: u64) -> Result<(), Error> {
+        self.checked_range(offset, 8)?;
+        BigEndian::write_u64(&mut self.data[offset..offset + 8], value);
+        Ok(())
+    }
+
+    /// 检查 offset 开始 width 大小的区域是否在页内
+    fn checked_range(&self, offset: usize, width: usize) -> Result<&[u8], Error> {
+        match offset.checked_add(width) {
+            Some(end) if end <= self.data.len() => Ok(&self.data[offset..end]),
+            _ => Err(Error::UnexpectedError)
+        }
+    }
+
     /// 向 offset 到 end_offset 的每个偏移上插入大小为 size 的字节数组
     /// 腾出 offset 到 end_offset 的空间， 然后插入
     pub fn insert_bytes_at_offset(
@@ -90,7 +150,7 @@ impl Page {
         } else {
             size
         };
-        self.data[offset..offset + siz].clone_from_slice(&bytes);
+        self.data[offset..offset + siz].clone_from_slice(&bytes[..siz]);
         Ok(())
     }
 
@@ -100,8 +160,17 @@ impl Page {
     }
 
     /// 获取整个 data 数组
+    /// 只用于默认大小的页，其他大小的页会被截断或补零到 PAGE_SIZE
     pub fn get_data(&self) -> [u8; PAGE_SIZE] {
-        *self.data
+        let mut data = [0u8; PAGE_SIZE];
+        let len = self.data.len().min(PAGE_SIZE);
+        data[..len].clone_from_slice(&self.data[..len]);
+        data
+    }
+
+    /// 获取整个页的字节切片
+    pub fn get_bytes(&self) -> &[u8] {
+        &self.data
     }
 }
 
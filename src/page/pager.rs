@@ -1,13 +1,36 @@
-use crate::data_item::buffer::Buffer;
-use crate::page::page_item::{Page, PAGE_SIZE};
+use std::cmp::{max, min};
+use std::collections::HashMap;
+
+use crate::data_item::buffer::{Buffer, FREE_LIST_PAGE, VALUE_COUNT_PAGE};
+use crate::page::page_item::Page;
 use crate::util::error::Error;
 
+/// insert_value 选择写入位置的策略
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FitStrategy {
+    /// 使用第一个放得下的空洞或页
+    FirstFit,
+    /// 使用剩余空间最小但放得下的空洞或页，减少页尾浪费的空间
+    BestFit,
+}
+
 /// 每个 Pager 管理一个文件
 pub struct Pager {
     pub(crate) cnt: usize,
     max_size: usize,
+    /// 页大小，与创建时的缓冲区一致
+    page_size: usize,
     file_name: String,
-    remain_size: Vec<(usize, usize)>
+    remain_size: Vec<(usize, usize)>,
+    /// 可以复用的空闲页号，保存在文件头的 FREE_LIST_PAGE 中
+    /// 格式: 页数(u32) 页号(u32)...
+    free_pages: Vec<usize>,
+    /// 页内被删除、可以复用的空间 (offset, size)
+    /// offset 与 insert_value 返回的偏移编码一致
+    holes: Vec<(usize, usize)>,
+    fit: FitStrategy,
+    /// insert_value 写入且没有被 delete_value 删除的值的个数，保存在文件头的 VALUE_COUNT_PAGE 中
+    value_count: usize,
 }
 
 impl Clone for Pager {
@@ -15,8 +38,13 @@ impl Clone for Pager {
         Self {
             cnt: self.cnt,
             max_size: self.max_size,
+            page_size: self.page_size,
             file_name: self.file_name.clone(),
             remain_size: self.remain_size.clone(),
+            free_pages: self.free_pages.clone(),
+            holes: self.holes.clone(),
+            fit: self.fit,
+            value_count: self.value_count,
         }
     }
 }
@@ -29,18 +57,105 @@ impl Pager {
             Pager {
                 cnt: 0,
                 max_size,
+                page_size: buffer.get_page_size(),
                 file_name,
                 remain_size: vec,
+                free_pages: Vec::new(),
+                holes: Vec::new(),
+                fit: FitStrategy::FirstFit,
+                value_count: 0,
             }
         );
         pager.fill_up_to(&max_size, buffer)?;
+        pager.load_free_pages(buffer)?;
+        pager.load_value_count(buffer)?;
         Ok(pager)
     }
 
+    /// 设置 insert_value 选择写入位置的策略，默认为 FirstFit
+    pub fn set_fit_strategy(&mut self, fit: FitStrategy) {
+        self.fit = fit;
+    }
+
+    /// 在 (下标, 剩余大小) 中按策略选出一个
+    fn choose<I: Iterator<Item = (usize, usize)>>(&self, candidates: I) -> Option<usize> {
+        match self.fit {
+            FitStrategy::FirstFit => candidates.map(|(i, _)| i).next(),
+            FitStrategy::BestFit => candidates.min_by_key(|(_, siz)| *siz).map(|(i, _)| i),
+        }
+    }
+
+    /// 从保留页中读出空闲页链表
+    fn load_free_pages(&mut self, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let page = Page::from_vec(buffer.read_reserved_page(self.file_name.as_str(), FREE_LIST_PAGE)?, self.file_name.as_str(), 0);
+        let cnt = page.read_u32_at(0)? as usize;
+        let mut free_pages = Vec::with_capacity(cnt);
+        for i in 1..=cnt {
+            free_pages.push(page.read_u32_at(4 * i)? as usize);
+        }
+        self.free_pages = free_pages;
+        Ok(())
+    }
+
+    /// 将空闲页链表写入保留页
+    fn save_free_pages(&self, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let mut page = Page::from_vec(vec![0u8; 4 * (self.free_pages.len() + 1)], self.file_name.as_str(), 0);
+        page.write_u32_at(0, self.free_pages.len() as u32)?;
+        for (i, page_num) in self.free_pages.iter().enumerate() {
+            page.write_u32_at(4 * i + 4, *page_num as u32)?;
+        }
+        buffer.write_reserved_page(self.file_name.as_str(), FREE_LIST_PAGE, page.get_ptr_from_offset(0, page.size()))
+    }
+
+    /// 从保留页中读出值的个数
+    fn load_value_count(&mut self, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let page = Page::from_vec(buffer.read_reserved_page(self.file_name.as_str(), VALUE_COUNT_PAGE)?, self.file_name.as_str(), 0);
+        self.value_count = page.read_u64_at(0)? as usize;
+        Ok(())
+    }
+
+    /// 将值的个数写入保留页
+    fn save_value_count(&self, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let mut page = Page::from_vec(vec![0u8; 8], self.file_name.as_str(), 0);
+        page.write_u64_at(0, self.value_count as u64)?;
+        buffer.write_reserved_page(self.file_name.as_str(), VALUE_COUNT_PAGE, page.get_ptr_from_offset(0, page.size()))
+    }
+
+    /// 文件中存放的值的个数，TEXT 和 BLOB 的内容单独计数
+    pub fn value_count(&self) -> usize {
+        self.value_count
+    }
+
+    /// 释放一个已分配的页并将其清零，之后的 get_new_page 会优先复用它
+    pub fn free_page(&mut self, page_num: usize, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        if page_num == 0 || page_num > self.cnt || self.free_pages.contains(&page_num) {
+            return Err(Error::InvalidPageNum);
+        }
+        if 4 * (self.free_pages.len() + 2) > self.page_size {
+            return Err(Error::PageNumOutOfSize);
+        }
+        let mut page = self.get_page(&page_num, buffer)?;
+        page.clear();
+        self.write_page(page, buffer)?;
+        self.free_pages.push(page_num);
+        self.save_free_pages(buffer)?;
+        // 空闲页不再存放值
+        self.remain_size[page_num] = (0, self.page_size);
+        let page_size = self.page_size;
+        self.holes.retain(|(offset, _)| offset / page_size + 1 != page_num);
+        Ok(())
+    }
+
+    /// 空闲页的数量
+    pub fn free_page_count(&self) -> usize {
+        self.free_pages.len()
+    }
+
     /// 将文件大小扩充到指定页数
     pub fn fill_up_to(&mut self, num_of_page: &usize, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        buffer.fill_up_to(self.file_name.as_str(), *num_of_page)?;
         self.max_size = *num_of_page;
-        buffer.fill_up_to(self.file_name.as_str(), *num_of_page)
+        Ok(())
     }
 
     /// 读取一个页
@@ -53,46 +168,286 @@ impl Pager {
         buffer.write_page(page)
     }
 
+    /// 恢复已分配的页数，用于重新打开已有的文件
+    /// 恢复出来的页均视为已占满
+    pub(crate) fn restore_cnt(&mut self, cnt: usize, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        if cnt > self.max_size {
+            self.fill_up_to(&cnt, buffer)?;
+        }
+        self.cnt = cnt;
+        self.remain_size.resize(cnt + 1, (0, self.page_size));
+        Ok(())
+    }
+
+    /// 将 offset 开始 size 大小的空间标记为已用，用于重新打开已有的文件
+    /// 空间可以跨越多个页，size 为 0 时只保证 offset 所在的页已分配
+    pub(crate) fn mark_used(&mut self, offset: usize, size: usize, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let mut offset = offset;
+        let mut size = size;
+        loop {
+            let page_num = offset / self.page_size + 1;
+            if page_num > self.cnt {
+                if page_num > self.max_size {
+                    self.fill_up_to(&page_num, buffer)?;
+                }
+                self.cnt = page_num;
+                self.remain_size.resize(page_num + 1, (self.page_size, 0));
+            }
+
+            let page_offset = offset % self.page_size;
+            let len = min(size, self.page_size - page_offset);
+            let end = page_offset + len;
+            let (_, next_offset) = self.remain_size[page_num];
+            if end > next_offset {
+                self.remain_size[page_num] = (self.page_size - end, end);
+            }
+
+            size -= len;
+            offset += len;
+            if size == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// 分配一个新页，优先复用空闲页，没有空闲页时扩展文件
     pub fn get_new_page(&mut self, buffer: &mut Box<dyn Buffer>) -> Result<Page, Error> {
+        if let Some(page_num) = self.free_pages.pop() {
+            self.save_free_pages(buffer)?;
+            self.remain_size[page_num] = (self.page_size, 0);
+            // 页在释放时已经清零
+            return self.get_page(&page_num, buffer);
+        }
+
         // 如果文件大小不够，则扩大文件
         if self.cnt >= self.max_size {
-            self.fill_up_to(&(2 * self.max_size), buffer)?;
+            // 文件头的页表能记录的页数有限，翻倍超出时只扩大一页
+            if self.fill_up_to(&(2 * self.max_size), buffer).is_err() {
+                self.fill_up_to(&(self.cnt + 1), buffer)?;
+            }
         }
         self.cnt += 1;
-        self.remain_size.push((PAGE_SIZE, 0));
+        self.remain_size.push((self.page_size, 0));
         self.get_page(&self.cnt.clone(), buffer)
     }
 
+    /// 写入一个值，返回其偏移
+    /// 优先复用 delete_value 释放的空间，其次使用页尾的剩余空间，都不够时分配新页
+    /// 有多个位置放得下时按 set_fit_strategy 设置的策略选择
     pub fn insert_value(&mut self, bytes: &[u8], buffer: &mut Box<dyn Buffer>) -> Result<usize, Error> {
+        let offset = self.place_value(bytes, buffer)?;
+        self.value_count += 1;
+        self.save_value_count(buffer)?;
+        Ok(offset)
+    }
+
+    /// 按 insert_value 的规则找到位置并写入，返回偏移
+    fn place_value(&mut self, bytes: &[u8], buffer: &mut Box<dyn Buffer>) -> Result<usize, Error> {
         let len = bytes.len();
-        for (i, (siz, offset)) in self.remain_size.clone().iter().enumerate() {
-            if i == 0 {
-                continue;
+        let hole = self.choose(self.holes.iter().enumerate().map(|(i, (_, siz))| (i, *siz)).filter(|(_, siz)| *siz >= len));
+        if let Some(index) = hole {
+            let (offset, siz) = self.holes[index];
+            self.write_value(offset, bytes, buffer)?;
+            if siz == len {
+                self.holes.remove(index);
+            } else {
+                self.holes[index] = (offset + len, siz - len);
             }
-            if *siz > len {
-                let mut page = self.get_page(&i, buffer)?;
-                page.write_bytes_at_offset(bytes, *offset, len)?;
-                self.write_page(page, buffer)?;
-
-                let new_siz = *siz - len;
-                let new_offset = *offset + len;
-                self.remain_size[i] = (new_siz, new_offset);
-                return Ok(*offset + (i - 1) * PAGE_SIZE)
+            return Ok(offset);
+        }
+
+        let page = self.choose(self.remain_size.iter().map(|(siz, _)| *siz).enumerate().skip(1).filter(|(_, siz)| *siz > len));
+        if let Some(i) = page {
+            let (siz, offset) = self.remain_size[i];
+            let mut page = self.get_page(&i, buffer)?;
+            page.write_bytes_at_offset(bytes, offset, len)?;
+            self.write_page(page, buffer)?;
+
+            self.remain_size[i] = (siz - len, offset + len);
+            return Ok(offset + (i - 1) * self.page_size)
+        }
+
+        // 一页放不下的值写入文件末尾连续的新页
+        if len > self.page_size {
+            let offset = self.cnt * self.page_size;
+            let last_page_num = (offset + len - 1) / self.page_size + 1;
+            if last_page_num > self.max_size {
+                self.fill_up_to(&max(2 * self.max_size, last_page_num), buffer)?;
             }
+            self.write_value(offset, bytes, buffer)?;
+            self.mark_used(offset, len, buffer)?;
+            return Ok(offset);
         }
 
         let mut page = self.get_new_page(buffer)?;
         page.write_bytes_at_offset(bytes, 0, len)?;
         self.write_page(page, buffer)?;
-        self.remain_size[self.cnt] = (PAGE_SIZE - len, len);
-        Ok((self.cnt - 1) * PAGE_SIZE)
+        self.remain_size[self.cnt] = (self.page_size - len, len);
+        Ok((self.cnt - 1) * self.page_size)
+    }
+
+    /// 删除从 offset 开始 size 大小的值，将其清零
+    /// 释放的空间与同页相邻的空洞合并，位于页内已用空间末尾时直接还给页尾
+    /// 之后大小合适的 insert_value 会复用这些空间
+    pub fn delete_value(&mut self, offset: usize, size: usize, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        if size == 0 {
+            return Ok(());
+        }
+        self.write_value(offset, vec![0u8; size].as_slice(), buffer)?;
+        self.value_count = self.value_count.saturating_sub(1);
+        self.save_value_count(buffer)?;
+
+        let page_size = self.page_size;
+        let same_page = |a: usize, b: usize| a / page_size == b / page_size;
+        let mut offset = offset;
+        let mut size = size;
+        // 合并前后相邻的空洞
+        if let Some(index) = self.holes.iter().position(|(o, s)| o + s == offset && same_page(*o, offset)) {
+            let (o, s) = self.holes.remove(index);
+            offset = o;
+            size += s;
+        }
+        if let Some(index) = self.holes.iter().position(|(o, _)| *o == offset + size && same_page(*o, offset)) {
+            let (_, s) = self.holes.remove(index);
+            size += s;
+        }
+
+        let page_num = offset / self.page_size + 1;
+        let page_offset = offset % self.page_size;
+        match self.remain_size.get(page_num).cloned() {
+            Some((siz, next_offset)) if page_offset + size == next_offset => {
+                self.remain_size[page_num] = (siz + size, page_offset);
+            }
+            _ => self.holes.push((offset, size)),
+        }
+        Ok(())
+    }
+
+    /// 整理存储空间，把 values 给出的所有存活值 (offset, size) 从第 1 页开始依次紧凑写入
+    /// 不超过一页的值不跨越页边界，与 insert_value 的约定一致
+    /// 整理后不再存放数据的页加入空闲页链表，所有空洞被清空
+    /// 返回旧偏移到新偏移的映射，调用者需要据此更新索引中保存的偏移
+    /// 值之间有重叠时返回 UnexpectedError，不修改文件
+    pub fn defragment(&mut self, values: &[(usize, usize)], buffer: &mut Box<dyn Buffer>) -> Result<HashMap<usize, usize>, Error> {
+        let mut values = values.to_vec();
+        values.sort_unstable();
+        values.dedup();
+        for pair in values.windows(2) {
+            if pair[0].0 + pair[0].1 > pair[1].0 {
+                return Err(Error::UnexpectedError);
+            }
+        }
+
+        // 先读出所有值，整理时旧位置会被覆盖
+        let mut data = Vec::with_capacity(values.len());
+        for (offset, size) in values.iter() {
+            data.push(self.get_value(*offset, *size, buffer)?);
+        }
+
+        // 按旧偏移的顺序排列，新偏移不会超过旧偏移，不需要扩充文件
+        let mut res = HashMap::<usize, usize>::new();
+        let mut layout = Vec::<u8>::new();
+        for ((offset, size), bytes) in values.iter().zip(data.iter()) {
+            let page_offset = layout.len() % self.page_size;
+            if *size <= self.page_size && page_offset + size > self.page_size {
+                layout.resize(layout.len() + self.page_size - page_offset, 0);
+            }
+            res.insert(*offset, layout.len());
+            layout.extend_from_slice(bytes);
+        }
+
+        let used_pages = layout.chunks(self.page_size).len();
+        for page_num in 1..=self.cnt {
+            let mut page = vec![0u8; self.page_size];
+            if page_num <= used_pages {
+                let start = (page_num - 1) * self.page_size;
+                let end = min(layout.len(), start + self.page_size);
+                page[..end - start].clone_from_slice(&layout[start..end]);
+                self.remain_size[page_num] = (self.page_size - (end - start), end - start);
+            }
+            self.write_page(Page::from_vec(page, self.file_name.as_str(), page_num), buffer)?;
+        }
+
+        self.holes.clear();
+        self.free_pages.clear();
+        for page_num in used_pages + 1..=self.cnt {
+            self.free_page(page_num, buffer)?;
+        }
+        self.save_free_pages(buffer)?;
+        Ok(res)
+    }
+
+    /// 已经写入数据的字节数
+    pub fn used_size(&self) -> usize {
+        let mut res = 0;
+        for (i, (_siz, offset)) in self.remain_size.iter().enumerate() {
+            // 空闲页不存放值
+            if i == 0 || self.free_pages.contains(&i) {
+                continue;
+            }
+            res += offset;
+        }
+        // 被删除的空间不算已用
+        res - self.holes.iter().map(|(_, siz)| siz).sum::<usize>()
+    }
+
+    /// offset 是否落在已经写入且没有被删除的空间中
+    pub fn is_allocated(&self, offset: usize) -> bool {
+        let page_num = offset / self.page_size + 1;
+        if page_num > self.cnt || self.free_pages.contains(&page_num) {
+            return false;
+        }
+        match self.remain_size.get(page_num) {
+            Some((_, used)) => offset % self.page_size < *used
+                && !self.holes.iter().any(|(hole, siz)| *hole <= offset && offset < hole + siz),
+            None => false
+        }
     }
 
+    /// 读取从 offset 开始 size 大小的值
+    /// 值跨越页边界时，依次读取后续的页并拼接
+    /// 值的任何部分落在未分配的页上时返回 PageNumOutOfSize
     pub fn get_value(&self, offset:usize, size: usize, buffer: &mut Box<dyn Buffer>) -> Result<Vec<u8>, Error> {
-        let page_num = offset / PAGE_SIZE + 1;
-        let page_offset = offset % PAGE_SIZE;
+        let mut page_num = offset / self.page_size + 1;
+        let mut page_offset = offset % self.page_size;
+        let last_page_num = (offset + size.max(1) - 1) / self.page_size + 1;
+        if last_page_num > self.cnt {
+            return Err(Error::PageNumOutOfSize);
+        }
 
-        let page = self.get_page(&page_num, buffer)?;
-        Ok(page.get_ptr_from_offset(page_offset, size).to_vec())
+        let mut res = Vec::<u8>::with_capacity(size);
+        while res.len() < size {
+            let page = self.get_page(&page_num, buffer)?;
+            let len = min(size - res.len(), self.page_size - page_offset);
+            res.extend_from_slice(page.get_ptr_from_offset(page_offset, len));
+            page_num += 1;
+            page_offset = 0;
+        }
+        Ok(res)
+    }
+
+    /// 读取从 offset 开始最多 size 字节，不越过所在页的页尾
+    /// 用于按最大行长读取不跨页的行
+    pub fn get_value_in_page(&self, offset: usize, size: usize, buffer: &mut Box<dyn Buffer>) -> Result<Vec<u8>, Error> {
+        self.get_value(offset, min(size, self.page_size - offset % self.page_size), buffer)
+    }
+
+    /// 从 offset 开始覆盖写入一个值
+    /// 值跨越页边界时，依次写入后续的页
+    pub fn write_value(&self, offset: usize, bytes: &[u8], buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let mut page_num = offset / self.page_size + 1;
+        let mut page_offset = offset % self.page_size;
+
+        let mut written = 0;
+        while written < bytes.len() {
+            let mut page = self.get_page(&page_num, buffer)?;
+            let len = min(bytes.len() - written, self.page_size - page_offset);
+            page.write_bytes_at_offset(&bytes[written..written + len], page_offset, len)?;
+            self.write_page(page, buffer)?;
+            written += len;
+            page_num += 1;
+            page_offset = 0;
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,65 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use uuid::Uuid;
+
+use crate::util::error::Error;
+
+/// 启动文件中第一个uuid的偏移
+pub const BOOT_UUID_OFFSET: usize = 0;
+/// uuid 的字节数
+pub const BOOT_UUID_SIZE: usize = 16;
+
+/// 启动文件，保存引导目录所需的第一个uuid
+/// 与缓冲区的 get_first_uuid/update_first_uuid 相同，但不依赖缓冲区
+pub struct Booter {
+    file: File,
+}
+
+impl Booter {
+    /// 创建启动文件，文件已存在时直接打开
+    pub fn create(path: &Path) -> Result<Booter, Error> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(path)?;
+
+        // 新文件写入空的uuid
+        if file.metadata()?.len() < (BOOT_UUID_OFFSET + BOOT_UUID_SIZE) as u64 {
+            file.seek(SeekFrom::Start(BOOT_UUID_OFFSET as u64))?;
+            file.write_all(Uuid::nil().as_bytes())?;
+            file.flush()?;
+        }
+        Ok(Booter { file })
+    }
+
+    /// 打开已有的启动文件
+    pub fn open(path: &Path) -> Result<Booter, Error> {
+        match OpenOptions::new().read(true).write(true).open(path) {
+            Ok(file) => Ok(Booter { file }),
+            Err(_) => Err(Error::FileNotFound)
+        }
+    }
+
+    /// 读取保存的uuid
+    pub fn load(&mut self) -> Result<Uuid, Error> {
+        let mut bytes = [0u8; BOOT_UUID_SIZE];
+        self.file.seek(SeekFrom::Start(BOOT_UUID_OFFSET as u64))?;
+        self.file.read_exact(&mut bytes)?;
+        match Uuid::from_slice(&bytes) {
+            Ok(uuid) => Ok(uuid),
+            Err(_) => Err(Error::UnexpectedError)
+        }
+    }
+
+    /// 写入新的uuid并落盘
+    pub fn update(&mut self, uuid: Uuid) -> Result<(), Error> {
+        self.file.seek(SeekFrom::Start(BOOT_UUID_OFFSET as u64))?;
+        self.file.write_all(uuid.as_bytes())?;
+        self.file.sync_all()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use crate::index::btree::BTree;
+use crate::index::key_value_pair::KeyValuePair;
+use crate::util::error::Error;
+use crate::page::pager::Pager;
+use crate::data_item::buffer::Buffer;
+use crate::table::field::{Field, FieldType, FieldValue};
+use crate::table::entry::Entry;
+use std::path::Path;
+
+/// 由多个字段组成的主键
+/// 各字段的值先编码成定长、按字节比较即保持大小顺序的字节串，拼接后再写成十六进制字符串作为索引键，
+/// 因此 BTree 中键的顺序与按字段依次比较元组的顺序一致
+pub struct CompositeKey {
+    pub(crate) columns: Vec<usize>,
+    btree: BTree,
+}
+
+impl CompositeKey {
+    /// 在 file_name 上为 columns 中的字段创建组合键索引，键宽由各字段类型决定
+    pub(crate) fn create(file_name: String, columns: Vec<usize>, fields: &[Field], buffer: &mut Box<dyn Buffer>) -> Result<CompositeKey, Error> {
+        let key_size = CompositeKey::key_size(&columns, fields)?;
+        BTree::check_key_size(key_size)?;
+        buffer.add_file(Path::new(file_name.as_str()))?;
+        let pager = Pager::new(file_name.clone(), 40, buffer)?;
+        Ok(CompositeKey {
+            columns,
+            btree: BTree::new_with_key_size(pager, file_name, key_size, buffer)?,
+        })
+    }
+
+    /// 重新打开已有的组合键索引文件
+    pub(crate) fn open(file_name: String, columns: Vec<usize>, fields: &[Field], buffer: &mut Box<dyn Buffer>) -> Result<CompositeKey, Error> {
+        CompositeKey::key_size(&columns, fields)?;
+        buffer.add_file(Path::new(file_name.as_str()))?;
+        let pager = Pager::new(file_name.clone(), 40, buffer)?;
+        Ok(CompositeKey {
+            columns,
+            btree: BTree::open(pager, file_name, buffer)?,
+        })
+    }
+
+    /// 索引文件的页面被恢复后重新读入索引的根节点
+    pub(crate) fn reload(&mut self, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        self.btree.reload(buffer)
+    }
+
+    /// 组合键编码后的长度，字段下标越界、重复或类型不能作为键时返回错误
+    fn key_size(columns: &[usize], fields: &[Field]) -> Result<usize, Error> {
+        if columns.is_empty() {
+            return Err(Error::UnexpectedError)
+        }
+        let mut size = 0;
+        for (i, column) in columns.iter().enumerate() {
+            if columns[..i].contains(column) {
+                return Err(Error::UnexpectedError)
+            }
+            let field = match fields.get(*column) {
+                Some(field) => field,
+                None => return Err(Error::UnexpectedError)
+            };
+            // 每个字节写成两个十六进制字符
+            size += 2 * CompositeKey::component_size(&field.field_type)?;
+        }
+        Ok(size)
+    }
+
+    /// 一个字段在组合键中占用的字节数，TEXT 和 BLOB 不定长，不能作为键
+    fn component_size(field_type: &FieldType) -> Result<usize, Error> {
+        match field_type {
+            FieldType::TEXT | FieldType::BLOB => Err(Error::FieldValueNotCompatible),
+            _ => Ok(field_type.to_size())
+        }
+    }
+
+    /// 将一个值编码成定长的字节，按字节比较的结果与值的大小顺序一致
+    /// 数值的编码见 FieldValue::ordered_bytes；定长字符串末尾补 \0
+    fn encode_component(fv: &FieldValue) -> Result<Vec<u8>, Error> {
+        if let Some(bytes) = fv.ordered_bytes() {
+            return Ok(bytes)
+        }
+        match fv {
+            FieldValue::BOOL(data) => Ok(vec![*data as u8]),
+            FieldValue::VARCHAR40(data) => {
+                if data.len() > 40 {
+                    return Err(Error::VarcharTooLong)
+                }
+                let mut bytes = data.as_bytes().to_vec();
+                bytes.resize(40, 0);
+                Ok(bytes)
+            }
+            _ => Err(Error::FieldValueNotCompatible)
+        }
+    }
+
+    /// 将组合键各字段的值按顺序编码成索引键
+    pub fn encode(values: &[FieldValue]) -> Result<String, Error> {
+        let mut key = String::new();
+        for fv in values {
+            for byte in CompositeKey::encode_component(fv)? {
+                key.push_str(format!("{:02x}", byte).as_str());
+            }
+        }
+        Ok(key)
+    }
+
+    /// 取出一行中组成键的字段并编码
+    pub(crate) fn key_of(&self, entry: &Entry) -> Result<String, Error> {
+        let mut values = Vec::<FieldValue>::new();
+        for column in &self.columns {
+            match entry.data.get(*column) {
+                Some(fv) => values.push(fv.clone()),
+                None => return Err(Error::UnexpectedError)
+            }
+        }
+        CompositeKey::encode(values.as_slice())
+    }
+
+    /// 向索引中加入一个指向 offset 处的行的键
+    pub(crate) fn insert_key(&mut self, key: String, offset: usize, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        self.btree.insert(KeyValuePair::new(key, offset), buffer)
+    }
+
+    /// 将索引中的键改为指向 offset 处的行
+    pub(crate) fn update_key(&mut self, key: String, offset: usize, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        self.btree.update(KeyValuePair::new(key, offset), buffer)?;
+        Ok(())
+    }
+
+    /// 从索引中删除一个键
+    pub(crate) fn delete_key(&mut self, key: String, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        self.btree.delete(key, buffer)
+    }
+
+    /// 查询键对应的行在表文件中的偏移量
+    pub(crate) fn search_offset(&self, key: String, buffer: &mut Box<dyn Buffer>) -> Result<usize, Error> {
+        Ok(self.btree.search(key, buffer)?.value)
+    }
+
+    /// 索引中所有行的偏移量，按键的顺序排列
+    pub(crate) fn offsets(&self, buffer: &mut Box<dyn Buffer>) -> Result<Vec<usize>, Error> {
+        Ok(self.btree.search_range(None, None, buffer)?.iter().map(|kv| kv.value).collect())
+    }
+
+    /// 索引中所有行的偏移量，按键从大到小排列
+    pub(crate) fn offsets_rev(&self, buffer: &mut Box<dyn Buffer>) -> Result<Vec<usize>, Error> {
+        self.btree.iter_rev(buffer).map(|kv| kv.map(|kv| kv.value)).collect()
+    }
+
+    /// 索引中的行数
+    pub(crate) fn count(&self, buffer: &mut Box<dyn Buffer>) -> Result<usize, Error> {
+        self.btree.count(None, None, buffer)
+    }
+}
+
+impl Clone for CompositeKey {
+    fn clone(&self) -> Self {
+        CompositeKey {
+            columns: self.columns.clone(),
+            btree: self.btree.clone(),
+        }
+    }
+}
@@ -1,16 +1,162 @@
-use crate::table::field::{FieldValue};
+use crate::table::field::{Field, FieldValue, NULL_FLAG, NOT_NULL_FLAG};
+use crate::util::error::Error;
+use crate::table::table_item::Table;
 
+#[derive(Debug, PartialEq, Eq)]
 pub struct Entry {
     pub(crate) data: Vec<FieldValue>
 }
 
 impl Entry {
 
+    /// 将一行编码成字节数组，每个字段前有一个字节的空值标志
     pub fn to_bytes(&self) -> Vec<u8>{
         let mut raw_bytes = Vec::<u8>::new();
         for item in &self.data {
+            match item {
+                FieldValue::Null => raw_bytes.push(NULL_FLAG),
+                _ => raw_bytes.push(NOT_NULL_FLAG),
+            }
             raw_bytes = [raw_bytes, item.clone().into()].concat();
         }
         raw_bytes
     }
-}
\ No newline at end of file
+
+    /// 按 columns 给出的下标和顺序取出部分字段，下标越界时返回 FieldNotFound
+    pub fn project(&self, columns: &[usize]) -> Result<Entry, Error> {
+        let mut data = Vec::<FieldValue>::new();
+        for column in columns {
+            match self.data.get(*column) {
+                Some(fv) => data.push(fv.clone()),
+                None => return Err(Error::FieldNotFound)
+            }
+        }
+        Ok(Entry { data })
+    }
+
+    /// 将一行写成以字段名为键的 JSON 对象
+    /// 整数和浮点数写成数字，字符串写成字符串，BLOB 写成十六进制字符串，空值写成 null
+    pub fn to_json(&self, fields: &[Field]) -> String {
+        let members: Vec<String> = fields.iter().zip(self.data.iter()).map(|(field, fv)| {
+            format!("{}:{}", json_string(field.field_name.as_str()), json_value(fv))
+        }).collect();
+        format!("{{{}}}", members.join(","))
+    }
+
+    /// 将多行写成 JSON 数组
+    pub fn to_json_array(entries: &[Entry], fields: &[Field]) -> String {
+        let items: Vec<String> = entries.iter().map(|entry| entry.to_json(fields)).collect();
+        format!("[{}]", items.join(","))
+    }
+}
+
+/// Table::new_entry 返回的构造器，字段下标或类型不对时记下第一个错误，在 build 时返回
+pub struct EntryBuilder<'a> {
+    fields: &'a [Field],
+    data: Vec<Option<FieldValue>>,
+    error: Option<Error>,
+}
+
+impl<'a> EntryBuilder<'a> {
+    pub(crate) fn new(fields: &'a [Field]) -> EntryBuilder<'a> {
+        EntryBuilder {
+            fields,
+            data: vec![None; fields.len()],
+            error: None,
+        }
+    }
+
+    /// 设置第 col 个字段，下标越界时记下 FieldNotFound，类型不符时记下 FieldValueNotCompatible
+    pub fn set(mut self, col: usize, fv: FieldValue) -> Self {
+        if self.error.is_some() {
+            return self;
+        }
+        match self.fields.get(col) {
+            Some(field) => match Table::check_field(field, &fv) {
+                Ok(()) => self.data[col] = Some(fv),
+                Err(err) => self.error = Some(err)
+            },
+            None => self.error = Some(Error::FieldNotFound)
+        }
+        self
+    }
+
+    pub fn set_int(self, col: usize, v: i32) -> Self {
+        self.set(col, FieldValue::INT32(v))
+    }
+
+    pub fn set_int64(self, col: usize, v: i64) -> Self {
+        self.set(col, FieldValue::INT64(v))
+    }
+
+    pub fn set_float32(self, col: usize, v: f32) -> Self {
+        self.set(col, FieldValue::FLOAT32(v))
+    }
+
+    pub fn set_float64(self, col: usize, v: f64) -> Self {
+        self.set(col, FieldValue::FLOAT64(v))
+    }
+
+    pub fn set_varchar(self, col: usize, s: &str) -> Self {
+        self.set(col, FieldValue::VARCHAR40(s.to_string()))
+    }
+
+    pub fn set_text(self, col: usize, s: &str) -> Self {
+        self.set(col, FieldValue::TEXT(s.to_string()))
+    }
+
+    pub fn set_blob(self, col: usize, v: Vec<u8>) -> Self {
+        self.set(col, FieldValue::BLOB(v))
+    }
+
+    pub fn set_bool(self, col: usize, v: bool) -> Self {
+        self.set(col, FieldValue::BOOL(v))
+    }
+
+    pub fn set_null(self, col: usize) -> Self {
+        self.set(col, FieldValue::Null)
+    }
+
+    /// 返回设置字段时记下的第一个错误，没有设置的字段按空值处理，不允许为空时返回 FieldValueNotCompatible
+    pub fn build(self) -> Result<Entry, Error> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        let mut data = Vec::<FieldValue>::new();
+        for (field, fv) in self.fields.iter().zip(self.data) {
+            let fv = fv.unwrap_or(FieldValue::Null);
+            Table::check_field(field, &fv)?;
+            data.push(fv);
+        }
+        Ok(Entry { data })
+    }
+}
+
+fn json_value(fv: &FieldValue) -> String {
+    match fv {
+        // JSON 中没有 NaN 和无穷大
+        FieldValue::FLOAT32(data) if !data.is_finite() => "null".to_string(),
+        FieldValue::FLOAT64(data) if !data.is_finite() => "null".to_string(),
+        FieldValue::VARCHAR40(_) | FieldValue::TEXT(_) | FieldValue::BLOB(_) => json_string(fv.to_string().as_str()),
+        FieldValue::Null => "null".to_string(),
+        fv => fv.to_string(),
+    }
+}
+
+/// 写成带引号的 JSON 字符串，转义引号、反斜杠和控制字符
+fn json_string(data: &str) -> String {
+    let mut res = String::from("\"");
+    for c in data.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if (c as u32) < 0x20 => res.push_str(format!("\\u{:04x}", c as u32).as_str()),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
@@ -0,0 +1,365 @@
+use sqlparser::ast::{BinaryOperator, ColumnDef, ColumnOption, DataType, Expr, Ident, Query, SelectItem, SetExpr, Statement, TableConstraint, TableFactor, UnaryOperator, Value};
+use sqlparser::dialect::GenericDialect;
+use sqlparser::parser::Parser;
+use crate::table::table_manager::TableManager;
+use crate::table::field::{Field, FieldType, FieldValue};
+use crate::table::entry::Entry;
+use crate::table::planner::Condition;
+use crate::table::predicate::compare;
+use crate::util::error::Error;
+use std::cmp::Ordering;
+
+/// 解析一段 SQL 并依次执行其中的语句，返回最后一条语句的结果
+pub fn execute_sql(sql: &str, table_manager: &mut TableManager) -> Result<Vec<Entry>, Error> {
+    let dialect = GenericDialect {};
+    let statements = match Parser::parse_sql(&dialect, sql) {
+        Ok(statements) => statements,
+        Err(e) => return Err(Error::SqlParseError(format!("{:?}", e)))
+    };
+    let mut res = Vec::<Entry>::new();
+    for statement in &statements {
+        res = execute(statement, table_manager)?;
+    }
+    Ok(res)
+}
+
+/// 执行一条解析好的语句，查询返回结果行，其他语句返回空
+pub fn execute(statement: &Statement, table_manager: &mut TableManager) -> Result<Vec<Entry>, Error> {
+    match statement {
+        Statement::CreateTable { name, columns, constraints, .. } => {
+            create_table(name.to_string(), columns, constraints, table_manager)?;
+            Ok(Vec::<Entry>::new())
+        }
+        Statement::Insert { table_name, columns, source, .. } => {
+            let rows = match &source.body {
+                SetExpr::Values(values) => &values.0,
+                _ => return Err(Error::UnsupportedStatement)
+            };
+            insert(table_name.to_string(), columns, rows, table_manager)?;
+            Ok(Vec::<Entry>::new())
+        }
+        Statement::Query(query) => select(query, table_manager),
+        _ => Err(Error::UnsupportedStatement)
+    }
+}
+
+/// 建表，PRIMARY KEY 中的字段按顺序组成主键，没有声明主键时以第一个字段为键
+/// 主键字段不能为空，其余字段除非声明了 NOT NULL 都可以为空
+fn create_table(table_name: String, columns: &[ColumnDef], constraints: &[TableConstraint], table_manager: &mut TableManager) -> Result<(), Error> {
+    let mut key_columns = Vec::<usize>::new();
+    for (i, column) in columns.iter().enumerate() {
+        let is_primary = column.options.iter().any(|def| match def.option {
+            ColumnOption::Unique { is_primary } => is_primary,
+            _ => false
+        });
+        if is_primary {
+            key_columns.push(i);
+        }
+    }
+    for constraint in constraints {
+        if let TableConstraint::Unique { columns: key_names, is_primary: true, .. } = constraint {
+            for key_name in key_names {
+                match columns.iter().position(|column| column.name.value == key_name.value) {
+                    Some(i) if !key_columns.contains(&i) => key_columns.push(i),
+                    Some(_) => (),
+                    None => return Err(Error::FieldNotFound)
+                }
+            }
+        }
+    }
+    if key_columns.is_empty() && !columns.is_empty() {
+        key_columns.push(0);
+    }
+
+    let mut fields = Vec::<Field>::new();
+    for (i, column) in columns.iter().enumerate() {
+        let field_type = to_field_type(&column.data_type)?;
+        let not_null = key_columns.contains(&i) || column.options.iter().any(|def| matches!(def.option, ColumnOption::NotNull));
+        fields.push(if not_null {
+            Field::create_field(column.name.value.clone(), field_type)?
+        } else {
+            Field::create_nullable_field(column.name.value.clone(), field_type)?
+        });
+    }
+    table_manager.create_table_with_key(table_name, fields, &key_columns)
+}
+
+/// 按表结构把 VALUES 中的每一行转换成字段值后逐行插入
+/// 给出了字段列表时按字段名对应，未列出的字段为空
+fn insert(table_name: String, columns: &[Ident], rows: &[Vec<Expr>], table_manager: &mut TableManager) -> Result<(), Error> {
+    let fields = table_manager.describe_table(table_name.clone())?;
+    let mut positions = Vec::<usize>::new();
+    if columns.is_empty() {
+        positions.extend(0..fields.len());
+    } else {
+        for column in columns {
+            match fields.iter().position(|field| field.0 == column.value) {
+                Some(i) => positions.push(i),
+                None => return Err(Error::FieldNotFound)
+            }
+        }
+    }
+
+    for row in rows {
+        if row.len() != positions.len() {
+            return Err(Error::FieldValueNotCompatible)
+        }
+        let mut data = vec![FieldValue::Null; fields.len()];
+        for (expr, i) in row.iter().zip(positions.iter()) {
+            data[*i] = to_field_value(expr, &fields[*i].1)?;
+        }
+        table_manager.insert(table_name.clone(), Entry { data })?;
+    }
+    Ok(())
+}
+
+/// 执行单表的 SELECT
+/// WHERE 先翻译成条件交给查询计划选出候选行，再对候选行求值过滤，最后按投影取出字段
+fn select(query: &Query, table_manager: &mut TableManager) -> Result<Vec<Entry>, Error> {
+    let select = match &query.body {
+        SetExpr::Select(select) => select,
+        _ => return Err(Error::UnsupportedStatement)
+    };
+    if !query.order_by.is_empty() || query.limit.is_some() || select.from.len() != 1 || !select.from[0].joins.is_empty() {
+        return Err(Error::UnsupportedStatement)
+    }
+    let table_name = match &select.from[0].relation {
+        TableFactor::Table { name, .. } => name.to_string(),
+        _ => return Err(Error::UnsupportedStatement)
+    };
+    let fields = table_manager.describe_table(table_name.clone())?;
+
+    let mut projection = Vec::<usize>::new();
+    for item in &select.projection {
+        match item {
+            SelectItem::Wildcard => projection.extend(0..fields.len()),
+            SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => {
+                projection.push(column_index(expr, &fields)?)
+            }
+            _ => return Err(Error::UnsupportedStatement)
+        }
+    }
+
+    let mut conditions = Vec::<Condition>::new();
+    if let Some(selection) = &select.selection {
+        collect_conditions(selection, &fields, &mut conditions)?;
+    }
+    let mut res = Vec::<Entry>::new();
+    for entry in table_manager.execute_plan(table_name, &conditions)? {
+        if let Some(selection) = &select.selection {
+            if !eval(selection, &entry, &fields)? {
+                continue
+            }
+        }
+        let data = projection.iter().map(|i| entry.data[*i].clone()).collect();
+        res.push(Entry { data });
+    }
+    Ok(res)
+}
+
+/// 表达式引用的字段下标，表达式不是字段名时返回错误
+fn column_index(expr: &Expr, fields: &[(String, FieldType)]) -> Result<usize, Error> {
+    let name = match expr {
+        Expr::Identifier(ident) => &ident.value,
+        Expr::CompoundIdentifier(idents) if !idents.is_empty() => &idents[idents.len() - 1].value,
+        _ => return Err(Error::UnsupportedStatement)
+    };
+    match fields.iter().position(|field| field.0 == *name) {
+        Some(i) => Ok(i),
+        None => Err(Error::FieldNotFound)
+    }
+}
+
+/// 若表达式是字段与常量的比较，返回字段下标、比较符和转换成字段类型的常量
+/// 常量在左边时交换两边，比较符随之反向
+fn column_comparison(left: &Expr, op: &BinaryOperator, right: &Expr, fields: &[(String, FieldType)]) -> Result<Option<(usize, BinaryOperator, FieldValue)>, Error> {
+    let (column, op, literal) = match (is_column(left), is_column(right)) {
+        (true, false) => (left, op.clone(), right),
+        (false, true) => {
+            let op = match op {
+                BinaryOperator::Lt => BinaryOperator::Gt,
+                BinaryOperator::Gt => BinaryOperator::Lt,
+                BinaryOperator::LtEq => BinaryOperator::GtEq,
+                BinaryOperator::GtEq => BinaryOperator::LtEq,
+                op => op.clone()
+            };
+            (right, op, left)
+        }
+        _ => return Ok(None)
+    };
+    let i = column_index(column, fields)?;
+    Ok(Some((i, op, to_field_value(literal, &fields[i].1)?)))
+}
+
+fn is_column(expr: &Expr) -> bool {
+    matches!(expr, Expr::Identifier(_) | Expr::CompoundIdentifier(_))
+}
+
+/// 把以 AND 连接的 WHERE 拆成若干条件，字段与常量的等值和范围比较可以用索引，其余的只能过滤
+/// 范围条件一律按包括边界交给索引，严格不等由过滤去掉边界上的行
+fn collect_conditions(expr: &Expr, fields: &[(String, FieldType)], conditions: &mut Vec<Condition>) -> Result<(), Error> {
+    match expr {
+        Expr::Nested(expr) => collect_conditions(expr, fields, conditions),
+        Expr::BinaryOp { left, op: BinaryOperator::And, right } => {
+            collect_conditions(left, fields, conditions)?;
+            collect_conditions(right, fields, conditions)
+        }
+        Expr::BinaryOp { left, op, right } => {
+            match column_comparison(left, op, right, fields)? {
+                Some((i, BinaryOperator::Eq, fv)) => conditions.push(Condition::Eq(i, fv)),
+                Some((i, op, fv)) if key_order_preserved(&fields[i].1) => match op {
+                    BinaryOperator::Gt | BinaryOperator::GtEq => add_range(conditions, i, Some(fv), None),
+                    BinaryOperator::Lt | BinaryOperator::LtEq => add_range(conditions, i, None, Some(fv)),
+                    _ => conditions.push(Condition::Other)
+                },
+                _ => conditions.push(Condition::Other)
+            }
+            Ok(())
+        }
+        Expr::Between { expr, negated: false, low, high } if is_column(expr) => {
+            let i = column_index(expr, fields)?;
+            let low = to_field_value(low, &fields[i].1)?;
+            let high = to_field_value(high, &fields[i].1)?;
+            if key_order_preserved(&fields[i].1) {
+                add_range(conditions, i, Some(low), Some(high));
+            } else {
+                conditions.push(Condition::Other);
+            }
+            Ok(())
+        }
+        _ => {
+            conditions.push(Condition::Other);
+            Ok(())
+        }
+    }
+}
+
+/// 索引键的顺序是否与字段值的顺序一致，一致时范围条件才能交给索引
+/// 数值的键由 ordered_bytes 编码，字符串的键就是字符串本身；BLOB 转换成键时可能丢失字节
+fn key_order_preserved(field_type: &FieldType) -> bool {
+    matches!(field_type, FieldType::INT32 | FieldType::INT64 | FieldType::FLOAT32 | FieldType::FLOAT64 | FieldType::VARCHAR40 | FieldType::TEXT)
+}
+
+/// 加入字段 i 上的范围条件，与同一字段已有的范围合并，取更紧的边界
+fn add_range(conditions: &mut Vec<Condition>, i: usize, left: Option<FieldValue>, right: Option<FieldValue>) {
+    for condition in conditions.iter_mut() {
+        if let Condition::Range(key_index, old_left, old_right) = condition {
+            if *key_index == i {
+                *old_left = tighter(old_left.take(), left, Ordering::Greater);
+                *old_right = tighter(old_right.take(), right, Ordering::Less);
+                return
+            }
+        }
+    }
+    conditions.push(Condition::Range(i, left, right));
+}
+
+/// 两个边界中更紧的一个，preferred 为 Greater 时取较大者，为 Less 时取较小者
+fn tighter(a: Option<FieldValue>, b: Option<FieldValue>, preferred: Ordering) -> Option<FieldValue> {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            if compare(&b, &a) == Some(preferred) { Some(b) } else { Some(a) }
+        }
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+/// 对一行求 WHERE 的值，支持 AND、OR、NOT 连接的字段与常量的比较和 BETWEEN
+/// 空值参与的比较结果为假
+fn eval(expr: &Expr, entry: &Entry, fields: &[(String, FieldType)]) -> Result<bool, Error> {
+    match expr {
+        Expr::Nested(expr) => eval(expr, entry, fields),
+        Expr::BinaryOp { left, op: BinaryOperator::And, right } => {
+            Ok(eval(left, entry, fields)? && eval(right, entry, fields)?)
+        }
+        Expr::BinaryOp { left, op: BinaryOperator::Or, right } => {
+            Ok(eval(left, entry, fields)? || eval(right, entry, fields)?)
+        }
+        Expr::UnaryOp { op: UnaryOperator::Not, expr } => Ok(!eval(expr, entry, fields)?),
+        Expr::BinaryOp { left, op, right } => {
+            let (i, op, fv) = match column_comparison(left, op, right, fields)? {
+                Some(comparison) => comparison,
+                None => return Err(Error::UnsupportedStatement)
+            };
+            let ordering = match compare(&entry.data[i], &fv) {
+                Some(ordering) => ordering,
+                None => return Ok(false)
+            };
+            match op {
+                BinaryOperator::Eq => Ok(ordering == Ordering::Equal),
+                BinaryOperator::NotEq => Ok(ordering != Ordering::Equal),
+                BinaryOperator::Lt => Ok(ordering == Ordering::Less),
+                BinaryOperator::LtEq => Ok(ordering != Ordering::Greater),
+                BinaryOperator::Gt => Ok(ordering == Ordering::Greater),
+                BinaryOperator::GtEq => Ok(ordering != Ordering::Less),
+                _ => Err(Error::UnsupportedStatement)
+            }
+        }
+        Expr::Between { expr, negated, low, high } if is_column(expr) => {
+            let i = column_index(expr, fields)?;
+            let low = to_field_value(low, &fields[i].1)?;
+            let high = to_field_value(high, &fields[i].1)?;
+            let inside = match (compare(&entry.data[i], &low), compare(&entry.data[i], &high)) {
+                (Some(l), Some(h)) => l != Ordering::Less && h != Ordering::Greater,
+                _ => return Ok(false)
+            };
+            Ok(inside != *negated)
+        }
+        _ => Err(Error::UnsupportedStatement)
+    }
+}
+
+/// 将 SQL 中的常量转换成 field_type 类型的字段值，类型不符时返回错误
+fn to_field_value(expr: &Expr, field_type: &FieldType) -> Result<FieldValue, Error> {
+    let value = match expr {
+        Expr::Value(value) => value.clone(),
+        Expr::Nested(expr) => return to_field_value(expr, field_type),
+        // 负数被解析成取负的一元表达式
+        Expr::UnaryOp { op: UnaryOperator::Minus, expr } => match expr.as_ref() {
+            Expr::Value(Value::Number(n)) => Value::Number(format!("-{}", n)),
+            _ => return Err(Error::FieldValueNotCompatible)
+        },
+        _ => return Err(Error::FieldValueNotCompatible)
+    };
+    let fv = match (value, field_type) {
+        (Value::Null, _) => FieldValue::Null,
+        (Value::Number(n), FieldType::INT32) => FieldValue::INT32(parse_number(&n)?),
+        (Value::Number(n), FieldType::INT64) => FieldValue::INT64(parse_number(&n)?),
+        (Value::Number(n), FieldType::FLOAT32) => FieldValue::FLOAT32(parse_number(&n)?),
+        (Value::Number(n), FieldType::FLOAT64) => FieldValue::FLOAT64(parse_number(&n)?),
+        (Value::SingleQuotedString(s), FieldType::VARCHAR40) => {
+            if s.len() > 40 {
+                return Err(Error::VarcharTooLong)
+            }
+            FieldValue::VARCHAR40(s)
+        }
+        (Value::SingleQuotedString(s), FieldType::TEXT) => FieldValue::TEXT(s),
+        (Value::SingleQuotedString(s), FieldType::BLOB) => FieldValue::BLOB(s.into_bytes()),
+        (Value::Boolean(b), FieldType::BOOL) => FieldValue::BOOL(b),
+        _ => return Err(Error::FieldValueNotCompatible)
+    };
+    Ok(fv)
+}
+
+fn parse_number<T: std::str::FromStr>(n: &str) -> Result<T, Error> {
+    match n.parse::<T>() {
+        Ok(data) => Ok(data),
+        Err(_) => Err(Error::FieldValueNotCompatible)
+    }
+}
+
+/// 将 SQL 中的类型映射为字段类型，VARCHAR 最长 40 字节
+fn to_field_type(data_type: &DataType) -> Result<FieldType, Error> {
+    match data_type {
+        DataType::Int => Ok(FieldType::INT32),
+        DataType::BigInt => Ok(FieldType::INT64),
+        DataType::Float(_) | DataType::Real => Ok(FieldType::FLOAT32),
+        DataType::Double => Ok(FieldType::FLOAT64),
+        DataType::Varchar(Some(len)) if *len <= 40 => Ok(FieldType::VARCHAR40),
+        DataType::Text => Ok(FieldType::TEXT),
+        DataType::Bytea => Ok(FieldType::BLOB),
+        DataType::Boolean => Ok(FieldType::BOOL),
+        _ => Err(Error::UnsupportedType)
+    }
+}
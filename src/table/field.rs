@@ -1,15 +1,25 @@
-use crate::index::btree::BTree;
+use crate::index::btree::{BTree, to_bound};
 use crate::util::error::Error;
 use crate::page::pager::Pager;
 use crate::data_item::buffer::Buffer;
 use crate::index::key_value_pair::KeyValuePair;
+use crate::index::node::KEY_SIZE;
 use crate::table::entry::Entry;
 use std::path::Path;
+use std::ops::Bound;
+use byteorder::{BigEndian, ByteOrder};
 
 pub enum FieldType {
     INT32,
+    INT64,
     FLOAT32,
+    FLOAT64,
     VARCHAR40,
+    /// 任意长度的字符串，内容单独写入表文件，行中只保存长度和偏移
+    TEXT,
+    /// 任意字节，与 TEXT 一样单独保存，但不做 UTF-8 解码
+    BLOB,
+    BOOL,
 }
 
 impl Clone for FieldType {
@@ -17,49 +27,327 @@ impl Clone for FieldType {
         match self {
             FieldType::FLOAT32 => FieldType::FLOAT32,
             FieldType::INT32 => FieldType::INT32,
+            FieldType::INT64 => FieldType::INT64,
+            FieldType::FLOAT64 => FieldType::FLOAT64,
             FieldType::VARCHAR40 => FieldType::VARCHAR40,
+            FieldType::TEXT => FieldType::TEXT,
+            FieldType::BLOB => FieldType::BLOB,
+            FieldType::BOOL => FieldType::BOOL,
         }
     }
 }
 
+impl FieldType {
+    /// 类型在表结构目录中的编号
+    pub(crate) fn to_code(&self) -> u8 {
+        match self {
+            FieldType::INT32 => 0,
+            FieldType::INT64 => 1,
+            FieldType::FLOAT32 => 2,
+            FieldType::FLOAT64 => 3,
+            FieldType::VARCHAR40 => 4,
+            FieldType::TEXT => 5,
+            FieldType::BLOB => 6,
+            FieldType::BOOL => 7,
+        }
+    }
+
+    /// 从表结构目录中的编号还原类型
+    pub(crate) fn from_code(code: u8) -> Result<FieldType, Error> {
+        match code {
+            0 => Ok(FieldType::INT32),
+            1 => Ok(FieldType::INT64),
+            2 => Ok(FieldType::FLOAT32),
+            3 => Ok(FieldType::FLOAT64),
+            4 => Ok(FieldType::VARCHAR40),
+            5 => Ok(FieldType::TEXT),
+            6 => Ok(FieldType::BLOB),
+            7 => Ok(FieldType::BOOL),
+            _ => Err(Error::UnexpectedError),
+        }
+    }
+
+    /// 该类型的值编码后的字节数，不包括空值标志
+    pub(crate) fn to_size(&self) -> usize {
+        match self {
+            FieldType::INT32 => 4,
+            FieldType::INT64 => 8,
+            FieldType::FLOAT32 => 4,
+            FieldType::FLOAT64 => 8,
+            FieldType::VARCHAR40 => 40,
+            FieldType::TEXT => TEXT_LOCATOR_SIZE,
+            FieldType::BLOB => TEXT_LOCATOR_SIZE,
+            FieldType::BOOL => 1,
+        }
+    }
+
+    /// 内容是否单独写入表文件，行中只保存长度和偏移
+    pub(crate) fn is_out_of_line(&self) -> bool {
+        matches!(self, FieldType::TEXT | FieldType::BLOB)
+    }
+
+    /// 是否为数值类型，只有数值字段可以求和、求平均
+    pub(crate) fn is_numeric(&self) -> bool {
+        matches!(self, FieldType::INT32 | FieldType::INT64 | FieldType::FLOAT32 | FieldType::FLOAT64)
+    }
+
+    /// 为该类型的字段建立索引时默认的键宽，能容纳任意值转换成的键
+    /// 数值的键是定长的十六进制串，见 FieldValue::to_key
+    /// TEXT 和 BLOB 的键没有上限，使用索引的默认键宽
+    pub(crate) fn key_size(&self) -> usize {
+        match self {
+            FieldType::INT32 | FieldType::INT64 | FieldType::FLOAT32 | FieldType::FLOAT64 => 2 * self.to_size(),
+            FieldType::VARCHAR40 => 40,
+            // "false"
+            FieldType::BOOL => 5,
+            FieldType::TEXT | FieldType::BLOB => KEY_SIZE,
+        }
+    }
+}
+
+#[derive(Debug)]
 pub enum FieldValue {
     INT32(i32),
+    INT64(i64),
     FLOAT32(f32),
+    FLOAT64(f64),
     VARCHAR40(String),
+    TEXT(String),
+    BLOB(Vec<u8>),
+    BOOL(bool),
+    Null,
 }
 
+/// 每个字段编码前的空值标志
+pub(crate) const NULL_FLAG_SIZE: usize = 1;
+pub(crate) const NULL_FLAG: u8 = 1;
+pub(crate) const NOT_NULL_FLAG: u8 = 0;
+
+/// TEXT 和 BLOB 字段在行中保存的长度(u64)和内容偏移(u64)
+pub(crate) const TEXT_LOCATOR_SIZE: usize = 16;
+
+/// 索引中的值最高位为 1 时，其余位是表的 pager 中偏移量列表的偏移，而不是一行的偏移
+pub(crate) const MULTI_VALUE_FLAG: usize = 1 << (usize::BITS - 1);
+
+/// 偏移量列表开头保存偏移数的字节数
+const OFFSET_LIST_LEN_SIZE: usize = 4;
+
 impl FieldValue {
-    fn to_size(&self) -> usize {
+    /// 值编码后在行中占用的字节数，不包括空值标志
+    pub fn to_size(&self) -> usize {
         match self {
             FieldValue::INT32(_data) => 4,
+            FieldValue::INT64(_data) => 8,
             FieldValue::FLOAT32(_data) => 4,
+            FieldValue::FLOAT64(_data) => 8,
             FieldValue::VARCHAR40(_data) => 40,
+            FieldValue::TEXT(_data) => TEXT_LOCATOR_SIZE,
+            FieldValue::BLOB(_data) => TEXT_LOCATOR_SIZE,
+            FieldValue::BOOL(_data) => 1,
+            FieldValue::Null => 0,
+        }
+    }
+
+    /// 数值编码成定长的字节，按字节比较的结果与数值的大小顺序一致，其他类型返回 None
+    /// 整数翻转符号位；浮点数为负时按位取反，否则翻转符号位
+    pub(crate) fn ordered_bytes(&self) -> Option<Vec<u8>> {
+        match self {
+            FieldValue::INT32(data) => Some(((*data as u32) ^ (1 << 31)).to_be_bytes().to_vec()),
+            FieldValue::INT64(data) => Some(((*data as u64) ^ (1 << 63)).to_be_bytes().to_vec()),
+            FieldValue::FLOAT32(data) => {
+                let bits = data.to_bits();
+                let bits = if bits >> 31 == 1 { !bits } else { bits ^ (1 << 31) };
+                Some(bits.to_be_bytes().to_vec())
+            }
+            FieldValue::FLOAT64(data) => {
+                let bits = data.to_bits();
+                let bits = if bits >> 63 == 1 { !bits } else { bits ^ (1 << 63) };
+                Some(bits.to_be_bytes().to_vec())
+            }
+            _ => None
+        }
+    }
+
+    /// ordered_bytes 的逆变换，字节数与类型不符时返回 FieldValueNotCompatible
+    fn from_ordered_bytes(field_type: &FieldType, bytes: &[u8]) -> Result<FieldValue, Error> {
+        match (field_type, bytes.len()) {
+            (FieldType::INT32, 4) => Ok(FieldValue::INT32((BigEndian::read_u32(bytes) ^ (1 << 31)) as i32)),
+            (FieldType::INT64, 8) => Ok(FieldValue::INT64((BigEndian::read_u64(bytes) ^ (1 << 63)) as i64)),
+            (FieldType::FLOAT32, 4) => {
+                let bits = BigEndian::read_u32(bytes);
+                let bits = if bits >> 31 == 1 { bits ^ (1 << 31) } else { !bits };
+                Ok(FieldValue::FLOAT32(f32::from_bits(bits)))
+            }
+            (FieldType::FLOAT64, 8) => {
+                let bits = BigEndian::read_u64(bytes);
+                let bits = if bits >> 63 == 1 { bits ^ (1 << 63) } else { !bits };
+                Ok(FieldValue::FLOAT64(f64::from_bits(bits)))
+            }
+            _ => Err(Error::FieldValueNotCompatible)
+        }
+    }
+
+    /// 值在单字段索引中的键
+    /// 数值写成 ordered_bytes 的十六进制串，使索引按字符串比较的顺序与数值大小一致
+    /// 其他类型直接转换成字符串，并去掉定长字符串末尾填充的 \0
+    pub(crate) fn to_key(&self) -> String {
+        match self.ordered_bytes() {
+            Some(bytes) => bytes.iter().map(|byte| format!("{:02x}", byte)).collect(),
+            None => {
+                let key: String = self.into();
+                key.trim_matches(char::from(0)).to_string()
+            }
+        }
+    }
+
+    /// to_key 的逆变换，从 field_type 类型字段的索引键还原出值
+    /// BLOB 转换成键时可能丢失字节，不能还原
+    pub fn from_key(field_type: &FieldType, key: &str) -> Result<FieldValue, Error> {
+        match field_type {
+            FieldType::INT32 | FieldType::INT64 | FieldType::FLOAT32 | FieldType::FLOAT64 => match parse_hex(key) {
+                Some(bytes) => FieldValue::from_ordered_bytes(field_type, bytes.as_slice()),
+                None => Err(Error::FieldValueNotCompatible)
+            },
+            FieldType::BLOB => Err(Error::FieldValueNotCompatible),
+            _ => FieldValue::parse(field_type, key)
+        }
+    }
+}
+
+impl FieldValue {
+    /// 将文本解析成 field_type 类型的值，BLOB 以十六进制表示
+    pub fn parse(field_type: &FieldType, text: &str) -> Result<FieldValue, Error> {
+        let fv = match field_type {
+            FieldType::INT32 => text.trim().parse().map(FieldValue::INT32).ok(),
+            FieldType::INT64 => text.trim().parse().map(FieldValue::INT64).ok(),
+            FieldType::FLOAT32 => text.trim().parse().map(FieldValue::FLOAT32).ok(),
+            FieldType::FLOAT64 => text.trim().parse().map(FieldValue::FLOAT64).ok(),
+            FieldType::BOOL => match text.trim().to_lowercase().as_str() {
+                "true" => Some(FieldValue::BOOL(true)),
+                "false" => Some(FieldValue::BOOL(false)),
+                _ => None
+            },
+            FieldType::VARCHAR40 => {
+                if text.len() > 40 {
+                    return Err(Error::VarcharTooLong)
+                }
+                Some(FieldValue::VARCHAR40(text.to_string()))
+            }
+            FieldType::TEXT => Some(FieldValue::TEXT(text.to_string())),
+            FieldType::BLOB => parse_hex(text).map(FieldValue::BLOB),
+        };
+        match fv {
+            Some(fv) => Ok(fv),
+            None => Err(Error::FieldValueNotCompatible)
         }
     }
 }
 
+fn parse_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.is_ascii() {
+        return None
+    }
+    text.as_bytes().chunks(2).map(|pair| match pair {
+        [_, _] => u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok(),
+        _ => None
+    }).collect()
+}
+
 impl Clone for FieldValue {
     fn clone(&self) -> Self {
         match self {
             FieldValue::INT32(data) => FieldValue::INT32(*data),
+            FieldValue::INT64(data) => FieldValue::INT64(*data),
             FieldValue::FLOAT32(data) => FieldValue::FLOAT32(*data),
-            FieldValue::VARCHAR40(data) => FieldValue::VARCHAR40(data.clone())
+            FieldValue::FLOAT64(data) => FieldValue::FLOAT64(*data),
+            FieldValue::VARCHAR40(data) => FieldValue::VARCHAR40(data.clone()),
+            FieldValue::TEXT(data) => FieldValue::TEXT(data.clone()),
+            FieldValue::BLOB(data) => FieldValue::BLOB(data.clone()),
+            FieldValue::BOOL(data) => FieldValue::BOOL(*data),
+            FieldValue::Null => FieldValue::Null,
         }
     }
 }
 
+/// 浮点数按位比较，NaN 与自身相等，0.0 与 -0.0 不相等
+/// 这样比较满足自反性，测试中可以直接 assert_eq! 读出的行
+impl PartialEq for FieldValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (FieldValue::INT32(a), FieldValue::INT32(b)) => a == b,
+            (FieldValue::INT64(a), FieldValue::INT64(b)) => a == b,
+            (FieldValue::FLOAT32(a), FieldValue::FLOAT32(b)) => a.to_bits() == b.to_bits(),
+            (FieldValue::FLOAT64(a), FieldValue::FLOAT64(b)) => a.to_bits() == b.to_bits(),
+            (FieldValue::VARCHAR40(a), FieldValue::VARCHAR40(b)) => a == b,
+            (FieldValue::TEXT(a), FieldValue::TEXT(b)) => a == b,
+            (FieldValue::BLOB(a), FieldValue::BLOB(b)) => a == b,
+            (FieldValue::BOOL(a), FieldValue::BOOL(b)) => a == b,
+            (FieldValue::Null, FieldValue::Null) => true,
+            _ => false
+        }
+    }
+}
+
+impl Eq for FieldValue {}
+
+/// 面向用户的格式，用于导出和输出查询结果
+/// 浮点数取能还原原值的最短十进制形式，整数值也保留一位小数，3.0 输出为 3.0 而不是 3
+/// 定长字符串去掉末尾填充的 \0，BLOB 写成十六进制，空值写成 NULL
+impl std::fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldValue::INT32(data) => write!(f, "{}", data),
+            FieldValue::INT64(data) => write!(f, "{}", data),
+            FieldValue::FLOAT32(data) => write_float(f, data.to_string(), data.is_finite()),
+            FieldValue::FLOAT64(data) => write_float(f, data.to_string(), data.is_finite()),
+            FieldValue::VARCHAR40(data) => write!(f, "{}", data.trim_end_matches(char::from(0))),
+            FieldValue::TEXT(data) => write!(f, "{}", data),
+            FieldValue::BLOB(data) => data.iter().try_for_each(|byte| write!(f, "{:02x}", byte)),
+            FieldValue::BOOL(data) => write!(f, "{}", data),
+            FieldValue::Null => write!(f, "NULL"),
+        }
+    }
+}
+
+/// 有限的浮点数没有小数部分时补上 .0，NaN 和无穷大原样输出
+fn write_float(f: &mut std::fmt::Formatter<'_>, text: String, finite: bool) -> std::fmt::Result {
+    if finite && !text.contains('.') {
+        write!(f, "{}.0", text)
+    } else {
+        write!(f, "{}", text)
+    }
+}
+
 impl From<i32> for FieldValue {
     fn from(data: i32) -> Self {
         FieldValue::INT32(data)
     }
 }
 
+impl From<i64> for FieldValue {
+    fn from(data: i64) -> Self {
+        FieldValue::INT64(data)
+    }
+}
+
 impl From<f32> for FieldValue {
     fn from(data: f32) -> Self {
         FieldValue::FLOAT32(data)
     }
 }
 
+impl From<f64> for FieldValue {
+    fn from(data: f64) -> Self {
+        FieldValue::FLOAT64(data)
+    }
+}
+
+impl From<bool> for FieldValue {
+    fn from(data: bool) -> Self {
+        FieldValue::BOOL(data)
+    }
+}
+
 impl From<String> for FieldValue {
     fn from(data: String) -> Self {
         FieldValue::VARCHAR40(data)
@@ -76,12 +364,36 @@ impl From<FieldValue> for i32 {
     }
 }
 
+impl From<FieldValue> for i64 {
+    fn from(fv: FieldValue) -> Self {
+        match fv {
+            FieldValue::INT64(data) => data,
+            _ => 0,
+        }
+    }
+}
+
+impl From<FieldValue> for bool {
+    fn from(fv: FieldValue) -> Self {
+        match fv {
+            FieldValue::BOOL(data) => data,
+            _ => false,
+        }
+    }
+}
+
 impl From<FieldValue> for String {
     fn from(fv: FieldValue) -> Self {
         match fv {
             FieldValue::INT32(data) => data.to_string(),
+            FieldValue::INT64(data) => data.to_string(),
             FieldValue::FLOAT32(data) => data.to_string(),
-            FieldValue::VARCHAR40(data) => data
+            FieldValue::FLOAT64(data) => data.to_string(),
+            FieldValue::VARCHAR40(data) => data,
+            FieldValue::TEXT(data) => data,
+            FieldValue::BLOB(data) => String::from_utf8_lossy(&data).into_owned(),
+            FieldValue::BOOL(data) => data.to_string(),
+            FieldValue::Null => String::new(),
         }
     }
 }
@@ -90,8 +402,14 @@ impl From<&FieldValue> for String {
     fn from(fv: &FieldValue) -> Self {
         match fv {
             FieldValue::INT32(data) => data.to_string(),
+            FieldValue::INT64(data) => data.to_string(),
             FieldValue::FLOAT32(data) => data.to_string(),
-            FieldValue::VARCHAR40(data) => data.clone()
+            FieldValue::FLOAT64(data) => data.to_string(),
+            FieldValue::VARCHAR40(data) => data.clone(),
+            FieldValue::TEXT(data) => data.clone(),
+            FieldValue::BLOB(data) => String::from_utf8_lossy(data).into_owned(),
+            FieldValue::BOOL(data) => data.to_string(),
+            FieldValue::Null => String::new(),
         }
     }
 }
@@ -101,8 +419,21 @@ impl From<FieldValue> for Vec<u8> {
     fn from(fv: FieldValue) -> Self {
         match fv {
             FieldValue::INT32(data) => data.to_be_bytes().to_vec(),
+            FieldValue::INT64(data) => data.to_be_bytes().to_vec(),
             FieldValue::FLOAT32(data) => data.to_be_bytes().to_vec(),
-            FieldValue::VARCHAR40(data) => data.into_bytes()
+            FieldValue::FLOAT64(data) => data.to_be_bytes().to_vec(),
+            FieldValue::VARCHAR40(data) => {
+                // 定长字符串补 \0 到 40 字节，与解析时读取的长度一致
+                let mut bytes = data.into_bytes();
+                if bytes.len() < 40 {
+                    bytes.resize(40, 0);
+                }
+                bytes
+            }
+            FieldValue::TEXT(data) => data.into_bytes(),
+            FieldValue::BLOB(data) => data,
+            FieldValue::BOOL(data) => vec![data as u8],
+            FieldValue::Null => Vec::<u8>::new(),
         }
     }
 }
@@ -110,6 +441,9 @@ impl From<FieldValue> for Vec<u8> {
 pub struct Field {
     pub(crate) field_name: String,
     pub(crate) field_type: FieldType,
+    pub(crate) nullable: bool,
+    /// 唯一索引，插入前先检查索引中没有相同的值
+    pub(crate) unique: bool,
     btree: Option<BTree>,
 }
 
@@ -118,6 +452,8 @@ impl Clone for Field {
         Self {
             field_name: self.field_name.clone(),
             field_type: self.field_type.clone(),
+            nullable: self.nullable,
+            unique: self.unique,
             btree: self.btree.clone(),
         }
     }
@@ -125,7 +461,37 @@ impl Clone for Field {
 
 impl Field {
 
-    pub fn parse_self(&self, bytes: &[u8], offset: usize) -> Result<(FieldValue, usize), Error> {
+    /// 从 offset 处解析一个字段，返回字段值和解析用掉的字节数
+    /// 每个字段先是一个字节的空值标志，非空时后面才是字段的数据
+    /// TEXT 和 BLOB 字段的内容从表的 pager 中读取
+    pub fn parse_self(&self, bytes: &[u8], offset: usize, pager: &Pager, buffer: &mut Box<dyn Buffer>) -> Result<(FieldValue, usize), Error> {
+        if bytes[offset] == NULL_FLAG {
+            return Ok((FieldValue::Null, NULL_FLAG_SIZE));
+        }
+        let (fv, siz) = self.parse_value(bytes, offset + NULL_FLAG_SIZE, pager, buffer)?;
+        Ok((fv, siz + NULL_FLAG_SIZE))
+    }
+
+    /// 读取 offset 处 TEXT 或 BLOB 字段的长度和内容偏移
+    pub(crate) fn text_locator(bytes: &[u8], offset: usize) -> (usize, usize) {
+        let mut len = [0u8; 8];
+        len.clone_from_slice(&bytes[offset..offset + 8]);
+        let mut text_offset = [0u8; 8];
+        text_offset.clone_from_slice(&bytes[offset + 8..offset + TEXT_LOCATOR_SIZE]);
+        (u64::from_be_bytes(len) as usize, u64::from_be_bytes(text_offset) as usize)
+    }
+
+    /// 按 offset 处的长度和偏移从 pager 中读出单独保存的内容
+    fn read_out_of_line(bytes: &[u8], offset: usize, pager: &Pager, buffer: &mut Box<dyn Buffer>) -> Result<Vec<u8>, Error> {
+        let (len, text_offset) = Field::text_locator(bytes, offset);
+        // 空内容没有单独保存
+        if len == 0 {
+            return Ok(Vec::<u8>::new());
+        }
+        pager.get_value(text_offset, len, buffer)
+    }
+
+    fn parse_value(&self, bytes: &[u8], offset: usize, pager: &Pager, buffer: &mut Box<dyn Buffer>) -> Result<(FieldValue, usize), Error> {
         match self.field_type {
             FieldType::INT32 => {
                 let mut i32_data: [u8; 4] = [0; 4];
@@ -133,12 +499,24 @@ impl Field {
                 let res = i32::from_be_bytes(i32_data);
                 Ok((FieldValue::INT32(res), 4))
             }
+            FieldType::INT64 => {
+                let mut i64_data: [u8; 8] = [0; 8];
+                i64_data.clone_from_slice(&bytes[offset..offset + 8]);
+                let res = i64::from_be_bytes(i64_data);
+                Ok((FieldValue::INT64(res), 8))
+            }
             FieldType::FLOAT32 => {
                 let mut f32_data = [0u8; 4];
                 f32_data.clone_from_slice(&bytes[offset..offset + 4]);
                 let res = f32::from_be_bytes(f32_data);
                 Ok((FieldValue::FLOAT32(res), 4))
             }
+            FieldType::FLOAT64 => {
+                let mut f64_data = [0u8; 8];
+                f64_data.clone_from_slice(&bytes[offset..offset + 8]);
+                let res = f64::from_be_bytes(f64_data);
+                Ok((FieldValue::FLOAT64(res), 8))
+            }
             FieldType::VARCHAR40 => {
                 let mut char_data: [u8; 40] = [0; 40];
                 char_data.clone_from_slice(&bytes[offset..offset + 40]);
@@ -146,8 +524,21 @@ impl Field {
                     Ok(data) => data,
                     Err(_) => return Err(Error::UnexpectedError)
                 };
-                Ok((FieldValue::VARCHAR40(res.to_owned()), 40))
+                // 只去掉末尾填充的 \0
+                Ok((FieldValue::VARCHAR40(res.trim_end_matches(char::from(0)).to_owned()), 40))
+            }
+            FieldType::TEXT => {
+                let data = Field::read_out_of_line(bytes, offset, pager, buffer)?;
+                match String::from_utf8(data) {
+                    Ok(res) => Ok((FieldValue::TEXT(res), TEXT_LOCATOR_SIZE)),
+                    Err(_) => Err(Error::UTF8Error)
+                }
             }
+            FieldType::BLOB => {
+                let data = Field::read_out_of_line(bytes, offset, pager, buffer)?;
+                Ok((FieldValue::BLOB(data), TEXT_LOCATOR_SIZE))
+            }
+            FieldType::BOOL => Ok((FieldValue::BOOL(bytes[offset] != 0), 1)),
         }
     }
 
@@ -155,15 +546,29 @@ impl Field {
         Ok(Field {
             field_name,
             field_type,
+            nullable: false,
+            unique: false,
             btree: None,
         })
     }
 
-    pub fn create_btree(&mut self, file_name: String, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+    /// 创建一个允许为空的字段
+    pub fn create_nullable_field(field_name: String, field_type: FieldType) -> Result<Field, Error> {
+        Ok(Field {
+            field_name,
+            field_type,
+            nullable: true,
+            unique: false,
+            btree: None,
+        })
+    }
+
+    pub fn create_btree(&mut self, file_name: String, key_size: usize, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
         match &self.btree {
             Some(_) => return Err(Error::IndexExist),
             None => ()
         }
+        BTree::check_key_size(key_size)?;
         buffer.add_file(Path::new(file_name.as_str()))?;
         let pager = Pager::new(
             file_name.clone(),
@@ -171,85 +576,328 @@ impl Field {
             buffer,
         )?;
         self.btree = Some(
-            BTree::new(
+            BTree::new_with_key_size(
                 pager,
                 file_name,
+                key_size,
                 buffer,
             )?
         );
         Ok(())
     }
 
+    /// 唯一索引中已经有 fv 时返回 KeyAlreadyExists，非唯一索引不检查
+    pub(crate) fn check_unique(&self, fv: &FieldValue, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let btree = match &self.btree {
+            Some(btree) if self.unique => btree,
+            _ => return Ok(())
+        };
+        match btree.search(fv.to_key(), buffer) {
+            Ok(_) => Err(Error::KeyAlreadyExists),
+            Err(Error::KeyNotFound) => Ok(()),
+            Err(err) => Err(err)
+        }
+    }
+
+    /// 丢弃该字段在内存中的索引，索引文件保持不变，原来没有索引时返回 false
+    pub(crate) fn close_btree(&mut self) -> bool {
+        self.btree.take().is_some()
+    }
+
+    /// 重新打开该字段已有的索引文件
+    pub(crate) fn open_btree(&mut self, file_name: String, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        if self.btree.is_some() {
+            return Err(Error::IndexExist)
+        }
+        buffer.add_file(Path::new(file_name.as_str()))?;
+        let pager = Pager::new(
+            file_name.clone(),
+            40,
+            buffer,
+        )?;
+        self.btree = Some(BTree::open(pager, file_name, buffer)?);
+        Ok(())
+    }
+
+    /// 索引文件的页面被恢复后重新读入索引的根节点，没有索引时什么也不做
+    pub(crate) fn reload_btree(&mut self, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        match self.btree.as_mut() {
+            Some(btree) => btree.reload(buffer),
+            None => Ok(())
+        }
+    }
+
+    /// 索引中所有行的偏移量，按键的顺序排列，同一个键的多行按插入顺序排列
+    pub(crate) fn offsets(&self, buffer: &mut Box<dyn Buffer>, table_pager: &Pager) -> Result<Vec<usize>, Error> {
+        let values: Vec<usize> = match &self.btree {
+            Some(btree) => btree.search_range(None, None, buffer)?.iter().map(|kv| kv.value).collect(),
+            None => return Err(Error::IndexWithoutBTree)
+        };
+        let mut res = Vec::<usize>::new();
+        for value in values {
+            res.extend(Field::value_offsets(value, table_pager, buffer)?);
+        }
+        Ok(res)
+    }
+
+    /// 索引中所有行的偏移量，与 offsets 的顺序正好相反
+    pub(crate) fn offsets_rev(&self, buffer: &mut Box<dyn Buffer>, table_pager: &Pager) -> Result<Vec<usize>, Error> {
+        let values = match &self.btree {
+            Some(btree) => btree.iter_rev(buffer).map(|kv| kv.map(|kv| kv.value)).collect::<Result<Vec<usize>, Error>>()?,
+            None => return Err(Error::IndexWithoutBTree)
+        };
+        let mut res = Vec::<usize>::new();
+        for value in values {
+            res.extend(Field::value_offsets(value, table_pager, buffer)?.into_iter().rev());
+        }
+        Ok(res)
+    }
+
     pub fn insert(&mut self, key_index: usize, entry: Entry, pager: &mut Box<Pager>, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let key = self.check_insert(key_index, &entry)?;
+        let bytes = entry.to_bytes();
+        let offset = pager.insert_value(bytes.as_slice(), buffer)?;
+        let kv = KeyValuePair::new(key, offset);
         match &mut self.btree {
-            Some(btree) => {
-                if key_index > entry.data.len() {
-                    return Err(Error::UnexpectedError)
-                }
-                match (&self.field_type, &entry.data.get(key_index).unwrap()) {
-                    (FieldType::INT32, FieldValue::INT32(_data)) => (),
-                    (FieldType::FLOAT32, FieldValue::FLOAT32(_data)) => (),
-                    (FieldType::VARCHAR40, FieldValue::VARCHAR40(_data)) => (),
-                    _ => return Err(Error::UnexpectedError)
-                }
-                let key: String = entry.data.get(key_index).unwrap().into();
-                let mut bytes = Vec::<u8>::new();
-                for i in entry.data {
-                    let bs: Vec<u8> = i.into();
-                    bytes = [bytes, bs].concat()
+            Some(btree) => btree.insert(kv, buffer),
+            None => Err(Error::IndexWithoutBTree)
+        }
+    }
+
+    /// 与 insert 相同，但键已存在时不报错，而是把新行追加到该键的偏移量列表中
+    /// 用于一个键对应多行的非唯一索引，这样插入的键需要用 search_all 查询
+    pub fn insert_multi(&mut self, key_index: usize, entry: Entry, pager: &mut Box<Pager>, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        self.check_insert(key_index, &entry)?;
+        let bytes = entry.to_bytes();
+        let offset = pager.insert_value(bytes.as_slice(), buffer)?;
+        self.insert_key_multi(&entry.data[key_index], offset, pager, buffer)
+    }
+
+    /// 检查要插入的行，返回 key_index 处字段的索引键
+    fn check_insert(&self, key_index: usize, entry: &Entry) -> Result<String, Error> {
+        let btree = match &self.btree {
+            Some(btree) => btree,
+            None => return Err(Error::IndexWithoutBTree)
+        };
+        if key_index > entry.data.len() {
+            return Err(Error::UnexpectedError)
+        }
+        match (&self.field_type, &entry.data.get(key_index).unwrap()) {
+            (FieldType::INT32, FieldValue::INT32(_data)) => (),
+            (FieldType::INT64, FieldValue::INT64(_data)) => (),
+            (FieldType::FLOAT32, FieldValue::FLOAT32(_data)) => (),
+            (FieldType::FLOAT64, FieldValue::FLOAT64(_data)) => (),
+            (FieldType::VARCHAR40, FieldValue::VARCHAR40(_data)) => (),
+            (FieldType::TEXT, FieldValue::TEXT(_data)) => (),
+            (FieldType::BLOB, FieldValue::BLOB(_data)) => (),
+            (FieldType::BOOL, FieldValue::BOOL(_data)) => (),
+            _ => return Err(Error::UnexpectedError)
+        }
+        // 超长的定长字符串写入时会被截断，直接拒绝而不是丢掉数据
+        for fv in &entry.data {
+            if let FieldValue::VARCHAR40(data) = fv {
+                if data.len() > 40 {
+                    return Err(Error::VarcharTooLong)
                 }
-                let offset = pager.insert_value(bytes.as_slice(), buffer)?;
-                let kv = KeyValuePair::new(key, offset);
-                btree.insert(kv, buffer)
-            }
-            None => {
-                Err(Error::IndexWithoutBTree)
             }
         }
+        let key = entry.data.get(key_index).unwrap().to_key();
+        // 先检查键宽，避免写入行数据之后才发现键放不进索引
+        BTree::check_key_len(&key, btree.get_key_size())?;
+        Ok(key)
     }
 
-    pub fn search(&self, fv: FieldValue, buffer: &mut Box<dyn Buffer>) -> Result<Vec<u8>, Error> {
-        match &self.btree {
-            Some(btree) => {
-                let key = (&fv).into();
-                match btree.search(key, buffer) {
-                    Ok(data) => {
-                        let offset = data.value;
-                        let siz = fv.to_size();
-                        btree.pager.get_value(offset, siz, buffer)
-                    }
-                    Err(err) => Err(err)
+    /// 向索引中加入一个指向 offset 处的行的键，键已存在时把 offset 追加到该键的偏移量列表中
+    /// 列表写入表的 pager，旧列表被释放，索引中的值改为带 MULTI_VALUE_FLAG 的列表偏移
+    pub(crate) fn insert_key_multi(&mut self, fv: &FieldValue, offset: usize, pager: &mut Box<Pager>, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let btree = match &mut self.btree {
+            Some(btree) => btree,
+            None => return Err(Error::IndexWithoutBTree)
+        };
+        let key = fv.to_key();
+        let old = match btree.search(key.clone(), buffer) {
+            Ok(kv) => kv.value,
+            Err(Error::KeyNotFound) => return btree.insert(KeyValuePair::new(key, offset), buffer),
+            Err(err) => return Err(err)
+        };
+
+        let mut offsets = Field::value_offsets(old, pager, buffer)?;
+        offsets.push(offset);
+        Field::store_offsets(btree, key, old, offsets.as_slice(), pager, buffer)
+    }
+
+    /// 从键 fv 对应的行中去掉 offset 处的一行，键不再对应任何行时从索引中删除
+    /// 唯一索引的键只对应一行，效果与 delete 相同
+    pub(crate) fn remove_offset(&mut self, fv: &FieldValue, offset: usize, pager: &mut Box<Pager>, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let btree = match &mut self.btree {
+            Some(btree) => btree,
+            None => return Err(Error::IndexWithoutBTree)
+        };
+        let key = fv.to_key();
+        let old = btree.search(key.clone(), buffer)?.value;
+        let mut offsets = Field::value_offsets(old, pager, buffer)?;
+        match offsets.iter().position(|item| *item == offset) {
+            Some(pos) => offsets.remove(pos),
+            None => return Err(Error::KeyNotFound)
+        };
+        Field::store_offsets(btree, key, old, offsets.as_slice(), pager, buffer)
+    }
+
+    /// 键 fv 对应的行从 old_offset 移到了 new_offset，其他行不变
+    pub(crate) fn replace_offset(&mut self, fv: &FieldValue, old_offset: usize, new_offset: usize, pager: &mut Box<Pager>, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let btree = match &mut self.btree {
+            Some(btree) => btree,
+            None => return Err(Error::IndexWithoutBTree)
+        };
+        let key = fv.to_key();
+        let old = btree.search(key.clone(), buffer)?.value;
+        let mut offsets = Field::value_offsets(old, pager, buffer)?;
+        match offsets.iter_mut().find(|item| **item == old_offset) {
+            Some(item) => *item = new_offset,
+            None => return Err(Error::KeyNotFound)
+        }
+        Field::store_offsets(btree, key, old, offsets.as_slice(), pager, buffer)
+    }
+
+    /// 把索引中 key 的值由 old 改为 offsets：没有偏移时删除键，只有一个时直接保存该偏移，
+    /// 多个时写入新的偏移量列表；old 是偏移量列表时先释放它
+    fn store_offsets(btree: &mut BTree, key: String, old: usize, offsets: &[usize], pager: &mut Box<Pager>, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        Field::free_offset_list(old, pager, buffer)?;
+        match offsets {
+            [] => btree.delete(key, buffer),
+            [offset] => btree.update(KeyValuePair::new(key, *offset), buffer).map(|_| ()),
+            _ => {
+                let mut bytes = (offsets.len() as u32).to_be_bytes().to_vec();
+                for offset in offsets {
+                    bytes.extend_from_slice(&(*offset as u64).to_be_bytes());
                 }
+                let list_offset = pager.insert_value(bytes.as_slice(), buffer)?;
+                btree.update(KeyValuePair::new(key, list_offset | MULTI_VALUE_FLAG), buffer).map(|_| ())
             }
-            None => {
-                Err(Error::IndexWithoutBTree)
+        }
+    }
+
+    /// 索引中的值是偏移量列表时释放列表占用的空间，否则什么也不做
+    fn free_offset_list(value: usize, pager: &mut Box<Pager>, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        if value & MULTI_VALUE_FLAG == 0 {
+            return Ok(());
+        }
+        let len = Field::value_offsets(value, pager, buffer)?.len();
+        pager.delete_value(value & !MULTI_VALUE_FLAG, OFFSET_LIST_LEN_SIZE + 8 * len, buffer)
+    }
+
+    /// 索引中的值对应的所有行偏移，值带 MULTI_VALUE_FLAG 时从 pager 中读出偏移量列表
+    /// 列表格式: 偏移数(u32) 偏移(u64)...
+    pub(crate) fn value_offsets(value: usize, pager: &Pager, buffer: &mut Box<dyn Buffer>) -> Result<Vec<usize>, Error> {
+        if value & MULTI_VALUE_FLAG == 0 {
+            return Ok(vec![value]);
+        }
+        let list_offset = value & !MULTI_VALUE_FLAG;
+        let len = Field::offset_list_len(list_offset, pager, buffer)?;
+        let bytes = pager.get_value(list_offset + OFFSET_LIST_LEN_SIZE, 8 * len, buffer)?;
+        Ok(bytes.chunks(8).map(|chunk| {
+            let mut offset = [0u8; 8];
+            offset.clone_from_slice(chunk);
+            u64::from_be_bytes(offset) as usize
+        }).collect())
+    }
+
+    /// list_offset 处的偏移量列表中的偏移数
+    fn offset_list_len(list_offset: usize, pager: &Pager, buffer: &mut Box<dyn Buffer>) -> Result<usize, Error> {
+        let mut len = [0u8; OFFSET_LIST_LEN_SIZE];
+        len.clone_from_slice(pager.get_value(list_offset, OFFSET_LIST_LEN_SIZE, buffer)?.as_slice());
+        Ok(u32::from_be_bytes(len) as usize)
+    }
+
+    /// 在新打开的表的 pager 中标记索引里所有偏移量列表占用的空间，没有索引时什么也不做
+    pub(crate) fn recover_offset_lists(&self, pager: &mut Box<Pager>, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let btree = match &self.btree {
+            Some(btree) => btree,
+            None => return Ok(())
+        };
+        for kv in btree.search_range(None, None, buffer)? {
+            if kv.value & MULTI_VALUE_FLAG == 0 {
+                continue;
             }
+            let list_offset = kv.value & !MULTI_VALUE_FLAG;
+            // 先保证列表开头所在的页已分配，才能读出偏移数
+            pager.mark_used(list_offset, OFFSET_LIST_LEN_SIZE, buffer)?;
+            let len = Field::offset_list_len(list_offset, pager, buffer)?;
+            pager.mark_used(list_offset, OFFSET_LIST_LEN_SIZE + 8 * len, buffer)?;
+        }
+        Ok(())
+    }
+
+    /// 通过索引查询键为 fv 的所有行，从表的 pager 中读取每行 sizz 字节
+    /// 用 insert 插入的键只有一行，用 insert_multi 插入的键可能有多行，按插入顺序返回
+    pub fn search_all(&self, fv: FieldValue, buffer: &mut Box<dyn Buffer>, sizz: usize, table_pager: &Pager) -> Result<Vec<Vec<u8>>, Error> {
+        let mut res = Vec::<Vec<u8>>::new();
+        for offset in self.search_offsets(fv, buffer, table_pager)? {
+            res.push(table_pager.get_value_in_page(offset, sizz, buffer)?);
+        }
+        Ok(res)
+    }
+
+    /// 通过索引查询一行，从表的 pager 中读取 sizz 字节的行数据
+    /// 键对应多行时返回最先插入的一行
+    pub fn search(&self, fv: FieldValue, buffer: &mut Box<dyn Buffer>, sizz: usize, table_pager: &Pager) -> Result<Vec<u8>, Error> {
+        let offset = self.search_offset(fv, buffer, table_pager)?;
+        table_pager.get_value_in_page(offset, sizz, buffer)
+    }
+
+    /// 通过索引查询一行在表文件中的偏移量，键对应多行时返回最先插入的一行
+    pub fn search_offset(&self, fv: FieldValue, buffer: &mut Box<dyn Buffer>, table_pager: &Pager) -> Result<usize, Error> {
+        match self.search_offsets(fv, buffer, table_pager)?.first() {
+            Some(offset) => Ok(*offset),
+            None => Err(Error::KeyNotFound)
+        }
+    }
+
+    /// 通过索引查询键为 fv 的所有行在表文件中的偏移量，按插入顺序排列
+    pub fn search_offsets(&self, fv: FieldValue, buffer: &mut Box<dyn Buffer>, table_pager: &Pager) -> Result<Vec<usize>, Error> {
+        let value = match &self.btree {
+            Some(btree) => btree.search(fv.to_key(), buffer)?.value,
+            None => return Err(Error::IndexWithoutBTree)
+        };
+        Field::value_offsets(value, table_pager, buffer)
+    }
+
+    /// 向索引中加入一个指向 offset 处的行的键
+    pub fn insert_key(&mut self, fv: &FieldValue, offset: usize, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        match &mut self.btree {
+            Some(btree) => btree.insert(KeyValuePair::new(fv.to_key(), offset), buffer),
+            None => Err(Error::IndexWithoutBTree)
         }
     }
 
+    /// 从索引中删除一个键，键对应多行时一并释放表的 pager 中的偏移量列表
+    pub fn delete(&mut self, fv: FieldValue, buffer: &mut Box<dyn Buffer>, table_pager: &mut Box<Pager>) -> Result<(), Error> {
+        let btree = match &mut self.btree {
+            Some(btree) => btree,
+            None => return Err(Error::IndexWithoutBTree)
+        };
+        let key = fv.to_key();
+        let old = btree.search(key.clone(), buffer)?.value;
+        btree.delete(key, buffer)?;
+        Field::free_offset_list(old, table_pager, buffer)
+    }
+
+    /// 通过索引查询键在 left 和 right 之间的行，从表的 pager 中读取每行 sizz 字节
+    /// sizz 是整行的大小，不能用键的大小代替
     pub fn search_range(&self, left: Option<FieldValue>, right: Option<FieldValue>, buffer: &mut Box<dyn Buffer>, sizz: usize, table_pager: &mut Box<Pager>) -> Result<Vec<Vec<u8>>, Error> {
+        self.search_range_ex(to_bound(left), to_bound(right), buffer, sizz, table_pager)
+    }
+
+    /// 与 search_range 相同，但每一端可以选择是否包括边界
+    pub fn search_range_ex(&self, left: Bound<FieldValue>, right: Bound<FieldValue>, buffer: &mut Box<dyn Buffer>, sizz: usize, table_pager: &mut Box<Pager>) -> Result<Vec<Vec<u8>>, Error> {
         match &self.btree {
             Some(btree) => {
-                let mut siz = sizz;
-                let left_string = match left {
-                    Some(left_value) => {
-                        siz = left_value.to_size();
-                        Some((&left_value).into())
-                    },
-
-                    None => None
-                };
-                let right_string = match right {
-                    Some(right_value) => {
-                        siz = right_value.to_size();
-                        Some((&right_value).into())
-                    },
-                    None => None
-                };
-                let res = btree.search_range(left_string, right_string, buffer)?;
+                let res = btree.search_range_ex(Field::key_bound(left), Field::key_bound(right), buffer)?;
                 let mut res_vec = Vec::<Vec<u8>>::new();
-                for (_i, item) in res.iter().enumerate() {
-                    res_vec.push(table_pager.get_value(item.value, siz, buffer)?);
+                for item in res.iter() {
+                    for offset in Field::value_offsets(item.value, table_pager, buffer)? {
+                        res_vec.push(table_pager.get_value_in_page(offset, sizz, buffer)?);
+                    }
                 }
                 Ok(res_vec)
             }
@@ -257,6 +905,20 @@ impl Field {
         }
     }
 
+    /// 将字段值的边界转换成索引键的边界
+    pub(crate) fn key_bound(bound: Bound<FieldValue>) -> Bound<String> {
+        match bound {
+            Bound::Included(fv) => Bound::Included(fv.to_key()),
+            Bound::Excluded(fv) => Bound::Excluded(fv.to_key()),
+            Bound::Unbounded => Bound::Unbounded
+        }
+    }
+
+    /// 字段上的索引
+    pub(crate) fn btree(&self) -> Option<&BTree> {
+        self.btree.as_ref()
+    }
+
     pub fn is_indexed(&self) -> bool {
         self.btree.is_some()
     }
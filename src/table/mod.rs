@@ -1,4 +1,9 @@
 pub mod table_manager;
 pub mod table_item;
 pub mod field;
+pub mod composite_key;
+pub mod planner;
+pub mod executor;
+pub mod predicate;
+pub mod booter;
 pub(crate) mod entry;
@@ -0,0 +1,72 @@
+use crate::table::field::FieldValue;
+use crate::table::table_item::TableStatistics;
+
+/// 没有更多信息时，假设范围查询命中表中三分之一的行
+const RANGE_SELECTIVITY: f64 = 1.0 / 3.0;
+
+/// WHERE 子句中单个字段与常量的比较
+/// SQL 执行器把解析出的 WHERE 翻译成若干个以 AND 连接的条件
+pub enum Condition {
+    /// 字段等于常量
+    Eq(usize, FieldValue),
+    /// 字段落在两个常量之间，None 表示该侧无界
+    Range(usize, Option<FieldValue>, Option<FieldValue>),
+    /// 无法用索引加速的条件，如两个字段的比较
+    Other,
+}
+
+/// 查询计划选择的访问路径
+pub enum AccessPath {
+    /// 通过索引查询一个键
+    IndexPoint {
+        key_index: usize,
+        value: FieldValue,
+    },
+    /// 通过索引查询一个范围
+    IndexRange {
+        key_index: usize,
+        left: Option<FieldValue>,
+        right: Option<FieldValue>,
+    },
+    /// 扫描全表
+    FullScan,
+}
+
+/// 按照索引和统计信息，为一组条件选择代价最小的访问路径
+/// indexes 是建有索引的字段下标
+pub fn plan(conditions: &[Condition], indexes: &[usize], stats: &TableStatistics) -> AccessPath {
+    let rows = stats.row_count as f64;
+
+    // 全表扫描需要从第一个叶子节点开始读出每一行
+    let mut best = AccessPath::FullScan;
+    let mut best_cost = 1.0 + rows;
+
+    for condition in conditions {
+        let (path, cost) = match condition {
+            Condition::Eq(key_index, value) if indexes.contains(key_index) => {
+                (AccessPath::IndexPoint { key_index: *key_index, value: value.clone() }, 1.0)
+            }
+            Condition::Range(key_index, left, right) if indexes.contains(key_index) => {
+                let selectivity = if left.is_none() && right.is_none() {
+                    1.0
+                } else {
+                    RANGE_SELECTIVITY
+                };
+                let path = AccessPath::IndexRange {
+                    key_index: *key_index,
+                    left: left.clone(),
+                    right: right.clone(),
+                };
+                // 定位到第一个叶子节点需要额外一次读
+                (path, 1.0 + rows * selectivity)
+            }
+            _ => continue,
+        };
+        // 代价相同时优先使用索引
+        if cost <= best_cost {
+            best = path;
+            best_cost = cost;
+        }
+    }
+    best
+}
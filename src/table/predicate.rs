@@ -0,0 +1,79 @@
+use crate::table::entry::Entry;
+use crate::table::field::{Field, FieldValue};
+use crate::table::table_item::Table;
+use crate::util::error::Error;
+use std::cmp::Ordering;
+
+/// 对一行求值的过滤条件，字段用下标表示，与常量比较
+/// 用于不能通过索引完成的 WHERE 子句，见 Table::scan_filtered
+pub enum Predicate {
+    /// 字段等于常量
+    Eq(usize, FieldValue),
+    /// 字段小于常量
+    Lt(usize, FieldValue),
+    /// 字段大于常量
+    Gt(usize, FieldValue),
+    And(Box<Predicate>, Box<Predicate>),
+    Or(Box<Predicate>, Box<Predicate>),
+}
+
+impl Predicate {
+    pub fn and(self, other: Predicate) -> Predicate {
+        Predicate::And(Box::new(self), Box::new(other))
+    }
+
+    pub fn or(self, other: Predicate) -> Predicate {
+        Predicate::Or(Box::new(self), Box::new(other))
+    }
+
+    /// 检查条件中的每个比较，字段下标越界返回 FieldNotFound，常量与字段类型不符返回 FieldValueNotCompatible
+    pub fn check(&self, fields: &[Field]) -> Result<(), Error> {
+        match self {
+            Predicate::Eq(i, fv) | Predicate::Lt(i, fv) | Predicate::Gt(i, fv) => match fields.get(*i) {
+                Some(field) => Table::check_field(field, fv),
+                None => Err(Error::FieldNotFound)
+            },
+            Predicate::And(left, right) | Predicate::Or(left, right) => {
+                left.check(fields)?;
+                right.check(fields)
+            }
+        }
+    }
+
+    /// 对按 fields 排列的一行求值，空值参与的比较结果为假
+    /// 先检查整个条件，因此短路求值不会跳过类型错误
+    pub fn eval(&self, entry: &Entry, fields: &[Field]) -> Result<bool, Error> {
+        self.check(fields)?;
+        Ok(self.matches(entry))
+    }
+
+    /// 对已经检查过的条件求值
+    pub(crate) fn matches(&self, entry: &Entry) -> bool {
+        let (i, fv, expected) = match self {
+            Predicate::Eq(i, fv) => (i, fv, Ordering::Equal),
+            Predicate::Lt(i, fv) => (i, fv, Ordering::Less),
+            Predicate::Gt(i, fv) => (i, fv, Ordering::Greater),
+            Predicate::And(left, right) => return left.matches(entry) && right.matches(entry),
+            Predicate::Or(left, right) => return left.matches(entry) || right.matches(entry),
+        };
+        match entry.data.get(*i) {
+            Some(value) => compare(value, fv) == Some(expected),
+            None => false
+        }
+    }
+}
+
+/// 比较两个同类型的字段值，空值或类型不同时无法比较
+pub(crate) fn compare(a: &FieldValue, b: &FieldValue) -> Option<Ordering> {
+    match (a, b) {
+        (FieldValue::INT32(a), FieldValue::INT32(b)) => a.partial_cmp(b),
+        (FieldValue::INT64(a), FieldValue::INT64(b)) => a.partial_cmp(b),
+        (FieldValue::FLOAT32(a), FieldValue::FLOAT32(b)) => a.partial_cmp(b),
+        (FieldValue::FLOAT64(a), FieldValue::FLOAT64(b)) => a.partial_cmp(b),
+        (FieldValue::VARCHAR40(a), FieldValue::VARCHAR40(b)) => a.partial_cmp(b),
+        (FieldValue::TEXT(a), FieldValue::TEXT(b)) => a.partial_cmp(b),
+        (FieldValue::BLOB(a), FieldValue::BLOB(b)) => a.partial_cmp(b),
+        (FieldValue::BOOL(a), FieldValue::BOOL(b)) => a.partial_cmp(b),
+        _ => None
+    }
+}
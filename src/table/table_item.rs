@@ -1,14 +1,45 @@
-use crate::table::field::{Field, FieldValue, FieldType};
+use crate::table::field::{Field, FieldValue, FieldType, NULL_FLAG, NOT_NULL_FLAG, NULL_FLAG_SIZE};
 use crate::util::error::Error;
-use crate::table::entry::Entry;
+use crate::table::entry::{Entry, EntryBuilder};
 use crate::data_item::buffer::Buffer;
 use crate::page::pager::Pager;
+use crate::table::planner::AccessPath;
+use crate::table::composite_key::CompositeKey;
+use crate::table::predicate::Predicate;
+use crate::index::btree::{BTree, LeafCursor, to_bound};
+use crate::index::key_value_pair::trim_key;
+use std::collections::VecDeque;
+use std::ops::Bound;
 use std::path::Path;
 
+/// 表的统计信息，供查询计划选择访问路径
+pub struct TableStatistics {
+    pub row_count: usize,
+    pub index_count: usize,
+    pub avg_row_size: f64,
+    pub page_count: usize,
+    pub used_bytes: usize,
+}
+
+/// Table::aggregate 支持的聚合函数
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Agg {
+    /// 整数求和得到 INT64，浮点数求和得到 FLOAT64
+    Sum,
+    /// 平均值总是 FLOAT64
+    Avg,
+    Min,
+    Max,
+}
+
 pub struct Table {
     pub(crate) table_name: String,
     pub(crate) fields: Vec<Field>,
-    pager: Box<Pager>
+    /// 组合主键，存在时行通过它定位，否则通过第一个字段的索引定位
+    pub(crate) primary_key: Option<CompositeKey>,
+    pub(crate) pager: Box<Pager>,
+    /// 事务开始时表的内存状态，没有进行中的事务时为 None
+    snapshot: Option<Box<Table>>,
 }
 
 impl Table {
@@ -17,21 +48,254 @@ impl Table {
         Ok(Table {
             table_name: table_name.clone(),
             fields: Vec::<Field>::new(),
+            primary_key: None,
             pager: Pager::new(table_name, 40, buffer)?,
+            snapshot: None,
         })
     }
 
+    /// 重新打开已有的表，indexed 为建有索引的字段下标，key_columns 为组合主键的字段下标，为空表示没有组合主键
+    /// 表文件中的空间使用情况通过定位行的索引找到所有行后恢复
+    pub fn open(table_name: String, fields: Vec<Field>, indexed: &[usize], key_columns: &[usize], buffer: &mut Box<dyn Buffer>) -> Result<Table, Error> {
+        let mut table = Table::new(table_name, buffer)?;
+        table.add_fields(fields);
+        for i in indexed {
+            let file_name = match table.fields.get(*i) {
+                Some(field) => table.index_file_name(&field.field_name),
+                None => return Err(Error::UnexpectedError)
+            };
+            table.fields[*i].open_btree(file_name, buffer)?;
+        }
+        if !key_columns.is_empty() {
+            let file_name = table.key_file_name();
+            table.primary_key = Some(CompositeKey::open(file_name, key_columns.to_vec(), table.fields.as_slice(), buffer)?);
+        }
+
+        table.recover_space(buffer)?;
+        Ok(table)
+    }
+
+    /// 通过定位行的索引找到所有行，在新打开的 pager 中标记它们以及非唯一索引的偏移量列表占用的空间
+    fn recover_space(&mut self, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let offsets = match self.row_offsets(buffer) {
+            Ok(offsets) => offsets,
+            Err(Error::IndexWithoutBTree) => Vec::<usize>::new(),
+            Err(err) => return Err(err)
+        };
+        for offset in offsets {
+            // insert_value 写入的行不会跨页，最多读到页尾
+            self.pager.mark_used(offset, 0, buffer)?;
+            let row = self.pager.get_value_in_page(offset, self.row_size(), buffer)?;
+            let (size, texts) = self.row_layout(row.as_slice());
+            self.pager.mark_used(offset, size, buffer)?;
+            for (len, text_offset) in texts {
+                self.pager.mark_used(text_offset, len, buffer)?;
+            }
+        }
+        for i in 0..self.fields.len() {
+            if self.allows_duplicates(i) {
+                self.fields[i].recover_offset_lists(&mut self.pager, buffer)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 将表改名为 new_name，表文件、字段的索引文件和组合主键的索引文件随之在磁盘上改名
+    /// 事务进行中不能改名
+    pub(crate) fn rename(&mut self, new_name: String, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        if self.snapshot.is_some() {
+            return Err(Error::TransactionState)
+        }
+        let old_key_file = self.key_file_name();
+        let old_index_files: Vec<String> = self.fields.iter().map(|field| self.index_file_name(&field.field_name)).collect();
+        buffer.remove_file(self.table_name.as_str())?;
+        std::fs::rename(self.table_name.as_str(), new_name.as_str())?;
+        self.table_name = new_name;
+        buffer.add_file(Path::new(self.table_name.as_str()))?;
+        self.pager = Pager::new(self.table_name.clone(), 40, buffer)?;
+
+        for (i, old_index_file) in old_index_files.iter().enumerate() {
+            if !self.fields[i].close_btree() {
+                continue;
+            }
+            let index_file = self.index_file_name(&self.fields[i].field_name);
+            buffer.remove_file(old_index_file.as_str())?;
+            std::fs::rename(old_index_file.as_str(), index_file.as_str())?;
+            self.fields[i].open_btree(index_file, buffer)?;
+        }
+        if let Some(primary_key) = self.primary_key.take() {
+            let key_file = self.key_file_name();
+            buffer.remove_file(old_key_file.as_str())?;
+            std::fs::rename(old_key_file.as_str(), key_file.as_str())?;
+            self.primary_key = Some(CompositeKey::open(key_file, primary_key.columns, self.fields.as_slice(), buffer)?);
+        }
+        self.recover_space(buffer)
+    }
+
+    /// 开始一个事务，记下表的内存状态，之后对表文件和索引文件的页面修改都可以回滚
+    /// 事务作用于整个缓冲区，同一时间只能有一个事务
+    pub fn begin(&mut self, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        if self.snapshot.is_some() {
+            return Err(Error::TransactionState)
+        }
+        buffer.begin_transaction()?;
+        self.snapshot = Some(Box::new(self.clone()));
+        Ok(())
+    }
+
+    /// 提交事务，将修改过的页面写回磁盘
+    pub fn commit(&mut self, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        if self.snapshot.take().is_none() {
+            return Err(Error::TransactionState)
+        }
+        buffer.commit_transaction()
+    }
+
+    /// 回滚事务，页面恢复成事务开始时的内容，表回到开始时的状态
+    pub fn rollback(&mut self, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let snapshot = match self.snapshot.take() {
+            Some(snapshot) => snapshot,
+            None => return Err(Error::TransactionState)
+        };
+        buffer.rollback_transaction()?;
+        *self = *snapshot;
+        // 快照与当前的表共享索引的根节点，需要从恢复后的页面重新读入
+        for field in self.fields.iter_mut() {
+            field.reload_btree(buffer)?;
+        }
+        if let Some(primary_key) = self.primary_key.as_mut() {
+            primary_key.reload(buffer)?;
+        }
+        Ok(())
+    }
+
     pub fn insert(&mut self, entry: Entry, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        self.check_entry(&entry)?;
+        let key = match &self.primary_key {
+            Some(primary_key) => Some(primary_key.key_of(&entry)?),
+            None => None
+        };
+        // 唯一索引在写入行之前检查，不通过时表不会被修改
+        for (field, fv) in self.fields.iter().zip(entry.data.iter()) {
+            field.check_unique(fv, buffer)?;
+        }
+
+        // 行数据只在表文件中存一份，每个索引都指向同一个偏移量
+        let bytes = self.encode_row(&entry, buffer)?;
+        let offset = self.pager.insert_value(bytes.as_slice(), buffer)?;
+        if let (Some(primary_key), Some(key)) = (self.primary_key.as_mut(), key.clone()) {
+            if let Err(err) = primary_key.insert_key(key, offset, buffer) {
+                self.free_row(offset, bytes.as_slice(), buffer)?;
+                return Err(err)
+            }
+        }
+        for i in 0..self.fields.len() {
+            if !self.fields[i].is_indexed() {
+                continue;
+            }
+            if let Err(err) = self.insert_index_key(i, &entry.data[i], offset, buffer) {
+                // 撤销已经插入的索引键和行数据
+                for j in 0..i {
+                    if self.fields[j].is_indexed() {
+                        self.fields[j].remove_offset(&entry.data[j], offset, &mut self.pager, buffer)?;
+                    }
+                }
+                if let (Some(primary_key), Some(key)) = (self.primary_key.as_mut(), key) {
+                    primary_key.delete_key(key, buffer)?;
+                }
+                self.free_row(offset, bytes.as_slice(), buffer)?;
+                return Err(err)
+            }
+        }
+        Ok(())
+    }
+
+    /// 非唯一索引的一个键可以对应多行；没有组合主键时第一个字段的索引用来定位行，键不能重复
+    fn allows_duplicates(&self, key_index: usize) -> bool {
+        !self.fields[key_index].unique && (key_index > 0 || self.primary_key.is_some())
+    }
+
+    /// 向 key_index 处字段的索引中加入指向 offset 处的行的键
+    /// 允许重复的索引把行追加到键的偏移量列表中，其他索引在键已存在时返回 KeyAlreadyExists
+    fn insert_index_key(&mut self, key_index: usize, fv: &FieldValue, offset: usize, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        if self.allows_duplicates(key_index) {
+            self.fields[key_index].insert_key_multi(fv, offset, &mut self.pager, buffer)
+        } else {
+            self.fields[key_index].insert_key(fv, offset, buffer)
+        }
+    }
+
+    /// 检查一行的字段数和每个字段的类型，并确认表中有能读出行的索引
+    fn check_entry(&self, entry: &Entry) -> Result<(), Error> {
         if self.fields.len() != entry.data.len() {
             return Err(Error::UnexpectedError)
         }
 
-        for (i, item) in self.fields.iter().enumerate() {
-            Table::check_field(item, entry.data.get(i).unwrap())?;
+        for (item, fv) in self.fields.iter().zip(entry.data.iter()) {
+            Table::check_field(item, fv)?;
+        }
+
+        // 行总是通过组合主键或第一个字段的索引读出，必须先建立其中之一
+        if self.primary_key.is_none() && !self.fields[0].is_indexed() {
+            return Err(Error::IndexWithoutBTree)
+        }
+        Ok(())
+    }
+
+    /// 插入一批行，先按定位行的索引键排序再逐行插入，使叶子节点的分裂集中在相邻的位置
+    /// 插入前先检查所有行的类型以及不允许重复的索引键在批内和索引中都不重复，检查不通过时表不会被修改
+    pub fn insert_batch(&mut self, entries: Vec<Entry>, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        for entry in &entries {
+            self.check_entry(entry)?;
+        }
+
+        if let Some(primary_key) = &self.primary_key {
+            let mut keys = Vec::<String>::new();
+            for entry in &entries {
+                keys.push(primary_key.key_of(entry)?);
+            }
+            Table::check_unique(keys, |key, buffer| primary_key.search_offset(key, buffer), buffer)?;
+        }
+        for (i, field) in self.fields.iter().enumerate() {
+            if self.allows_duplicates(i) {
+                continue;
+            }
+            if let Some(btree) = field.btree() {
+                let keys = entries.iter().map(|entry| entry.data[i].to_key()).collect();
+                Table::check_unique(keys, |key, buffer| Ok(btree.search(key, buffer)?.value), buffer)?;
+            }
+        }
+
+        let mut sorted = Vec::<(String, Entry)>::new();
+        for entry in entries {
+            let key = match &self.primary_key {
+                Some(primary_key) => primary_key.key_of(&entry)?,
+                None => entry.data[0].to_key()
+            };
+            sorted.push((key, entry));
+        }
+        sorted.sort_by(|a, b| a.0.cmp(&b.0));
+        for (_, entry) in sorted {
+            self.insert(entry, buffer)?;
         }
+        Ok(())
+    }
 
-        let primary_key = self.fields.get_mut(0).unwrap();
-        primary_key.insert(0, entry, &mut self.pager, buffer)
+    /// 检查一批键互不相同，且 search 都找不到
+    fn check_unique<F>(mut keys: Vec<String>, search: F, buffer: &mut Box<dyn Buffer>) -> Result<(), Error>
+        where F: Fn(String, &mut Box<dyn Buffer>) -> Result<usize, Error> {
+        keys.sort();
+        for i in 0..keys.len() {
+            if i > 0 && keys[i] == keys[i - 1] {
+                return Err(Error::KeyAlreadyExists)
+            }
+            match search(keys[i].clone(), buffer) {
+                Ok(_) => return Err(Error::KeyAlreadyExists),
+                Err(Error::KeyNotFound) => (),
+                Err(err) => return Err(err)
+            }
+        }
+        Ok(())
     }
 
     pub fn add_fields(&mut self, fields: Vec<Field>) {
@@ -50,79 +314,587 @@ impl Table {
         } else {
             return Err(Error::IndexWithoutBTree)
         };
-        let res = field.search(fv, buffer)?;
-        let res_slice = res.as_slice();
+        let res = field.search(fv, buffer, self.row_size(), &self.pager)?;
+        self.parse_row(res.as_slice(), buffer)
+    }
+
+    /// 通过索引查询键为 fv 的所有行，非唯一索引的一个键可能对应多行，按插入顺序返回
+    pub fn search_all(&self, key_index: usize, fv: FieldValue, buffer: &mut Box<dyn Buffer>) -> Result<Vec<Entry>, Error> {
+        let field = match self.fields.get(key_index) {
+            Some(field) => field,
+            None => return Err(Error::UnexpectedError)
+        };
+        Table::check_field(field, &fv)?;
+        if !field.is_indexed() {
+            return Err(Error::IndexWithoutBTree)
+        }
+        let mut res = Vec::<Entry>::new();
+        for row in field.search_all(fv, buffer, self.row_size(), &self.pager)? {
+            res.push(self.parse_row(row.as_slice(), buffer)?);
+        }
+        Ok(res)
+    }
+
+    /// 与 search 相同，但只返回 columns 中的字段，None 表示返回所有字段
+    /// 一行的字段存放在一起，仍需读出整行再投影
+    pub fn search_projected(&self, key_index: usize, fv: FieldValue, columns: Option<&[usize]>, buffer: &mut Box<dyn Buffer>) -> Result<Entry, Error> {
+        let entry = self.search(key_index, fv, buffer)?;
+        match columns {
+            Some(columns) => entry.project(columns),
+            None => Ok(entry)
+        }
+    }
+
+    /// 更新索引键为 key_value 的行
+    /// 新行编码不超过旧行时原地覆盖，否则另找位置写入并让索引指向新位置
+    /// 主键（组合主键，没有时为第一个字段）不允许修改，返回更新前的行
+    pub fn update(&mut self, key_index: usize, key_value: FieldValue, new_entry: Entry, buffer: &mut Box<dyn Buffer>) -> Result<Entry, Error> {
+        if self.fields.len() != new_entry.data.len() {
+            return Err(Error::UnexpectedError)
+        }
+
+        for (i, item) in self.fields.iter().enumerate() {
+            Table::check_field(item, new_entry.data.get(i).unwrap())?;
+        }
+
+        let old_entry = self.search(key_index, key_value.clone(), buffer)?;
+        let key = match &self.primary_key {
+            Some(primary_key) => {
+                let key = primary_key.key_of(&old_entry)?;
+                if key != primary_key.key_of(&new_entry)? {
+                    return Err(Error::PrimaryKeyImmutable)
+                }
+                Some(key)
+            }
+            None => {
+                if old_entry.data[0].to_key() != new_entry.data[0].to_key() {
+                    return Err(Error::PrimaryKeyImmutable)
+                }
+                None
+            }
+        };
+
+        let old_offset = self.fields[key_index].search_offset(key_value, buffer, &self.pager)?;
+        self.rewrite_row(old_offset, &old_entry, new_entry, key, buffer)?;
+        Ok(old_entry)
+    }
+
+    /// 插入一行，表中已有主键（组合主键，没有时为第一个字段）相同的行时改为更新该行
+    pub fn upsert(&mut self, entry: Entry, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        self.check_entry(&entry)?;
+        let (key, found) = match &self.primary_key {
+            Some(primary_key) => {
+                let key = primary_key.key_of(&entry)?;
+                (Some(key.clone()), primary_key.search_offset(key, buffer))
+            }
+            None => (None, self.fields[0].search_offset(entry.data[0].clone(), buffer, &self.pager))
+        };
+        match found {
+            Ok(offset) => {
+                let old_entry = self.read_row(offset, buffer)?;
+                self.rewrite_row(offset, &old_entry, entry, key, buffer)
+            }
+            Err(Error::KeyNotFound) => self.insert(entry, buffer),
+            Err(err) => Err(err)
+        }
+    }
+
+    /// 用 new_entry 替换 old_offset 处的 old_entry，并维护各个索引
+    /// key 为行的组合主键，调用前已确认新旧两行的主键相同
+    fn rewrite_row(&mut self, old_offset: usize, old_entry: &Entry, new_entry: Entry, key: Option<String>, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        // 唯一索引上改变了的键在写入之前检查，不通过时表和各个索引都不会被修改
+        for ((field, old_value), new_value) in self.fields.iter().zip(old_entry.data.iter()).zip(new_entry.data.iter()) {
+            if old_value.to_key() != new_value.to_key() {
+                field.check_unique(new_value, buffer)?;
+            }
+        }
+
+        let old_row = self.pager.get_value_in_page(old_offset, self.row_size(), buffer)?;
+        let (old_size, old_texts) = self.row_layout(old_row.as_slice());
+        let bytes = self.encode_row(&new_entry, buffer)?;
+        let offset = if bytes.len() <= old_size {
+            self.pager.write_value(old_offset, bytes.as_slice(), buffer)?;
+            old_offset
+        } else {
+            self.pager.insert_value(bytes.as_slice(), buffer)?
+        };
+
+        for i in 0..self.fields.len() {
+            if !self.fields[i].is_indexed() {
+                continue;
+            }
+            let old_value = &old_entry.data[i];
+            let new_value = &new_entry.data[i];
+            if old_value.to_key() != new_value.to_key() {
+                // 索引键发生变化，从旧键中去掉这一行再插入新键
+                self.fields[i].remove_offset(old_value, old_offset, &mut self.pager, buffer)?;
+                self.insert_index_key(i, new_value, offset, buffer)?;
+            } else if offset != old_offset {
+                self.fields[i].replace_offset(new_value, old_offset, offset, &mut self.pager, buffer)?;
+            }
+        }
+
+        // 行被移到了新位置，释放旧位置的空间
+        if offset != old_offset {
+            if let (Some(primary_key), Some(key)) = (self.primary_key.as_mut(), key) {
+                primary_key.update_key(key, offset, buffer)?;
+            }
+            self.pager.delete_value(old_offset, old_size, buffer)?;
+        }
+        // 单独保存的内容总是重新写入，旧内容不再使用
+        for (len, text_offset) in old_texts {
+            self.pager.delete_value(text_offset, len, buffer)?;
+        }
+        Ok(())
+    }
+
+    /// 删除索引键为 key_value 的行
+    /// 行的键会从每个建有索引的字段中删除，行数据所占的空间交还给 pager
+    pub fn delete(&mut self, key_index: usize, key_value: FieldValue, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let entry = self.search(key_index, key_value.clone(), buffer)?;
+        let offset = self.fields[key_index].search_offset(key_value, buffer, &self.pager)?;
+
+        for (i, field) in self.fields.iter_mut().enumerate() {
+            if field.is_indexed() {
+                field.remove_offset(&entry.data[i], offset, &mut self.pager, buffer)?;
+            }
+        }
+        if let Some(primary_key) = self.primary_key.as_mut() {
+            let key = primary_key.key_of(&entry)?;
+            primary_key.delete_key(key, buffer)?;
+        }
+        let row = self.pager.get_value_in_page(offset, self.row_size(), buffer)?;
+        self.free_row(offset, row.as_slice(), buffer)
+    }
+
+    /// 编码后一行最多占用的字节数，每个字段前有一个字节的空值标志
+    fn row_size(&self) -> usize {
+        self.fields.iter().map(|f| NULL_FLAG_SIZE + f.field_type.to_size()).sum()
+    }
+
+    /// 将一行编码成写入表文件的字节，每个字段前有一个字节的空值标志
+    /// TEXT 和 BLOB 字段的内容单独写入表文件，行中只保存长度和偏移
+    fn encode_row(&mut self, entry: &Entry, buffer: &mut Box<dyn Buffer>) -> Result<Vec<u8>, Error> {
+        let mut bytes = Vec::<u8>::new();
+        for item in &entry.data {
+            match item {
+                FieldValue::Null => bytes.push(NULL_FLAG),
+                FieldValue::TEXT(_) | FieldValue::BLOB(_) => {
+                    bytes.push(NOT_NULL_FLAG);
+                    let data: Vec<u8> = item.clone().into();
+                    let text_offset = if data.is_empty() {
+                        0
+                    } else {
+                        self.pager.insert_value(data.as_slice(), buffer)?
+                    };
+                    bytes.extend_from_slice(&(data.len() as u64).to_be_bytes());
+                    bytes.extend_from_slice(&(text_offset as u64).to_be_bytes());
+                }
+                _ => {
+                    bytes.push(NOT_NULL_FLAG);
+                    bytes.extend_from_slice(Vec::<u8>::from(item.clone()).as_slice());
+                }
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// 计算一行编码后的字节数，以及其中单独保存的非空内容的 (长度, 偏移)
+    fn row_layout(&self, row: &[u8]) -> (usize, Vec<(usize, usize)>) {
+        let mut offset = 0;
+        let mut texts = Vec::<(usize, usize)>::new();
+        for item in &self.fields {
+            match row.get(offset) {
+                Some(flag) if *flag == NULL_FLAG => {
+                    offset += NULL_FLAG_SIZE;
+                    continue;
+                }
+                Some(_) => (),
+                None => break
+            }
+            if item.field_type.is_out_of_line() {
+                let (len, text_offset) = Field::text_locator(row, offset + NULL_FLAG_SIZE);
+                if len > 0 {
+                    texts.push((len, text_offset));
+                }
+            }
+            offset += NULL_FLAG_SIZE + item.field_type.to_size();
+        }
+        (offset, texts)
+    }
+
+    /// 释放 offset 处的行以及行中单独保存的内容
+    fn free_row(&mut self, offset: usize, row: &[u8], buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let (size, texts) = self.row_layout(row);
+        self.pager.delete_value(offset, size, buffer)?;
+        for (len, text_offset) in texts {
+            self.pager.delete_value(text_offset, len, buffer)?;
+        }
+        Ok(())
+    }
+
+    /// 依次用各字段解析一行的字节
+    fn parse_row(&self, bytes: &[u8], buffer: &mut Box<dyn Buffer>) -> Result<Entry, Error> {
         let mut offset = 0;
         let mut entry = Entry {
             data: Vec::<FieldValue>::new()
         };
 
         for item in &self.fields {
-            let (fv, siz) = item.parse_self(res_slice, offset)?;
+            let (fv, siz) = item.parse_self(bytes, offset, &self.pager, buffer)?;
             offset += siz;
             entry.data.push(fv);
         }
 
         Ok(entry)
-
     }
 
     pub fn search_range(&mut self, key_index: usize, raw_left_value: Option<FieldValue>, raw_right_value: Option<FieldValue>, buffer: &mut Box<dyn Buffer>) -> Result<Vec<Entry>, Error> {
-        if key_index > self.fields.len() {
+        self.search_range_ex(key_index, to_bound(raw_left_value), to_bound(raw_right_value), buffer)
+    }
+
+    /// 通过索引查询键在 left 和 right 之间的行，每一端可以包括或不包括边界，也可以无界
+    pub fn search_range_ex(&mut self, key_index: usize, left: Bound<FieldValue>, right: Bound<FieldValue>, buffer: &mut Box<dyn Buffer>) -> Result<Vec<Entry>, Error> {
+        if key_index >= self.fields.len() {
             return Err(Error::UnexpectedError)
         }
 
-        match &raw_left_value {
-            Some(left_value) => {
-                Table::check_field(self.fields.get(key_index).unwrap(), left_value)?;
-            }
-            None => ()
-        };
-        match &raw_right_value {
-            Some(right_value) => {
-                Table::check_field(self.fields.get(key_index).unwrap(), right_value)?;
+        for bound in [&left, &right] {
+            match bound {
+                Bound::Included(fv) | Bound::Excluded(fv) => Table::check_field(&self.fields[key_index], fv)?,
+                Bound::Unbounded => ()
             }
-            None => ()
-        };
+        }
 
-        let field = if self.fields.get(key_index).unwrap().is_indexed() {
-            self.fields.get(key_index).unwrap()
+        let field = if self.fields[key_index].is_indexed() {
+            &self.fields[key_index]
         } else {
             return Err(Error::IndexWithoutBTree)
         };
 
-        let mut siz = 0;
-        for f in &self.fields {
-            siz += match f.field_type {
-                FieldType::INT32 => 4,
-                FieldType::FLOAT32 => 4,
-                FieldType::VARCHAR40 => 40,
-            };
-        }
-        let res = field.search_range(raw_left_value, raw_right_value, buffer, siz, &mut self.pager)?;
+        let res = field.search_range_ex(left, right, buffer, self.row_size(), &mut self.pager)?;
         let mut res_vec = Vec::<Entry>::new();
         for row in res {
-            let res_slice = row.as_slice();
-            let mut offset = 0;
-            let mut entry = Entry {
-                data: Vec::<FieldValue>::new()
+            res_vec.push(self.parse_row(row.as_slice(), buffer)?);
+        }
+
+        Ok(res_vec)
+    }
+
+    /// 按定位行的索引的顺序读出表中的所有行
+    pub fn read_all(&self, buffer: &mut Box<dyn Buffer>) -> Result<Vec<Entry>, Error> {
+        let mut res = Vec::<Entry>::new();
+        for offset in self.row_offsets(buffer)? {
+            res.push(self.read_row(offset, buffer)?);
+        }
+        Ok(res)
+    }
+
+    /// 与 right 表做等值连接，返回 left_col 与 right_col 相等的所有行对
+    /// 按 left_col 的索引顺序扫描本表，每行用它的值查询 right 表在 right_col 上的索引
+    /// 两个字段都必须建有索引，否则返回 IndexWithoutBTree；空值不与任何行匹配
+    pub fn join(&self, left_col: usize, right: &Table, right_col: usize, buffer: &mut Box<dyn Buffer>) -> Result<Vec<(Entry, Entry)>, Error> {
+        let (left_field, right_field) = match (self.fields.get(left_col), right.fields.get(right_col)) {
+            (Some(left_field), Some(right_field)) => (left_field, right_field),
+            _ => return Err(Error::FieldNotFound)
+        };
+        if !left_field.is_indexed() || !right_field.is_indexed() {
+            return Err(Error::IndexWithoutBTree)
+        }
+        // 不同类型的值编码出的索引键不同，不可能相等
+        if left_field.field_type.to_code() != right_field.field_type.to_code() {
+            return Err(Error::FieldValueNotCompatible)
+        }
+
+        let mut res = Vec::<(Entry, Entry)>::new();
+        for offset in left_field.offsets(buffer, &self.pager)? {
+            let row = self.read_row(offset, buffer)?;
+            let fv = row.data[left_col].clone();
+            if let FieldValue::Null = fv {
+                continue;
+            }
+            match right_field.search_offsets(fv, buffer, &right.pager) {
+                Ok(right_offsets) => {
+                    for right_offset in right_offsets {
+                        let right_row = right.row_by_offset(right_offset, buffer)?;
+                        res.push((Entry { data: row.data.clone() }, right_row));
+                    }
+                }
+                Err(Error::KeyNotFound) => (),
+                Err(err) => return Err(err)
+            }
+        }
+        Ok(res)
+    }
+
+    /// 全表扫描，返回满足 predicate 的行
+    /// 扫描前检查条件，字段不存在返回 FieldNotFound，常量类型不符返回 FieldValueNotCompatible
+    pub fn scan_filtered(&self, predicate: &Predicate, buffer: &mut Box<dyn Buffer>) -> Result<Vec<Entry>, Error> {
+        predicate.check(&self.fields)?;
+        let mut res = Vec::new();
+        for offset in self.row_offsets(buffer)? {
+            let row = self.read_row(offset, buffer)?;
+            if predicate.matches(&row) {
+                res.push(row);
+            }
+        }
+        Ok(res)
+    }
+
+    /// 对第 col 个字段的非空值做聚合，字段不是数值类型时返回 FieldValueNotCompatible
+    /// 没有非空值时 Sum 返回 0，其余返回 Null
+    /// 字段建有索引时 Min 和 Max 直接读索引两端的键，不扫描整张表
+    pub fn aggregate(&self, col: usize, agg: Agg, buffer: &mut Box<dyn Buffer>) -> Result<FieldValue, Error> {
+        let field = match self.fields.get(col) {
+            Some(field) => field,
+            None => return Err(Error::FieldNotFound)
+        };
+        if !field.field_type.is_numeric() {
+            return Err(Error::FieldValueNotCompatible)
+        }
+        if let (Agg::Min | Agg::Max, Some(btree)) = (agg, field.btree()) {
+            // 空值的键是空串，排在所有键之前
+            let kv = if agg == Agg::Min {
+                btree.iter_range(None, None, buffer)
+                    .find(|kv| !matches!(kv, Ok(kv) if trim_key(&kv.key).is_empty()))
+                    .transpose()?
+            } else {
+                match btree.max_key(buffer) {
+                    Ok(kv) => Some(kv),
+                    Err(Error::KeyNotFound) => None,
+                    Err(err) => return Err(err)
+                }
             };
+            return match kv {
+                Some(kv) if !trim_key(&kv.key).is_empty() => FieldValue::from_key(&field.field_type, trim_key(&kv.key)),
+                _ => Ok(FieldValue::Null)
+            }
+        }
 
-            for item in &self.fields {
-                let (fv, siz) = item.parse_self(res_slice, offset)?;
-                offset += siz;
-                entry.data.push(fv);
+        let mut values = Vec::<FieldValue>::new();
+        for offset in self.row_offsets(buffer)? {
+            let mut entry = self.read_row(offset, buffer)?;
+            match entry.data.swap_remove(col) {
+                FieldValue::Null => (),
+                fv => values.push(fv)
+            }
+        }
+        match agg {
+            Agg::Sum => Table::sum(&field.field_type, &values),
+            Agg::Avg => {
+                if values.is_empty() {
+                    return Ok(FieldValue::Null)
+                }
+                let sum: f64 = values.iter().map(Table::numeric_value).sum();
+                Ok(FieldValue::FLOAT64(sum / values.len() as f64))
             }
-            res_vec.push(entry);
+            // ordered_bytes 的字节顺序与数值大小一致
+            Agg::Min => Ok(values.into_iter().min_by_key(|fv| fv.ordered_bytes()).unwrap_or(FieldValue::Null)),
+            Agg::Max => Ok(values.into_iter().max_by_key(|fv| fv.ordered_bytes()).unwrap_or(FieldValue::Null)),
         }
+    }
 
-        Ok(res_vec)
+    /// 数值字段的和，整数求和溢出 i64 时返回 FieldValueTooLong
+    fn sum(field_type: &FieldType, values: &[FieldValue]) -> Result<FieldValue, Error> {
+        match field_type {
+            FieldType::INT32 | FieldType::INT64 => {
+                let mut sum: i64 = 0;
+                for fv in values {
+                    let value = match fv {
+                        FieldValue::INT32(data) => *data as i64,
+                        FieldValue::INT64(data) => *data,
+                        _ => return Err(Error::FieldValueNotCompatible)
+                    };
+                    sum = match sum.checked_add(value) {
+                        Some(sum) => sum,
+                        None => return Err(Error::FieldValueTooLong)
+                    };
+                }
+                Ok(FieldValue::INT64(sum))
+            }
+            _ => Ok(FieldValue::FLOAT64(values.iter().map(Table::numeric_value).sum()))
+        }
     }
 
-    fn check_field(field: &Field, fv: &FieldValue) -> Result<(), Error> {
+    /// 数值转换成 f64，非数值为 0
+    fn numeric_value(fv: &FieldValue) -> f64 {
+        match fv {
+            FieldValue::INT32(data) => *data as f64,
+            FieldValue::INT64(data) => *data as f64,
+            FieldValue::FLOAT32(data) => *data as f64,
+            FieldValue::FLOAT64(data) => *data,
+            _ => 0.0
+        }
+    }
+
+    /// 按定位行的索引的逆序读出表中的所有行，沿叶子的前向指针读取，不需要在内存中排序
+    pub fn read_all_desc(&self, buffer: &mut Box<dyn Buffer>) -> Result<Vec<Entry>, Error> {
+        let offsets = match (&self.primary_key, self.fields.first()) {
+            (Some(primary_key), _) => primary_key.offsets_rev(buffer)?,
+            (None, Some(field)) if field.is_indexed() => field.offsets_rev(buffer, &self.pager)?,
+            _ => return Err(Error::IndexWithoutBTree)
+        };
+        let mut res = Vec::<Entry>::new();
+        for offset in offsets {
+            res.push(self.read_row(offset, buffer)?);
+        }
+        Ok(res)
+    }
+
+    /// 与 read_all 相同，但每行只返回 columns 中的字段，None 表示返回所有字段
+    pub fn read_all_projected(&self, columns: Option<&[usize]>, buffer: &mut Box<dyn Buffer>) -> Result<Vec<Entry>, Error> {
+        let rows = self.read_all(buffer)?;
+        match columns {
+            Some(columns) => rows.iter().map(|row| row.project(columns)).collect(),
+            None => Ok(rows)
+        }
+    }
+
+    /// 读出并解析 offset 处的一行，offset 通常由索引查询得到，例如 Field::search_offset
+    /// offset 不在表文件已写入的空间中时返回 PageNumOutOfSize
+    pub fn row_by_offset(&self, offset: usize, buffer: &mut Box<dyn Buffer>) -> Result<Entry, Error> {
+        if !self.pager.is_allocated(offset) {
+            return Err(Error::PageNumOutOfSize)
+        }
+        self.read_row(offset, buffer)
+    }
+
+    /// 读出并解析 offset 处的一行
+    fn read_row(&self, offset: usize, buffer: &mut Box<dyn Buffer>) -> Result<Entry, Error> {
+        let row = self.pager.get_value_in_page(offset, self.row_size(), buffer)?;
+        self.parse_row(row.as_slice(), buffer)
+    }
+
+    /// 所有行在表文件中的偏移量，有组合主键时按组合主键排列，否则按第一个字段的索引排列
+    fn row_offsets(&self, buffer: &mut Box<dyn Buffer>) -> Result<Vec<usize>, Error> {
+        match (&self.primary_key, self.fields.first()) {
+            (Some(primary_key), _) => primary_key.offsets(buffer),
+            (None, Some(field)) if field.is_indexed() => field.offsets(buffer, &self.pager),
+            _ => Err(Error::IndexWithoutBTree)
+        }
+    }
+
+    /// 沿定位行的索引的叶子链统计表的行数，不读出和解析行
+    /// 没有定位行的索引时使用表文件中值的个数
+    pub fn count(&self, buffer: &mut Box<dyn Buffer>) -> Result<usize, Error> {
+        match (&self.primary_key, self.fields.first().and_then(|field| field.btree())) {
+            (Some(primary_key), _) => primary_key.count(buffer),
+            (None, Some(btree)) => btree.count(None, None, buffer),
+            _ => Ok(self.pager.value_count())
+        }
+    }
+
+    /// 通过组合主键查询一行，values 按组合主键的字段顺序给出
+    pub fn search_by_key(&self, values: Vec<FieldValue>, buffer: &mut Box<dyn Buffer>) -> Result<Entry, Error> {
+        let primary_key = match &self.primary_key {
+            Some(primary_key) => primary_key,
+            None => return Err(Error::IndexWithoutBTree)
+        };
+        if primary_key.columns.len() != values.len() {
+            return Err(Error::UnexpectedError)
+        }
+        for (column, fv) in primary_key.columns.iter().zip(values.iter()) {
+            Table::check_field(&self.fields[*column], fv)?;
+        }
+
+        let offset = primary_key.search_offset(CompositeKey::encode(values.as_slice())?, buffer)?;
+        self.read_row(offset, buffer)
+    }
+
+    /// 通过索引按键的顺序逐行读取键在 left 和 right 之间的行，None 表示该侧无界
+    /// 与 search_range 不同，行在迭代时才读出和解析，调用者可以随时停止
+    pub fn scan<'a>(&'a self, key_index: usize, left: Option<FieldValue>, right: Option<FieldValue>, buffer: &'a mut Box<dyn Buffer>) -> Result<TableScan<'a>, Error> {
+        let field = match self.fields.get(key_index) {
+            Some(field) => field,
+            None => return Err(Error::UnexpectedError)
+        };
+        for fv in left.iter().chain(right.iter()) {
+            Table::check_field(field, fv)?;
+        }
+        let btree = match field.btree() {
+            Some(btree) => btree,
+            None => return Err(Error::IndexWithoutBTree)
+        };
+
+        let cursor = btree.cursor(left.map(|fv| fv.to_key()), right.map(|fv| fv.to_key()));
+        Ok(TableScan {
+            table: self,
+            btree,
+            buffer,
+            cursor,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// 与 scan 相同，但先跳过 offset 个符合条件的行，再最多返回 limit 行
+    /// 跳过的行只读索引不读行，取够 limit 行后就停止
+    pub fn scan_limited(&self, key_index: usize, left: Option<FieldValue>, right: Option<FieldValue>, limit: usize, offset: usize, buffer: &mut Box<dyn Buffer>) -> Result<Vec<Entry>, Error> {
+        let mut scan = self.scan(key_index, left, right, buffer)?;
+        scan.skip_rows(offset)?;
+        scan.take(limit).collect()
+    }
+
+    /// 统计表的行数、索引数、平均行大小和数据文件占用的页数
+    /// 行数来自索引或 pager 的计数，平均行大小由数据文件已用的字节数算出，不读出任何一行
+    pub fn statistics(&mut self, buffer: &mut Box<dyn Buffer>) -> Result<TableStatistics, Error> {
+        let index_count = self.fields.iter().filter(|f| f.is_indexed()).count();
+        let row_count = self.count(buffer)?;
+        let used_bytes = self.pager.used_size();
+        let avg_row_size = if row_count == 0 {
+            0.0
+        } else {
+            used_bytes as f64 / row_count as f64
+        };
+
+        Ok(TableStatistics {
+            row_count,
+            index_count,
+            avg_row_size,
+            page_count: self.pager.cnt,
+            used_bytes,
+        })
+    }
+
+    /// 建有索引的字段下标
+    pub fn indexes(&self) -> Vec<usize> {
+        self.fields
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| f.is_indexed())
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// 按照查询计划选择的访问路径读取候选行
+    pub fn execute_plan(&mut self, path: AccessPath, buffer: &mut Box<dyn Buffer>) -> Result<Vec<Entry>, Error> {
+        match path {
+            AccessPath::IndexPoint { key_index, value } => {
+                match self.search_all(key_index, value, buffer) {
+                    Ok(entries) => Ok(entries),
+                    Err(Error::KeyNotFound) => Ok(Vec::<Entry>::new()),
+                    Err(err) => Err(err)
+                }
+            }
+            AccessPath::IndexRange { key_index, left, right } => self.search_range(key_index, left, right, buffer),
+            AccessPath::FullScan => self.read_all(buffer),
+        }
+    }
+
+    pub(crate) fn check_field(field: &Field, fv: &FieldValue) -> Result<(), Error> {
         match (&field.field_type, fv) {
+            (_, FieldValue::Null) => {
+                if field.nullable {
+                    return Ok(())
+                }
+                Err(Error::FieldValueNotCompatible)
+            },
             (FieldType::INT32, FieldValue::INT32(_)) => Ok(()),
+            (FieldType::INT64, FieldValue::INT64(_)) => Ok(()),
             (FieldType::FLOAT32, FieldValue::FLOAT32(_)) => Ok(()),
+            (FieldType::FLOAT64, FieldValue::FLOAT64(_)) => Ok(()),
+            (FieldType::TEXT, FieldValue::TEXT(_)) => Ok(()),
+            (FieldType::BLOB, FieldValue::BLOB(_)) => Ok(()),
+            (FieldType::BOOL, FieldValue::BOOL(_)) => Ok(()),
             (FieldType::VARCHAR40, FieldValue::VARCHAR40(data)) => {
                 if data.as_bytes().len() > 40 {
                     return Err(Error::VarcharTooLong)
@@ -135,14 +907,55 @@ impl Table {
         }
     }
 
+    /// 按表结构构造一行，每次设置字段时检查下标和类型
+    pub fn new_entry(&self) -> EntryBuilder<'_> {
+        EntryBuilder::new(self.fields.as_slice())
+    }
+
+    /// 以字段类型的默认键宽创建索引，VARCHAR40 的索引使用完整的 40 字节作为键
     pub fn create_index(&mut self, key_index: usize, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        let key_size = match self.fields.get(key_index) {
+            Some(field) => field.field_type.key_size(),
+            None => return Err(Error::UnexpectedError)
+        };
+        self.create_index_with_key_size(key_index, key_size, buffer)
+    }
+
+    /// 以指定的键宽创建索引，键宽过大时返回 KeySizeOutOfPage
+    pub fn create_index_with_key_size(&mut self, key_index: usize, key_size: usize, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
         if self.fields.len() <= key_index {
             return Err(Error::UnexpectedError)
         }
 
-        let k = self.fields.get_mut(key_index).unwrap();
-        let file_name = k.field_name.clone() + ".idx";
-        k.create_btree(file_name, buffer)
+        let file_name = self.index_file_name(&self.fields[key_index].field_name);
+        self.fields[key_index].create_btree(file_name, key_size, buffer)
+    }
+
+    /// 以字段类型的默认键宽创建唯一索引，之后插入的行在该字段上不能与已有的行重复
+    pub fn create_unique_index(&mut self, key_index: usize, buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        self.create_index(key_index, buffer)?;
+        self.fields[key_index].unique = true;
+        Ok(())
+    }
+
+    /// 以 columns 中的字段按顺序组成组合主键，一个表只能有一个组合主键
+    pub fn create_primary_key(&mut self, columns: &[usize], buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        if self.primary_key.is_some() {
+            return Err(Error::IndexExist)
+        }
+        let file_name = self.key_file_name();
+        self.primary_key = Some(CompositeKey::create(file_name, columns.to_vec(), self.fields.as_slice(), buffer)?);
+        Ok(())
+    }
+
+    /// 组合主键索引的文件名
+    fn key_file_name(&self) -> String {
+        self.table_name.clone() + ".key.idx"
+    }
+
+    /// 字段索引的文件名，带上表名以免不同表的同名字段共用一个文件
+    fn index_file_name(&self, field_name: &str) -> String {
+        self.table_name.clone() + "_" + field_name + ".idx"
     }
 }
 
@@ -155,7 +968,62 @@ impl Clone for Table {
         Table {
             table_name: self.table_name.clone(),
             fields,
-            pager: self.pager.clone()
+            primary_key: self.primary_key.clone(),
+            pager: self.pager.clone(),
+            snapshot: None,
+        }
+    }
+}
+
+/// Table::scan 返回的迭代器，每次读出并解析一行
+pub struct TableScan<'a> {
+    table: &'a Table,
+    btree: &'a BTree,
+    buffer: &'a mut Box<dyn Buffer>,
+    cursor: LeafCursor,
+    /// 当前键还没有读出的行的偏移量，键对应多行时一次取出整个偏移量列表
+    pending: VecDeque<usize>,
+}
+
+impl<'a> TableScan<'a> {
+    /// 跳过接下来的 n 行，只移动索引上的游标，不读出行
+    pub fn skip_rows(&mut self, n: usize) -> Result<(), Error> {
+        for _ in 0..n {
+            match self.next_offset() {
+                Some(Ok(_)) => {}
+                Some(Err(err)) => return Err(err),
+                None => break
+            }
+        }
+        Ok(())
+    }
+
+    /// 下一行的偏移量，当前键的行都取完后才移动游标
+    fn next_offset(&mut self) -> Option<Result<usize, Error>> {
+        if let Some(offset) = self.pending.pop_front() {
+            return Some(Ok(offset));
+        }
+        let kv = match self.cursor.next(self.btree, self.buffer)? {
+            Ok(kv) => kv,
+            Err(err) => return Some(Err(err))
+        };
+        match Field::value_offsets(kv.value, &self.table.pager, self.buffer) {
+            Ok(offsets) => {
+                self.pending = offsets.into();
+                self.pending.pop_front().map(Ok)
+            }
+            Err(err) => Some(Err(err))
+        }
+    }
+}
+
+impl<'a> Iterator for TableScan<'a> {
+    type Item = Result<Entry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_offset()? {
+            Ok(offset) => Some(self.table.read_row(offset, self.buffer)),
+            Err(err) => Some(Err(err))
         }
     }
 }
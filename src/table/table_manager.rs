@@ -1,27 +1,230 @@
 use std::collections::HashMap;
-use crate::table::table_item::Table;
+use crate::table::table_item::{Agg, Table, TableScan, TableStatistics};
 use crate::util::error::Error;
 use crate::data_item::buffer::Buffer;
 use crate::table::entry::Entry;
-use crate::table::field::{Field};
+use crate::table::field::{Field, FieldType, FieldValue};
+use crate::page::pager::Pager;
+use std::path::Path;
+use std::ops::Bound;
+use crate::table::planner::{plan, Condition};
+use crate::table::predicate::Predicate;
+use crate::util::csv;
+
+/// 表结构目录文件的后缀，目录文件名为缓冲区元数据文件名加上该后缀
+pub const CATALOG_SUFFIX: &str = ".catalog";
+
+/// 目录开头记录目录编码后的长度
+const CATALOG_LEN_SIZE: usize = 4;
 
 pub struct TableManager {
     pub(crate) table_cache: HashMap<String, Table>,
-    buffer: Box<dyn Buffer>
+    buffer: Box<dyn Buffer>,
+    catalog: Option<Box<Pager>>,
 }
 
 impl TableManager {
     pub fn new(buffer: Box<dyn Buffer>) -> TableManager {
         TableManager {
             table_cache: HashMap::<String, Table>::new(),
-            buffer
+            buffer,
+            catalog: None,
+        }
+    }
+
+    /// 从表结构目录中恢复所有的表，并重新打开它们的索引
+    pub fn open(mut buffer: Box<dyn Buffer>) -> Result<TableManager, Error> {
+        let mut catalog = TableManager::open_catalog(&mut buffer)?;
+        catalog.mark_used(0, CATALOG_LEN_SIZE, &mut buffer)?;
+        let len_bytes = catalog.get_value(0, CATALOG_LEN_SIZE, &mut buffer)?;
+        let len = u32::from_be_bytes([len_bytes[0], len_bytes[1], len_bytes[2], len_bytes[3]]) as usize;
+        catalog.mark_used(0, CATALOG_LEN_SIZE + len, &mut buffer)?;
+        let bytes = catalog.get_value(CATALOG_LEN_SIZE, len, &mut buffer)?;
+
+        let mut table_cache = HashMap::<String, Table>::new();
+        let mut offset = 0;
+        let table_num = read_u32(&bytes, &mut offset)?;
+        for _ in 0..table_num {
+            let table_name = read_string(&bytes, &mut offset)?;
+            let field_num = read_u32(&bytes, &mut offset)?;
+            let mut fields = Vec::<Field>::new();
+            let mut indexed = Vec::<usize>::new();
+            for i in 0..field_num as usize {
+                let field_name = read_string(&bytes, &mut offset)?;
+                let field_type = FieldType::from_code(read_u8(&bytes, &mut offset)?)?;
+                let nullable = read_u8(&bytes, &mut offset)? != 0;
+                // 0 表示没有索引，1 表示普通索引，2 表示唯一索引
+                let index_flag = read_u8(&bytes, &mut offset)?;
+                if index_flag != 0 {
+                    indexed.push(i);
+                }
+                let mut field = if nullable {
+                    Field::create_nullable_field(field_name, field_type)?
+                } else {
+                    Field::create_field(field_name, field_type)?
+                };
+                field.unique = index_flag == 2;
+                fields.push(field);
+            }
+            let key_num = read_u32(&bytes, &mut offset)?;
+            let mut key_columns = Vec::<usize>::new();
+            for _ in 0..key_num {
+                key_columns.push(read_u32(&bytes, &mut offset)? as usize);
+            }
+            let table = Table::open(table_name, fields, &indexed, &key_columns, &mut buffer)?;
+            table_cache.insert(table.table_name.clone(), table);
+        }
+
+        Ok(TableManager {
+            table_cache,
+            buffer,
+            catalog: Some(catalog),
+        })
+    }
+
+    /// 将缓冲区中的所有页写回磁盘
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.buffer.flush_all()
+    }
+
+    fn open_catalog(buffer: &mut Box<dyn Buffer>) -> Result<Box<Pager>, Error> {
+        let file_name = buffer.get_meta_file_name() + CATALOG_SUFFIX;
+        buffer.add_file(Path::new(file_name.as_str()))?;
+        Pager::new(file_name, 40, buffer)
+    }
+
+    /// 将所有表的表名、字段名、字段类型、索引情况和组合主键写入表结构目录
+    fn write_catalog(&mut self) -> Result<(), Error> {
+        let mut bytes = Vec::<u8>::new();
+        bytes.extend_from_slice(&(self.table_cache.len() as u32).to_be_bytes());
+        for table in self.table_cache.values() {
+            write_string(&mut bytes, &table.table_name);
+            bytes.extend_from_slice(&(table.fields.len() as u32).to_be_bytes());
+            for field in &table.fields {
+                write_string(&mut bytes, &field.field_name);
+                bytes.push(field.field_type.to_code());
+                bytes.push(field.nullable as u8);
+                bytes.push(field.is_indexed() as u8 + (field.is_indexed() && field.unique) as u8);
+            }
+            let key_columns = match &table.primary_key {
+                Some(primary_key) => primary_key.columns.clone(),
+                None => Vec::<usize>::new()
+            };
+            bytes.extend_from_slice(&(key_columns.len() as u32).to_be_bytes());
+            for column in key_columns {
+                bytes.extend_from_slice(&(column as u32).to_be_bytes());
+            }
+        }
+
+        if self.catalog.is_none() {
+            self.catalog = Some(TableManager::open_catalog(&mut self.buffer)?);
+        }
+        let mut raw = (bytes.len() as u32).to_be_bytes().to_vec();
+        raw.extend_from_slice(bytes.as_slice());
+        if let Some(catalog) = self.catalog.as_mut() {
+            catalog.mark_used(0, raw.len(), &mut self.buffer)?;
+            catalog.write_value(0, raw.as_slice(), &mut self.buffer)?;
+        }
+        // 表结构的修改立即落盘
+        let file_name = self.buffer.get_meta_file_name() + CATALOG_SUFFIX;
+        self.buffer.flush_file(file_name.as_str())
+    }
+
+    /// 所有表的表名，按字典序排列
+    pub fn list_tables(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.table_cache.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// 表中每个字段的字段名和类型，按字段顺序排列
+    pub fn describe_table(&self, table_name: String) -> Result<Vec<(String, FieldType)>, Error> {
+        match self.table_cache.get(table_name.as_str()) {
+            Some(table) => Ok(table.fields.iter().map(|f| (f.field_name.clone(), f.field_type.clone())).collect()),
+            None => Err(Error::TableNotFound)
         }
     }
 
     pub fn read_full_table(&mut self, table_name: String) -> Result<Vec<Entry>, Error> {
         let raw_table = self.table_cache.get_mut(table_name.as_str());
         match raw_table {
-            Some(table) => Ok(table.search_range(0, None, None, &mut self.buffer)?),
+            Some(table) => table.read_all(&mut self.buffer),
+            None => Err(Error::TableNotFound)
+        }
+    }
+
+    /// 按主键从大到小读出整张表
+    pub fn read_full_table_desc(&mut self, table_name: String) -> Result<Vec<Entry>, Error> {
+        match self.table_cache.get(table_name.as_str()) {
+            Some(table) => table.read_all_desc(&mut self.buffer),
+            None => Err(Error::TableNotFound)
+        }
+    }
+
+    /// 读出整张表，每行只保留 columns 中的字段，None 表示保留所有字段
+    pub fn read_full_table_projected(&mut self, table_name: String, columns: Option<&[usize]>) -> Result<Vec<Entry>, Error> {
+        match self.table_cache.get(table_name.as_str()) {
+            Some(table) => table.read_all_projected(columns, &mut self.buffer),
+            None => Err(Error::TableNotFound)
+        }
+    }
+
+    /// 表的行数，比读出整张表再计数代价小得多
+    pub fn count(&mut self, table_name: String) -> Result<usize, Error> {
+        match self.table_cache.get(table_name.as_str()) {
+            Some(table) => table.count(&mut self.buffer),
+            None => Err(Error::TableNotFound)
+        }
+    }
+
+    /// 两张表按字段值相等做连接，返回匹配的 (左表行, 右表行)
+    /// 扫描左表，用每行的值查询右表在 right_col 上的索引，两个字段都必须建有索引
+    pub fn join(&mut self, left_table: String, left_col: usize, right_table: String, right_col: usize) -> Result<Vec<(Entry, Entry)>, Error> {
+        let left = match self.table_cache.get(left_table.as_str()) {
+            Some(table) => table,
+            None => return Err(Error::TableNotFound)
+        };
+        let right = match self.table_cache.get(right_table.as_str()) {
+            Some(table) => table,
+            None => return Err(Error::TableNotFound)
+        };
+        left.join(left_col, right, right_col, &mut self.buffer)
+    }
+
+    /// 按条件过滤表中的行，见 Table::scan_filtered
+    pub fn scan_filtered(&mut self, table_name: String, predicate: &Predicate) -> Result<Vec<Entry>, Error> {
+        match self.table_cache.get(table_name.as_str()) {
+            Some(table) => table.scan_filtered(predicate, &mut self.buffer),
+            None => Err(Error::TableNotFound)
+        }
+    }
+
+    /// 对表中第 col 个数值字段求和、平均、最小或最大值
+    pub fn aggregate(&mut self, table_name: String, col: usize, agg: Agg) -> Result<FieldValue, Error> {
+        match self.table_cache.get(table_name.as_str()) {
+            Some(table) => table.aggregate(col, agg, &mut self.buffer),
+            None => Err(Error::TableNotFound)
+        }
+    }
+
+    pub fn statistics(&mut self, table_name: String) -> Result<TableStatistics, Error> {
+        let raw_table = self.table_cache.get_mut(table_name.as_str());
+        match raw_table {
+            Some(table) => table.statistics(&mut self.buffer),
+            None => Err(Error::TableNotFound)
+        }
+    }
+
+    /// 根据条件和表的统计信息选择访问路径，并读出候选行
+    /// 没有用上索引的条件由调用者再过滤
+    pub fn execute_plan(&mut self, table_name: String, conditions: &[Condition]) -> Result<Vec<Entry>, Error> {
+        let raw_table = self.table_cache.get_mut(table_name.as_str());
+        match raw_table {
+            Some(table) => {
+                let stats = table.statistics(&mut self.buffer)?;
+                let path = plan(conditions, &table.indexes(), &stats);
+                table.execute_plan(path, &mut self.buffer)
+            }
             None => Err(Error::TableNotFound)
         }
     }
@@ -36,6 +239,180 @@ impl TableManager {
         }
     }
 
+    /// 插入一行，主键相同的行已存在时改为更新该行
+    pub fn upsert(&mut self, table_name: String, entry: Entry) -> Result<(), Error> {
+        match self.table_cache.get_mut(table_name.as_str()) {
+            Some(table) => table.upsert(entry, &mut self.buffer),
+            None => Err(Error::TableNotFound)
+        }
+    }
+
+    /// 在表上开始一个事务，之后的修改可以通过 rollback 撤销
+    pub fn begin(&mut self, table_name: String) -> Result<(), Error> {
+        match self.table_cache.get_mut(table_name.as_str()) {
+            Some(table) => table.begin(&mut self.buffer),
+            None => Err(Error::TableNotFound)
+        }
+    }
+
+    pub fn commit(&mut self, table_name: String) -> Result<(), Error> {
+        match self.table_cache.get_mut(table_name.as_str()) {
+            Some(table) => table.commit(&mut self.buffer),
+            None => Err(Error::TableNotFound)
+        }
+    }
+
+    pub fn rollback(&mut self, table_name: String) -> Result<(), Error> {
+        match self.table_cache.get_mut(table_name.as_str()) {
+            Some(table) => table.rollback(&mut self.buffer),
+            None => Err(Error::TableNotFound)
+        }
+    }
+
+    /// 从 CSV 文件导入行，每个单元格按字段类型解析，不带引号的空单元格为空值
+    /// has_header 为真时跳过第一行，返回导入的行数
+    /// 列数不符或单元格无法解析时返回出错的记录序号，之前的行已经插入
+    pub fn import_csv(&mut self, table_name: String, path: &Path, has_header: bool) -> Result<usize, Error> {
+        let columns = self.describe_table(table_name.clone())?;
+        let text = std::fs::read_to_string(path)?;
+        let records = csv::parse_records(text.as_str())?;
+        let skip = if has_header { 1 } else { 0 };
+
+        let mut count = 0;
+        for (i, record) in records.iter().enumerate().skip(skip) {
+            if record.len() != columns.len() {
+                return Err(Error::CsvFormat(i + 1))
+            }
+            let mut data = Vec::<FieldValue>::new();
+            for (cell, (_, field_type)) in record.iter().zip(columns.iter()) {
+                data.push(match cell {
+                    Some(text) => match FieldValue::parse(field_type, text) {
+                        Ok(fv) => fv,
+                        Err(_) => return Err(Error::CsvFormat(i + 1))
+                    },
+                    None => FieldValue::Null
+                });
+            }
+            self.insert(table_name.clone(), Entry { data })?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// 将表中所有行按键的顺序导出为 CSV 文件，返回导出的行数
+    /// 空值写成不带引号的空单元格，BLOB 写成十六进制，与 import_csv 的格式一致
+    pub fn export_csv(&mut self, table_name: String, path: &Path) -> Result<usize, Error> {
+        let rows = self.read_full_table(table_name)?;
+        let mut text = String::new();
+        for row in &rows {
+            let record: Vec<Option<String>> = row.data.iter().map(|fv| match fv {
+                FieldValue::Null => None,
+                fv => Some(fv.to_string())
+            }).collect();
+            text.push_str(csv::write_record(record.as_slice()).as_str());
+        }
+        std::fs::write(path, text)?;
+        Ok(rows.len())
+    }
+
+    /// 批量插入行，任何一行不合法或键重复时不插入任何行
+    pub fn insert_batch(&mut self, table_name: String, entries: Vec<Entry>) -> Result<(), Error> {
+        let raw_table = self.table_cache.get_mut(&table_name);
+        match raw_table {
+            Some(table) => table.insert_batch(entries, &mut self.buffer),
+            None => Err(Error::TableNotFound)
+        }
+    }
+
+    pub fn search(&mut self, table_name: String, key_index: usize, key_value: FieldValue) -> Result<Entry, Error> {
+        let raw_table = self.table_cache.get(&table_name);
+        match raw_table {
+            Some(table) => table.search(key_index, key_value, &mut self.buffer),
+            None => Err(Error::TableNotFound)
+        }
+    }
+
+    /// 通过索引查询一行，只返回 columns 中的字段，None 表示返回所有字段
+    pub fn search_projected(&mut self, table_name: String, key_index: usize, key_value: FieldValue, columns: Option<&[usize]>) -> Result<Entry, Error> {
+        match self.table_cache.get(&table_name) {
+            Some(table) => table.search_projected(key_index, key_value, columns, &mut self.buffer),
+            None => Err(Error::TableNotFound)
+        }
+    }
+
+    /// 通过索引查询键为 key_value 的所有行，非唯一索引的一个键可能对应多行
+    pub fn search_all(&mut self, table_name: String, key_index: usize, key_value: FieldValue) -> Result<Vec<Entry>, Error> {
+        match self.table_cache.get(&table_name) {
+            Some(table) => table.search_all(key_index, key_value, &mut self.buffer),
+            None => Err(Error::TableNotFound)
+        }
+    }
+
+    /// 通过组合主键查询一行，values 按组合主键的字段顺序给出
+    pub fn search_by_key(&mut self, table_name: String, values: Vec<FieldValue>) -> Result<Entry, Error> {
+        let raw_table = self.table_cache.get(&table_name);
+        match raw_table {
+            Some(table) => table.search_by_key(values, &mut self.buffer),
+            None => Err(Error::TableNotFound)
+        }
+    }
+
+    /// 通过索引查询键在 left 和 right 之间的行，None 表示该侧无界
+    pub fn search_range(&mut self, table_name: String, key_index: usize, left: Option<FieldValue>, right: Option<FieldValue>) -> Result<Vec<Entry>, Error> {
+        let raw_table = self.table_cache.get_mut(&table_name);
+        match raw_table {
+            Some(table) => table.search_range(key_index, left, right, &mut self.buffer),
+            None => Err(Error::TableNotFound)
+        }
+    }
+
+    /// 通过索引查询键在 left 和 right 之间的行，每一端可以包括或不包括边界，也可以无界
+    pub fn search_range_ex(&mut self, table_name: String, key_index: usize, left: Bound<FieldValue>, right: Bound<FieldValue>) -> Result<Vec<Entry>, Error> {
+        let raw_table = self.table_cache.get_mut(&table_name);
+        match raw_table {
+            Some(table) => table.search_range_ex(key_index, left, right, &mut self.buffer),
+            None => Err(Error::TableNotFound)
+        }
+    }
+
+    /// 通过索引逐行读取键在 left 和 right 之间的行，行在迭代时才读出
+    pub fn scan(&mut self, table_name: String, key_index: usize, left: Option<FieldValue>, right: Option<FieldValue>) -> Result<TableScan<'_>, Error> {
+        let raw_table = self.table_cache.get(&table_name);
+        match raw_table {
+            Some(table) => table.scan(key_index, left, right, &mut self.buffer),
+            None => Err(Error::TableNotFound)
+        }
+    }
+
+    /// 通过索引读取键在 left 和 right 之间的行，跳过前 offset 行后最多返回 limit 行
+    pub fn scan_limited(&mut self, table_name: String, key_index: usize, left: Option<FieldValue>, right: Option<FieldValue>, limit: usize, offset: usize) -> Result<Vec<Entry>, Error> {
+        match self.table_cache.get(&table_name) {
+            Some(table) => table.scan_limited(key_index, left, right, limit, offset, &mut self.buffer),
+            None => Err(Error::TableNotFound)
+        }
+    }
+
+    /// 更新索引键为 key_value 的行，返回更新前的行
+    pub fn update(&mut self, table_name: String, key_index: usize, key_value: FieldValue, new_entry: Entry) -> Result<Entry, Error> {
+        let raw_table = self.table_cache.get_mut(&table_name);
+        match raw_table {
+            Some(table) => {
+                table.update(key_index, key_value, new_entry, &mut self.buffer)
+            }
+            None => Err(Error::TableNotFound)
+        }
+    }
+
+    pub fn delete(&mut self, table_name: String, key_index: usize, key_value: FieldValue) -> Result<(), Error> {
+        let raw_table = self.table_cache.get_mut(&table_name);
+        match raw_table {
+            Some(table) => {
+                table.delete(key_index, key_value, &mut self.buffer)
+            }
+            None => Err(Error::TableNotFound)
+        }
+    }
+
     pub fn create_table(&mut self, table_name: String, fields: Vec<Field>) -> Result<(), Error> {
         let raw_table = self.table_cache.get(table_name.as_str());
         if raw_table.is_some() {
@@ -45,7 +422,46 @@ impl TableManager {
         let mut table = Table::new(table_name, &mut self.buffer)?;
         table.add_fields(fields);
         self.table_cache.insert(table.table_name.clone(), table);
-        Ok(())
+        self.write_catalog()
+    }
+
+    /// 重命名表，同时改名表文件并更新表结构目录，新表名已被占用时返回 TableAlreadyExists
+    pub fn rename_table(&mut self, old_name: String, new_name: String) -> Result<(), Error> {
+        if !self.table_cache.contains_key(old_name.as_str()) {
+            return Err(Error::TableNotFound)
+        }
+        if self.table_cache.contains_key(new_name.as_str()) {
+            return Err(Error::TableAlreadyExists)
+        }
+
+        if let Some(table) = self.table_cache.get_mut(old_name.as_str()) {
+            table.rename(new_name.clone(), &mut self.buffer)?;
+        }
+        if let Some(table) = self.table_cache.remove(old_name.as_str()) {
+            self.table_cache.insert(new_name, table);
+        }
+        self.write_catalog()
+    }
+
+    /// 创建表并以 key_columns 中的字段按顺序组成主键
+    pub fn create_table_with_key(&mut self, table_name: String, fields: Vec<Field>, key_columns: &[usize]) -> Result<(), Error> {
+        self.create_table(table_name.clone(), fields)?;
+        self.create_index_on(table_name, key_columns)
+    }
+
+    /// 在 columns 中的字段上创建索引
+    /// 只有一个字段时与 create_index 相同，多个字段时按顺序组成组合主键
+    pub fn create_index_on(&mut self, table_name: String, columns: &[usize]) -> Result<(), Error> {
+        if columns.len() == 1 {
+            return self.create_index(table_name, columns[0])
+        }
+        let raw_table = self.table_cache.get_mut(table_name.as_str());
+        let table = match raw_table {
+            Some(table) => table,
+            None => return Err(Error::TableNotFound)
+        };
+        table.create_primary_key(columns, &mut self.buffer)?;
+        self.write_catalog()
     }
 
     pub fn create_index(&mut self, table_name: String, key_index: usize) -> Result<(), Error> {
@@ -54,6 +470,66 @@ impl TableManager {
             Some(table) => table,
             None => return Err(Error::TableNotFound)
         };
-        table.create_index(key_index, &mut self.buffer)
+        table.create_index(key_index, &mut self.buffer)?;
+        self.write_catalog()
+    }
+
+    /// 在 key_index 字段上创建唯一索引
+    pub fn create_unique_index(&mut self, table_name: String, key_index: usize) -> Result<(), Error> {
+        let raw_table = self.table_cache.get_mut(table_name.as_str());
+        let table = match raw_table {
+            Some(table) => table,
+            None => return Err(Error::TableNotFound)
+        };
+        table.create_unique_index(key_index, &mut self.buffer)?;
+        self.write_catalog()
+    }
+
+    pub fn create_index_with_key_size(&mut self, table_name: String, key_index: usize, key_size: usize) -> Result<(), Error> {
+        let raw_table = self.table_cache.get_mut(table_name.as_str());
+        let table = match raw_table {
+            Some(table) => table,
+            None => return Err(Error::TableNotFound)
+        };
+        table.create_index_with_key_size(key_index, key_size, &mut self.buffer)?;
+        self.write_catalog()
+    }
+}
+
+fn write_string(bytes: &mut Vec<u8>, data: &str) {
+    bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(data.as_bytes());
+}
+
+fn read_u8(bytes: &[u8], offset: &mut usize) -> Result<u8, Error> {
+    match bytes.get(*offset) {
+        Some(data) => {
+            *offset += 1;
+            Ok(*data)
+        }
+        None => Err(Error::UnexpectedError)
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: &mut usize) -> Result<u32, Error> {
+    if *offset + 4 > bytes.len() {
+        return Err(Error::UnexpectedError)
+    }
+    let mut data = [0u8; 4];
+    data.clone_from_slice(&bytes[*offset..*offset + 4]);
+    *offset += 4;
+    Ok(u32::from_be_bytes(data))
+}
+
+fn read_string(bytes: &[u8], offset: &mut usize) -> Result<String, Error> {
+    let len = read_u32(bytes, offset)? as usize;
+    if *offset + len > bytes.len() {
+        return Err(Error::UnexpectedError)
     }
+    let res = match std::str::from_utf8(&bytes[*offset..*offset + len]) {
+        Ok(data) => data.to_string(),
+        Err(_) => return Err(Error::UTF8Error)
+    };
+    *offset += len;
+    Ok(res)
 }
@@ -4,4 +4,9 @@ pub mod test_node;
 pub mod test_page_item;
 pub mod test_pager;
 pub mod test_field;
-pub mod test_table_manager;
\ No newline at end of file
+pub mod test_table_manager;
+pub mod test_planner;
+pub mod test_booter;
+pub mod test_error;
+pub mod test_executor;
+pub mod test_entry;
@@ -0,0 +1,40 @@
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use uuid::Uuid;
+    use crate::util::error::Error;
+    use crate::util::test_lib::rm_files;
+    use crate::table::booter::Booter;
+
+    #[test]
+    fn test_update_and_load() -> Result<(), Error> {
+        let files = ["update_and_load.boot"];
+        rm_files(&files);
+
+        let mut booter = Booter::create(Path::new("update_and_load.boot"))?;
+        assert_eq!(booter.load()?, Uuid::nil());
+
+        let uuid = Uuid::new_v4();
+        booter.update(uuid)?;
+        assert_eq!(booter.load()?, uuid);
+
+        // 重新打开后读到的仍是最后写入的uuid
+        drop(booter);
+        let mut booter = Booter::open(Path::new("update_and_load.boot"))?;
+        assert_eq!(booter.load()?, uuid);
+        let mut booter = Booter::create(Path::new("update_and_load.boot"))?;
+        assert_eq!(booter.load()?, uuid);
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_missing() -> Result<(), Error> {
+        match Booter::open(Path::new("missing.boot")) {
+            Err(Error::FileNotFound) => (),
+            _ => panic!("expected FileNotFound")
+        }
+        Ok(())
+    }
+}
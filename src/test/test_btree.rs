@@ -2,8 +2,17 @@
 #[cfg(test)]
 mod test_btree {
     use crate::util::error::Error;
-    use crate::util::test_lib::{rm_test_file, gen_tree, gen_kv, gen_2_kv, gen_buffer};
+    use crate::util::test_lib::{rm_test_file, rm_files, gen_tree, gen_kv, gen_2_kv, gen_buffer, gen_pager};
     use crate::index::key_value_pair::KeyValuePair;
+    use crate::index::btree::{BTree, TREE_META_PAGE_NUM};
+    use crate::index::node::{Node, NodeType};
+    use crate::page::pager::Pager;
+    use crate::page::page_item::PTR_SIZE;
+    use crate::data_item::buffer::{Buffer, ClockBuffer, FIFOBuffer, LRUBuffer};
+    use std::ops::Bound;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
 
     #[test]
     fn test_search_empty_tree() -> Result<(), Error> {
@@ -64,12 +73,700 @@ mod test_btree {
         assert_eq!(tree.search(kv1.key.clone(), &mut buffer)?.value, kv1.value);
 
         let kv3 = KeyValuePair::new(kv1.key.clone(), kv2.value.clone());
-        tree.update(kv3, &mut buffer)?;
+        // 返回更新前的键值对
+        let old = tree.update(kv3, &mut buffer)?;
+        assert!(old == kv1);
 
         assert_ne!(tree.search(kv1.key.clone(), &mut buffer)?.value, kv1.value);
         assert_eq!(tree.search(kv1.key.clone(), &mut buffer)?.value, kv2.value);
 
+        // 不存在的键不会被插入
+        match tree.update(KeyValuePair::new(kv2.key.clone(), kv2.value), &mut buffer) {
+            Err(Error::KeyNotFound) => (),
+            _ => panic!("expected KeyNotFound")
+        }
+        match tree.search(kv2.key.clone(), &mut buffer) {
+            Err(Error::KeyNotFound) => (),
+            _ => panic!("expected KeyNotFound")
+        }
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_upsert() -> Result<(), Error> {
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let mut tree = gen_tree(&mut buffer)?;
+
+        // 键不存在时插入
+        tree.upsert(KeyValuePair::new("Hello".to_string(), 1), &mut buffer)?;
+        assert_eq!(tree.search("Hello".to_string(), &mut buffer)?.value, 1);
+
+        // 键已存在时更新
+        tree.upsert(KeyValuePair::new("Hello".to_string(), 2), &mut buffer)?;
+        assert_eq!(tree.search("Hello".to_string(), &mut buffer)?.value, 2);
+        assert_eq!(tree.count(None, None, &mut buffer)?, 1);
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_if() -> Result<(), Error> {
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let mut tree = gen_tree(&mut buffer)?;
+        tree.insert(KeyValuePair::new("Hello".to_string(), 1), &mut buffer)?;
+
+        // 期望值正确时更新
+        tree.update_if("Hello".to_string(), 1, 2, &mut buffer)?;
+        assert_eq!(tree.search("Hello".to_string(), &mut buffer)?.value, 2);
+
+        // 期望值错误时不修改
+        assert!(matches!(tree.update_if("Hello".to_string(), 1, 3, &mut buffer), Err(Error::CasMismatch)));
+        assert_eq!(tree.search("Hello".to_string(), &mut buffer)?.value, 2);
+
+        assert!(matches!(tree.update_if("Test".to_string(), 1, 3, &mut buffer), Err(Error::KeyNotFound)));
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_persisted_tree() -> Result<(), Error> {
+        rm_test_file();
+
+        {
+            let mut buffer = gen_buffer()?;
+            let mut tree = gen_tree(&mut buffer)?;
+
+            let (kv1, kv2) = gen_2_kv()?;
+            tree.insert(kv1, &mut buffer)?;
+            tree.insert(kv2, &mut buffer)?;
+
+            buffer.flush_all()?;
+        }
+
+        let mut buffer = gen_buffer()?;
+        let pager = gen_pager(&mut buffer)?;
+        let tree = BTree::open(pager, "test.db".to_string(), &mut buffer)?;
+
+        let res1 = tree.search("Hello".to_string(), &mut buffer)?;
+        assert_eq!(res1.value, 4096usize);
+        let res2 = tree.search("Test".to_string(), &mut buffer)?;
+        assert_eq!(res2.value, 4096 * 2usize);
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_bad_root_page() -> Result<(), Error> {
+        rm_test_file();
+
+        {
+            let mut buffer = gen_buffer()?;
+            let mut tree = gen_tree(&mut buffer)?;
+            let (kv1, _) = gen_2_kv()?;
+            tree.insert(kv1, &mut buffer)?;
+
+            // 把元数据页中的根节点页号改成一个不存在的页
+            let pager = gen_pager(&mut buffer)?;
+            let mut meta_page = pager.get_page(&TREE_META_PAGE_NUM, &mut buffer)?;
+            meta_page.write_value_at_offset(0, 10000)?;
+            pager.write_page(meta_page, &mut buffer)?;
+            buffer.flush_all()?;
+        }
+
+        let mut buffer = gen_buffer()?;
+        let pager = gen_pager(&mut buffer)?;
+        assert!(BTree::open(pager, "test.db".to_string(), &mut buffer).is_err());
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_size_capacity() -> Result<(), Error> {
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let pager = gen_pager(&mut buffer)?;
+        match BTree::new_with_key_size(pager, "test.db".to_string(), 4000, &mut buffer) {
+            Err(Error::KeySizeOutOfPage) => (),
+            _ => panic!("expected KeySizeOutOfPage")
+        }
+
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let pager = gen_pager(&mut buffer)?;
+        let mut tree = BTree::new_with_key_size(pager, "test.db".to_string(), 40, &mut buffer)?;
+        assert_eq!(tree.get_key_size(), 40);
+        let kv = KeyValuePair::new("a_key_much_longer_than_ten_bytes".to_string(), 4096);
+        tree.insert(kv.clone(), &mut buffer)?;
+        assert_eq!(tree.search(kv.key.clone(), &mut buffer)?.value, kv.value);
+
+        // 重新打开后键宽保持不变
+        let pager = gen_pager(&mut buffer)?;
+        let reopened = BTree::open(pager, "test.db".to_string(), &mut buffer)?;
+        assert_eq!(reopened.get_key_size(), 40);
+        assert_eq!(reopened.search(kv.key, &mut buffer)?.value, kv.value);
+
+        rm_test_file();
+        Ok(())
+    }
+
+    fn range_keys(tree: &BTree, left: Option<&str>, right: Option<&str>, buffer: &mut Box<dyn Buffer>) -> Result<Vec<String>, Error> {
+        let res = tree.search_range(left.map(|k| k.to_string()), right.map(|k| k.to_string()), buffer)?;
+        Ok(res.into_iter().map(|kv| kv.key).collect())
+    }
+
+    #[test]
+    fn test_search_range_bounds() -> Result<(), Error> {
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let mut tree = gen_tree(&mut buffer)?;
+        for (i, key) in ["d", "h", "b", "f"].iter().enumerate() {
+            tree.insert(KeyValuePair::new(key.to_string(), i), &mut buffer)?;
+        }
+
+        // 两端都有界，边界包括在内，且不必是已有的键
+        assert_eq!(range_keys(&tree, Some("b"), Some("f"), &mut buffer)?, vec!["b", "d", "f"]);
+        assert_eq!(range_keys(&tree, Some("c"), Some("g"), &mut buffer)?, vec!["d", "f"]);
+        // 只有左边界
+        assert_eq!(range_keys(&tree, Some("d"), None, &mut buffer)?, vec!["d", "f", "h"]);
+        assert_eq!(range_keys(&tree, Some("e"), None, &mut buffer)?, vec!["f", "h"]);
+        // 只有右边界，按正序返回
+        assert_eq!(range_keys(&tree, None, Some("f"), &mut buffer)?, vec!["b", "d", "f"]);
+        assert!(range_keys(&tree, None, Some("a"), &mut buffer)?.is_empty());
+        // 两端都无界
+        assert_eq!(range_keys(&tree, None, None, &mut buffer)?, vec!["b", "d", "f", "h"]);
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_range_ex_bounds() -> Result<(), Error> {
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let mut tree = gen_tree(&mut buffer)?;
+        for (i, key) in ["d", "h", "b", "f"].iter().enumerate() {
+            tree.insert(KeyValuePair::new(key.to_string(), i), &mut buffer)?;
+        }
+
+        let keys = |res: Vec<KeyValuePair>| res.into_iter().map(|kv| kv.key).collect::<Vec<String>>();
+        // 不包括左边界，包括右边界
+        let res = tree.search_range_ex(Bound::Excluded("b".to_string()), Bound::Included("f".to_string()), &mut buffer)?;
+        assert_eq!(keys(res), vec!["d", "f"]);
+        // 左边无界，不包括右边界
+        let res = tree.search_range_ex(Bound::Unbounded, Bound::Excluded("f".to_string()), &mut buffer)?;
+        assert_eq!(keys(res), vec!["b", "d"]);
+        // 两端都不包括且之间没有键
+        let res = tree.search_range_ex(Bound::Excluded("d".to_string()), Bound::Excluded("f".to_string()), &mut buffer)?;
+        assert!(res.is_empty());
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_count() -> Result<(), Error> {
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let mut tree = gen_tree(&mut buffer)?;
+        assert_eq!(tree.count(None, None, &mut buffer)?, 0);
+
+        // 打乱插入顺序，50 个键会让叶子分裂多次
+        for i in 0..50 {
+            let key = format!("k{:02}", (i * 7) % 50);
+            tree.insert(KeyValuePair::new(key, i), &mut buffer)?;
+        }
+
+        assert_eq!(tree.count(None, None, &mut buffer)?, 50);
+        assert_eq!(tree.count(Some("k10".to_string()), Some("k29".to_string()), &mut buffer)?, 20);
+        assert_eq!(tree.count(Some("k45".to_string()), None, &mut buffer)?, 5);
+        assert_eq!(tree.count(None, Some("k04".to_string()), &mut buffer)?, 5);
+        assert_eq!(tree.count(Some("k50".to_string()), None, &mut buffer)?, 0);
+        for i in 0..50 {
+            assert!(tree.search(format!("k{:02}", i), &mut buffer).is_ok());
+        }
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_min_max_key() -> Result<(), Error> {
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let mut tree = gen_tree(&mut buffer)?;
+        match tree.min_key(&mut buffer) {
+            Err(Error::KeyNotFound) => (),
+            _ => panic!("expected KeyNotFound")
+        }
+        match tree.max_key(&mut buffer) {
+            Err(Error::KeyNotFound) => (),
+            _ => panic!("expected KeyNotFound")
+        }
+
+        let mut keys = Vec::<String>::new();
+        for i in 0..30 {
+            let key = format!("m{:02}", (i * 11) % 31 + 3);
+            tree.insert(KeyValuePair::new(key.clone(), i), &mut buffer)?;
+            keys.push(key);
+        }
+        keys.sort();
+
+        assert_eq!(tree.min_key(&mut buffer)?.key, keys[0]);
+        assert_eq!(tree.max_key(&mut buffer)?.key, keys[keys.len() - 1]);
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_iter_rev() -> Result<(), Error> {
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let mut tree = gen_tree(&mut buffer)?;
+        assert!(tree.iter_rev(&mut buffer).next().is_none());
+
+        for i in 0..50 {
+            let key = format!("r{:02}", (i * 13) % 50);
+            tree.insert(KeyValuePair::new(key, i), &mut buffer)?;
+        }
+
+        let mut forward = tree.search_range(None, None, &mut buffer)?;
+        forward.reverse();
+        let backward = tree.iter_rev(&mut buffer).collect::<Result<Vec<KeyValuePair>, Error>>()?;
+        assert_eq!(backward.len(), 50);
+        assert!(backward == forward);
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_bulk_load() -> Result<(), Error> {
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let pairs: Vec<KeyValuePair> = (0..1000).map(|i| KeyValuePair::new(format!("k{:04}", i), i)).collect();
+        let pager = gen_pager(&mut buffer)?;
+        let mut tree = BTree::bulk_load(pairs, pager, "test.db".to_string(), &mut buffer)?;
+
+        for i in [0usize, 1, 9, 10, 499, 998, 999].iter() {
+            assert_eq!(tree.search(format!("k{:04}", i), &mut buffer)?.value, *i);
+        }
+        let keys = range_keys(&tree, None, None, &mut buffer)?;
+        let expected: Vec<String> = (0..1000).map(|i| format!("k{:04}", i)).collect();
+        assert_eq!(keys, expected);
+
+        // 重新打开后仍能查询，之后还能继续插入
+        let pager = gen_pager(&mut buffer)?;
+        let reopened = BTree::open(pager, "test.db".to_string(), &mut buffer)?;
+        assert_eq!(reopened.search("k0500".to_string(), &mut buffer)?.value, 500);
+        tree.insert(KeyValuePair::new("k0500a".to_string(), 5000), &mut buffer)?;
+        assert_eq!(tree.count(None, None, &mut buffer)?, 1001);
+
+        rm_test_file();
+
+        // 键宽 400 时中间节点最多 6 个儿子，会建出多层中间节点
+        let mut buffer = gen_buffer()?;
+        let pairs: Vec<KeyValuePair> = (0..300).map(|i| KeyValuePair::new(format!("k{:04}", i), i)).collect();
+        let pager = gen_pager(&mut buffer)?;
+        let tree = BTree::bulk_load_with_key_size(pairs, pager, "test.db".to_string(), 400, &mut buffer)?;
+        assert_eq!(range_keys(&tree, Some("k0100"), Some("k0102"), &mut buffer)?, vec!["k0100", "k0101", "k0102"]);
+        assert_eq!(tree.search("k0299".to_string(), &mut buffer)?.value, 299);
+        assert_eq!(tree.count(None, None, &mut buffer)?, 300);
+
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let unsorted = vec![KeyValuePair::new("b".to_string(), 1), KeyValuePair::new("a".to_string(), 2)];
+        let pager = gen_pager(&mut buffer)?;
+        match BTree::bulk_load(unsorted, pager, "test.db".to_string(), &mut buffer) {
+            Err(Error::KeysNotSorted) => (),
+            _ => panic!("expected KeysNotSorted")
+        }
+        let duplicated = vec![KeyValuePair::new("a".to_string(), 1), KeyValuePair::new("a".to_string(), 2)];
+        let pager = gen_pager(&mut buffer)?;
+        match BTree::bulk_load(duplicated, pager, "test.db".to_string(), &mut buffer) {
+            Err(Error::KeyAlreadyExists) => (),
+            _ => panic!("expected KeyAlreadyExists")
+        }
+
+        rm_test_file();
+        Ok(())
+    }
+
+    /// 检查以 offset 为根的子树中每个节点页里保存的父节点，返回子树中中间节点的个数
+    fn check_parent_offsets(pager: &Pager, offset: usize, parent_offset: usize, buffer: &mut Box<dyn Buffer>) -> Result<usize, Error> {
+        let node = Node::load(pager, offset, buffer)?;
+        assert_eq!(node.parent_offset, parent_offset);
+        if node.node_type == NodeType::Leaf {
+            return Ok(0);
+        }
+        let mut internal = 1;
+        for child in node.get_children()? {
+            internal += check_parent_offsets(pager, child, offset, buffer)?;
+        }
+        Ok(internal)
+    }
+
+    #[test]
+    fn test_split_parent_offset() -> Result<(), Error> {
+        rm_test_file();
+
+        // 键宽 400 时中间节点最多 6 个键，插入 100 个键会让根和中间节点都分裂
+        {
+            let mut buffer = gen_buffer()?;
+            let pager = gen_pager(&mut buffer)?;
+            let mut tree = BTree::new_with_key_size(pager, "test.db".to_string(), 400, &mut buffer)?;
+            for i in 0..100 {
+                tree.insert(KeyValuePair::new(format!("k{:03}", (i * 37) % 100), i), &mut buffer)?;
+            }
+            buffer.flush_all()?;
+        }
+
+        // 从磁盘重新读入，每个节点都能找到真正的父节点
+        let mut buffer = gen_buffer()?;
+        let pager = gen_pager(&mut buffer)?;
+        let meta_page = pager.get_page(&TREE_META_PAGE_NUM, &mut buffer)?;
+        let root_offset = meta_page.get_value_from_offset(0)?;
+        assert!(check_parent_offsets(&pager, root_offset, 0, &mut buffer)? > 2);
+
+        rm_test_file();
+
+        // 批量构建的树同样保存了父节点
+        let mut buffer = gen_buffer()?;
+        let pairs: Vec<KeyValuePair> = (0..300).map(|i| KeyValuePair::new(format!("k{:04}", i), i)).collect();
+        let pager = gen_pager(&mut buffer)?;
+        let tree = BTree::bulk_load_with_key_size(pairs, pager, "test.db".to_string(), 400, &mut buffer)?;
+        let root_offset = tree.pager.get_page(&TREE_META_PAGE_NUM, &mut buffer)?.get_value_from_offset(0)?;
+        assert!(check_parent_offsets(&tree.pager, root_offset, 0, &mut buffer)? > 2);
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_with_small_buffer() -> Result<(), Error> {
+        for kind in 0..3 {
+            rm_test_file();
+            // 只有 4 个页框，分裂时修改的节点会被频繁换出
+            let open = |kind: usize| -> Result<Box<dyn Buffer>, Error> {
+                let mut buffer: Box<dyn Buffer> = match kind {
+                    0 => Box::new(LRUBuffer::new(4, "metadata.db".to_string())?),
+                    1 => Box::new(ClockBuffer::new(4, "metadata.db".to_string())?),
+                    _ => Box::new(FIFOBuffer::new(4, "metadata.db".to_string())?),
+                };
+                buffer.add_file(Path::new("test.db"))?;
+                buffer.fill_up_to("test.db", 10)?;
+                Ok(buffer)
+            };
+
+            let mut buffer = open(kind)?;
+            let pager = gen_pager(&mut buffer)?;
+            let mut tree = BTree::new_with_key_size(pager, "test.db".to_string(), 400, &mut buffer)?;
+            for i in 0..200 {
+                tree.insert(KeyValuePair::new(format!("k{:03}", (i * 73) % 200), i), &mut buffer)?;
+            }
+            for i in 0..200 {
+                assert_eq!(tree.search(format!("k{:03}", (i * 73) % 200), &mut buffer)?.value, i);
+            }
+            assert_eq!(tree.count(None, None, &mut buffer)?, 200);
+            buffer.flush_all()?;
+            drop(buffer);
+
+            // 换一个缓冲区重新打开，所有的修改都已写回
+            let mut buffer = open(kind)?;
+            let pager = gen_pager(&mut buffer)?;
+            let tree = BTree::open(pager, "test.db".to_string(), &mut buffer)?;
+            for i in 0..200 {
+                assert_eq!(tree.search(format!("k{:03}", (i * 73) % 200), &mut buffer)?.value, i);
+            }
+        }
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_order_with_padding() -> Result<(), Error> {
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let mut tree = gen_tree(&mut buffer)?;
+        // "a" < "ab" < "b"，长短不同的键交错插入，叶子会分裂出中间节点
+        let mut keys = Vec::<String>::new();
+        for c in "abcdefghijkl".chars() {
+            keys.push(c.to_string());
+            keys.push(format!("{}b", c));
+        }
+        for (i, key) in keys.iter().enumerate().rev() {
+            tree.insert(KeyValuePair::new(key.clone(), i), &mut buffer)?;
+        }
+
+        let root_offset = tree.pager.get_page(&TREE_META_PAGE_NUM, &mut buffer)?.get_value_from_offset(0)?;
+        let root = Node::load(&tree.pager, root_offset, &mut buffer)?;
+        assert!(root.node_type == NodeType::Internal);
+        let root_keys = root.get_keys()?;
+        let mut sorted = root_keys.clone();
+        sorted.sort();
+        assert_eq!(root_keys, sorted);
+        assert_eq!(range_keys(&tree, None, None, &mut buffer)?, keys);
+
+        // 带填充的键与不带填充的键相同
+        assert_eq!(tree.search("ab\0\0".to_string(), &mut buffer)?.value, 1);
+        match tree.insert(KeyValuePair::new("b\0".to_string(), 100), &mut buffer) {
+            Err(Error::KeyAlreadyExists) => (),
+            _ => panic!("expected KeyAlreadyExists")
+        }
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_padded_key_comparisons() -> Result<(), Error> {
+        rm_test_file();
+
+        // 键值对的相等与排序一致，填充不影响比较，值不同则不相等
+        assert!(KeyValuePair::new("a\0\0".to_string(), 1) == KeyValuePair::new("a".to_string(), 1));
+        assert!(KeyValuePair::new("a".to_string(), 1) != KeyValuePair::new("a".to_string(), 2));
+        assert!(KeyValuePair::new("a".to_string(), 1) < KeyValuePair::new("a\0".to_string(), 2));
+
+        let mut buffer = gen_buffer()?;
+        let mut tree = gen_tree(&mut buffer)?;
+        for (i, key) in ["b", "d", "f"].iter().enumerate() {
+            tree.insert(KeyValuePair::new(key.to_string(), i), &mut buffer)?;
+        }
+        let root_offset = tree.pager.get_page(&TREE_META_PAGE_NUM, &mut buffer)?.get_value_from_offset(0)?;
+        let root = Node::load(&tree.pager, root_offset, &mut buffer)?;
+        assert_eq!(root.find_key_value_pair("d\0\0".to_string())?.value, 1);
+
+        // 带填充的边界与不带填充的边界相同
+        let keys = |res: Vec<KeyValuePair>| res.into_iter().map(|kv| kv.key).collect::<Vec<String>>();
+        let res = tree.search_range_ex(Bound::Excluded("b\0".to_string()), Bound::Included("f\0\0".to_string()), &mut buffer)?;
+        assert_eq!(keys(res), vec!["d", "f"]);
+        let res = tree.search_range_ex(Bound::Included("b\0".to_string()), Bound::Excluded("f\0".to_string()), &mut buffer)?;
+        assert_eq!(keys(res), vec!["b", "d"]);
+
+        // 批量构建时带填充的重复键同样被拒绝
+        let pager = gen_pager(&mut buffer)?;
+        let pairs = vec![KeyValuePair::new("a".to_string(), 1), KeyValuePair::new("a\0".to_string(), 2)];
+        match BTree::bulk_load(pairs, pager, "test.db".to_string(), &mut buffer) {
+            Err(Error::KeyAlreadyExists) => (),
+            _ => panic!("expected KeyAlreadyExists")
+        }
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_key_too_long() -> Result<(), Error> {
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let mut tree = gen_tree(&mut buffer)?;
+        // 截断后两个键会相同
+        match tree.insert(KeyValuePair::new("longkey1234".to_string(), 1), &mut buffer) {
+            Err(Error::KeyTooLong) => (),
+            _ => panic!("expected KeyTooLong")
+        }
+        assert_eq!(tree.count(None, None, &mut buffer)?, 0);
+
+        // 恰好等于键宽的键，以及带填充的键可以插入
+        tree.insert(KeyValuePair::new("longkey123".to_string(), 2), &mut buffer)?;
+        tree.insert(KeyValuePair::new("key\0\0\0\0\0\0\0\0".to_string(), 3), &mut buffer)?;
+        assert_eq!(tree.search("longkey123".to_string(), &mut buffer)?.value, 2);
+        assert_eq!(tree.search("key".to_string(), &mut buffer)?.value, 3);
+
+        let pager = gen_pager(&mut buffer)?;
+        let pairs = vec![KeyValuePair::new("longkey1234".to_string(), 1)];
+        match BTree::bulk_load(pairs, pager, "test.db".to_string(), &mut buffer) {
+            Err(Error::KeyTooLong) => (),
+            _ => panic!("expected KeyTooLong")
+        }
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_search() -> Result<(), Error> {
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let mut tree = gen_tree(&mut buffer)?;
+        for i in 0..200 {
+            tree.insert(KeyValuePair::new(format!("k{:03}", i), i), &mut buffer)?;
+        }
+
+        // 多个线程共用一棵树和一个缓冲区，各自查询不同的键
+        let tree = Arc::new(tree);
+        let buffer = Arc::new(Mutex::new(buffer));
+        let mut handles = Vec::new();
+        for t in 0..4 {
+            let tree = Arc::clone(&tree);
+            let buffer = Arc::clone(&buffer);
+            handles.push(thread::spawn(move || -> Result<(), Error> {
+                for i in (t..200).step_by(4) {
+                    assert_eq!(tree.search_shared(format!("k{:03}", i), &buffer)?.value, i);
+                }
+                match tree.search_shared("k999".to_string(), &buffer) {
+                    Err(Error::KeyNotFound) => Ok(()),
+                    _ => panic!("expected KeyNotFound")
+                }
+            }));
+        }
+        for handle in handles {
+            handle.join().unwrap()?;
+        }
+
+        rm_test_file();
+        Ok(())
+    }
+
+    /// 键宽 400 时通过插入建一棵有多层中间节点的树
+    fn gen_split_tree(buffer: &mut Box<dyn Buffer>) -> Result<BTree, Error> {
+        let pager = gen_pager(buffer)?;
+        let mut tree = BTree::new_with_key_size(pager, "test.db".to_string(), 400, buffer)?;
+        for i in 0..100 {
+            tree.insert(KeyValuePair::new(format!("k{:03}", (i * 37) % 100), i), buffer)?;
+        }
+        Ok(tree)
+    }
+
+    #[test]
+    fn test_validate() -> Result<(), Error> {
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let tree = gen_tree(&mut buffer)?;
+        tree.validate(&mut buffer)?;
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let mut tree = gen_split_tree(&mut buffer)?;
+        tree.validate(&mut buffer)?;
+        // 删除不合并节点，树仍然合法
+        for i in (0..100).step_by(3) {
+            tree.delete(format!("k{:03}", i), &mut buffer)?;
+        }
+        tree.validate(&mut buffer)?;
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let pairs: Vec<KeyValuePair> = (0..300).map(|i| KeyValuePair::new(format!("k{:04}", i), i)).collect();
+        let pager = gen_pager(&mut buffer)?;
+        tree = BTree::bulk_load_with_key_size(pairs, pager, "test.db".to_string(), 400, &mut buffer)?;
+        tree.validate(&mut buffer)?;
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_corrupted() -> Result<(), Error> {
+        rm_test_file();
+
+        // 断开第一个叶子指向下一个叶子的指针
+        let mut buffer = gen_buffer()?;
+        let tree = gen_split_tree(&mut buffer)?;
+        let first_offset = tree.pager.get_page(&TREE_META_PAGE_NUM, &mut buffer)?.get_value_from_offset(PTR_SIZE)?;
+        let mut leaf = Node::load(&tree.pager, first_offset, &mut buffer)?;
+        leaf.add_next_node(0)?;
+        tree.pager.write_page(leaf.page, &mut buffer)?;
+        assert!(matches!(tree.validate(&mut buffer), Err(Error::CorruptNode)));
+        rm_test_file();
+
+        // 把根节点的键和儿子倒过来，键不再有序
+        let mut buffer = gen_buffer()?;
+        let tree = gen_split_tree(&mut buffer)?;
+        let root_offset = tree.pager.get_page(&TREE_META_PAGE_NUM, &mut buffer)?.get_value_from_offset(0)?;
+        let mut root = Node::load(&tree.pager, root_offset, &mut buffer)?;
+        let mut keys = root.get_keys()?;
+        let mut children = root.get_children()?;
+        keys.reverse();
+        children.reverse();
+        root.set_keys_and_children(&keys, &children)?;
+        tree.pager.write_page(root.page, &mut buffer)?;
+        assert!(matches!(tree.validate(&mut buffer), Err(Error::CorruptNode)));
+        rm_test_file();
+
+        // 叶子中的键超出了父节点给出的范围
+        let mut buffer = gen_buffer()?;
+        let tree = gen_split_tree(&mut buffer)?;
+        let mut leaf = Node::load(&tree.pager, first_offset, &mut buffer)?;
+        leaf.add_key_value_pair(KeyValuePair::new("z".to_string(), 0))?;
+        tree.pager.write_page(leaf.page, &mut buffer)?;
+        assert!(matches!(tree.validate(&mut buffer), Err(Error::CorruptNode)));
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_dump_and_load_from() -> Result<(), Error> {
+        rm_test_file();
+        rm_files(&["test_copy.db"]);
+
+        // 空树导出为空，重建后仍是空树
+        let mut buffer = gen_buffer()?;
+        let empty = gen_tree(&mut buffer)?;
+        let pairs = empty.dump(&mut buffer)?;
+        assert!(pairs.is_empty());
+        buffer.add_file(Path::new("test_copy.db"))?;
+        let pager = Pager::new("test_copy.db".to_string(), 50, &mut buffer)?;
+        let copy = BTree::load_from(pairs, pager, "test_copy.db".to_string(), &mut buffer)?;
+        assert!(copy.dump(&mut buffer)?.is_empty());
+        drop(buffer);
+        rm_test_file();
+        rm_files(&["test_copy.db"]);
+
+        let mut buffer = gen_buffer()?;
+        let pager = gen_pager(&mut buffer)?;
+        let mut tree = BTree::new_with_key_size(pager, "test.db".to_string(), 400, &mut buffer)?;
+        for i in 0..100 {
+            tree.insert(KeyValuePair::new(format!("k{:03}", (i * 37) % 100), i), &mut buffer)?;
+        }
+        tree.delete("k050".to_string(), &mut buffer)?;
+
+        // 导出的键值对按键排列
+        let pairs = tree.dump(&mut buffer)?;
+        assert_eq!(pairs.len(), 99);
+        assert!(pairs.windows(2).all(|pair| pair[0].key < pair[1].key));
+
+        buffer.add_file(Path::new("test_copy.db"))?;
+        let pager = Pager::new("test_copy.db".to_string(), 50, &mut buffer)?;
+        let copy = BTree::load_from(pairs, pager, "test_copy.db".to_string(), &mut buffer)?;
+        copy.validate(&mut buffer)?;
+        for i in 0..100 {
+            let key = format!("k{:03}", i);
+            match (tree.search(key.clone(), &mut buffer), copy.search(key, &mut buffer)) {
+                (Ok(a), Ok(b)) => assert!(a == b),
+                (Err(Error::KeyNotFound), Err(Error::KeyNotFound)) => assert_eq!(i, 50),
+                _ => panic!("search results differ")
+            }
+        }
+
+        drop(buffer);
         rm_test_file();
+        rm_files(&["test_copy.db"]);
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -1,11 +1,12 @@
 #[cfg(test)]
 mod test_buffer {
-    use crate::data_item::buffer::{Buffer, LRUBuffer, ClockBuffer};
+    use crate::data_item::buffer::{Buffer, BufferStats, LRUBuffer, ClockBuffer, FIFOBuffer, LFUBuffer, Position, LFU_DECAY_INTERVAL, NON_DATA_PAGE, MIN_BUFFER_SIZE, WRITE_LOG};
     use std::path::Path;
     use std::fs;
+    use std::io::{Seek, SeekFrom, Write};
     use crate::page::page_item::{PAGE_SIZE, Page};
     use crate::util::error::Error;
-    use crate::util::test_lib::rm_test_file;
+    use crate::util::test_lib::{rm_test_file, rm_files};
 
     #[test]
     fn test_add_file() -> Result<(), Error> {
@@ -155,6 +156,32 @@ mod test_buffer {
         Ok(())
     }
 
+    #[test]
+    fn test_lru_evict_dirty() -> Result<(), Error> {
+        let files = ["lru_evict_metadata.db", "lru_evict.db"];
+        rm_files(&files);
+
+        let mut buffer = LRUBuffer::new(2, "lru_evict_metadata.db".to_string())?;
+        buffer.add_file(Path::new("lru_evict.db"))?;
+        buffer.fill_up_to("lru_evict.db", 4)?;
+
+        // 缓冲区装满两个被修改过的页
+        buffer.write_page(Page::new([1u8; PAGE_SIZE], "lru_evict.db", 1))?;
+        buffer.write_page(Page::new([2u8; PAGE_SIZE], "lru_evict.db", 2))?;
+
+        // 写入第三页时淘汰最久未使用的第一页
+        buffer.write_page(Page::new([3u8; PAGE_SIZE], "lru_evict.db", 3))?;
+        let pages: Vec<usize> = buffer.list.iter().map(|item| item.page.page_num).collect();
+        assert!(!pages.contains(&1));
+        assert!(pages.contains(&3));
+
+        // 第一页在淘汰时已写回磁盘，重新读入时仍是修改后的内容
+        assert!(buffer.get_page("lru_evict.db", 1)?.get_data().iter().all(|b| *b == 1));
+
+        rm_files(&files);
+        Ok(())
+    }
+
     #[test]
     fn test_clock_algo() -> Result<(), Error> {
         rm_test_file();
@@ -189,4 +216,780 @@ mod test_buffer {
         rm_test_file();
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_lfu_algo() -> Result<(), Error> {
+        let files = ["lfu_metadata.db", "lfu_test.db"];
+        rm_files(&files);
+
+        let mut buffer = LFUBuffer::new(3, "lfu_metadata.db".to_string())?;
+        buffer.add_file(Path::new("lfu_test.db"))?;
+        buffer.fill_up_to("lfu_test.db", 10)?;
+
+        // 页 1 访问多次，页 2 和页 3 各访问一次
+        for _ in 0..10 {
+            buffer.get_page("lfu_test.db", 1)?;
+        }
+        buffer.get_page("lfu_test.db", 2)?;
+        buffer.get_page("lfu_test.db", 3)?;
+
+        // 缓冲区已满，淘汰访问次数最少且最早载入的页 2
+        buffer.get_page("lfu_test.db", 4)?;
+        let pages: Vec<usize> = buffer.list.iter().map(|i| i.page.page_num).collect();
+        assert_eq!(pages, vec![1, 3, 4]);
+        buffer.get_page("lfu_test.db", 5)?;
+        buffer.get_page("lfu_test.db", 6)?;
+        buffer.reset_stats();
+        buffer.get_page("lfu_test.db", 1)?;
+        assert_eq!(buffer.stats().hits, 1);
+
+        // 访问次数定期减半，不再访问的页面最终会被淘汰
+        for i in 0..4 * LFU_DECAY_INTERVAL {
+            buffer.get_page("lfu_test.db", 2 + i % 8)?;
+        }
+        assert!(buffer.list.iter().all(|i| i.page.page_num != 1));
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fifo_algo() -> Result<(), Error> {
+        let files = ["fifo_metadata.db", "fifo_test.db"];
+        rm_files(&files);
+
+        let mut buffer = FIFOBuffer::new(4, "fifo_metadata.db".to_string())?;
+        buffer.add_file(Path::new("fifo_test.db"))?;
+        buffer.fill_up_to("fifo_test.db", 10)?;
+
+        buffer.get_page("fifo_test.db", 2)?;
+        buffer.get_page("fifo_test.db", 4)?;
+        buffer.get_page("fifo_test.db", 3)?;
+        buffer.get_page("fifo_test.db", 1)?;
+
+        let vec = [2, 4, 3, 1];
+        let list = &buffer.list;
+        for (i, item) in list.iter().enumerate() {
+            assert_eq!(item.page.page_num, vec[i]);
+        }
+
+        // 再次访问 3 不会让它免于淘汰
+        buffer.get_page("fifo_test.db", 5)?;
+        buffer.get_page("fifo_test.db", 7)?;
+        buffer.get_page("fifo_test.db", 3)?;
+        buffer.get_page("fifo_test.db", 6)?;
+
+        let vec2 = [1, 5, 7, 6];
+        let list = &buffer.list;
+        for (i, item) in list.iter().enumerate() {
+            assert_eq!(item.page.page_num, vec2[i]);
+        }
+
+        // 被淘汰的页面已经写回磁盘
+        let mut page = buffer.get_page("fifo_test.db", 8)?;
+        page.write_bytes_at_offset(&[7u8; 4], 0, 4)?;
+        buffer.write_page(page)?;
+        for i in 1..=4 {
+            buffer.get_page("fifo_test.db", i)?;
+        }
+        assert_eq!(buffer.get_page("fifo_test.db", 8)?.get_ptr_from_offset(0, 4), &[7u8; 4]);
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_clean_page_not_written() -> Result<(), Error> {
+        let files = ["dirty_metadata.db", "dirty_test.db"];
+        rm_files(&files);
+
+        let mut buffer = LRUBuffer::new(4, "dirty_metadata.db".to_string())?;
+        buffer.add_file(Path::new("dirty_test.db"))?;
+        buffer.fill_up_to("dirty_test.db", 10)?;
+
+        // 只读过的页面不需要写回
+        let page = buffer.get_page("dirty_test.db", 1)?;
+        buffer.flush_all()?;
+        assert_eq!(buffer.stats().writes, 0);
+
+        // 修改过的页面只写回一次
+        buffer.write_page(page)?;
+        buffer.flush("dirty_test.db", &1)?;
+        buffer.flush_all()?;
+        assert_eq!(buffer.stats().writes, 1);
+
+        rm_files(&files);
+
+        let mut buffer = ClockBuffer::new(4, "dirty_metadata.db".to_string())?;
+        buffer.add_file(Path::new("dirty_test.db"))?;
+        buffer.fill_up_to("dirty_test.db", 10)?;
+
+        let page = buffer.get_page("dirty_test.db", 1)?;
+        buffer.flush_file("dirty_test.db")?;
+        assert_eq!(buffer.stats().writes, 0);
+
+        buffer.write_page(page)?;
+        buffer.flush_file("dirty_test.db")?;
+        buffer.flush_all()?;
+        assert_eq!(buffer.stats().writes, 1);
+
+        rm_files(&files);
+
+        let mut buffer = FIFOBuffer::new(4, "dirty_metadata.db".to_string())?;
+        buffer.add_file(Path::new("dirty_test.db"))?;
+        buffer.fill_up_to("dirty_test.db", 10)?;
+
+        let page = buffer.get_page("dirty_test.db", 1)?;
+        buffer.flush_all()?;
+        assert_eq!(buffer.stats().writes, 0);
+
+        buffer.write_page(page)?;
+        buffer.flush_all()?;
+        buffer.flush_all()?;
+        assert_eq!(buffer.stats().writes, 1);
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    fn check_pin(buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        buffer.add_file(Path::new("pin_test.db"))?;
+        buffer.fill_up_to("pin_test.db", 10)?;
+
+        let mut page = buffer.get_page("pin_test.db", 1)?;
+        page.write_bytes_at_offset(&[9u8; 4], 0, 4)?;
+        buffer.write_page(page)?;
+        for i in 1..=4 {
+            buffer.pin("pin_test.db", i)?;
+        }
+
+        // 所有页面都被固定时无法载入新页面
+        match buffer.get_page("pin_test.db", 5) {
+            Err(Error::NoEvictablePage) => (),
+            _ => panic!("expected NoEvictablePage")
+        }
+        match buffer.write_page(Page::new([0u8; PAGE_SIZE], "pin_test.db", 6)) {
+            Err(Error::NoEvictablePage) => (),
+            _ => panic!("expected NoEvictablePage")
+        }
+        assert_eq!(buffer.get_page("pin_test.db", 1)?.get_ptr_from_offset(0, 4), &[9u8; 4]);
+
+        // 取消固定后可以淘汰该页
+        buffer.unpin("pin_test.db", 2)?;
+        buffer.get_page("pin_test.db", 5)?;
+        match buffer.unpin("pin_test.db", 2) {
+            Err(Error::NotInBufferError) => (),
+            _ => panic!("expected NotInBufferError")
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_pin() -> Result<(), Error> {
+        let files = ["pin_metadata.db", "pin_test.db"];
+        rm_files(&files);
+
+        let mut buffer: Box<dyn Buffer> = Box::new(LRUBuffer::new(4, "pin_metadata.db".to_string())?);
+        check_pin(&mut buffer)?;
+        rm_files(&files);
+
+        let mut buffer: Box<dyn Buffer> = Box::new(ClockBuffer::new(4, "pin_metadata.db".to_string())?);
+        check_pin(&mut buffer)?;
+        rm_files(&files);
+
+        let mut buffer: Box<dyn Buffer> = Box::new(FIFOBuffer::new(4, "pin_metadata.db".to_string())?);
+        check_pin(&mut buffer)?;
+        rm_files(&files);
+
+        let mut buffer: Box<dyn Buffer> = Box::new(LFUBuffer::new(4, "pin_metadata.db".to_string())?);
+        check_pin(&mut buffer)?;
+        rm_files(&files);
+        Ok(())
+    }
+
+    fn check_prefetch(buffer: &mut Box<dyn Buffer>) -> Result<(), Error> {
+        buffer.add_file(Path::new("prefetch_test.db"))?;
+        buffer.fill_up_to("prefetch_test.db", 10)?;
+        buffer.reset_stats();
+
+        // 预取的页从磁盘载入，重复的页只读一次
+        buffer.prefetch("prefetch_test.db", &[3, 4, 5, 4])?;
+        assert_eq!(buffer.stats().misses, 3);
+        for i in 3..=5 {
+            buffer.get_page("prefetch_test.db", i)?;
+        }
+        assert_eq!(buffer.stats(), BufferStats { hits: 3, misses: 3, evictions: 0, writes: 0 });
+
+        assert!(matches!(buffer.prefetch("missing.db", &[1]), Err(Error::FileNotFound)));
+        assert!(matches!(buffer.prefetch("prefetch_test.db", &[0]), Err(Error::InvalidPageNum)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_prefetch() -> Result<(), Error> {
+        let files = ["prefetch_metadata.db", "prefetch_test.db"];
+        rm_files(&files);
+
+        let mut buffer: Box<dyn Buffer> = Box::new(LRUBuffer::new(4, "prefetch_metadata.db".to_string())?);
+        check_prefetch(&mut buffer)?;
+        rm_files(&files);
+
+        let mut buffer: Box<dyn Buffer> = Box::new(ClockBuffer::new(4, "prefetch_metadata.db".to_string())?);
+        check_prefetch(&mut buffer)?;
+        rm_files(&files);
+
+        let mut buffer: Box<dyn Buffer> = Box::new(FIFOBuffer::new(4, "prefetch_metadata.db".to_string())?);
+        check_prefetch(&mut buffer)?;
+        rm_files(&files);
+
+        let mut buffer: Box<dyn Buffer> = Box::new(LFUBuffer::new(4, "prefetch_metadata.db".to_string())?);
+        check_prefetch(&mut buffer)?;
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reject_page_num_zero() -> Result<(), Error> {
+        let files = ["zero_metadata.db", "zero_test.db"];
+        rm_files(&files);
+
+        let buffers: Vec<Box<dyn Buffer>> = vec![
+            Box::new(LRUBuffer::new(4, "zero_metadata.db".to_string())?),
+            Box::new(ClockBuffer::new(4, "zero_metadata.db".to_string())?),
+            Box::new(FIFOBuffer::new(4, "zero_metadata.db".to_string())?),
+            Box::new(LFUBuffer::new(4, "zero_metadata.db".to_string())?),
+        ];
+        for mut buffer in buffers {
+            buffer.add_file(Path::new("zero_test.db"))?;
+            match buffer.get_page("zero_test.db", 0) {
+                Err(Error::InvalidPageNum) => (),
+                _ => panic!("expected InvalidPageNum")
+            }
+            match buffer.write_page(Page::new([0u8; PAGE_SIZE], "zero_test.db", 0)) {
+                Err(Error::InvalidPageNum) => (),
+                _ => panic!("expected InvalidPageNum")
+            }
+            match buffer.flush("zero_test.db", &0) {
+                Err(Error::InvalidPageNum) => (),
+                _ => panic!("expected InvalidPageNum")
+            }
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_and_read_bytes() -> Result<(), Error> {
+        let files = ["bytes_metadata.db", "bytes_test.db"];
+        rm_files(&files);
+
+        let buffers: Vec<Box<dyn Buffer>> = vec![
+            Box::new(LRUBuffer::new(4, "bytes_metadata.db".to_string())?),
+            Box::new(ClockBuffer::new(4, "bytes_metadata.db".to_string())?),
+            Box::new(FIFOBuffer::new(4, "bytes_metadata.db".to_string())?),
+            Box::new(LFUBuffer::new(4, "bytes_metadata.db".to_string())?),
+        ];
+        for mut buffer in buffers {
+            rm_files(&files[1..]);
+            buffer.add_file(Path::new("bytes_test.db"))?;
+
+            let first = b"hello".to_vec();
+            let second = b"system software".to_vec();
+            let pos1 = buffer.insert_bytes("bytes_test.db", first.as_slice())?;
+            let pos2 = buffer.insert_bytes("bytes_test.db", second.as_slice())?;
+            assert_eq!(buffer.read_bytes(pos1, first.len())?, first);
+            assert_eq!(buffer.read_bytes(pos2, second.len())?, second);
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_bytes_after_fill() -> Result<(), Error> {
+        let files = ["table_metadata.db", "table_test.db"];
+        rm_files(&files);
+
+        let buffers: Vec<Box<dyn Buffer>> = vec![
+            Box::new(LRUBuffer::new(4, "table_metadata.db".to_string())?),
+            Box::new(ClockBuffer::new(4, "table_metadata.db".to_string())?),
+            Box::new(FIFOBuffer::new(4, "table_metadata.db".to_string())?),
+            Box::new(LFUBuffer::new(4, "table_metadata.db".to_string())?),
+        ];
+        for mut buffer in buffers {
+            rm_files(&files[1..]);
+            buffer.add_file(Path::new("table_test.db"))?;
+            buffer.fill_up_to("table_test.db", 3)?;
+
+            // 每个值超过半页，两个值只能放在不同的页
+            let first = vec![3u8; PAGE_SIZE / 2 + 1];
+            let second = vec![4u8; PAGE_SIZE / 2 + 1];
+            let pos1 = buffer.insert_bytes("table_test.db", first.as_slice())?;
+            let pos2 = buffer.insert_bytes("table_test.db", second.as_slice())?;
+            assert_eq!((pos1.get_page_num(), pos1.get_offset()), (0, 0));
+            assert_eq!((pos2.get_page_num(), pos2.get_offset()), (1, 0));
+            // 填充的页足够，不需要扩充文件
+            assert_eq!(fs::metadata("table_test.db")?.len(), (7 * PAGE_SIZE) as u64);
+
+            // 重新打开文件后页表仍然一致
+            buffer.remove_file("table_test.db")?;
+            buffer.add_file(Path::new("table_test.db"))?;
+            assert_eq!(buffer.read_bytes(pos1, first.len())?, first);
+            assert_eq!(buffer.read_bytes(pos2, second.len())?, second);
+            let pos3 = buffer.insert_bytes("table_test.db", b"tail")?;
+            assert_eq!(pos3.get_page_num(), 0);
+            assert_eq!(pos3.get_offset(), first.len());
+            let pos4 = Position::new("table_test.db".to_string(), 3, 0);
+            match buffer.read_bytes(pos4, 4) {
+                Err(Error::PageNumOutOfSize) => (),
+                _ => panic!("expected PageNumOutOfSize")
+            }
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_bytes_too_large() -> Result<(), Error> {
+        let files = ["large_metadata.db", "large_test.db"];
+        rm_files(&files);
+
+        let buffers: Vec<Box<dyn Buffer>> = vec![
+            Box::new(LRUBuffer::new(4, "large_metadata.db".to_string())?),
+            Box::new(ClockBuffer::new(4, "large_metadata.db".to_string())?),
+            Box::new(FIFOBuffer::new(4, "large_metadata.db".to_string())?),
+            Box::new(LFUBuffer::new(4, "large_metadata.db".to_string())?),
+        ];
+        for mut buffer in buffers {
+            rm_files(&files[1..]);
+            buffer.add_file(Path::new("large_test.db"))?;
+            let size = fs::metadata("large_test.db")?.len();
+
+            for len in [PAGE_SIZE, PAGE_SIZE + 1].iter() {
+                match buffer.insert_bytes("large_test.db", vec![1u8; *len].as_slice()) {
+                    Err(Error::ValueTooLarge) => (),
+                    _ => panic!("expected ValueTooLarge")
+                }
+            }
+            // 出错时不会扩充文件
+            assert_eq!(fs::metadata("large_test.db")?.len(), size);
+
+            // 单页能放下的最大数据仍然可以写入
+            let bytes = vec![2u8; PAGE_SIZE - 1];
+            let pos = buffer.insert_bytes("large_test.db", bytes.as_slice())?;
+            assert_eq!(buffer.read_bytes(pos, bytes.len())?, bytes);
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rebuild_position() -> Result<(), Error> {
+        let files = ["pos_metadata.db", "pos_test.db"];
+        rm_files(&files);
+
+        let mut buffer = LRUBuffer::new(4, "pos_metadata.db".to_string())?;
+        buffer.add_file(Path::new("pos_test.db"))?;
+        buffer.insert_bytes("pos_test.db", b"first")?;
+        let bytes = b"row locator".to_vec();
+        let pos = buffer.insert_bytes("pos_test.db", bytes.as_slice())?;
+
+        // 只保存位置的各部分，之后重建位置再读取
+        let (file_name, page_num, offset) = (pos.get_file_name().to_string(), pos.get_page_num(), pos.get_offset());
+        assert_eq!(file_name, "pos_test.db");
+        assert_eq!(offset, 5);
+        let rebuilt = Position::new(file_name, page_num, offset);
+        assert_eq!(buffer.read_bytes(rebuilt, bytes.len())?, bytes);
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_file() -> Result<(), Error> {
+        let files = ["remove_metadata.db", "remove_test.db"];
+        rm_files(&files);
+
+        let buffers: Vec<Box<dyn Buffer>> = vec![
+            Box::new(LRUBuffer::new(4, "remove_metadata.db".to_string())?),
+            Box::new(ClockBuffer::new(4, "remove_metadata.db".to_string())?),
+            Box::new(FIFOBuffer::new(4, "remove_metadata.db".to_string())?),
+            Box::new(LFUBuffer::new(4, "remove_metadata.db".to_string())?),
+        ];
+        for mut buffer in buffers {
+            rm_files(&files[1..]);
+            buffer.add_file(Path::new("remove_test.db"))?;
+            buffer.fill_up_to("remove_test.db", 4)?;
+            let mut page = buffer.get_page("remove_test.db", 1)?;
+            page.write_bytes_at_offset(&[3u8; 4], 0, 4)?;
+            buffer.write_page(page)?;
+
+            buffer.remove_file("remove_test.db")?;
+            // 页面已被移出缓冲区，且文件已经关闭，不能再通过缓冲区访问
+            match buffer.get_page("remove_test.db", 1) {
+                Err(Error::FileNotFound) => (),
+                _ => panic!("expected FileNotFound")
+            }
+            match buffer.remove_file("remove_test.db") {
+                Err(Error::FileNotFound) => (),
+                _ => panic!("expected FileNotFound")
+            }
+
+            // 缓存的修改在移除前已经写回
+            buffer.add_file(Path::new("remove_test.db"))?;
+            assert_eq!(buffer.get_page("remove_test.db", 1)?.get_ptr_from_offset(0, 4), &[3u8; 4]);
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats() -> Result<(), Error> {
+        let files = ["stats_metadata.db", "stats_test.db"];
+        rm_files(&files);
+
+        let buffers: Vec<Box<dyn Buffer>> = vec![
+            Box::new(LRUBuffer::new(4, "stats_metadata.db".to_string())?),
+            Box::new(ClockBuffer::new(4, "stats_metadata.db".to_string())?),
+            Box::new(FIFOBuffer::new(4, "stats_metadata.db".to_string())?),
+            Box::new(LFUBuffer::new(4, "stats_metadata.db".to_string())?),
+        ];
+        for mut buffer in buffers {
+            rm_files(&files[1..]);
+            buffer.add_file(Path::new("stats_test.db"))?;
+            buffer.fill_up_to("stats_test.db", 10)?;
+            buffer.reset_stats();
+
+            // 同一页读两次，一次缺页一次命中
+            buffer.get_page("stats_test.db", 1)?;
+            buffer.get_page("stats_test.db", 1)?;
+            assert_eq!(buffer.stats(), BufferStats { hits: 1, misses: 1, evictions: 0, writes: 0 });
+
+            // 缓冲区已满后再载入新页面需要淘汰
+            let page = buffer.get_page("stats_test.db", 1)?;
+            buffer.write_page(page)?;
+            for i in 2..=5 {
+                buffer.get_page("stats_test.db", i)?;
+            }
+            let stats = buffer.stats();
+            assert_eq!(stats.misses, 5);
+            assert_eq!(stats.evictions, 1);
+
+            buffer.reset_stats();
+            assert_eq!(buffer.stats(), BufferStats::default());
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_page_matches_file_name() -> Result<(), Error> {
+        let files = ["wfile_metadata.db", "wfile_a.db", "wfile_b.db"];
+        rm_files(&files);
+
+        let buffers: Vec<Box<dyn Buffer>> = vec![
+            Box::new(LRUBuffer::new(4, "wfile_metadata.db".to_string())?),
+            Box::new(ClockBuffer::new(4, "wfile_metadata.db".to_string())?),
+            Box::new(FIFOBuffer::new(4, "wfile_metadata.db".to_string())?),
+            Box::new(LFUBuffer::new(4, "wfile_metadata.db".to_string())?),
+        ];
+        for mut buffer in buffers {
+            rm_files(&files[1..]);
+            for file_name in &files[1..] {
+                buffer.add_file(Path::new(file_name))?;
+                buffer.fill_up_to(file_name, 4)?;
+            }
+            // 两个文件的第 1 页同时在缓冲区中，写入一个不能覆盖另一个未写回的修改
+            let mut page = buffer.get_page("wfile_a.db", 1)?;
+            page.write_bytes_at_offset(&[7u8; 4], 0, 4)?;
+            buffer.write_page(page)?;
+            let mut page = buffer.get_page("wfile_b.db", 1)?;
+            page.write_bytes_at_offset(&[3u8; 4], 0, 4)?;
+            buffer.write_page(page)?;
+
+            assert_eq!(buffer.get_page("wfile_a.db", 1)?.get_ptr_from_offset(0, 4), &[7u8; 4]);
+            assert_eq!(buffer.get_page("wfile_b.db", 1)?.get_ptr_from_offset(0, 4), &[3u8; 4]);
+            buffer.flush_all()?;
+            assert_eq!(fs::read("wfile_a.db")?[NON_DATA_PAGE * PAGE_SIZE], 7);
+            assert_eq!(fs::read("wfile_b.db")?[NON_DATA_PAGE * PAGE_SIZE], 3);
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_flush_order() -> Result<(), Error> {
+        let files = ["order_metadata.db", "order_a.db", "order_b.db"];
+        rm_files(&files);
+
+        let buffers: Vec<Box<dyn Buffer>> = vec![
+            Box::new(LRUBuffer::new(8, "order_metadata.db".to_string())?),
+            Box::new(ClockBuffer::new(8, "order_metadata.db".to_string())?),
+            Box::new(FIFOBuffer::new(8, "order_metadata.db".to_string())?),
+            Box::new(LFUBuffer::new(8, "order_metadata.db".to_string())?),
+        ];
+        for mut buffer in buffers {
+            rm_files(&files[1..]);
+            for file_name in &files[1..] {
+                buffer.add_file(Path::new(file_name))?;
+                buffer.fill_up_to(file_name, 10)?;
+            }
+            // 页面开头是页号，随后一个字节标明所属的文件
+            let writes = [("order_b.db", 5), ("order_a.db", 9), ("order_b.db", 2), ("order_a.db", 1), ("order_b.db", 7), ("order_a.db", 4)];
+            for (file_name, page_num) in writes.iter() {
+                let mut page = buffer.get_page(file_name, *page_num)?;
+                page.write_bytes_at_offset(&[*page_num as u8; 4], 0, 4)?;
+                page.write_bytes_at_offset(&file_name.as_bytes()[6..7], 4, 1)?;
+                buffer.write_page(page)?;
+            }
+
+            // 记下 flush_all 真正写回磁盘的顺序：按文件分组，同一文件按页号递增
+            WRITE_LOG.with(|log| *log.borrow_mut() = Some(Vec::new()));
+            buffer.flush_all()?;
+            let log = WRITE_LOG.with(|log| log.borrow_mut().take()).unwrap();
+            let order: Vec<(u8, usize)> = log.iter().map(|(page_num, data)| (data[4], *page_num)).collect();
+            assert_eq!(order, vec![
+                (b'a', 1), (b'a', 4), (b'a', 9),
+                (b'b', 2), (b'b', 5), (b'b', 7),
+            ]);
+
+            assert_eq!(buffer.stats().writes, writes.len());
+            for (file_name, page_num) in writes.iter() {
+                let data = fs::read(file_name)?;
+                assert_eq!(data[(NON_DATA_PAGE + page_num - 1) * PAGE_SIZE], *page_num as u8);
+            }
+            for file_name in &files[1..] {
+                buffer.remove_file(file_name)?;
+            }
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_buffer_size() -> Result<(), Error> {
+        let files = ["bsize_metadata.db", "bsize_test.db"];
+        rm_files(&files);
+
+        assert!(matches!(LRUBuffer::new(0, "bsize_metadata.db".to_string()), Err(Error::InvalidBufferSize)));
+        assert!(matches!(ClockBuffer::new(0, "bsize_metadata.db".to_string()), Err(Error::InvalidBufferSize)));
+        assert!(matches!(FIFOBuffer::new(0, "bsize_metadata.db".to_string()), Err(Error::InvalidBufferSize)));
+        assert!(matches!(LFUBuffer::new(0, "bsize_metadata.db".to_string()), Err(Error::InvalidBufferSize)));
+        assert!(!Path::new("bsize_metadata.db").exists());
+
+        // 最小的缓冲区每次载入新页都要换出旧页
+        let buffers: Vec<Box<dyn Buffer>> = vec![
+            Box::new(LRUBuffer::new(MIN_BUFFER_SIZE, "bsize_metadata.db".to_string())?),
+            Box::new(ClockBuffer::new(MIN_BUFFER_SIZE, "bsize_metadata.db".to_string())?),
+            Box::new(FIFOBuffer::new(MIN_BUFFER_SIZE, "bsize_metadata.db".to_string())?),
+            Box::new(LFUBuffer::new(MIN_BUFFER_SIZE, "bsize_metadata.db".to_string())?),
+        ];
+        for mut buffer in buffers {
+            rm_files(&files[1..]);
+            buffer.add_file(Path::new("bsize_test.db"))?;
+            buffer.fill_up_to("bsize_test.db", 4)?;
+            let mut page = buffer.get_page("bsize_test.db", 1)?;
+            page.write_bytes_at_offset(&[5u8; 4], 0, 4)?;
+            buffer.write_page(page)?;
+            buffer.get_page("bsize_test.db", 2)?;
+            assert_eq!(buffer.get_page("bsize_test.db", 1)?.get_ptr_from_offset(0, 4), &[5u8; 4]);
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_custom_page_size() -> Result<(), Error> {
+        let files = ["small_metadata.db", "small_test.db"];
+        rm_files(&files);
+
+        // 页大小不足以容纳文件头
+        match LRUBuffer::with_page_size(4, "small_metadata.db".to_string(), 64) {
+            Err(Error::InvalidPageSize) => (),
+            _ => panic!("expected InvalidPageSize")
+        }
+
+        let buffers: Vec<Box<dyn Buffer>> = vec![
+            Box::new(LRUBuffer::with_page_size(4, "small_metadata.db".to_string(), 512)?),
+            Box::new(ClockBuffer::with_page_size(4, "small_metadata.db".to_string(), 512)?),
+            Box::new(FIFOBuffer::with_page_size(4, "small_metadata.db".to_string(), 512)?),
+            Box::new(LFUBuffer::with_page_size(4, "small_metadata.db".to_string(), 512)?),
+        ];
+        for mut buffer in buffers {
+            rm_files(&files[1..]);
+            assert_eq!(buffer.get_page_size(), 512);
+            buffer.add_file(Path::new("small_test.db"))?;
+            buffer.fill_up_to("small_test.db", 8)?;
+            assert_eq!(fs::metadata("small_test.db")?.len(), 12 * 512);
+
+            let mut page = buffer.get_page("small_test.db", 3)?;
+            assert_eq!(page.size(), 512);
+            page.write_bytes_at_offset(&[7u8; 8], 504, 8)?;
+            buffer.write_page(page)?;
+            buffer.flush_all()?;
+
+            // 重新打开文件，数据按 512 字节的页读回
+            buffer.remove_file("small_test.db")?;
+            buffer.add_file(Path::new("small_test.db"))?;
+            let page = buffer.get_page("small_test.db", 3)?;
+            assert_eq!(page.get_ptr_from_offset(504, 8), &[7u8; 8]);
+            assert_eq!(buffer.get_page("small_test.db", 4)?.get_ptr_from_offset(0, 8), &[0u8; 8]);
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_flush_on_drop() -> Result<(), Error> {
+        let files = ["drop_metadata.db", "drop_test.db"];
+        rm_files(&files);
+
+        for kind in 0..4 {
+            rm_files(&files[1..]);
+            let open = |kind: usize| -> Result<Box<dyn Buffer>, Error> {
+                let mut buffer: Box<dyn Buffer> = match kind {
+                    0 => Box::new(LRUBuffer::new(4, "drop_metadata.db".to_string())?),
+                    1 => Box::new(ClockBuffer::new(4, "drop_metadata.db".to_string())?),
+                    2 => Box::new(FIFOBuffer::new(4, "drop_metadata.db".to_string())?),
+                    _ => Box::new(LFUBuffer::new(4, "drop_metadata.db".to_string())?),
+                };
+                buffer.add_file(Path::new("drop_test.db"))?;
+                buffer.fill_up_to("drop_test.db", 10)?;
+                Ok(buffer)
+            };
+
+            // 不显式刷新，析构时写回
+            let mut buffer = open(kind)?;
+            let mut page = buffer.get_page("drop_test.db", 2)?;
+            page.write_bytes_at_offset(&[9u8; 8], 40, 8)?;
+            buffer.write_page(page)?;
+            drop(buffer);
+
+            let mut buffer = open(kind)?;
+            assert_eq!(buffer.get_page("drop_test.db", 2)?.get_ptr_from_offset(40, 8), &[9u8; 8]);
+
+            // 关闭写回后修改被丢弃
+            let mut page = buffer.get_page("drop_test.db", 3)?;
+            page.write_bytes_at_offset(&[9u8; 8], 40, 8)?;
+            buffer.write_page(page)?;
+            buffer.no_sync_on_drop();
+            drop(buffer);
+
+            let mut buffer = open(kind)?;
+            assert_eq!(buffer.get_page("drop_test.db", 3)?.get_ptr_from_offset(40, 8), &[0u8; 8]);
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    fn open_wal_buffer(kind: usize) -> Result<Box<dyn Buffer>, Error> {
+        let mut buffer: Box<dyn Buffer> = match kind {
+            0 => Box::new(LRUBuffer::new(4, "wal_metadata.db".to_string())?),
+            1 => Box::new(ClockBuffer::new(4, "wal_metadata.db".to_string())?),
+            2 => Box::new(FIFOBuffer::new(4, "wal_metadata.db".to_string())?),
+            _ => Box::new(LFUBuffer::new(4, "wal_metadata.db".to_string())?),
+        };
+        buffer.open_wal(Path::new("wal_test.wal"))?;
+        buffer.add_file(Path::new("wal_test.db"))?;
+        buffer.fill_up_to("wal_test.db", 10)?;
+        Ok(buffer)
+    }
+
+    #[test]
+    fn test_wal_recover() -> Result<(), Error> {
+        let files = ["wal_metadata.db", "wal_test.db", "wal_test.wal"];
+        rm_files(&files);
+
+        for kind in 0..4 {
+            rm_files(&files[1..]);
+            let mut buffer = open_wal_buffer(kind)?;
+            let mut page = buffer.get_page("wal_test.db", 2)?;
+            page.write_bytes_at_offset(&[5u8; 8], 100, 8)?;
+            buffer.write_page(page)?;
+            // 模拟崩溃，页面没有刷新到数据文件
+            buffer.no_sync_on_drop();
+            drop(buffer);
+
+            // 崩溃时写了一半的记录
+            let mut wal = fs::OpenOptions::new().append(true).open("wal_test.wal")?;
+            wal.write_all(&[0, 0, 0, 11, b'w'])?;
+            drop(wal);
+
+            let mut buffer = open_wal_buffer(kind)?;
+            assert_eq!(buffer.get_page("wal_test.db", 2)?.get_ptr_from_offset(100, 8), &[0u8; 8]);
+            drop(buffer);
+
+            let mut buffer = open_wal_buffer(kind)?;
+            buffer.recover()?;
+            assert_eq!(buffer.get_page("wal_test.db", 2)?.get_ptr_from_offset(100, 8), &[5u8; 8]);
+
+            // 检查点之后日志被清空，数据已经在数据文件中
+            buffer.checkpoint()?;
+            assert_eq!(fs::metadata("wal_test.wal")?.len(), 0);
+            drop(buffer);
+            let mut buffer = open_wal_buffer(kind)?;
+            assert_eq!(buffer.get_page("wal_test.db", 2)?.get_ptr_from_offset(100, 8), &[5u8; 8]);
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_file() -> Result<(), Error> {
+        let files = ["verify_metadata.db", "verify_test.db"];
+        rm_files(&files);
+
+        let buffers: Vec<Box<dyn Buffer>> = vec![
+            Box::new(LRUBuffer::new(4, "verify_metadata.db".to_string())?),
+            Box::new(ClockBuffer::new(4, "verify_metadata.db".to_string())?),
+            Box::new(FIFOBuffer::new(4, "verify_metadata.db".to_string())?),
+            Box::new(LFUBuffer::new(4, "verify_metadata.db".to_string())?),
+        ];
+        for mut buffer in buffers {
+            rm_files(&files[1..]);
+            buffer.add_file(Path::new("verify_test.db"))?;
+            buffer.fill_up_to("verify_test.db", 6)?;
+            // 第 6 页保持全零
+            for page_num in 1..=5 {
+                buffer.write_page(Page::new([page_num as u8; PAGE_SIZE], "verify_test.db", page_num))?;
+            }
+            // 脏页先写回再检查
+            assert!(buffer.verify_file("verify_test.db")?.is_empty());
+
+            // 直接修改磁盘上第 2 页和第 4 页的内容
+            let mut file = fs::OpenOptions::new().write(true).open("verify_test.db")?;
+            for page_num in [2usize, 4] {
+                file.seek(SeekFrom::Start(((NON_DATA_PAGE + page_num - 1) * PAGE_SIZE + 10) as u64))?;
+                file.write_all(&[0xffu8; 3])?;
+            }
+            file.sync_all()?;
+            drop(file);
+            assert_eq!(buffer.verify_file("verify_test.db")?, vec![2, 4]);
+
+            // 重新写入后恢复正常
+            buffer.remove_file("verify_test.db")?;
+            buffer.add_file(Path::new("verify_test.db"))?;
+            buffer.write_page(Page::new([2u8; PAGE_SIZE], "verify_test.db", 2))?;
+            assert_eq!(buffer.verify_file("verify_test.db")?, vec![4]);
+
+            match buffer.verify_file("no_such_file.db") {
+                Err(Error::FileNotFound) => (),
+                _ => panic!("expected FileNotFound")
+            }
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+}
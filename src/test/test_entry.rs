@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod test {
+    use crate::util::error::Error;
+    use crate::table::entry::Entry;
+    use crate::table::field::{Field, FieldType, FieldValue};
+    use crate::table::table_item::Table;
+    use crate::data_item::buffer::{Buffer, LRUBuffer};
+    use crate::util::test_lib::rm_files;
+
+    #[test]
+    fn test_to_json() -> Result<(), Error> {
+        let fields = vec![
+            Field::create_field("id".to_string(), FieldType::INT32)?,
+            Field::create_nullable_field("name".to_string(), FieldType::VARCHAR40)?,
+        ];
+        let entry = Entry { data: vec![FieldValue::INT32(1), FieldValue::VARCHAR40("abc".to_string())] };
+        assert_eq!(entry.to_json(&fields), r#"{"id":1,"name":"abc"}"#);
+
+        let entries = vec![
+            entry,
+            Entry { data: vec![FieldValue::INT32(2), FieldValue::VARCHAR40("a\"b\\\n".to_string())] },
+            Entry { data: vec![FieldValue::INT32(3), FieldValue::Null] },
+        ];
+        assert_eq!(
+            Entry::to_json_array(&entries, &fields),
+            r#"[{"id":1,"name":"abc"},{"id":2,"name":"a\"b\\\n"},{"id":3,"name":null}]"#
+        );
+        assert_eq!(Entry::to_json_array(&[], &fields), "[]");
+        Ok(())
+    }
+
+    #[test]
+    fn test_eq() {
+        let entry = Entry { data: vec![FieldValue::INT32(1), FieldValue::FLOAT64(0.5), FieldValue::VARCHAR40("abc".to_string()), FieldValue::Null] };
+        let expected = Entry { data: vec![FieldValue::INT32(1), FieldValue::FLOAT64(0.5), FieldValue::VARCHAR40("abc".to_string()), FieldValue::Null] };
+        assert_eq!(entry, expected);
+        assert_ne!(entry, Entry { data: vec![FieldValue::INT32(1)] });
+
+        // 不同类型的值即使数值相同也不相等
+        assert_ne!(FieldValue::INT32(1), FieldValue::INT64(1));
+        assert_ne!(FieldValue::Null, FieldValue::BOOL(false));
+
+        // 浮点数按位比较
+        assert_eq!(FieldValue::FLOAT64(f64::NAN), FieldValue::FLOAT64(f64::NAN));
+        assert_eq!(FieldValue::FLOAT32(f32::NAN), FieldValue::FLOAT32(f32::NAN));
+        assert_ne!(FieldValue::FLOAT64(0.0), FieldValue::FLOAT64(-0.0));
+    }
+
+    #[test]
+    fn test_entry_builder() -> Result<(), Error> {
+        let files = ["eb_table", "eb_metadata.db"];
+        rm_files(&files);
+
+        let mut buffer: Box<dyn Buffer> = Box::new(LRUBuffer::new(4, "eb_metadata.db".to_string())?);
+        let mut table = Table::new("eb_table".to_string(), &mut buffer)?;
+        table.add_fields(vec![
+            Field::create_field("id".to_string(), FieldType::INT32)?,
+            Field::create_field("name".to_string(), FieldType::VARCHAR40)?,
+            Field::create_nullable_field("score".to_string(), FieldType::FLOAT64)?,
+        ]);
+
+        let entry = table.new_entry().set_int(0, 1).set_varchar(1, "abc").build()?;
+        assert_eq!(entry, Entry { data: vec![FieldValue::INT32(1), FieldValue::VARCHAR40("abc".to_string()), FieldValue::Null] });
+
+        // 类型不符的字段在 build 时报错
+        match table.new_entry().set_varchar(0, "1").set_varchar(1, "abc").build() {
+            Err(Error::FieldValueNotCompatible) => (),
+            _ => panic!("expected FieldValueNotCompatible")
+        }
+        match table.new_entry().set_int(0, 1).set_varchar(1, &"x".repeat(41)).build() {
+            Err(Error::VarcharTooLong) => (),
+            _ => panic!("expected VarcharTooLong")
+        }
+        match table.new_entry().set_int(3, 1).build() {
+            Err(Error::FieldNotFound) => (),
+            _ => panic!("expected FieldNotFound")
+        }
+        // 不允许为空的字段必须设置
+        match table.new_entry().set_int(0, 1).build() {
+            Err(Error::FieldValueNotCompatible) => (),
+            _ => panic!("expected FieldValueNotCompatible")
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+}
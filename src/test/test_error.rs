@@ -0,0 +1,113 @@
+#[cfg(test)]
+mod test {
+    use std::io::ErrorKind;
+    use crate::util::error::Error;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Error::KeyNotFound.to_string(), "键不存在");
+        assert_eq!(Error::TryFromSliceError("page").to_string(), "字节数组转换失败: page");
+    }
+
+    #[test]
+    fn test_io_source() {
+        let err: Error = std::io::Error::new(ErrorKind::NotFound, "missing").into();
+        match &err {
+            Error::Io(e) => assert_eq!(e.kind(), ErrorKind::NotFound),
+            _ => panic!("expected Io")
+        }
+        assert!(std::error::Error::source(&err).is_some());
+        assert!(std::error::Error::source(&Error::KeyNotFound).is_none());
+
+        let boxed: Box<dyn std::error::Error> = Box::new(err);
+        assert!(boxed.to_string().contains("missing"));
+    }
+
+    /// 没有通配分支，新增变体时这里不能编译，提醒同时补上 Display 和这个测试
+    fn variant_index(err: &Error) -> usize {
+        match err {
+            Error::KeyNotFound => 0,
+            Error::KeyAlreadyExists => 1,
+            Error::TableAlreadyExists => 2,
+            Error::UnexpectedError => 3,
+            Error::TryFromSliceError(_) => 4,
+            Error::UTF8Error => 5,
+            Error::NotInBufferError => 6,
+            Error::TableNotFound => 7,
+            Error::FileNotFound => 8,
+            Error::PageNumOutOfSize => 9,
+            Error::FieldValueTooLong => 10,
+            Error::FieldValueNotCompatible => 11,
+            Error::IndexWithoutBTree => 12,
+            Error::VarcharTooLong => 13,
+            Error::IndexExist => 14,
+            Error::KeySizeOutOfPage => 15,
+            Error::PrimaryKeyImmutable => 16,
+            Error::NoEvictablePage => 17,
+            Error::InvalidPageNum => 18,
+            Error::InvalidPageSize => 19,
+            Error::Io(_) => 20,
+            Error::SqlParseError(_) => 21,
+            Error::UnsupportedStatement => 22,
+            Error::UnsupportedType => 23,
+            Error::FieldNotFound => 24,
+            Error::CsvFormat(_) => 25,
+            Error::KeysNotSorted => 26,
+            Error::ValueTooLarge => 27,
+            Error::CorruptNode => 28,
+            Error::KeyTooLong => 29,
+            Error::TransactionState => 30,
+            Error::InvalidBufferSize => 31,
+            Error::CasMismatch => 32,
+        }
+    }
+
+    #[test]
+    fn test_every_variant() {
+        let errors = vec![
+            Error::KeyNotFound,
+            Error::KeyAlreadyExists,
+            Error::TableAlreadyExists,
+            Error::UnexpectedError,
+            Error::TryFromSliceError("page"),
+            Error::UTF8Error,
+            Error::NotInBufferError,
+            Error::TableNotFound,
+            Error::FileNotFound,
+            Error::PageNumOutOfSize,
+            Error::FieldValueTooLong,
+            Error::FieldValueNotCompatible,
+            Error::IndexWithoutBTree,
+            Error::VarcharTooLong,
+            Error::IndexExist,
+            Error::KeySizeOutOfPage,
+            Error::PrimaryKeyImmutable,
+            Error::NoEvictablePage,
+            Error::InvalidPageNum,
+            Error::InvalidPageSize,
+            Error::Io(std::io::Error::new(ErrorKind::NotFound, "io")),
+            Error::SqlParseError("sql".to_string()),
+            Error::UnsupportedStatement,
+            Error::UnsupportedType,
+            Error::FieldNotFound,
+            Error::CsvFormat(1),
+            Error::KeysNotSorted,
+            Error::ValueTooLarge,
+            Error::CorruptNode,
+            Error::KeyTooLong,
+            Error::TransactionState,
+            Error::InvalidBufferSize,
+            Error::CasMismatch,
+        ];
+        let mut indexes: Vec<usize> = errors.iter().map(variant_index).collect();
+        indexes.dedup();
+        assert_eq!(indexes, (0..33).collect::<Vec<usize>>());
+
+        // 每个变体都有各自的提示信息
+        let mut messages: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+        assert!(messages.iter().all(|msg| !msg.is_empty()));
+        messages.sort();
+        messages.dedup();
+        assert_eq!(messages.len(), errors.len());
+    }
+}
@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod test {
+    use crate::util::test_lib::rm_files;
+    use crate::util::error::Error;
+    use crate::table::table_manager::TableManager;
+    use crate::table::executor::execute_sql;
+    use crate::table::field::{FieldType, FieldValue};
+    use crate::table::entry::Entry;
+    use crate::data_item::buffer::LRUBuffer;
+
+    fn gen_manager() -> Result<TableManager, Error> {
+        Ok(TableManager::new(Box::new(LRUBuffer::new(4, "metadata.db".to_string())?)))
+    }
+
+    #[test]
+    fn test_create_table() -> Result<(), Error> {
+        let files = ["metadata.db", "metadata.db.catalog", "t", "t_id.idx"];
+        rm_files(&files);
+
+        let mut table_manager = gen_manager()?;
+        execute_sql("CREATE TABLE t (id INT, name VARCHAR(40))", &mut table_manager)?;
+
+        let table = table_manager.table_cache.get("t").unwrap();
+        assert_eq!(table.fields.len(), 2);
+        assert_eq!(table.fields[0].field_name, "id".to_string());
+        assert!(matches!(table.fields[0].field_type, FieldType::INT32));
+        assert_eq!(table.fields[1].field_name, "name".to_string());
+        assert!(matches!(table.fields[1].field_type, FieldType::VARCHAR40));
+
+        match execute_sql("CREATE TABLE u (id INT, d DATE)", &mut table_manager) {
+            Err(Error::UnsupportedType) => (),
+            _ => panic!("expected UnsupportedType")
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert() -> Result<(), Error> {
+        let files = ["metadata.db", "metadata.db.catalog", "t_insert", "t_insert_ins_id.idx"];
+        rm_files(&files);
+
+        let mut table_manager = gen_manager()?;
+        execute_sql("CREATE TABLE t_insert (ins_id INT, name VARCHAR(40))", &mut table_manager)?;
+        execute_sql("INSERT INTO t_insert VALUES (1, 'abc')", &mut table_manager)?;
+        execute_sql("INSERT INTO t_insert VALUES (2, 'de'), (-3, 'f')", &mut table_manager)?;
+        execute_sql("INSERT INTO t_insert (ins_id) VALUES (4)", &mut table_manager)?;
+
+        let rows = table_manager.read_full_table("t_insert".to_string())?;
+        assert_eq!(rows.len(), 4);
+        let row = table_manager.search("t_insert".to_string(), 0, FieldValue::INT32(1))?;
+        match &row.data[1] {
+            FieldValue::VARCHAR40(name) => assert_eq!(name, "abc"),
+            _ => panic!("expected 'abc'")
+        }
+        table_manager.search("t_insert".to_string(), 0, FieldValue::INT32(-3))?;
+        let row = table_manager.search("t_insert".to_string(), 0, FieldValue::INT32(4))?;
+        assert!(matches!(row.data[1], FieldValue::Null));
+
+        match execute_sql("INSERT INTO t_insert VALUES ('x', 'y')", &mut table_manager) {
+            Err(Error::FieldValueNotCompatible) => (),
+            _ => panic!("expected FieldValueNotCompatible")
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_eq() -> Result<(), Error> {
+        let files = ["metadata.db", "metadata.db.catalog", "t_select", "t_select_sel_id.idx"];
+        rm_files(&files);
+
+        let mut table_manager = gen_manager()?;
+        execute_sql("CREATE TABLE t_select (sel_id INT, name VARCHAR(40), score INT)", &mut table_manager)?;
+        execute_sql("INSERT INTO t_select VALUES (1, 'a', 90), (2, 'b', 80), (3, 'c', 80)", &mut table_manager)?;
+
+        // 索引字段上的等值查询
+        let rows = execute_sql("SELECT name FROM t_select WHERE sel_id = 1", &mut table_manager)?;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].data.len(), 1);
+        match &rows[0].data[0] {
+            FieldValue::VARCHAR40(name) => assert_eq!(name, "a"),
+            _ => panic!("expected 'a'")
+        }
+
+        // 非索引字段退化为全表扫描后过滤
+        let rows = execute_sql("SELECT sel_id, score FROM t_select WHERE 80 = score", &mut table_manager)?;
+        assert_eq!(rows.len(), 2);
+        assert!(rows.iter().all(|row| matches!(row.data[1], FieldValue::INT32(80))));
+
+        assert_eq!(execute_sql("SELECT * FROM t_select WHERE sel_id = 4", &mut table_manager)?.len(), 0);
+        match execute_sql("SELECT * FROM t_select WHERE missing = 1", &mut table_manager) {
+            Err(Error::FieldNotFound) => (),
+            _ => panic!("expected FieldNotFound")
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_range() -> Result<(), Error> {
+        let files = ["metadata.db", "metadata.db.catalog", "t_range", "t_range_rng_id.idx", "t_range_rng_name.idx"];
+        rm_files(&files);
+
+        let mut table_manager = gen_manager()?;
+        execute_sql("CREATE TABLE t_range (rng_id INT, rng_name VARCHAR(40))", &mut table_manager)?;
+        table_manager.create_index("t_range".to_string(), 1)?;
+        for i in 0..12 {
+            execute_sql(format!("INSERT INTO t_range VALUES ({}, 'n{:02}')", i, i).as_str(), &mut table_manager)?;
+        }
+
+        let ids = |rows: Vec<Entry>| -> Vec<i32> {
+            let mut ids: Vec<i32> = rows.iter().map(|row| match row.data[0] {
+                FieldValue::INT32(id) => id,
+                _ => panic!("expected INT32")
+            }).collect();
+            ids.sort();
+            ids
+        };
+
+        // 整数主键上的范围通过索引查询，键按数值而不是十进制字符串排序
+        let rows = execute_sql("SELECT rng_id FROM t_range WHERE rng_id >= 2 AND rng_id <= 5", &mut table_manager)?;
+        assert_eq!(ids(rows), vec![2, 3, 4, 5]);
+        let rows = execute_sql("SELECT rng_id FROM t_range WHERE rng_id > 9", &mut table_manager)?;
+        assert_eq!(ids(rows), vec![10, 11]);
+
+        // 字符串字段上的范围通过索引查询，严格不等去掉边界
+        let rows = execute_sql("SELECT * FROM t_range WHERE rng_name > 'n03' AND rng_name < 'n07'", &mut table_manager)?;
+        assert_eq!(ids(rows), vec![4, 5, 6]);
+        let rows = execute_sql("SELECT * FROM t_range WHERE rng_name BETWEEN 'n09' AND 'n10'", &mut table_manager)?;
+        assert_eq!(ids(rows), vec![9, 10]);
+
+        rm_files(&files);
+        Ok(())
+    }
+}
@@ -2,7 +2,13 @@
 #[cfg(test)]
 mod test {
     use crate::util::error::Error;
-    use crate::util::test_lib::rm_test_file;
+    use crate::util::test_lib::{rm_test_file, rm_files};
+    use crate::data_item::buffer::{Buffer, LRUBuffer};
+    use crate::index::node::KEY_SIZE;
+    use crate::page::pager::Pager;
+    use crate::table::entry::Entry;
+    use crate::table::field::{Field, FieldType, FieldValue};
+    use std::path::Path;
 
     #[test]
     fn test_create_field() -> Result<(), Error> {
@@ -37,4 +43,176 @@ mod test {
         rm_test_file();
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_insert_varchar_too_long() -> Result<(), Error> {
+        let files = ["varchar_metadata.db", "varchar_name.idx", "varchar_table"];
+        rm_files(&files);
+
+        let mut buffer: Box<dyn Buffer> = Box::new(LRUBuffer::new(4, "varchar_metadata.db".to_string())?);
+        buffer.add_file(Path::new("varchar_table"))?;
+        let mut pager = Pager::new("varchar_table".to_string(), 10, &mut buffer)?;
+        let mut field = Field::create_field("varchar_name".to_string(), FieldType::VARCHAR40)?;
+        field.create_btree("varchar_name.idx".to_string(), KEY_SIZE, &mut buffer)?;
+
+        let entry = Entry {
+            data: vec![FieldValue::VARCHAR40("x".repeat(50))]
+        };
+        match field.insert(0, entry, &mut pager, &mut buffer) {
+            Err(Error::VarcharTooLong) => (),
+            _ => panic!("expected VarcharTooLong")
+        }
+
+        // 超过索引键宽的键不写入行数据
+        let entry = Entry {
+            data: vec![FieldValue::VARCHAR40("x".repeat(KEY_SIZE + 1))]
+        };
+        match field.insert(0, entry, &mut pager, &mut buffer) {
+            Err(Error::KeyTooLong) => (),
+            _ => panic!("expected KeyTooLong")
+        }
+        assert_eq!(pager.used_size(), 0);
+
+        // 读出时只去掉填充的 \0，保留字符串中的空格
+        let entry = Entry {
+            data: vec![FieldValue::VARCHAR40(" hello ".to_string())]
+        };
+        field.insert(0, entry, &mut pager, &mut buffer)?;
+        let row = field.search(FieldValue::VARCHAR40(" hello ".to_string()), &mut buffer, 41, &pager)?;
+        match field.parse_self(row.as_slice(), 0, &pager, &mut buffer)? {
+            (FieldValue::VARCHAR40(data), 41) => assert_eq!(data, " hello "),
+            _ => panic!("expected VARCHAR40")
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete() -> Result<(), Error> {
+        let files = ["fdel_metadata.db", "fdel_id.idx", "fdel_table"];
+        rm_files(&files);
+
+        let mut buffer: Box<dyn Buffer> = Box::new(LRUBuffer::new(4, "fdel_metadata.db".to_string())?);
+        buffer.add_file(Path::new("fdel_table"))?;
+        let mut pager = Pager::new("fdel_table".to_string(), 10, &mut buffer)?;
+        let mut field = Field::create_field("fdel_id".to_string(), FieldType::INT32)?;
+
+        // 没有索引的字段不能删除键
+        match field.delete(FieldValue::INT32(1), &mut buffer, &mut pager) {
+            Err(Error::IndexWithoutBTree) => (),
+            _ => panic!("expected IndexWithoutBTree")
+        }
+
+        field.create_btree("fdel_id.idx".to_string(), KEY_SIZE, &mut buffer)?;
+        for i in 0..3 {
+            let entry = Entry {
+                data: vec![FieldValue::INT32(i)]
+            };
+            field.insert(0, entry, &mut pager, &mut buffer)?;
+        }
+        field.search(FieldValue::INT32(1), &mut buffer, 5, &pager)?;
+
+        field.delete(FieldValue::INT32(1), &mut buffer, &mut pager)?;
+        match field.search(FieldValue::INT32(1), &mut buffer, 5, &pager) {
+            Err(Error::KeyNotFound) => (),
+            _ => panic!("expected KeyNotFound")
+        }
+        // 其他键不受影响
+        field.search(FieldValue::INT32(0), &mut buffer, 5, &pager)?;
+        field.search(FieldValue::INT32(2), &mut buffer, 5, &pager)?;
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_offset() -> Result<(), Error> {
+        let files = ["foff_metadata.db", "foff_id.idx", "foff_table"];
+        rm_files(&files);
+
+        let mut buffer: Box<dyn Buffer> = Box::new(LRUBuffer::new(4, "foff_metadata.db".to_string())?);
+        buffer.add_file(Path::new("foff_table"))?;
+        let mut pager = Pager::new("foff_table".to_string(), 10, &mut buffer)?;
+        let mut field = Field::create_field("foff_id".to_string(), FieldType::INT32)?;
+        field.create_btree("foff_id.idx".to_string(), KEY_SIZE, &mut buffer)?;
+
+        let mut offsets = Vec::<usize>::new();
+        for i in 0..3 {
+            let entry = Entry {
+                data: vec![FieldValue::INT32(i)]
+            };
+            let offset = pager.insert_value(entry.to_bytes().as_slice(), &mut buffer)?;
+            field.insert_key(&entry.data[0], offset, &mut buffer)?;
+            offsets.push(offset);
+        }
+        for (i, offset) in offsets.iter().enumerate() {
+            assert_eq!(field.search_offset(FieldValue::INT32(i as i32), &mut buffer, &pager)?, *offset);
+        }
+        match field.search_offset(FieldValue::INT32(3), &mut buffer, &pager) {
+            Err(Error::KeyNotFound) => (),
+            _ => panic!("expected KeyNotFound")
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_all() -> Result<(), Error> {
+        let files = ["fmulti_metadata.db", "fmulti_status.idx", "fmulti_table"];
+        rm_files(&files);
+
+        let mut buffer: Box<dyn Buffer> = Box::new(LRUBuffer::new(4, "fmulti_metadata.db".to_string())?);
+        buffer.add_file(Path::new("fmulti_table"))?;
+        let mut pager = Pager::new("fmulti_table".to_string(), 10, &mut buffer)?;
+        let mut status = Field::create_field("fmulti_status".to_string(), FieldType::INT32)?;
+        status.create_btree("fmulti_status.idx".to_string(), KEY_SIZE, &mut buffer)?;
+        let id = Field::create_field("fmulti_id".to_string(), FieldType::INT32)?;
+
+        // 三行的状态相同，一行不同
+        for (i, s) in [1, 2, 1, 1].iter().enumerate() {
+            let entry = Entry {
+                data: vec![FieldValue::INT32(*s), FieldValue::INT32(i as i32)]
+            };
+            status.insert_multi(0, entry, &mut pager, &mut buffer)?;
+        }
+
+        let rows = status.search_all(FieldValue::INT32(1), &mut buffer, 10, &pager)?;
+        let ids: Vec<i32> = rows.iter().map(|row| match id.parse_self(row.as_slice(), 5, &pager, &mut buffer) {
+            Ok((FieldValue::INT32(data), _)) => data,
+            _ => panic!("expected INT32")
+        }).collect();
+        assert_eq!(ids, vec![0, 2, 3]);
+        assert_eq!(status.search_all(FieldValue::INT32(2), &mut buffer, 10, &pager)?.len(), 1);
+        match status.search_all(FieldValue::INT32(3), &mut buffer, 10, &pager) {
+            Err(Error::KeyNotFound) => (),
+            _ => panic!("expected KeyNotFound")
+        }
+
+        // 四行各 10 字节，加上最新的三项偏移量列表，旧的列表已被释放
+        assert_eq!(pager.used_size(), 4 * 10 + 4 + 3 * 8);
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(FieldValue::INT32(-7).to_string(), "-7");
+        assert_eq!(FieldValue::INT64(1 << 40).to_string(), "1099511627776");
+        // 整数值的浮点数保留一位小数
+        assert_eq!(FieldValue::FLOAT64(3.0).to_string(), "3.0");
+        assert_eq!(FieldValue::FLOAT64(-2.5).to_string(), "-2.5");
+        assert_eq!(FieldValue::FLOAT32(0.1).to_string(), "0.1");
+        assert_eq!(FieldValue::FLOAT32(3.0).to_string(), "3.0");
+        assert_eq!(FieldValue::FLOAT64(f64::INFINITY).to_string(), "inf");
+        assert_eq!(FieldValue::FLOAT64(f64::NAN).to_string(), "NaN");
+        // 定长字符串去掉末尾填充的 \0
+        assert_eq!(FieldValue::VARCHAR40("abc\0\0\0".to_string()).to_string(), "abc");
+        assert_eq!(FieldValue::TEXT("a b".to_string()).to_string(), "a b");
+        assert_eq!(FieldValue::BLOB(vec![0x00, 0xab, 0x10]).to_string(), "00ab10");
+        assert_eq!(FieldValue::BOOL(true).to_string(), "true");
+        assert_eq!(FieldValue::Null.to_string(), "NULL");
+    }
+}
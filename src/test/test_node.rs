@@ -3,8 +3,9 @@
 mod test_node {
     use std::convert::TryFrom;
 
-    use crate::index::node::{INTERNAL_NODE_HEADER_SIZE, KEY_SIZE, LEAF_NODE_HEADER_SIZE, Node, NodeSpec, VALUE_SIZE, MAX_SPACE_FOR_KEYS, MAX_SPACE_FOR_CHILDREN};
-    use crate::page::page_item::{PAGE_SIZE, PTR_SIZE};
+    use crate::index::key_value_pair::KeyValuePair;
+    use crate::index::node::{INTERNAL_NODE_HEADER_SIZE, KEY_SIZE, LEAF_NODE_HEADER_SIZE, Node, NodeSpec, NodeType, VALUE_SIZE, MAX_SPACE_FOR_KEYS, MAX_SPACE_FOR_CHILDREN};
+    use crate::page::page_item::{Page, PAGE_SIZE, PTR_SIZE};
     use crate::util::error::Error;
 
     #[test]
@@ -235,4 +236,105 @@ mod test_node {
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn corrupt_node_rejected() -> Result<(), Error> {
+        // 中间节点声称有 5000 个儿子
+        let mut page = [0x00; PAGE_SIZE];
+        page[1] = 0x01;
+        page[10..10 + PTR_SIZE].clone_from_slice(&5000usize.to_be_bytes());
+        match Node::try_from(NodeSpec { offset: 1, page_data: page }) {
+            Err(Error::CorruptNode) => (),
+            _ => panic!("expected CorruptNode")
+        }
+
+        // 叶子节点的键值对数超过叶子的容量
+        let mut page = [0x00; PAGE_SIZE];
+        page[1] = 0x02;
+        page[10..10 + PTR_SIZE].clone_from_slice(&11usize.to_be_bytes());
+        match Node::try_from(NodeSpec { offset: 1, page_data: page }) {
+            Err(Error::CorruptNode) => (),
+            _ => panic!("expected CorruptNode")
+        }
+
+        // 计数在范围内的节点可以正常读取
+        page[10..10 + PTR_SIZE].clone_from_slice(&10usize.to_be_bytes());
+        assert!(Node::try_from(NodeSpec { offset: 1, page_data: page }).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn node_to_page_works() -> Result<(), Error> {
+        let page = Page::new_phantom([0x00; PAGE_SIZE]);
+        let mut node = Node::new(NodeType::Leaf, 7, 3, true, page, KEY_SIZE)?;
+        node.add_key_value_pair(KeyValuePair::new("hello".to_string(), 4096))?;
+        node.add_key_value_pair(KeyValuePair::new("world".to_string(), 8192))?;
+
+        let page_data = <[u8; PAGE_SIZE]>::try_from(node)?;
+        let node = Node::try_from(NodeSpec { offset: 3, page_data })?;
+
+        assert!(node.node_type == NodeType::Leaf);
+        assert!(node.is_root);
+        assert_eq!(node.parent_offset, 7);
+        assert_eq!(node.offset, 3);
+        assert_eq!(node.key_size, KEY_SIZE);
+        let kv_pairs = node.get_key_value_pairs()?;
+        assert_eq!(kv_pairs.len(), 2);
+        assert_eq!((kv_pairs[0].key.as_str(), kv_pairs[0].value), ("hello", 4096));
+        assert_eq!((kv_pairs[1].key.as_str(), kv_pairs[1].value), ("world", 8192));
+
+        Ok(())
+    }
+
+    #[test]
+    fn remove_key_and_child_works() -> Result<(), Error> {
+        let page = Page::new_phantom([0x00; PAGE_SIZE]);
+        let mut node = Node::new(NodeType::Internal, 0, 1, true, page, KEY_SIZE)?;
+        let keys = vec!["b".to_string(), "d".to_string(), "f".to_string(), "h".to_string()];
+        node.set_keys_and_children(&keys, &[10, 20, 30, 40])?;
+
+        // 删除中间的键，后面的键和儿子前移
+        assert_eq!(node.remove_key_and_child("d")?, 20);
+        assert_eq!(node.get_keys()?, vec!["b".to_string(), "f".to_string(), "h".to_string()]);
+        assert_eq!(node.get_children()?, vec![10, 30, 40]);
+
+        // 原来的最后一个位置已被清空，重新加入后内容正确
+        node.add_key_and_left_child("d".to_string(), 20)?;
+        assert_eq!(node.get_keys()?, keys);
+        assert_eq!(node.get_children()?, vec![10, 20, 30, 40]);
+
+        assert!(matches!(node.remove_key_and_child("e"), Err(Error::KeyNotFound)));
+        assert_eq!(node.get_keys_len()?, 4);
+
+        let page = Page::new_phantom([0x00; PAGE_SIZE]);
+        let mut leaf = Node::new(NodeType::Leaf, 0, 1, true, page, KEY_SIZE)?;
+        assert!(matches!(leaf.remove_key_and_child("b"), Err(Error::UnexpectedError)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn remove_key_value_pair_works() -> Result<(), Error> {
+        let page = Page::new_phantom([0x00; PAGE_SIZE]);
+        let mut leaf = Node::new(NodeType::Leaf, 0, 1, true, page, KEY_SIZE)?;
+        leaf.add_key_value_pair(KeyValuePair::new("a".to_string(), 1))?;
+        leaf.add_key_value_pair(KeyValuePair::new("b".to_string(), 2))?;
+        leaf.add_key_value_pair(KeyValuePair::new("c".to_string(), 3))?;
+
+        // 删除第一个键值对，后两个依次前移
+        leaf.remove_key_value_pair("a")?;
+        assert_eq!(leaf.get_keys_len()?, 2);
+        let kv_pairs = leaf.get_key_value_pairs()?;
+        assert_eq!((kv_pairs[0].key.as_str(), kv_pairs[0].value), ("b", 2));
+        assert_eq!((kv_pairs[1].key.as_str(), kv_pairs[1].value), ("c", 3));
+        // 原来的最后一个位置已被清空
+        let tail = LEAF_NODE_HEADER_SIZE + 2 * (KEY_SIZE + VALUE_SIZE);
+        assert!(leaf.page.get_ptr_from_offset(tail, KEY_SIZE + VALUE_SIZE).iter().all(|b| *b == 0));
+
+        assert!(matches!(leaf.remove_key_value_pair("a"), Err(Error::KeyNotFound)));
+        assert_eq!(leaf.get_keys_len()?, 2);
+
+        Ok(())
+    }
+}
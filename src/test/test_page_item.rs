@@ -1,8 +1,91 @@
 #[cfg(test)]
 mod test_page_item {
+    use crate::page::page_item::{PAGE_SIZE, Page};
+    use crate::util::error::Error;
 
     #[test]
-    fn test_needed_here() {
-        // todo
+    fn test_read_write_width() -> Result<(), Error> {
+        let mut page = Page::new_phantom([0u8; PAGE_SIZE]);
+
+        page.write_u32_at(100, 0xdead_beef)?;
+        assert_eq!(page.read_u32_at(100)?, 0xdead_beef);
+        assert_eq!(page.get_ptr_from_offset(100, 4), &[0xde, 0xad, 0xbe, 0xef]);
+
+        page.write_u64_at(200, 0x0102_0304_0506_0708)?;
+        assert_eq!(page.read_u64_at(200)?, 0x0102_0304_0506_0708);
+        assert_eq!(page.get_value_from_offset(200)?, 0x0102_0304_0506_0708);
+        // 只读前 4 字节得到高位
+        assert_eq!(page.read_u32_at(200)?, 0x0102_0304);
+
+        // 写到页尾为止可以，越过页尾报错
+        page.write_u32_at(PAGE_SIZE - 4, 7)?;
+        assert_eq!(page.read_u32_at(PAGE_SIZE - 4)?, 7);
+        assert!(page.write_u64_at(PAGE_SIZE - 4, 7).is_err());
+        assert!(page.read_u64_at(PAGE_SIZE - 4).is_err());
+        assert!(page.read_u32_at(usize::MAX).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_write_page_bounds() -> Result<(), Error> {
+        let mut page = Page::new_phantom([0u8; PAGE_SIZE]);
+
+        // 页首和恰好到页尾的位置都能读写
+        page.write_u32_at(0, 0x1234_5678)?;
+        assert_eq!(page.read_u32_at(0)?, 0x1234_5678);
+        page.write_u32_at(PAGE_SIZE - 4, 0x8765_4321)?;
+        assert_eq!(page.read_u32_at(PAGE_SIZE - 4)?, 0x8765_4321);
+        page.write_u64_at(0, u64::MAX - 1)?;
+        assert_eq!(page.read_u64_at(0)?, u64::MAX - 1);
+        page.write_u64_at(PAGE_SIZE - 8, 0x0a0b_0c0d_0e0f_1011)?;
+        assert_eq!(page.read_u64_at(PAGE_SIZE - 8)?, 0x0a0b_0c0d_0e0f_1011);
+
+        // 越界的偏移返回错误而不是 panic，页中数据不变
+        let before = page.get_bytes().to_vec();
+        assert!(page.read_u32_at(PAGE_SIZE - 3).is_err());
+        assert!(page.write_u32_at(PAGE_SIZE - 3, 1).is_err());
+        assert!(page.read_u64_at(PAGE_SIZE - 7).is_err());
+        assert!(page.write_u64_at(PAGE_SIZE - 7, 1).is_err());
+        assert!(page.read_u32_at(PAGE_SIZE).is_err());
+        assert!(page.write_u64_at(usize::MAX - 4, 1).is_err());
+        assert_eq!(page.get_bytes(), &before[..]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_clear() -> Result<(), Error> {
+        let mut page = Page::new([0u8; PAGE_SIZE], "clear.db", 3);
+        assert!(page.is_empty());
+
+        page.write_u32_at(PAGE_SIZE - 4, 1)?;
+        assert!(!page.is_empty());
+        page.write_bytes_at_offset(&[1, 2, 3], 0, 3)?;
+
+        page.clear();
+        assert!(page.is_empty());
+        assert_eq!(page.size(), PAGE_SIZE);
+        assert_eq!(page.file_name, "clear.db");
+        assert_eq!(page.page_num, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_into() -> Result<(), Error> {
+        let mut page = Page::from_vec(vec![0u8; 512], "copy.db", 2);
+        page.write_bytes_at_offset(&[1, 2, 3, 4], 100, 4)?;
+        page.write_u32_at(508, 7)?;
+
+        let mut copy = page.copy_into("copy_new.db", 9);
+        assert_eq!(copy.get_bytes(), page.get_bytes());
+        assert_eq!(copy.file_name, "copy_new.db");
+        assert_eq!(copy.page_num, 9);
+        assert_eq!(page.file_name, "copy.db");
+        assert_eq!(page.page_num, 2);
+
+        // 两个页的数据互不影响
+        copy.clear();
+        assert!(copy.is_empty());
+        assert_eq!(page.get_ptr_from_offset(100, 4), &[1, 2, 3, 4]);
+        Ok(())
     }
-}
\ No newline at end of file
+}
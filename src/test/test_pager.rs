@@ -2,8 +2,9 @@
 #[cfg(test)]
 mod test_pager {
     use crate::util::error::Error;
-    use crate::page::pager::Pager;
+    use crate::page::pager::{Pager, FitStrategy};
     use crate::util::test_lib::{rm_test_file, gen_buffer};
+    use crate::page::page_item::PAGE_SIZE;
 
     #[test]
     fn test_get_new_pager() -> Result<(), Error> {
@@ -21,4 +22,228 @@ mod test_pager {
         Ok(())
     }
 
+    #[test]
+    fn test_get_value_across_page_boundary() -> Result<(), Error> {
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let mut pager = Pager::new("test.db".to_string(), 50, &mut buffer)?;
+        let mut first_page = pager.get_new_page(&mut buffer)?;
+        let mut second_page = pager.get_new_page(&mut buffer)?;
+        first_page.write_bytes_at_offset(&[1, 2, 3, 4], PAGE_SIZE - 4, 4)?;
+        second_page.write_bytes_at_offset(&[5, 6, 7, 8], 0, 4)?;
+        pager.write_page(first_page, &mut buffer)?;
+        pager.write_page(second_page, &mut buffer)?;
+
+        // 恰好在页尾结束的值
+        let value = pager.get_value(PAGE_SIZE - 4, 4, &mut buffer)?;
+        assert_eq!(value, vec![1, 2, 3, 4]);
+
+        // 跨越页边界的值
+        let value = pager.get_value(PAGE_SIZE - 4, 8, &mut buffer)?;
+        assert_eq!(value, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_free_page_reuse() -> Result<(), Error> {
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let mut pager = Pager::new("test.db".to_string(), 50, &mut buffer)?;
+        for _ in 0..3 {
+            pager.get_new_page(&mut buffer)?;
+        }
+        let mut page = pager.get_page(&2, &mut buffer)?;
+        page.write_bytes_at_offset(&[1u8; 4], 0, 4)?;
+        pager.write_page(page, &mut buffer)?;
+
+        pager.free_page(2, &mut buffer)?;
+        // 释放时页被清零
+        assert!(pager.get_page(&2, &mut buffer)?.is_empty());
+        match pager.free_page(2, &mut buffer) {
+            Err(Error::InvalidPageNum) => (),
+            _ => panic!("expected InvalidPageNum")
+        }
+
+        // 空闲链表已经持久化，重新创建的 Pager 能读到
+        let reopened = Pager::new("test.db".to_string(), 50, &mut buffer)?;
+        assert_eq!(reopened.free_page_count(), 1);
+
+        // 复用被释放的页，旧数据已经清空
+        let page = pager.get_new_page(&mut buffer)?;
+        assert_eq!(page.page_num, 2);
+        assert_eq!(pager.get_page(&2, &mut buffer)?.get_ptr_from_offset(0, 4), &[0u8; 4]);
+        assert_eq!(pager.cnt, 3);
+        assert_eq!(pager.free_page_count(), 0);
+
+        // 没有空闲页时扩展文件
+        assert_eq!(pager.get_new_page(&mut buffer)?.page_num, 4);
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete_value_reuse() -> Result<(), Error> {
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let mut pager = Pager::new("test.db".to_string(), 50, &mut buffer)?;
+        let first = pager.insert_value(&[1u8; 10], &mut buffer)?;
+        let second = pager.insert_value(&[2u8; 10], &mut buffer)?;
+        assert_eq!(pager.used_size(), 20);
+
+        // 删除的值不在页尾，空间进入空洞
+        pager.delete_value(first, 10, &mut buffer)?;
+        assert_eq!(pager.get_value(first, 10, &mut buffer)?, vec![0u8; 10]);
+        assert_eq!(pager.used_size(), 10);
+
+        // 更小的值落在被释放的空间中，剩余部分继续复用
+        assert_eq!(pager.insert_value(&[3u8; 6], &mut buffer)?, first);
+        assert_eq!(pager.insert_value(&[4u8; 4], &mut buffer)?, first + 6);
+        assert_eq!(pager.get_value(second, 10, &mut buffer)?, vec![2u8; 10]);
+
+        // 页尾的值删除后直接还给页尾
+        pager.delete_value(second, 10, &mut buffer)?;
+        assert_eq!(pager.insert_value(&[5u8; 10], &mut buffer)?, second);
+        assert_eq!(pager.used_size(), 20);
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_value_out_of_range() -> Result<(), Error> {
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let mut pager = Pager::new("test.db".to_string(), 50, &mut buffer)?;
+        pager.get_new_page(&mut buffer)?;
+
+        // 第二页还没有分配
+        match pager.get_value(PAGE_SIZE, 4, &mut buffer) {
+            Err(Error::PageNumOutOfSize) => (),
+            _ => panic!("expected PageNumOutOfSize")
+        }
+        // 值的末尾越过了最后一个已分配的页
+        match pager.get_value(PAGE_SIZE - 4, 8, &mut buffer) {
+            Err(Error::PageNumOutOfSize) => (),
+            _ => panic!("expected PageNumOutOfSize")
+        }
+        assert_eq!(pager.get_value(PAGE_SIZE - 4, 4, &mut buffer)?.len(), 4);
+        assert_eq!(pager.get_value_in_page(PAGE_SIZE - 4, 8, &mut buffer)?.len(), 4);
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_defragment() -> Result<(), Error> {
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let mut pager = Pager::new("test.db".to_string(), 50, &mut buffer)?;
+        // 每页放 4 个值，共占用 2 页
+        let mut values = Vec::new();
+        for i in 0..8u8 {
+            let offset = pager.insert_value(&[i + 1; 1000], &mut buffer)?;
+            values.push((offset, i));
+        }
+        assert_eq!(pager.cnt, 2);
+
+        // 删除一半的值，留下分散在两页中的空洞
+        for (offset, i) in values.iter() {
+            if i % 2 == 0 {
+                pager.delete_value(*offset, 1000, &mut buffer)?;
+            }
+        }
+        let live: Vec<(usize, u8)> = values.into_iter().filter(|(_, i)| i % 2 == 1).collect();
+        let ranges: Vec<(usize, usize)> = live.iter().map(|(offset, _)| (*offset, 1000)).collect();
+
+        let moved = pager.defragment(ranges.as_slice(), &mut buffer)?;
+        assert_eq!(moved.len(), 4);
+        for (k, (offset, i)) in live.iter().enumerate() {
+            let new_offset = moved[offset];
+            assert_eq!(new_offset, k * 1000);
+            assert_eq!(pager.get_value(new_offset, 1000, &mut buffer)?, vec![i + 1; 1000]);
+        }
+        assert_eq!(pager.used_size(), 4000);
+
+        // 清空的第二页进入空闲链表，新值写入时复用
+        assert_eq!(pager.free_page_count(), 1);
+        assert_eq!(pager.get_value(PAGE_SIZE, 4, &mut buffer)?, vec![0u8; 4]);
+        assert_eq!(pager.insert_value(&[9u8; 1000], &mut buffer)?, PAGE_SIZE);
+        assert_eq!(pager.free_page_count(), 0);
+
+        // 重叠的值无法整理
+        match pager.defragment(&[(0, 1000), (500, 1000)], &mut buffer) {
+            Err(Error::UnexpectedError) => (),
+            _ => panic!("expected UnexpectedError")
+        }
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_best_fit() -> Result<(), Error> {
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let mut pager = Pager::new("test.db".to_string(), 50, &mut buffer)?;
+        // 第一页剩余较多，第二页剩余较少
+        assert_eq!(pager.insert_value(vec![1u8; PAGE_SIZE - 1000].as_slice(), &mut buffer)?, 0);
+        assert_eq!(pager.insert_value(vec![2u8; PAGE_SIZE - 100].as_slice(), &mut buffer)?, PAGE_SIZE);
+
+        // 首次适应写入第一页
+        assert_eq!(pager.insert_value(&[3u8; 50], &mut buffer)?, PAGE_SIZE - 1000);
+
+        // 最佳适应写入剩余空间更小的第二页
+        pager.set_fit_strategy(FitStrategy::BestFit);
+        assert_eq!(pager.insert_value(&[4u8; 50], &mut buffer)?, 2 * PAGE_SIZE - 100);
+        // 第二页放不下时仍然写入第一页
+        assert_eq!(pager.insert_value(&[5u8; 60], &mut buffer)?, PAGE_SIZE - 950);
+        assert_eq!(pager.cnt, 2);
+
+        // 空洞同样选择最小的一个
+        pager.delete_value(0, 200, &mut buffer)?;
+        pager.delete_value(1000, 80, &mut buffer)?;
+        assert_eq!(pager.insert_value(&[6u8; 70], &mut buffer)?, 1000);
+        assert_eq!(pager.insert_value(&[7u8; 70], &mut buffer)?, 0);
+
+        rm_test_file();
+        Ok(())
+    }
+
+    #[test]
+    fn test_value_count() -> Result<(), Error> {
+        rm_test_file();
+
+        let mut buffer = gen_buffer()?;
+        let mut pager = Pager::new("test.db".to_string(), 50, &mut buffer)?;
+        assert_eq!(pager.value_count(), 0);
+        let mut offsets = Vec::<usize>::new();
+        for i in 0..3u8 {
+            offsets.push(pager.insert_value(&[i; 10], &mut buffer)?);
+        }
+        assert_eq!(pager.value_count(), 3);
+
+        pager.delete_value(offsets[1], 10, &mut buffer)?;
+        assert_eq!(pager.value_count(), 2);
+        // 空值不占空间，删除时不计数
+        pager.delete_value(offsets[0], 0, &mut buffer)?;
+        assert_eq!(pager.value_count(), 2);
+        pager.insert_value(&[3u8; 10], &mut buffer)?;
+        assert_eq!(pager.value_count(), 3);
+
+        // 值的个数保存在文件头中，重新打开后仍然有效
+        let pager = Pager::new("test.db".to_string(), 50, &mut buffer)?;
+        assert_eq!(pager.value_count(), 3);
+
+        rm_test_file();
+        Ok(())
+    }
 }
@@ -0,0 +1,83 @@
+#[cfg(test)]
+mod test {
+    use crate::util::error::Error;
+    use crate::util::test_lib::rm_files;
+    use crate::table::planner::{plan, AccessPath, Condition};
+    use crate::table::table_item::TableStatistics;
+    use crate::table::table_manager::TableManager;
+    use crate::table::field::{Field, FieldType, FieldValue};
+    use crate::table::entry::Entry;
+    use crate::data_item::buffer::LRUBuffer;
+
+    fn gen_stats(row_count: usize) -> TableStatistics {
+        TableStatistics {
+            row_count,
+            index_count: 1,
+            avg_row_size: 10.0,
+            page_count: 1,
+            used_bytes: row_count * 10,
+        }
+    }
+
+    #[test]
+    fn test_plan_chooses_access_path() -> Result<(), Error> {
+        let stats = gen_stats(100);
+        let indexes = vec![0];
+
+        // 索引字段上的等值条件走索引
+        let conditions = vec![Condition::Eq(0, FieldValue::INT32(1))];
+        match plan(&conditions, &indexes, &stats) {
+            AccessPath::IndexPoint { key_index, .. } => assert_eq!(key_index, 0),
+            _ => panic!("expected IndexPoint")
+        };
+
+        // 没有索引的字段只能全表扫描
+        let conditions = vec![Condition::Eq(1, FieldValue::INT32(1)), Condition::Other];
+        match plan(&conditions, &indexes, &stats) {
+            AccessPath::FullScan => (),
+            _ => panic!("expected FullScan")
+        };
+
+        // 有界的范围查询走索引
+        let conditions = vec![Condition::Range(0, Some(FieldValue::INT32(1)), None)];
+        match plan(&conditions, &indexes, &stats) {
+            AccessPath::IndexRange { key_index, .. } => assert_eq!(key_index, 0),
+            _ => panic!("expected IndexRange")
+        };
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_with_plan() -> Result<(), Error> {
+        let files = ["plan_table_plan_id.idx", "plan_table", "plan_metadata.db", "plan_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "plan_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("plan_id".to_string(), FieldType::INT32)?,
+            Field::create_field("value".to_string(), FieldType::INT32)?,
+        ];
+        table.create_table("plan_table".to_string(), fields)?;
+        table.create_index("plan_table".to_string(), 0)?;
+        for i in 0..5 {
+            let entry = Entry {
+                data: vec![FieldValue::INT32(i), FieldValue::INT32(i * 10)]
+            };
+            table.insert("plan_table".to_string(), entry)?;
+        }
+
+        let res = table.execute_plan("plan_table".to_string(), &[Condition::Eq(0, FieldValue::INT32(3))])?;
+        assert_eq!(res.len(), 1);
+        match res[0].data[1] {
+            FieldValue::INT32(i) => assert_eq!(i, 30),
+            _ => panic!("expected INT32")
+        };
+        // 全表扫描返回所有候选行，由调用者再过滤
+        let res = table.execute_plan("plan_table".to_string(), &[Condition::Eq(1, FieldValue::INT32(30))])?;
+        assert_eq!(res.len(), 5);
+
+        rm_files(&files);
+        Ok(())
+    }
+}
@@ -1,12 +1,19 @@
 #[cfg(test)]
 mod test {
-    use crate::util::test_lib::{rm_test_file, gen_buffer};
+    use crate::util::test_lib::{rm_test_file, rm_files, gen_buffer};
     use crate::util::error::Error;
     use crate::table::table_manager::TableManager;
     use crate::table::field::{Field, FieldType, FieldValue};
+    use crate::table::composite_key::CompositeKey;
+    use crate::table::planner::Condition;
+    use crate::table::table_item::{Agg, Table};
+use crate::table::predicate::Predicate;
+    use crate::data_item::buffer::Buffer;
+    use std::ops::Bound;
     use crate::table::entry::{Entry};
     use crate::data_item::buffer::LRUBuffer;
     use std::fs;
+    use std::path::Path;
 
     #[test]
     fn test_create_table() -> Result<(), Error>{
@@ -35,7 +42,7 @@ mod test {
 
     #[test]
     fn test_insert_and_read_full_table() -> Result<(), Error>{
-        match fs::remove_file("id.idx") {
+        match fs::remove_file("test_table_id.idx") {
             Ok(_) => (),
             Err(_) => (),
         };
@@ -77,7 +84,7 @@ mod test {
             _ => assert!(false)
         };
 
-        match fs::remove_file("id.idx") {
+        match fs::remove_file("test_table_id.idx") {
             Ok(_) => (),
             Err(_) => (),
         };
@@ -88,4 +95,1610 @@ mod test {
         Ok(())
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_insert_and_read_int64() -> Result<(), Error>{
+        let files = ["int64_table_int64_id.idx", "int64_table", "int64_metadata.db", "int64_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "int64_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let mut fields = Vec::<Field>::new();
+        let f1 = Field::create_field("int64_id".to_string(), FieldType::INT32)?;
+        let f2 = Field::create_field("big".to_string(), FieldType::INT64)?;
+        let f3 = Field::create_field("ratio".to_string(), FieldType::FLOAT64)?;
+        fields.push(f1);
+        fields.push(f2);
+        fields.push(f3);
+        table.create_table("int64_table".to_string(), fields)?;
+        table.create_index("int64_table".to_string(), 0)?;
+
+        let mut entry = Entry {
+            data: Vec::<FieldValue>::new()
+        };
+        entry.data.push(FieldValue::INT32(1));
+        entry.data.push(FieldValue::INT64(i64::MAX - 1));
+        entry.data.push(FieldValue::FLOAT64(0.1));
+        table.insert("int64_table".to_string(), entry)?;
+
+        let res = table.read_full_table("int64_table".to_string())?;
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].data.len(), 3);
+        match res[0].data[1] {
+            FieldValue::INT64(i) => assert_eq!(i, i64::MAX - 1),
+            _ => panic!("expected INT64")
+        };
+        match res[0].data[2] {
+            FieldValue::FLOAT64(f) => assert_eq!(f.to_bits(), 0.1f64.to_bits()),
+            _ => panic!("expected FLOAT64")
+        };
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_and_read_null() -> Result<(), Error>{
+        let files = ["null_table_null_id.idx", "null_table", "null_metadata.db", "null_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "null_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("null_id".to_string(), FieldType::INT32)?,
+            Field::create_nullable_field("maybe".to_string(), FieldType::INT32)?,
+            Field::create_field("required".to_string(), FieldType::INT32)?,
+        ];
+        table.create_table("null_table".to_string(), fields)?;
+        table.create_index("null_table".to_string(), 0)?;
+
+        let entry = Entry {
+            data: vec![FieldValue::INT32(1), FieldValue::Null, FieldValue::INT32(3)]
+        };
+        table.insert("null_table".to_string(), entry)?;
+
+        // 不允许为空的字段拒绝空值
+        let entry = Entry {
+            data: vec![FieldValue::INT32(2), FieldValue::INT32(2), FieldValue::Null]
+        };
+        match table.insert("null_table".to_string(), entry) {
+            Err(Error::FieldValueNotCompatible) => (),
+            _ => panic!("expected FieldValueNotCompatible")
+        }
+
+        let res = table.read_full_table("null_table".to_string())?;
+        assert_eq!(res.len(), 1);
+        match res[0].data[1] {
+            FieldValue::Null => (),
+            _ => panic!("expected Null")
+        };
+        match res[0].data[2] {
+            FieldValue::INT32(i) => assert_eq!(i, 3),
+            _ => panic!("expected INT32")
+        };
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_statistics() -> Result<(), Error>{
+        let files = ["stat_table_stat_id.idx", "stat_table", "stat_metadata.db", "stat_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "stat_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("stat_id".to_string(), FieldType::INT32)?,
+            Field::create_field("value".to_string(), FieldType::INT64)?,
+        ];
+        table.create_table("stat_table".to_string(), fields)?;
+
+        let stats = table.statistics("stat_table".to_string())?;
+        assert_eq!(stats.row_count, 0);
+        assert_eq!(stats.index_count, 0);
+
+        table.create_index("stat_table".to_string(), 0)?;
+        for i in 0..5 {
+            let entry = Entry {
+                data: vec![FieldValue::INT32(i), FieldValue::INT64(i as i64 * 100)]
+            };
+            table.insert("stat_table".to_string(), entry)?;
+        }
+
+        let stats = table.statistics("stat_table".to_string())?;
+        assert_eq!(stats.row_count, 5);
+        assert_eq!(stats.index_count, 1);
+        assert_eq!(stats.page_count, 1);
+        // 每个字段一个字节的空值标志加上数据
+        assert!((stats.avg_row_size - 14.0).abs() < f64::EPSILON);
+        assert_eq!(stats.row_count as f64 * stats.avg_row_size, stats.used_bytes as f64);
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_update() -> Result<(), Error>{
+        let files = ["upd_table_upd_id.idx", "upd_table", "upd_metadata.db", "upd_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "upd_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("upd_id".to_string(), FieldType::INT32)?,
+            Field::create_field("value".to_string(), FieldType::INT64)?,
+        ];
+        table.create_table("upd_table".to_string(), fields)?;
+        table.create_index("upd_table".to_string(), 0)?;
+
+        for i in 0..3 {
+            let entry = Entry {
+                data: vec![FieldValue::INT32(i), FieldValue::INT64(i as i64)]
+            };
+            table.insert("upd_table".to_string(), entry)?;
+        }
+
+        let entry = Entry {
+            data: vec![FieldValue::INT32(1), FieldValue::INT64(100)]
+        };
+        // 返回更新前的行
+        let old = table.update("upd_table".to_string(), 0, FieldValue::INT32(1), entry)?;
+        assert_eq!(old.data, vec![FieldValue::INT32(1), FieldValue::INT64(1)]);
+
+        let row = table.search("upd_table".to_string(), 0, FieldValue::INT32(1))?;
+        match row.data[1] {
+            FieldValue::INT64(i) => assert_eq!(i, 100),
+            _ => panic!("expected INT64")
+        };
+        // 相邻的行不受影响
+        let row = table.search("upd_table".to_string(), 0, FieldValue::INT32(2))?;
+        match row.data[1] {
+            FieldValue::INT64(i) => assert_eq!(i, 2),
+            _ => panic!("expected INT64")
+        };
+
+        // 不存在的行不会被插入
+        let entry = Entry {
+            data: vec![FieldValue::INT32(7), FieldValue::INT64(7)]
+        };
+        match table.update("upd_table".to_string(), 0, FieldValue::INT32(7), entry) {
+            Err(Error::KeyNotFound) => (),
+            _ => panic!("expected KeyNotFound")
+        }
+        match table.search("upd_table".to_string(), 0, FieldValue::INT32(7)) {
+            Err(Error::KeyNotFound) => (),
+            _ => panic!("expected KeyNotFound")
+        }
+
+        // 主键不允许修改，字段类型必须与表结构一致
+        let entry = Entry {
+            data: vec![FieldValue::INT32(5), FieldValue::INT64(100)]
+        };
+        match table.update("upd_table".to_string(), 0, FieldValue::INT32(1), entry) {
+            Err(Error::PrimaryKeyImmutable) => (),
+            _ => panic!("expected PrimaryKeyImmutable")
+        }
+        let entry = Entry {
+            data: vec![FieldValue::INT32(1), FieldValue::INT32(100)]
+        };
+        match table.update("upd_table".to_string(), 0, FieldValue::INT32(1), entry) {
+            Err(Error::FieldValueNotCompatible) => (),
+            _ => panic!("expected FieldValueNotCompatible")
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete() -> Result<(), Error>{
+        let files = ["del_table_del_id.idx", "del_table", "del_metadata.db", "del_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "del_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("del_id".to_string(), FieldType::INT32)?,
+            Field::create_field("value".to_string(), FieldType::INT32)?,
+        ];
+        table.create_table("del_table".to_string(), fields)?;
+        table.create_index("del_table".to_string(), 0)?;
+
+        for i in 1..=2 {
+            let entry = Entry {
+                data: vec![FieldValue::INT32(i), FieldValue::INT32(i * 10)]
+            };
+            table.insert("del_table".to_string(), entry)?;
+        }
+
+        table.delete("del_table".to_string(), 0, FieldValue::INT32(1))?;
+        let res = table.read_full_table("del_table".to_string())?;
+        assert_eq!(res.len(), 1);
+        match res[0].data[0] {
+            FieldValue::INT32(i) => assert_eq!(i, 2),
+            _ => panic!("expected INT32")
+        };
+
+        match table.delete("del_table".to_string(), 0, FieldValue::INT32(1)) {
+            Err(Error::KeyNotFound) => (),
+            _ => panic!("expected KeyNotFound")
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_secondary_index() -> Result<(), Error>{
+        let files = ["sec_table_sec_id.idx", "sec_table_sec_code.idx", "sec_table", "sec_metadata.db", "sec_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "sec_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("sec_id".to_string(), FieldType::INT32)?,
+            Field::create_field("sec_code".to_string(), FieldType::INT64)?,
+        ];
+        table.create_table("sec_table".to_string(), fields)?;
+        table.create_index("sec_table".to_string(), 0)?;
+        table.create_index("sec_table".to_string(), 1)?;
+
+        for i in 0..4 {
+            let entry = Entry {
+                data: vec![FieldValue::INT32(i), FieldValue::INT64(1000 + i as i64)]
+            };
+            table.insert("sec_table".to_string(), entry)?;
+        }
+
+        let row = table.search("sec_table".to_string(), 1, FieldValue::INT64(1002))?;
+        match row.data[0] {
+            FieldValue::INT32(i) => assert_eq!(i, 2),
+            _ => panic!("expected INT32")
+        };
+
+        // 定位行的第一个字段的键重复时，整行都不插入
+        let entry = Entry {
+            data: vec![FieldValue::INT32(2), FieldValue::INT64(2000)]
+        };
+        match table.insert("sec_table".to_string(), entry) {
+            Err(Error::KeyAlreadyExists) => (),
+            _ => panic!("expected KeyAlreadyExists")
+        }
+        match table.search("sec_table".to_string(), 1, FieldValue::INT64(2000)) {
+            Err(Error::KeyNotFound) => (),
+            _ => panic!("expected KeyNotFound")
+        }
+        assert_eq!(table.read_full_table("sec_table".to_string())?.len(), 4);
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_unique_index() -> Result<(), Error>{
+        let files = ["nu_table_nu_id.idx", "nu_table_nu_status.idx", "nu_table", "nu_metadata.db", "nu_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "nu_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("nu_id".to_string(), FieldType::INT32)?,
+            Field::create_field("nu_status".to_string(), FieldType::INT32)?,
+        ];
+        table.create_table("nu_table".to_string(), fields)?;
+        table.create_index("nu_table".to_string(), 0)?;
+        table.create_index("nu_table".to_string(), 1)?;
+        let entry = |id: i32, status: i32| Entry {
+            data: vec![FieldValue::INT32(id), FieldValue::INT32(status)]
+        };
+        let ids = |rows: Vec<Entry>| -> Vec<i32> {
+            rows.iter().map(|row| match row.data[0] {
+                FieldValue::INT32(id) => id,
+                _ => panic!("expected INT32")
+            }).collect()
+        };
+        table.insert("nu_table".to_string(), entry(0, 2))?;
+        let used = table.table_cache.get("nu_table").unwrap().pager.used_size();
+
+        // 三行的状态相同，都能插入并通过非唯一索引查到
+        for id in 1..=3 {
+            table.insert("nu_table".to_string(), entry(id, 1))?;
+        }
+        assert_eq!(ids(table.search_all("nu_table".to_string(), 1, FieldValue::INT32(1))?), vec![1, 2, 3]);
+        assert_eq!(ids(table.search_range("nu_table".to_string(), 1, None, None)?), vec![1, 2, 3, 0]);
+        assert_eq!(ids(table.scan_limited("nu_table".to_string(), 1, None, None, 2, 1)?), vec![2, 3]);
+        let plan = table.execute_plan("nu_table".to_string(), &[Condition::Eq(1, FieldValue::INT32(1))])?;
+        assert_eq!(ids(plan), vec![1, 2, 3]);
+
+        // 改变其中一行的状态只把这一行移到新键下
+        table.update("nu_table".to_string(), 0, FieldValue::INT32(2), entry(2, 2))?;
+        assert_eq!(ids(table.search_all("nu_table".to_string(), 1, FieldValue::INT32(1))?), vec![1, 3]);
+        assert_eq!(ids(table.search_all("nu_table".to_string(), 1, FieldValue::INT32(2))?), vec![0, 2]);
+
+        // 删除行只从键的偏移量列表中去掉这一行，全部删除后偏移量列表也被释放
+        table.delete("nu_table".to_string(), 0, FieldValue::INT32(1))?;
+        assert_eq!(ids(table.search_all("nu_table".to_string(), 1, FieldValue::INT32(1))?), vec![3]);
+        for id in 2..=3 {
+            table.delete("nu_table".to_string(), 0, FieldValue::INT32(id))?;
+        }
+        assert!(matches!(table.search_all("nu_table".to_string(), 1, FieldValue::INT32(1)), Err(Error::KeyNotFound)));
+        assert_eq!(ids(table.search_all("nu_table".to_string(), 1, FieldValue::INT32(2))?), vec![0]);
+        assert_eq!(table.table_cache.get("nu_table").unwrap().pager.used_size(), used);
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_unique_index_reopen() -> Result<(), Error>{
+        let files = ["nur_table_nur_id.idx", "nur_table_nur_status.idx", "nur_table", "nur_metadata.db", "nur_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "nur_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("nur_id".to_string(), FieldType::INT32)?,
+            Field::create_field("nur_status".to_string(), FieldType::INT32)?,
+        ];
+        table.create_table("nur_table".to_string(), fields)?;
+        table.create_index("nur_table".to_string(), 0)?;
+        table.create_index("nur_table".to_string(), 1)?;
+        let entry = |id: i32, status: i32| Entry {
+            data: vec![FieldValue::INT32(id), FieldValue::INT32(status)]
+        };
+        for (id, status) in [(1, 1), (2, 1), (3, 1), (4, 2)].iter() {
+            table.insert("nur_table".to_string(), entry(*id, *status))?;
+        }
+        table.flush()?;
+        drop(table);
+
+        // 重新打开后偏移量列表占用的空间不会被新插入的行覆盖
+        let buffer = Box::new(LRUBuffer::new(4, "nur_metadata.db".to_string())?);
+        let mut table = TableManager::open(buffer)?;
+        table.insert("nur_table".to_string(), entry(5, 3))?;
+        table.insert("nur_table".to_string(), entry(6, 3))?;
+        let rows = table.search_all("nur_table".to_string(), 1, FieldValue::INT32(1))?;
+        assert_eq!(rows, vec![entry(1, 1), entry(2, 1), entry(3, 1)]);
+        let rows = table.search_all("nur_table".to_string(), 1, FieldValue::INT32(3))?;
+        assert_eq!(rows, vec![entry(5, 3), entry(6, 3)]);
+        assert_eq!(table.read_full_table("nur_table".to_string())?.len(), 6);
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_catalog_rewritten_across_reopens() -> Result<(), Error>{
+        let files = ["catw_wide_catw_id.idx", "catw_wide", "catw_small", "catw_metadata.db", "catw_metadata.db.catalog"];
+        rm_files(&files);
+
+        // 字段名较长的宽表，编码后的目录超过一页
+        let wide_fields = || -> Result<Vec<Field>, Error> {
+            let mut fields = vec![Field::create_field("catw_id".to_string(), FieldType::INT32)?];
+            for i in 0..150 {
+                fields.push(Field::create_field(format!("catalog_wide_column_{:03}", i), FieldType::INT32)?);
+            }
+            Ok(fields)
+        };
+        let buffer = Box::new(LRUBuffer::new(4, "catw_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        table.create_table("catw_wide".to_string(), wide_fields()?)?;
+        table.create_index("catw_wide".to_string(), 0)?;
+        drop(table);
+
+        // 重新打开后修改表结构，不调用 flush 目录也已经落盘
+        let buffer = Box::new(LRUBuffer::new(4, "catw_metadata.db".to_string())?);
+        let mut table = TableManager::open(buffer)?;
+        let fields = vec![Field::create_field("small_id".to_string(), FieldType::INT64)?];
+        table.create_table("catw_small".to_string(), fields)?;
+        drop(table);
+
+        let buffer = Box::new(LRUBuffer::new(4, "catw_metadata.db".to_string())?);
+        let table = TableManager::open(buffer)?;
+        assert_eq!(table.list_tables(), vec!["catw_small".to_string(), "catw_wide".to_string()]);
+        let wide = table.table_cache.get("catw_wide").unwrap();
+        let names: Vec<String> = wide.fields.iter().map(|field| field.field_name.clone()).collect();
+        let expected: Vec<String> = wide_fields()?.iter().map(|field| field.field_name.clone()).collect();
+        assert_eq!(names, expected);
+        assert!(wide.fields[0].is_indexed());
+        let small = table.table_cache.get("catw_small").unwrap();
+        assert_eq!(small.fields[0].field_name, "small_id".to_string());
+        match small.fields[0].field_type {
+            FieldType::INT64 => (),
+            _ => panic!("expected INT64")
+        };
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reopen_catalog() -> Result<(), Error>{
+        let files = ["cat_table_cat_id.idx", "cat_table", "cat_metadata.db", "cat_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "cat_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("cat_id".to_string(), FieldType::INT32)?,
+            Field::create_nullable_field("name".to_string(), FieldType::FLOAT64)?,
+        ];
+        table.create_table("cat_table".to_string(), fields)?;
+        table.create_index("cat_table".to_string(), 0)?;
+        for i in 0..3 {
+            let entry = Entry {
+                data: vec![FieldValue::INT32(i), FieldValue::FLOAT64(i as f64)]
+            };
+            table.insert("cat_table".to_string(), entry)?;
+        }
+        table.flush()?;
+        drop(table);
+
+        let buffer = Box::new(LRUBuffer::new(4, "cat_metadata.db".to_string())?);
+        let mut table = TableManager::open(buffer)?;
+        let t = table.table_cache.get("cat_table").unwrap();
+        assert_eq!(t.fields.len(), 2);
+        assert_eq!(t.fields[0].field_name, "cat_id".to_string());
+        assert!(t.fields[0].is_indexed());
+        assert!(!t.fields[1].is_indexed());
+        assert!(t.fields[1].nullable);
+        match t.fields[1].field_type {
+            FieldType::FLOAT64 => (),
+            _ => panic!("expected FLOAT64")
+        };
+
+        // 重新打开后插入的行不会覆盖已有的行
+        let entry = Entry {
+            data: vec![FieldValue::INT32(3), FieldValue::Null]
+        };
+        table.insert("cat_table".to_string(), entry)?;
+        let res = table.read_full_table("cat_table".to_string())?;
+        assert_eq!(res.len(), 4);
+        match res[2].data[1] {
+            FieldValue::FLOAT64(f) => assert_eq!(f.to_bits(), 2f64.to_bits()),
+            _ => panic!("expected FLOAT64")
+        };
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    fn text_of(entry: &Entry) -> String {
+        match &entry.data[1] {
+            FieldValue::TEXT(data) => data.clone(),
+            _ => panic!("expected TEXT")
+        }
+    }
+
+    #[test]
+    fn test_text_field() -> Result<(), Error>{
+        let files = ["text_table_text_id.idx", "text_table", "text_metadata.db", "text_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "text_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("text_id".to_string(), FieldType::INT32)?,
+            Field::create_field("body".to_string(), FieldType::TEXT)?,
+        ];
+        table.create_table("text_table".to_string(), fields)?;
+        table.create_index("text_table".to_string(), 0)?;
+
+        // 超过一页的字符串和空字符串
+        let long: String = (0..5000).map(|i| (b'a' + (i % 26) as u8) as char).collect();
+        let texts = [long.clone(), String::new(), "short".to_string()];
+        for (i, text) in texts.iter().enumerate() {
+            let entry = Entry {
+                data: vec![FieldValue::INT32(i as i32), FieldValue::TEXT(text.clone())]
+            };
+            table.insert("text_table".to_string(), entry)?;
+        }
+        for (i, text) in texts.iter().enumerate() {
+            let row = table.search("text_table".to_string(), 0, FieldValue::INT32(i as i32))?;
+            assert_eq!(&text_of(&row), text);
+        }
+
+        let entry = Entry {
+            data: vec![FieldValue::INT32(2), FieldValue::TEXT(long.clone() + "!")]
+        };
+        table.update("text_table".to_string(), 0, FieldValue::INT32(2), entry)?;
+        table.flush()?;
+        drop(table);
+
+        // 重新打开后内容仍然完整，新写入的内容不会覆盖已有的内容
+        let buffer = Box::new(LRUBuffer::new(4, "text_metadata.db".to_string())?);
+        let mut table = TableManager::open(buffer)?;
+        let entry = Entry {
+            data: vec![FieldValue::INT32(3), FieldValue::TEXT(long.clone())]
+        };
+        table.insert("text_table".to_string(), entry)?;
+        let res = table.read_full_table("text_table".to_string())?;
+        assert_eq!(res.len(), 4);
+        assert_eq!(text_of(&res[0]), long);
+        assert_eq!(text_of(&res[1]), "");
+        assert_eq!(text_of(&res[2]), long.clone() + "!");
+        assert_eq!(text_of(&res[3]), long);
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_blob_field() -> Result<(), Error>{
+        let files = ["blob_table_blob_id.idx", "blob_table", "blob_metadata.db", "blob_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "blob_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("blob_id".to_string(), FieldType::INT32)?,
+            Field::create_field("data".to_string(), FieldType::BLOB)?,
+        ];
+        table.create_table("blob_table".to_string(), fields)?;
+        table.create_index("blob_table".to_string(), 0)?;
+
+        // 不是合法 UTF-8 的字节
+        let data = vec![0xFFu8, 0x00, 0xFE, 0x00, 0x00, 0x80, 0x41];
+        let entry = Entry {
+            data: vec![FieldValue::INT32(1), FieldValue::BLOB(data.clone())]
+        };
+        table.insert("blob_table".to_string(), entry)?;
+
+        let row = table.search("blob_table".to_string(), 0, FieldValue::INT32(1))?;
+        match &row.data[1] {
+            FieldValue::BLOB(res) => assert_eq!(res, &data),
+            _ => panic!("expected BLOB")
+        };
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bool_field() -> Result<(), Error>{
+        let files = ["bool_table_bool_id.idx", "bool_table", "bool_metadata.db", "bool_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "bool_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("bool_id".to_string(), FieldType::INT32)?,
+            Field::create_field("flag".to_string(), FieldType::BOOL)?,
+            Field::create_field("value".to_string(), FieldType::INT32)?,
+        ];
+        table.create_table("bool_table".to_string(), fields)?;
+        table.create_index("bool_table".to_string(), 0)?;
+
+        for i in 0..2 {
+            let entry = Entry {
+                data: vec![FieldValue::INT32(i), FieldValue::from(i == 0), FieldValue::INT32(i * 10)]
+            };
+            table.insert("bool_table".to_string(), entry)?;
+        }
+
+        let res = table.read_full_table("bool_table".to_string())?;
+        assert_eq!(res.len(), 2);
+        assert!(bool::from(res[0].data[1].clone()));
+        assert!(!bool::from(res[1].data[1].clone()));
+        // 布尔值之后的字段也能正确解析
+        assert_eq!(i32::from(res[1].data[2].clone()), 10);
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_two_int32_columns() -> Result<(), Error>{
+        let files = ["pair_table_pair_id.idx", "pair_table", "pair_metadata.db", "pair_metadata.db.catalog"];
+        rm_files(&files);
+
+        // 大小是字节数，与解析时前进的字节数一致
+        assert_eq!(FieldValue::INT32(1).to_size(), 4);
+        assert_eq!(FieldValue::FLOAT32(1.0).to_size(), 4);
+        assert_eq!(FieldValue::VARCHAR40(String::new()).to_size(), 40);
+
+        let buffer = Box::new(LRUBuffer::new(4, "pair_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("pair_id".to_string(), FieldType::INT32)?,
+            Field::create_field("value".to_string(), FieldType::INT32)?,
+        ];
+        table.create_table("pair_table".to_string(), fields)?;
+        table.create_index("pair_table".to_string(), 0)?;
+        for i in 1..=3 {
+            let entry = Entry {
+                data: vec![FieldValue::INT32(i), FieldValue::INT32(-i * 100)]
+            };
+            table.insert("pair_table".to_string(), entry)?;
+        }
+
+        let row = table.search("pair_table".to_string(), 0, FieldValue::INT32(2))?;
+        assert_eq!(i32::from(row.data[0].clone()), 2);
+        assert_eq!(i32::from(row.data[1].clone()), -200);
+
+        // 带边界的范围查询按整行大小读取
+        let res = table.search_range("pair_table".to_string(), 0, Some(FieldValue::INT32(1)), None)?;
+        assert_eq!(res.len(), 3);
+        for (i, row) in res.iter().enumerate() {
+            assert_eq!(i32::from(row.data[0].clone()), i as i32 + 1);
+            assert_eq!(i32::from(row.data[1].clone()), -(i as i32 + 1) * 100);
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_composite_key() -> Result<(), Error>{
+        let files = ["comp_table", "comp_table.key.idx", "comp_metadata.db", "comp_metadata.db.catalog"];
+        rm_files(&files);
+
+        // 先比较第一个字段，相等时再比较第二个字段，负数排在正数前面
+        let key = |id: i32, name: &str| CompositeKey::encode(&[FieldValue::INT32(id), FieldValue::VARCHAR40(name.to_string())]);
+        assert!(key(1, "a")? < key(1, "b")?);
+        assert!(key(1, "b")? < key(2, "a")?);
+        assert!(key(-1, "z")? < key(1, "a")?);
+        assert!(key(1, "a")? < key(1, "ab")?);
+
+        let buffer = Box::new(LRUBuffer::new(4, "comp_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("comp_id".to_string(), FieldType::INT32)?,
+            Field::create_field("comp_name".to_string(), FieldType::VARCHAR40)?,
+            Field::create_field("value".to_string(), FieldType::INT32)?,
+        ];
+        table.create_table_with_key("comp_table".to_string(), fields, &[0, 1])?;
+        for (id, name, value) in [(2, "a", 3), (1, "b", 2), (1, "a", 1)].iter() {
+            let entry = Entry {
+                data: vec![FieldValue::INT32(*id), FieldValue::VARCHAR40(name.to_string()), FieldValue::INT32(*value)]
+            };
+            table.insert("comp_table".to_string(), entry)?;
+        }
+
+        // 第一个字段相同的两行不冲突，完全相同的键被拒绝
+        let entry = Entry {
+            data: vec![FieldValue::INT32(1), FieldValue::VARCHAR40("a".to_string()), FieldValue::INT32(4)]
+        };
+        match table.insert("comp_table".to_string(), entry) {
+            Err(Error::KeyAlreadyExists) => (),
+            _ => panic!("expected KeyAlreadyExists")
+        }
+
+        let row = table.search_by_key("comp_table".to_string(), vec![FieldValue::INT32(1), FieldValue::VARCHAR40("b".to_string())])?;
+        assert_eq!(i32::from(row.data[2].clone()), 2);
+
+        // 整表按组合键的顺序读出
+        let res = table.read_full_table("comp_table".to_string())?;
+        assert_eq!(res.len(), 3);
+        for (i, row) in res.iter().enumerate() {
+            assert_eq!(i32::from(row.data[2].clone()), i as i32 + 1);
+        }
+        table.flush()?;
+        drop(table);
+
+        let buffer = Box::new(LRUBuffer::new(4, "comp_metadata.db".to_string())?);
+        let mut table = TableManager::open(buffer)?;
+        let row = table.search_by_key("comp_table".to_string(), vec![FieldValue::INT32(2), FieldValue::VARCHAR40("a".to_string())])?;
+        assert_eq!(String::from(row.data[1].clone()), "a".to_string());
+        assert_eq!(i32::from(row.data[2].clone()), 3);
+        match table.search_by_key("comp_table".to_string(), vec![FieldValue::INT32(2), FieldValue::VARCHAR40("b".to_string())]) {
+            Err(Error::KeyNotFound) => (),
+            _ => panic!("expected KeyNotFound")
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_stops_early() -> Result<(), Error>{
+        let files = ["scan_table_scan_id.idx", "scan_table", "scan_metadata.db"];
+        rm_files(&files);
+
+        let mut buffer: Box<dyn Buffer> = Box::new(LRUBuffer::new(4, "scan_metadata.db".to_string())?);
+        let mut table = Table::new("scan_table".to_string(), &mut buffer)?;
+        table.add_fields(vec![
+            Field::create_field("scan_id".to_string(), FieldType::INT32)?,
+            Field::create_field("name".to_string(), FieldType::VARCHAR40)?,
+        ]);
+        table.create_index(0, &mut buffer)?;
+        for i in 1..=5 {
+            let entry = Entry {
+                data: vec![FieldValue::INT32(i), FieldValue::VARCHAR40(format!("row{}", i))]
+            };
+            table.insert(entry, &mut buffer)?;
+        }
+
+        // 破坏第四行的字符串，解析到这一行时会出错
+        let offset = table.fields[0].search_offset(FieldValue::INT32(4), &mut buffer, &table.pager)?;
+        table.pager.write_value(offset + 6, &[0xff], &mut buffer)?;
+
+        // 只取前两行时不会解析到被破坏的行
+        let rows = table.scan(0, Some(FieldValue::INT32(2)), None, &mut buffer)?
+            .take(2)
+            .collect::<Result<Vec<Entry>, Error>>()?;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(String::from(rows[0].data[1].clone()), "row2".to_string());
+        assert_eq!(String::from(rows[1].data[1].clone()), "row3".to_string());
+
+        // 右边界之外的行不会被读出
+        let rows = table.scan(0, None, Some(FieldValue::INT32(3)), &mut buffer)?
+            .collect::<Result<Vec<Entry>, Error>>()?;
+        assert_eq!(rows.len(), 3);
+
+        let res = table.scan(0, None, None, &mut buffer)?.collect::<Vec<Result<Entry, Error>>>();
+        assert_eq!(res.len(), 5);
+        assert!(res[3].is_err());
+        assert!(res[4].is_ok());
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_range_ex() -> Result<(), Error>{
+        let files = ["ex_table_ex_id.idx", "ex_table", "ex_metadata.db", "ex_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "ex_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("ex_id".to_string(), FieldType::INT32)?,
+            Field::create_field("value".to_string(), FieldType::INT32)?,
+        ];
+        table.create_table("ex_table".to_string(), fields)?;
+        table.create_index("ex_table".to_string(), 0)?;
+        for i in 1..=5 {
+            let entry = Entry {
+                data: vec![FieldValue::INT32(i), FieldValue::INT32(i * 10)]
+            };
+            table.insert("ex_table".to_string(), entry)?;
+        }
+
+        // ex_id > 1 AND ex_id <= 3
+        let res = table.search_range_ex("ex_table".to_string(), 0, Bound::Excluded(FieldValue::INT32(1)), Bound::Included(FieldValue::INT32(3)))?;
+        assert_eq!(res.iter().map(|row| i32::from(row.data[1].clone())).collect::<Vec<i32>>(), vec![20, 30]);
+        // ex_id < 4
+        let res = table.search_range_ex("ex_table".to_string(), 0, Bound::Unbounded, Bound::Excluded(FieldValue::INT32(4)))?;
+        assert_eq!(res.len(), 3);
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_and_describe_tables() -> Result<(), Error>{
+        let files = ["desc_a", "desc_b", "desc_a_desc_a_id.idx", "desc_b_desc_b_id.idx", "desc_metadata.db", "desc_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "desc_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        table.create_table("desc_b".to_string(), vec![
+            Field::create_field("desc_b_id".to_string(), FieldType::INT64)?,
+        ])?;
+        table.create_index("desc_b".to_string(), 0)?;
+        table.create_table("desc_a".to_string(), vec![
+            Field::create_field("desc_a_id".to_string(), FieldType::INT32)?,
+            Field::create_nullable_field("name".to_string(), FieldType::VARCHAR40)?,
+        ])?;
+        table.create_index("desc_a".to_string(), 0)?;
+        table.flush()?;
+        drop(table);
+
+        let buffer = Box::new(LRUBuffer::new(4, "desc_metadata.db".to_string())?);
+        let table = TableManager::open(buffer)?;
+        assert_eq!(table.list_tables(), vec!["desc_a".to_string(), "desc_b".to_string()]);
+
+        let columns = table.describe_table("desc_a".to_string())?;
+        assert_eq!(columns.len(), 2);
+        assert_eq!(columns[0].0, "desc_a_id");
+        assert!(matches!(columns[0].1, FieldType::INT32));
+        assert_eq!(columns[1].0, "name");
+        assert!(matches!(columns[1].1, FieldType::VARCHAR40));
+        match table.describe_table("missing".to_string()) {
+            Err(Error::TableNotFound) => (),
+            _ => panic!("expected TableNotFound")
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_csv() -> Result<(), Error>{
+        let files = ["csv_table", "csv_table_csv_id.idx", "csv_metadata.db", "csv_metadata.db.catalog", "import.csv", "import_bad.csv"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "csv_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        table.create_table("csv_table".to_string(), vec![
+            Field::create_field("csv_id".to_string(), FieldType::INT32)?,
+            Field::create_nullable_field("name".to_string(), FieldType::VARCHAR40)?,
+            Field::create_nullable_field("score".to_string(), FieldType::FLOAT64)?,
+        ])?;
+        table.create_index("csv_table".to_string(), 0)?;
+
+        fs::write("import.csv", "csv_id,name,score\n1,alice,90.5\n2,\"smith, \"\"bob\"\"\",\n3,,77\r\n").unwrap();
+        assert_eq!(table.import_csv("csv_table".to_string(), Path::new("import.csv"), true)?, 3);
+        assert_eq!(table.read_full_table("csv_table".to_string())?.len(), 3);
+
+        let row = table.search("csv_table".to_string(), 0, FieldValue::INT32(2))?;
+        match (&row.data[1], &row.data[2]) {
+            (FieldValue::VARCHAR40(name), FieldValue::Null) => assert_eq!(name, "smith, \"bob\""),
+            _ => panic!("expected quoted name and null score")
+        }
+        let row = table.search("csv_table".to_string(), 0, FieldValue::INT32(1))?;
+        assert!(matches!(row.data[2], FieldValue::FLOAT64(score) if score == 90.5));
+
+        // 第二条记录少一列，第三条无法解析
+        fs::write("import_bad.csv", "4,d,1\n5,e\n").unwrap();
+        match table.import_csv("csv_table".to_string(), Path::new("import_bad.csv"), false) {
+            Err(Error::CsvFormat(2)) => (),
+            _ => panic!("expected CsvFormat(2)")
+        }
+        fs::write("import_bad.csv", "6,f,x\n").unwrap();
+        match table.import_csv("csv_table".to_string(), Path::new("import_bad.csv"), false) {
+            Err(Error::CsvFormat(1)) => (),
+            _ => panic!("expected CsvFormat(1)")
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_csv() -> Result<(), Error>{
+        let files = ["exp_table", "exp_table_exp_id.idx", "exp_metadata.db", "exp_metadata.db.catalog", "export.csv"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "exp_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        table.create_table("exp_table".to_string(), vec![
+            Field::create_field("exp_id".to_string(), FieldType::INT32)?,
+            Field::create_nullable_field("name".to_string(), FieldType::VARCHAR40)?,
+        ])?;
+        table.create_index("exp_table".to_string(), 0)?;
+        let names = [FieldValue::VARCHAR40("plain".to_string()), FieldValue::VARCHAR40("a, \"b\"".to_string()), FieldValue::Null];
+        for (i, name) in names.iter().enumerate() {
+            table.insert("exp_table".to_string(), Entry { data: vec![FieldValue::INT32(i as i32), name.clone()] })?;
+        }
+
+        assert_eq!(table.export_csv("exp_table".to_string(), Path::new("export.csv"))?, 3);
+        assert_eq!(fs::read_to_string("export.csv").unwrap(), "0,plain\n1,\"a, \"\"b\"\"\"\n2,\n");
+
+        // 导出的文件可以原样导入
+        table.delete("exp_table".to_string(), 0, FieldValue::INT32(1))?;
+        fs::write("export.csv", "1,\"a, \"\"b\"\"\"\n").unwrap();
+        table.import_csv("exp_table".to_string(), Path::new("export.csv"), false)?;
+        let row = table.search("exp_table".to_string(), 0, FieldValue::INT32(1))?;
+        match &row.data[1] {
+            FieldValue::VARCHAR40(name) => assert_eq!(name, "a, \"b\""),
+            _ => panic!("expected VARCHAR40")
+        }
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_batch() -> Result<(), Error>{
+        let files = ["batch_table", "batch_table_batch_id.idx", "batch_metadata.db", "batch_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "batch_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        table.create_table("batch_table".to_string(), vec![
+            Field::create_field("batch_id".to_string(), FieldType::INT32)?,
+            Field::create_field("value".to_string(), FieldType::INT64)?,
+        ])?;
+        table.create_index("batch_table".to_string(), 0)?;
+
+        let entries: Vec<Entry> = (0..100).rev().map(|i| Entry {
+            data: vec![FieldValue::INT32(i), FieldValue::INT64(i as i64 * 10)]
+        }).collect();
+        table.insert_batch("batch_table".to_string(), entries)?;
+        for i in 0..100 {
+            let row = table.search("batch_table".to_string(), 0, FieldValue::INT32(i))?;
+            assert!(matches!(row.data[1], FieldValue::INT64(v) if v == i as i64 * 10));
+        }
+
+        // 批内重复、与已有行重复或类型不符时，整批都不插入
+        let bad_batches = vec![
+            vec![Entry { data: vec![FieldValue::INT32(200), FieldValue::INT64(0)] }, Entry { data: vec![FieldValue::INT32(200), FieldValue::INT64(1)] }],
+            vec![Entry { data: vec![FieldValue::INT32(201), FieldValue::INT64(0)] }, Entry { data: vec![FieldValue::INT32(5), FieldValue::INT64(1)] }],
+            vec![Entry { data: vec![FieldValue::INT32(202), FieldValue::INT64(0)] }, Entry { data: vec![FieldValue::INT32(203), FieldValue::INT32(1)] }],
+        ];
+        for batch in bad_batches {
+            assert!(table.insert_batch("batch_table".to_string(), batch).is_err());
+        }
+        assert_eq!(table.read_full_table("batch_table".to_string())?.len(), 100);
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_varchar_index_key_size() -> Result<(), Error>{
+        let files = ["vk_table_vk_name.idx", "vk_table", "vk_metadata.db", "vk_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "vk_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("vk_name".to_string(), FieldType::VARCHAR40)?,
+            Field::create_field("vk_no".to_string(), FieldType::INT32)?,
+        ];
+        table.create_table("vk_table".to_string(), fields)?;
+        table.create_index("vk_table".to_string(), 0)?;
+        let key_size = table.table_cache.get("vk_table").unwrap().fields[0].btree().unwrap().get_key_size();
+        assert_eq!(key_size, 40);
+
+        // 前 10 个字节相同的键仍然是不同的键
+        let names = ["prefix0123-a", "prefix0123-b", "prefix0123-0123456789012345678901234567"];
+        for (i, name) in names.iter().enumerate() {
+            let entry = Entry {
+                data: vec![FieldValue::VARCHAR40(name.to_string()), FieldValue::INT32(i as i32)]
+            };
+            table.insert("vk_table".to_string(), entry)?;
+        }
+        for (i, name) in names.iter().enumerate() {
+            let row = table.search("vk_table".to_string(), 0, FieldValue::VARCHAR40(name.to_string()))?;
+            match row.data[1] {
+                FieldValue::INT32(no) => assert_eq!(no, i as i32),
+                _ => panic!("expected INT32")
+            }
+        }
+        assert_eq!(table.read_full_table("vk_table".to_string())?.len(), 3);
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_count() -> Result<(), Error>{
+        let files = ["cnt_table_cnt_id.idx", "cnt_table", "cnt_metadata.db", "cnt_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "cnt_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("cnt_id".to_string(), FieldType::INT32)?,
+            Field::create_field("value".to_string(), FieldType::INT64)?,
+        ];
+        table.create_table("cnt_table".to_string(), fields)?;
+        assert_eq!(table.count("cnt_table".to_string())?, 0);
+
+        table.create_index("cnt_table".to_string(), 0)?;
+        for i in 0..5 {
+            let entry = Entry {
+                data: vec![FieldValue::INT32(i), FieldValue::INT64(i as i64 * 100)]
+            };
+            table.insert("cnt_table".to_string(), entry)?;
+        }
+        assert_eq!(table.count("cnt_table".to_string())?, 5);
+        assert!(matches!(table.count("no_such_table".to_string()), Err(Error::TableNotFound)));
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_projection() -> Result<(), Error>{
+        let files = ["proj_table_proj_id.idx", "proj_table", "proj_metadata.db", "proj_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "proj_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("proj_id".to_string(), FieldType::INT32)?,
+            Field::create_field("value".to_string(), FieldType::INT64)?,
+            Field::create_field("name".to_string(), FieldType::VARCHAR40)?,
+        ];
+        table.create_table("proj_table".to_string(), fields)?;
+        table.create_index("proj_table".to_string(), 0)?;
+        for i in 0..3 {
+            let entry = Entry {
+                data: vec![FieldValue::INT32(i), FieldValue::INT64(i as i64 * 100), FieldValue::VARCHAR40(format!("n{}", i))]
+            };
+            table.insert("proj_table".to_string(), entry)?;
+        }
+
+        let row = table.search_projected("proj_table".to_string(), 0, FieldValue::INT32(2), Some(&[1]))?;
+        assert_eq!(row.data.len(), 1);
+        assert!(matches!(row.data[0], FieldValue::INT64(200)));
+
+        // 按请求的顺序返回字段
+        let rows = table.read_full_table_projected("proj_table".to_string(), Some(&[2, 0]))?;
+        assert_eq!(rows.len(), 3);
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!(row.data.len(), 2);
+            match (&row.data[0], &row.data[1]) {
+                (FieldValue::VARCHAR40(name), FieldValue::INT32(id)) => {
+                    assert_eq!(name.trim_end_matches('\0'), format!("n{}", i));
+                    assert_eq!(*id, i as i32);
+                }
+                _ => panic!("expected VARCHAR40 and INT32")
+            }
+        }
+        assert_eq!(table.read_full_table_projected("proj_table".to_string(), None)?[0].data.len(), 3);
+        assert!(matches!(table.search_projected("proj_table".to_string(), 0, FieldValue::INT32(0), Some(&[3])), Err(Error::FieldNotFound)));
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_full_table_desc() -> Result<(), Error>{
+        let files = ["desc_table_desc_id.idx", "desc_table", "desc_metadata.db", "desc_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "desc_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("desc_id".to_string(), FieldType::INT32)?,
+            Field::create_field("value".to_string(), FieldType::INT64)?,
+        ];
+        table.create_table("desc_table".to_string(), fields)?;
+        table.create_index("desc_table".to_string(), 0)?;
+        // 插入足够多的行，使索引有多个叶子
+        for i in (0..300).rev().step_by(7).chain((0..300).step_by(7).map(|i| i + 3)) {
+            let entry = Entry {
+                data: vec![FieldValue::INT32(i), FieldValue::INT64(i as i64 * 10)]
+            };
+            table.insert("desc_table".to_string(), entry)?;
+        }
+
+        let ids = |rows: Vec<Entry>| -> Vec<i32> {
+            rows.iter().map(|row| match row.data[0] {
+                FieldValue::INT32(id) => id,
+                _ => panic!("expected INT32")
+            }).collect()
+        };
+        let asc = ids(table.read_full_table("desc_table".to_string())?);
+        let mut desc = ids(table.read_full_table_desc("desc_table".to_string())?);
+        assert_eq!(asc.len(), 86);
+        desc.reverse();
+        assert_eq!(desc, asc);
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_limited() -> Result<(), Error>{
+        let files = ["lim_table_lim_id.idx", "lim_table", "lim_metadata.db"];
+        rm_files(&files);
+
+        let mut buffer: Box<dyn Buffer> = Box::new(LRUBuffer::new(4, "lim_metadata.db".to_string())?);
+        let mut table = Table::new("lim_table".to_string(), &mut buffer)?;
+        table.add_fields(vec![
+            Field::create_field("lim_id".to_string(), FieldType::INT32)?,
+            Field::create_field("no".to_string(), FieldType::INT32)?,
+        ]);
+        table.create_index(0, &mut buffer)?;
+        for i in 1..=20 {
+            let entry = Entry {
+                data: vec![FieldValue::INT32(i), FieldValue::INT32(i)]
+            };
+            table.insert(entry, &mut buffer)?;
+        }
+
+        let rows = table.scan_limited(0, None, None, 5, 10, &mut buffer)?;
+        let nos: Vec<i32> = rows.iter().map(|row| match row.data[1] {
+            FieldValue::INT32(no) => no,
+            _ => panic!("expected INT32")
+        }).collect();
+        assert_eq!(nos, vec![11, 12, 13, 14, 15]);
+
+        // 超出范围的 offset 返回空，limit 超过剩余行数时返回剩余的行
+        assert!(table.scan_limited(0, None, None, 5, 20, &mut buffer)?.is_empty());
+        assert_eq!(table.scan_limited(0, None, None, 5, 18, &mut buffer)?.len(), 2);
+        assert_eq!(table.scan_limited(0, Some(FieldValue::INT32(11)), None, 100, 0, &mut buffer)?.len(), 10);
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unique_index() -> Result<(), Error>{
+        let files = ["uq_table_uq_id.idx", "uq_table_uq_email.idx", "uq_table", "uq_metadata.db", "uq_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "uq_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("uq_id".to_string(), FieldType::INT32)?,
+            Field::create_field("uq_email".to_string(), FieldType::VARCHAR40)?,
+        ];
+        table.create_table("uq_table".to_string(), fields)?;
+        table.create_index("uq_table".to_string(), 0)?;
+        table.create_unique_index("uq_table".to_string(), 1)?;
+        table.insert("uq_table".to_string(), Entry {
+            data: vec![FieldValue::INT32(1), FieldValue::VARCHAR40("a@example.com".to_string())]
+        })?;
+        let used = table.table_cache.get("uq_table").unwrap().pager.used_size();
+
+        let res = table.insert("uq_table".to_string(), Entry {
+            data: vec![FieldValue::INT32(2), FieldValue::VARCHAR40("a@example.com".to_string())]
+        });
+        assert!(matches!(res, Err(Error::KeyAlreadyExists)));
+        // 检查在写入行之前进行，表文件和主索引都没有被修改
+        assert_eq!(table.table_cache.get("uq_table").unwrap().pager.used_size(), used);
+        assert!(matches!(table.search("uq_table".to_string(), 0, FieldValue::INT32(2)), Err(Error::KeyNotFound)));
+        assert_eq!(table.count("uq_table".to_string())?, 1);
+
+        table.insert("uq_table".to_string(), Entry {
+            data: vec![FieldValue::INT32(2), FieldValue::VARCHAR40("b@example.com".to_string())]
+        })?;
+        table.flush()?;
+        drop(table);
+
+        // 唯一索引记录在表结构目录中
+        let buffer = Box::new(LRUBuffer::new(4, "uq_metadata.db".to_string())?);
+        let table = TableManager::open(buffer)?;
+        let t = table.table_cache.get("uq_table").unwrap();
+        assert!(!t.fields[0].unique);
+        assert!(t.fields[1].unique);
+        assert!(t.fields[1].is_indexed());
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unique_index_update() -> Result<(), Error>{
+        let files = ["uqu_table_uqu_id.idx", "uqu_table_uqu_code.idx", "uqu_table", "uqu_metadata.db", "uqu_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "uqu_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("uqu_id".to_string(), FieldType::INT32)?,
+            Field::create_field("uqu_code".to_string(), FieldType::INT32)?,
+        ];
+        table.create_table("uqu_table".to_string(), fields)?;
+        table.create_index("uqu_table".to_string(), 0)?;
+        table.create_unique_index("uqu_table".to_string(), 1)?;
+        let entry = |id: i32, code: i32| Entry {
+            data: vec![FieldValue::INT32(id), FieldValue::INT32(code)]
+        };
+        table.insert("uqu_table".to_string(), entry(1, 10))?;
+        table.insert("uqu_table".to_string(), entry(2, 20))?;
+        let used = table.table_cache.get("uqu_table").unwrap().pager.used_size();
+
+        // 更新和 upsert 都不能把唯一索引上的键改成已有的键，被拒绝时行和各个索引都保持原样
+        let res = table.update("uqu_table".to_string(), 0, FieldValue::INT32(2), entry(2, 10));
+        assert!(matches!(res, Err(Error::KeyAlreadyExists)));
+        let res = table.upsert("uqu_table".to_string(), entry(2, 10));
+        assert!(matches!(res, Err(Error::KeyAlreadyExists)));
+        assert_eq!(table.table_cache.get("uqu_table").unwrap().pager.used_size(), used);
+        assert_eq!(table.search("uqu_table".to_string(), 0, FieldValue::INT32(2))?, entry(2, 20));
+        assert_eq!(table.search("uqu_table".to_string(), 1, FieldValue::INT32(20))?, entry(2, 20));
+        assert_eq!(table.search("uqu_table".to_string(), 1, FieldValue::INT32(10))?, entry(1, 10));
+        assert_eq!(table.read_full_table("uqu_table".to_string())?, vec![entry(1, 10), entry(2, 20)]);
+
+        // 改成没有用过的键仍然可以
+        table.update("uqu_table".to_string(), 0, FieldValue::INT32(2), entry(2, 30))?;
+        assert_eq!(table.search("uqu_table".to_string(), 1, FieldValue::INT32(30))?, entry(2, 30));
+        assert!(matches!(table.search("uqu_table".to_string(), 1, FieldValue::INT32(20)), Err(Error::KeyNotFound)));
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_numeric_key_order() -> Result<(), Error>{
+        let files = ["num_table_num_id.idx", "num_table_num_value.idx", "num_table", "num_metadata.db", "num_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "num_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("num_id".to_string(), FieldType::INT32)?,
+            Field::create_field("num_value".to_string(), FieldType::FLOAT64)?,
+        ];
+        table.create_table("num_table".to_string(), fields)?;
+        table.create_index("num_table".to_string(), 0)?;
+        table.create_index("num_table".to_string(), 1)?;
+        for i in [10, 2, -3, 100, i32::MIN, i32::MAX] {
+            let entry = Entry {
+                data: vec![FieldValue::INT32(i), FieldValue::FLOAT64(i as f64 / 4.0)]
+            };
+            table.insert("num_table".to_string(), entry)?;
+        }
+
+        let ids = |rows: Vec<Entry>| -> Vec<FieldValue> {
+            rows.into_iter().map(|row| row.data[0].clone()).collect()
+        };
+        // 键按数值而不是按字符串排序，"10" 排在 "2" 之后
+        let rows = table.search_range("num_table".to_string(), 0, Some(FieldValue::INT32(2)), None)?;
+        assert_eq!(ids(rows), vec![FieldValue::INT32(2), FieldValue::INT32(10), FieldValue::INT32(100), FieldValue::INT32(i32::MAX)]);
+        let rows = table.read_full_table("num_table".to_string())?;
+        assert_eq!(ids(rows), [i32::MIN, -3, 2, 10, 100, i32::MAX].iter().map(|i| FieldValue::INT32(*i)).collect::<Vec<FieldValue>>());
+
+        // 负的浮点数排在正的浮点数之前
+        let rows = table.search_range("num_table".to_string(), 1, Some(FieldValue::FLOAT64(-1.0)), Some(FieldValue::FLOAT64(25.0)))?;
+        assert_eq!(ids(rows), vec![FieldValue::INT32(-3), FieldValue::INT32(2), FieldValue::INT32(10), FieldValue::INT32(100)]);
+
+        let row = table.search("num_table".to_string(), 0, FieldValue::INT32(-3))?;
+        assert_eq!(row.data[1], FieldValue::FLOAT64(-0.75));
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_float_key_order() -> Result<(), Error>{
+        let files = ["flt_table_flt_key.idx", "flt_table", "flt_metadata.db", "flt_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "flt_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("flt_key".to_string(), FieldType::FLOAT32)?,
+        ];
+        table.create_table("flt_table".to_string(), fields)?;
+        table.create_index("flt_table".to_string(), 0)?;
+        for f in [3.25f32, -1.5, 0.0, 10.0, 2.0] {
+            table.insert("flt_table".to_string(), Entry { data: vec![FieldValue::FLOAT32(f)] })?;
+        }
+
+        let keys: Vec<FieldValue> = table.scan("flt_table".to_string(), 0, Some(FieldValue::FLOAT32(-1.5)), Some(FieldValue::FLOAT32(3.25)))?
+            .map(|row| row.map(|row| row.data[0].clone()))
+            .collect::<Result<Vec<FieldValue>, Error>>()?;
+        assert_eq!(keys, [-1.5f32, 0.0, 2.0, 3.25].iter().map(|f| FieldValue::FLOAT32(*f)).collect::<Vec<FieldValue>>());
+
+        // 键可以还原成原来的值
+        for fv in [FieldValue::FLOAT32(-1.5), FieldValue::FLOAT32(0.0), FieldValue::FLOAT64(-0.0), FieldValue::FLOAT64(3.25), FieldValue::INT32(-7), FieldValue::INT64(i64::MAX)] {
+            let field_type = match fv {
+                FieldValue::FLOAT32(_) => FieldType::FLOAT32,
+                FieldValue::FLOAT64(_) => FieldType::FLOAT64,
+                FieldValue::INT32(_) => FieldType::INT32,
+                _ => FieldType::INT64,
+            };
+            assert_eq!(FieldValue::from_key(&field_type, fv.to_key().as_str())?, fv);
+        }
+        assert!(FieldValue::FLOAT64(-0.0).to_key() < FieldValue::FLOAT64(0.0).to_key());
+        assert!(matches!(FieldValue::from_key(&FieldType::INT32, "zz"), Err(Error::FieldValueNotCompatible)));
+        assert!(matches!(FieldValue::from_key(&FieldType::INT64, "00"), Err(Error::FieldValueNotCompatible)));
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_transaction() -> Result<(), Error>{
+        let files = ["tx_table", "tx_table.key.idx", "tx_table_tx_id.idx", "tx_table_tx_email.idx", "tx_metadata.db", "tx_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "tx_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        let fields = vec![
+            Field::create_field("tx_id".to_string(), FieldType::INT32)?,
+            Field::create_field("tx_email".to_string(), FieldType::VARCHAR40)?,
+        ];
+        table.create_table_with_key("tx_table".to_string(), fields, &[0])?;
+        table.create_unique_index("tx_table".to_string(), 1)?;
+        let entry = |id: i32, email: &str| Entry {
+            data: vec![FieldValue::INT32(id), FieldValue::VARCHAR40(email.to_string())]
+        };
+        table.insert("tx_table".to_string(), entry(0, "root@example.com"))?;
+        let used = table.table_cache.get("tx_table").unwrap().pager.used_size();
+
+        // 事务中的插入足够多，使两个索引都发生分裂，缓冲区也会换出事务中修改过的页
+        table.begin("tx_table".to_string())?;
+        assert!(matches!(table.begin("tx_table".to_string()), Err(Error::TransactionState)));
+        for i in 1..=100 {
+            table.insert("tx_table".to_string(), entry(i, format!("user{}@example.com", i).as_str()))?;
+        }
+        let res = table.insert("tx_table".to_string(), entry(101, "user1@example.com"));
+        assert!(matches!(res, Err(Error::KeyAlreadyExists)));
+        assert_eq!(table.count("tx_table".to_string())?, 101);
+        table.rollback("tx_table".to_string())?;
+
+        // 回滚后表回到事务开始时的状态，主键索引中的插入也被撤销
+        assert_eq!(table.count("tx_table".to_string())?, 1);
+        assert_eq!(table.table_cache.get("tx_table").unwrap().pager.used_size(), used);
+        assert!(matches!(table.search("tx_table".to_string(), 0, FieldValue::INT32(50)), Err(Error::KeyNotFound)));
+        assert!(matches!(table.search("tx_table".to_string(), 1, FieldValue::VARCHAR40("user50@example.com".to_string())), Err(Error::KeyNotFound)));
+        assert_eq!(table.read_full_table("tx_table".to_string())?, vec![entry(0, "root@example.com")]);
+        assert!(matches!(table.rollback("tx_table".to_string()), Err(Error::TransactionState)));
+        assert!(matches!(table.commit("tx_table".to_string()), Err(Error::TransactionState)));
+
+        // 提交的事务在重新打开后仍然可见
+        table.begin("tx_table".to_string())?;
+        table.insert("tx_table".to_string(), entry(1, "user1@example.com"))?;
+        table.commit("tx_table".to_string())?;
+        table.flush()?;
+        drop(table);
+
+        let buffer = Box::new(LRUBuffer::new(4, "tx_metadata.db".to_string())?);
+        let mut table = TableManager::open(buffer)?;
+        assert_eq!(table.count("tx_table".to_string())?, 2);
+        assert_eq!(table.search("tx_table".to_string(), 1, FieldValue::VARCHAR40("user1@example.com".to_string()))?, entry(1, "user1@example.com"));
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_row_by_offset() -> Result<(), Error>{
+        let files = ["rbo_table_rbo_id.idx", "rbo_table", "rbo_metadata.db"];
+        rm_files(&files);
+
+        let mut buffer: Box<dyn Buffer> = Box::new(LRUBuffer::new(4, "rbo_metadata.db".to_string())?);
+        let mut table = Table::new("rbo_table".to_string(), &mut buffer)?;
+        table.add_fields(vec![
+            Field::create_field("rbo_id".to_string(), FieldType::INT32)?,
+            Field::create_field("rbo_name".to_string(), FieldType::VARCHAR40)?,
+        ]);
+        table.create_index(0, &mut buffer)?;
+        let entry = |id: i32| Entry {
+            data: vec![FieldValue::INT32(id), FieldValue::VARCHAR40(format!("name{}", id))]
+        };
+        for i in 0..10 {
+            table.insert(entry(i), &mut buffer)?;
+        }
+
+        let offset = table.fields[0].search_offset(FieldValue::INT32(7), &mut buffer, &table.pager)?;
+        assert_eq!(table.row_by_offset(offset, &mut buffer)?, entry(7));
+        assert!(matches!(table.row_by_offset(1 << 30, &mut buffer), Err(Error::PageNumOutOfSize)));
+
+        // 删除后原来的偏移不再指向行
+        table.delete(0, FieldValue::INT32(7), &mut buffer)?;
+        assert!(matches!(table.row_by_offset(offset, &mut buffer), Err(Error::PageNumOutOfSize)));
+
+        drop(buffer);
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_join() -> Result<(), Error>{
+        let files = ["jo_orders_jo_id.idx", "jo_orders_jo_customer.idx", "jc_customers_jc_id.idx", "jo_orders", "jc_customers", "join_metadata.db", "join_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "join_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        table.create_table("jo_orders".to_string(), vec![
+            Field::create_field("jo_id".to_string(), FieldType::INT32)?,
+            Field::create_field("jo_customer".to_string(), FieldType::INT32)?,
+            Field::create_field("jo_amount".to_string(), FieldType::INT64)?,
+        ])?;
+        table.create_index("jo_orders".to_string(), 0)?;
+        table.create_index("jo_orders".to_string(), 1)?;
+        table.create_table("jc_customers".to_string(), vec![
+            Field::create_field("jc_id".to_string(), FieldType::INT32)?,
+            Field::create_field("jc_name".to_string(), FieldType::VARCHAR40)?,
+        ])?;
+        table.create_index("jc_customers".to_string(), 0)?;
+
+        let order = |id: i32, customer: i32, amount: i64| Entry {
+            data: vec![FieldValue::INT32(id), FieldValue::INT32(customer), FieldValue::INT64(amount)]
+        };
+        let customer = |id: i32, name: &str| Entry {
+            data: vec![FieldValue::INT32(id), FieldValue::VARCHAR40(name.to_string())]
+        };
+        for c in [customer(1, "alice"), customer(2, "bob"), customer(3, "carol")] {
+            table.insert("jc_customers".to_string(), c)?;
+        }
+        // 订单 12 的客户不存在，客户 2 没有订单
+        for o in [order(10, 3, 300), order(11, 1, 100), order(12, 9, 900)] {
+            table.insert("jo_orders".to_string(), o)?;
+        }
+
+        let pairs = table.join("jo_orders".to_string(), 1, "jc_customers".to_string(), 0)?;
+        assert_eq!(pairs, vec![
+            (order(11, 1, 100), customer(1, "alice")),
+            (order(10, 3, 300), customer(3, "carol")),
+        ]);
+        assert!(matches!(table.join("jo_orders".to_string(), 1, "missing".to_string(), 0), Err(Error::TableNotFound)));
+        // 连接字段没有索引
+        assert!(matches!(table.join("jo_orders".to_string(), 2, "jc_customers".to_string(), 0), Err(Error::IndexWithoutBTree)));
+        assert!(matches!(table.join("jo_orders".to_string(), 1, "jc_customers".to_string(), 1), Err(Error::IndexWithoutBTree)));
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregate() -> Result<(), Error>{
+        let files = ["agg_table_agg_id.idx", "agg_table_agg_score.idx", "agg_table", "agg_metadata.db", "agg_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "agg_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        table.create_table("agg_table".to_string(), vec![
+            Field::create_field("agg_id".to_string(), FieldType::INT32)?,
+            Field::create_field("agg_score".to_string(), FieldType::INT32)?,
+            Field::create_field("agg_weight".to_string(), FieldType::FLOAT64)?,
+            Field::create_field("agg_name".to_string(), FieldType::VARCHAR40)?,
+        ])?;
+        table.create_index("agg_table".to_string(), 0)?;
+        table.create_index("agg_table".to_string(), 1)?;
+
+        // 空表
+        assert!(matches!(table.aggregate("agg_table".to_string(), 1, Agg::Sum)?, FieldValue::INT64(0)));
+        assert!(matches!(table.aggregate("agg_table".to_string(), 1, Agg::Avg)?, FieldValue::Null));
+        assert!(matches!(table.aggregate("agg_table".to_string(), 1, Agg::Min)?, FieldValue::Null));
+        assert!(matches!(table.aggregate("agg_table".to_string(), 2, Agg::Max)?, FieldValue::Null));
+
+        for (id, score, weight) in [(1, 40, 1.5), (2, -5, 2.0), (3, 25, 0.5)] {
+            table.insert("agg_table".to_string(), Entry {
+                data: vec![FieldValue::INT32(id), FieldValue::INT32(score), FieldValue::FLOAT64(weight), FieldValue::VARCHAR40(format!("n{}", id))]
+            })?;
+        }
+
+        assert_eq!(table.aggregate("agg_table".to_string(), 1, Agg::Sum)?, FieldValue::INT64(60));
+        assert_eq!(table.aggregate("agg_table".to_string(), 1, Agg::Avg)?, FieldValue::FLOAT64(20.0));
+        // 有索引的字段读索引两端，没有索引的字段扫描整张表
+        assert_eq!(table.aggregate("agg_table".to_string(), 1, Agg::Min)?, FieldValue::INT32(-5));
+        assert_eq!(table.aggregate("agg_table".to_string(), 1, Agg::Max)?, FieldValue::INT32(40));
+        assert_eq!(table.aggregate("agg_table".to_string(), 2, Agg::Sum)?, FieldValue::FLOAT64(4.0));
+        assert_eq!(table.aggregate("agg_table".to_string(), 2, Agg::Min)?, FieldValue::FLOAT64(0.5));
+        assert_eq!(table.aggregate("agg_table".to_string(), 2, Agg::Max)?, FieldValue::FLOAT64(2.0));
+
+        assert!(matches!(table.aggregate("agg_table".to_string(), 3, Agg::Sum), Err(Error::FieldValueNotCompatible)));
+        assert!(matches!(table.aggregate("agg_table".to_string(), 4, Agg::Sum), Err(Error::FieldNotFound)));
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_filtered() -> Result<(), Error>{
+        let files = ["flt_table_flt_id.idx", "flt_table", "flt_metadata.db", "flt_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "flt_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        table.create_table("flt_table".to_string(), vec![
+            Field::create_field("flt_id".to_string(), FieldType::INT32)?,
+            Field::create_field("flt_a".to_string(), FieldType::INT32)?,
+            Field::create_field("flt_b".to_string(), FieldType::INT64)?,
+        ])?;
+        table.create_index("flt_table".to_string(), 0)?;
+
+        for (id, a, b) in [(1, 3, 10), (2, 6, 50), (3, 7, 100), (4, 9, 99), (5, 5, 1), (6, 20, 200)] {
+            table.insert("flt_table".to_string(), Entry {
+                data: vec![FieldValue::INT32(id), FieldValue::INT32(a), FieldValue::INT64(b)]
+            })?;
+        }
+
+        // a > 5 AND b < 100
+        let predicate = Predicate::Gt(1, FieldValue::INT32(5)).and(Predicate::Lt(2, FieldValue::INT64(100)));
+        let rows = table.scan_filtered("flt_table".to_string(), &predicate)?;
+        let ids: Vec<FieldValue> = rows.into_iter().map(|row| row.data[0].clone()).collect();
+        assert_eq!(ids, vec![FieldValue::INT32(2), FieldValue::INT32(4)]);
+
+        let predicate = Predicate::Eq(0, FieldValue::INT32(1)).or(Predicate::Gt(2, FieldValue::INT64(150)));
+        let rows = table.scan_filtered("flt_table".to_string(), &predicate)?;
+        let ids: Vec<FieldValue> = rows.into_iter().map(|row| row.data[0].clone()).collect();
+        assert_eq!(ids, vec![FieldValue::INT32(1), FieldValue::INT32(6)]);
+
+        // 常量与字段类型不符，即使被短路的一侧也会报错
+        let predicate = Predicate::Gt(1, FieldValue::INT64(5));
+        assert!(matches!(table.scan_filtered("flt_table".to_string(), &predicate), Err(Error::FieldValueNotCompatible)));
+        let predicate = Predicate::Eq(0, FieldValue::INT32(1)).or(Predicate::Lt(2, FieldValue::VARCHAR40("x".to_string())));
+        assert!(matches!(table.scan_filtered("flt_table".to_string(), &predicate), Err(Error::FieldValueNotCompatible)));
+        let predicate = Predicate::Eq(3, FieldValue::INT32(1));
+        assert!(matches!(table.scan_filtered("flt_table".to_string(), &predicate), Err(Error::FieldNotFound)));
+        assert!(matches!(table.scan_filtered("no_table".to_string(), &predicate), Err(Error::TableNotFound)));
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_table() -> Result<(), Error>{
+        let files = ["ren_table", "ren_table.key.idx", "ren_table_ren_b.idx", "ren_new", "ren_new.key.idx", "ren_new_ren_b.idx", "ren_other", "ren_other_ren_b.idx", "ren_metadata.db", "ren_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "ren_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        table.create_table_with_key("ren_table".to_string(), vec![
+            Field::create_field("ren_a".to_string(), FieldType::INT32)?,
+            Field::create_field("ren_b".to_string(), FieldType::INT32)?,
+        ], &[0, 1])?;
+        table.create_index("ren_table".to_string(), 1)?;
+        // 两个表有同名的索引字段，各自使用以表名区分的索引文件
+        table.create_table("ren_other".to_string(), vec![
+            Field::create_field("ren_b".to_string(), FieldType::INT32)?,
+        ])?;
+        table.create_index("ren_other".to_string(), 0)?;
+        for i in 0..5 {
+            table.insert("ren_table".to_string(), Entry {
+                data: vec![FieldValue::INT32(i), FieldValue::INT32(i * 10)]
+            })?;
+        }
+        table.insert("ren_other".to_string(), Entry {
+            data: vec![FieldValue::INT32(7)]
+        })?;
+        assert!(Path::new("ren_table_ren_b.idx").exists() && Path::new("ren_other_ren_b.idx").exists());
+        assert!(matches!(table.search("ren_other".to_string(), 0, FieldValue::INT32(30)), Err(Error::KeyNotFound)));
+
+        assert!(matches!(table.rename_table("ren_table".to_string(), "ren_other".to_string()), Err(Error::TableAlreadyExists)));
+        assert!(matches!(table.rename_table("ren_none".to_string(), "ren_new".to_string()), Err(Error::TableNotFound)));
+
+        table.rename_table("ren_table".to_string(), "ren_new".to_string())?;
+        assert!(Path::new("ren_new").exists() && Path::new("ren_new.key.idx").exists());
+        assert!(!Path::new("ren_table").exists() && !Path::new("ren_table.key.idx").exists());
+        assert!(Path::new("ren_new_ren_b.idx").exists() && !Path::new("ren_table_ren_b.idx").exists());
+        assert!(matches!(table.read_full_table("ren_table".to_string()), Err(Error::TableNotFound)));
+        assert_eq!(table.list_tables(), vec!["ren_new".to_string(), "ren_other".to_string()]);
+
+        // 改名后已有的行仍可读取，新插入的行不会覆盖它们
+        table.insert("ren_new".to_string(), Entry {
+            data: vec![FieldValue::INT32(5), FieldValue::INT32(50)]
+        })?;
+        let row = table.search_by_key("ren_new".to_string(), vec![FieldValue::INT32(3), FieldValue::INT32(30)])?;
+        assert_eq!(row.data, vec![FieldValue::INT32(3), FieldValue::INT32(30)]);
+        assert_eq!(table.read_full_table("ren_new".to_string())?.len(), 6);
+        assert_eq!(table.search_all("ren_new".to_string(), 1, FieldValue::INT32(50))?.len(), 1);
+        assert_eq!(table.search("ren_other".to_string(), 0, FieldValue::INT32(7))?.data, vec![FieldValue::INT32(7)]);
+        table.flush()?;
+        drop(table);
+
+        // 表结构目录中记录的是新表名
+        let buffer = Box::new(LRUBuffer::new(4, "ren_metadata.db".to_string())?);
+        let mut table = TableManager::open(buffer)?;
+        assert_eq!(table.list_tables(), vec!["ren_new".to_string(), "ren_other".to_string()]);
+        assert_eq!(table.read_full_table("ren_new".to_string())?.len(), 6);
+        let rows = table.search_all("ren_new".to_string(), 1, FieldValue::INT32(30))?;
+        assert_eq!(rows.iter().map(|row| row.data.clone()).collect::<Vec<_>>(), vec![vec![FieldValue::INT32(3), FieldValue::INT32(30)]]);
+        assert_eq!(table.search("ren_other".to_string(), 0, FieldValue::INT32(7))?.data, vec![FieldValue::INT32(7)]);
+
+        rm_files(&files);
+        Ok(())
+    }
+
+    #[test]
+    fn test_upsert() -> Result<(), Error>{
+        let files = ["ups_table_ups_id.idx", "ups_table_ups_name.idx", "ups_table", "ups_key_table", "ups_key_table.key.idx", "ups_metadata.db", "ups_metadata.db.catalog"];
+        rm_files(&files);
+
+        let buffer = Box::new(LRUBuffer::new(4, "ups_metadata.db".to_string())?);
+        let mut table = TableManager::new(buffer);
+        table.create_table("ups_table".to_string(), vec![
+            Field::create_field("ups_id".to_string(), FieldType::INT32)?,
+            Field::create_field("ups_name".to_string(), FieldType::VARCHAR40)?,
+        ])?;
+        table.create_index("ups_table".to_string(), 0)?;
+        table.create_index("ups_table".to_string(), 1)?;
+
+        // 主键不存在时插入
+        table.upsert("ups_table".to_string(), Entry {
+            data: vec![FieldValue::INT32(1), FieldValue::VARCHAR40("a".to_string())]
+        })?;
+        assert_eq!(table.search("ups_table".to_string(), 0, FieldValue::INT32(1))?.data[1], FieldValue::VARCHAR40("a".to_string()));
+
+        // 主键已存在时更新，其他字段的索引随之更新
+        table.upsert("ups_table".to_string(), Entry {
+            data: vec![FieldValue::INT32(1), FieldValue::VARCHAR40("b".to_string())]
+        })?;
+        assert_eq!(table.search("ups_table".to_string(), 0, FieldValue::INT32(1))?.data[1], FieldValue::VARCHAR40("b".to_string()));
+        assert_eq!(table.search("ups_table".to_string(), 1, FieldValue::VARCHAR40("b".to_string()))?.data[0], FieldValue::INT32(1));
+        assert!(matches!(table.search("ups_table".to_string(), 1, FieldValue::VARCHAR40("a".to_string())), Err(Error::KeyNotFound)));
+        assert_eq!(table.read_full_table("ups_table".to_string())?.len(), 1);
+
+        // 组合主键的表同样按主键判断
+        table.create_table_with_key("ups_key_table".to_string(), vec![
+            Field::create_field("ups_a".to_string(), FieldType::INT32)?,
+            Field::create_field("ups_b".to_string(), FieldType::INT32)?,
+            Field::create_field("ups_c".to_string(), FieldType::INT64)?,
+        ], &[0, 1])?;
+        for c in [10, 20] {
+            table.upsert("ups_key_table".to_string(), Entry {
+                data: vec![FieldValue::INT32(1), FieldValue::INT32(2), FieldValue::INT64(c)]
+            })?;
+        }
+        let row = table.search_by_key("ups_key_table".to_string(), vec![FieldValue::INT32(1), FieldValue::INT32(2)])?;
+        assert_eq!(row.data[2], FieldValue::INT64(20));
+        assert_eq!(table.read_full_table("ups_key_table".to_string())?.len(), 1);
+
+        assert!(matches!(table.upsert("no_table".to_string(), row), Err(Error::TableNotFound)));
+
+        rm_files(&files);
+        Ok(())
+    }
+}
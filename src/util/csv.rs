@@ -0,0 +1,83 @@
+use crate::util::error::Error;
+
+/// 解析 CSV 文本，返回每条记录的各个单元格
+/// 单元格可以用双引号括起，引号内的两个双引号表示一个双引号，引号内可以有逗号和换行
+/// 不带引号的空单元格为 None，表示空值；带引号的空单元格为空字符串
+/// 格式错误时返回出错记录的序号(从 1 开始)
+pub(crate) fn parse_records(text: &str) -> Result<Vec<Vec<Option<String>>>, Error> {
+    let mut records = Vec::<Vec<Option<String>>>::new();
+    let mut record = Vec::<Option<String>>::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    cell.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => cell.push(c),
+            }
+            continue
+        }
+        match c {
+            '"' => {
+                // 引号只能出现在单元格开头
+                if quoted || !cell.is_empty() {
+                    return Err(Error::CsvFormat(records.len() + 1))
+                }
+                quoted = true;
+                in_quotes = true;
+            }
+            ',' => record.push(take_cell(&mut cell, &mut quoted)),
+            '\r' if chars.peek() == Some(&'\n') => (),
+            '\n' => {
+                record.push(take_cell(&mut cell, &mut quoted));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => {
+                // 右引号后只能是分隔符或换行
+                if quoted {
+                    return Err(Error::CsvFormat(records.len() + 1))
+                }
+                cell.push(c)
+            }
+        }
+    }
+    if in_quotes {
+        return Err(Error::CsvFormat(records.len() + 1))
+    }
+    // 最后一条记录末尾可以没有换行
+    if quoted || !cell.is_empty() || !record.is_empty() {
+        record.push(take_cell(&mut cell, &mut quoted));
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn take_cell(cell: &mut String, quoted: &mut bool) -> Option<String> {
+    let res = if cell.is_empty() && !*quoted {
+        None
+    } else {
+        Some(std::mem::take(cell))
+    };
+    *quoted = false;
+    res
+}
+
+/// 将一条记录写成一行 CSV，末尾带换行
+/// 含有逗号、双引号或换行的单元格以及空字符串用双引号括起，None 写成不带引号的空单元格
+pub(crate) fn write_record(record: &[Option<String>]) -> String {
+    let cells: Vec<String> = record.iter().map(|cell| match cell {
+        Some(text) if text.is_empty() || text.contains(&[',', '"', '\r', '\n'][..]) => {
+            format!("\"{}\"", text.replace('"', "\"\""))
+        }
+        Some(text) => text.clone(),
+        None => String::new()
+    }).collect();
+    cells.join(",") + "\n"
+}
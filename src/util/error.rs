@@ -15,10 +15,90 @@ pub enum Error {
     IndexWithoutBTree,
     VarcharTooLong,
     IndexExist,
+    KeySizeOutOfPage,
+    PrimaryKeyImmutable,
+    NoEvictablePage,
+    InvalidPageNum,
+    InvalidPageSize,
+    /// 缓冲区的页数小于 MIN_BUFFER_SIZE
+    InvalidBufferSize,
+    /// 读写文件出错，保留原始的 IO 错误
+    Io(std::io::Error),
+    /// SQL 解析失败，附带解析器给出的信息
+    SqlParseError(String),
+    /// 不支持的 SQL 语句
+    UnsupportedStatement,
+    /// 不支持的 SQL 类型
+    UnsupportedType,
+    /// SQL 中引用的字段不存在
+    FieldNotFound,
+    /// CSV 格式错误或单元格无法解析，附带出错记录的序号(从 1 开始)
+    CsvFormat(usize),
+    /// 批量构建索引时键没有按升序排列
+    KeysNotSorted,
+    /// 要写入的数据超过单个页能容纳的大小
+    ValueTooLarge,
+    /// 节点页的头部信息超出结构允许的范围，页面已损坏
+    CorruptNode,
+    /// 键超过索引的键宽
+    KeyTooLong,
+    /// 没有进行中的事务时提交或回滚，或者事务已经开始时再次开始
+    TransactionState,
+    /// 条件更新时键当前的值与期望的旧值不同
+    CasMismatch,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::KeyNotFound => write!(f, "键不存在"),
+            Error::KeyAlreadyExists => write!(f, "键已存在"),
+            Error::TableAlreadyExists => write!(f, "表已存在"),
+            Error::UnexpectedError => write!(f, "意外错误"),
+            Error::TryFromSliceError(msg) => write!(f, "字节数组转换失败: {}", msg),
+            Error::UTF8Error => write!(f, "不是合法的 UTF-8 字符串"),
+            Error::NotInBufferError => write!(f, "页不在缓冲区中"),
+            Error::TableNotFound => write!(f, "表不存在"),
+            Error::FileNotFound => write!(f, "文件不存在"),
+            Error::PageNumOutOfSize => write!(f, "页号超出文件大小"),
+            Error::FieldValueTooLong => write!(f, "字段值过长"),
+            Error::FieldValueNotCompatible => write!(f, "字段值与字段类型不匹配"),
+            Error::IndexWithoutBTree => write!(f, "字段没有建立索引"),
+            Error::VarcharTooLong => write!(f, "字符串超过 40 字节"),
+            Error::IndexExist => write!(f, "索引已存在"),
+            Error::KeySizeOutOfPage => write!(f, "键宽过大，节点放不下"),
+            Error::PrimaryKeyImmutable => write!(f, "主键不允许修改"),
+            Error::NoEvictablePage => write!(f, "缓冲区中没有可以换出的页"),
+            Error::InvalidPageNum => write!(f, "非法的页号"),
+            Error::InvalidPageSize => write!(f, "非法的页大小"),
+            Error::InvalidBufferSize => write!(f, "缓冲区页数过小"),
+            Error::Io(err) => write!(f, "IO 错误: {}", err),
+            Error::SqlParseError(msg) => write!(f, "SQL 解析失败: {}", msg),
+            Error::UnsupportedStatement => write!(f, "不支持的 SQL 语句"),
+            Error::UnsupportedType => write!(f, "不支持的 SQL 类型"),
+            Error::FieldNotFound => write!(f, "字段不存在"),
+            Error::CsvFormat(record) => write!(f, "CSV 第 {} 条记录格式错误", record),
+            Error::KeysNotSorted => write!(f, "键没有按升序排列"),
+            Error::ValueTooLarge => write!(f, "数据超过单页能容纳的大小"),
+            Error::CorruptNode => write!(f, "节点页已损坏"),
+            Error::KeyTooLong => write!(f, "键超过索引的键宽"),
+            Error::TransactionState => write!(f, "事务状态错误"),
+            Error::CasMismatch => write!(f, "当前值与期望的旧值不一致"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
 }
 
 impl std::convert::From<std::io::Error> for Error {
-    fn from(_e: std::io::Error) -> Error {
-        Error::UnexpectedError
+    fn from(e: std::io::Error) -> Error {
+        Error::Io(e)
     }
 }
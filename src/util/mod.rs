@@ -1,3 +1,4 @@
 pub mod error;
+pub(crate) mod csv;
 pub(crate) mod data_gen;
 pub(crate) mod test_lib;
\ No newline at end of file
@@ -16,6 +16,13 @@ pub fn rm_test_file() {
     }
 }
 
+#[allow(dead_code)]
+pub fn rm_files(files: &[&str]) {
+    for file in files {
+        let _ = fs::remove_file(file);
+    }
+}
+
 #[allow(dead_code)]
 pub fn gen_buffer() -> Result<Box<dyn Buffer>, Error> {
     let mut buffer = Box::new(LRUBuffer::new(4, "metadata.db".to_string())?);